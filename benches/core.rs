@@ -0,0 +1,73 @@
+// cargo bench --no-default-features
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion};
+use hanabi::bench::{random_game, random_moves};
+use hanabi::state::{ActionRequest, State, NACTIONS};
+use ndarray::Array1;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use std::hint::black_box;
+
+// a 4 players game after 10 random actions
+fn midgame() -> State {
+    let mut rng = StdRng::seed_from_u64(0);
+    let mut state = State::with_seed(4, 0);
+    random_moves(&mut state, 10, &mut rng);
+    assert!(!state.gameover());
+    state
+}
+
+fn core(c: &mut Criterion) {
+    let state = midgame();
+
+    c.bench_function("new", |b| b.iter(|| State::with_seed(4, black_box(0))));
+
+    c.bench_function("play", |b| {
+        b.iter_batched(
+            || state.fork(),
+            |mut state| state.apply(&ActionRequest::Play { position: 0 }),
+            BatchSize::SmallInput,
+        )
+    });
+
+    let clue = (0..NACTIONS)
+        .map(ActionRequest::from_id)
+        .find(|action| {
+            matches!(
+                action,
+                ActionRequest::ColorClue { .. } | ActionRequest::ValueClue { .. }
+            ) && state.check(action).is_ok()
+        })
+        .unwrap();
+    c.bench_function("clue", |b| {
+        b.iter_batched(
+            || state.fork(),
+            |mut state| state.apply(&clue),
+            BatchSize::SmallInput,
+        )
+    });
+
+    c.bench_function("encode", |b| b.iter(|| state.encode()));
+
+    let mut buffer = Array1::zeros(state.encoding_size());
+    c.bench_function("encode_into", |b| {
+        b.iter(|| state.encode_into(&mut buffer.view_mut()))
+    });
+
+    // plays the first card
+    let mut x = Array1::zeros(state.decoding_size());
+    x[0] = 1.0;
+    x[3] = 1.0;
+    c.bench_function("decode", |b| {
+        b.iter_batched(
+            || state.fork(),
+            |mut state| state.decode(&x.view()),
+            BatchSize::SmallInput,
+        )
+    });
+
+    let mut rng = StdRng::seed_from_u64(0);
+    c.bench_function("random game", |b| b.iter(|| random_game(4, &mut rng)));
+}
+
+criterion_group!(benches, core);
+criterion_main!(benches);
@@ -0,0 +1,64 @@
+use std::path::Path;
+use std::process::Command;
+
+// the trimmed output of a git command, None without git or outside a checkout
+fn git(args: &[&str]) -> Option<String> {
+    let output = Command::new("git").args(args).output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    Some(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+fn main() {
+    // record the commit the engine was built from, when building from a git checkout
+    if let Some(hash) = git(&["rev-parse", "HEAD"]) {
+        println!("cargo:rustc-env=HANABI_GIT_HASH={}", hash);
+    }
+    // a checkout moves HEAD but a commit moves the branch it points to, loose or packed; the paths
+    // are asked to git, the git directory is elsewhere in a worktree or a submodule
+    let mut refs = vec!["HEAD".to_string(), "packed-refs".to_string()];
+    refs.extend(git(&["symbolic-ref", "-q", "HEAD"]));
+    for name in refs {
+        if let Some(path) = git(&["rev-parse", "--git-path", &name]) {
+            if Path::new(&path).exists() {
+                println!("cargo:rerun-if-changed={}", path);
+            }
+        }
+    }
+
+    // the grpc messages and service, with a protoc shipped as a crate so that none is needed
+    #[cfg(feature = "grpc")]
+    {
+        std::env::set_var("PROTOC", protoc_bin_vendored::protoc_bin_path().unwrap());
+        // the server only, clients are generated in their own language
+        tonic_prost_build::configure()
+            .build_client(false)
+            .compile_protos(&["proto/hanabi.proto"], &["proto"])
+            .unwrap();
+        println!("cargo:rerun-if-changed=proto/hanabi.proto");
+    }
+
+    // the header of the c api in OUT_DIR, the build does not write in the source tree; the copy
+    // kept in the repository is refreshed with an explicit cbindgen step, see the readme
+    #[cfg(feature = "capi")]
+    {
+        let config = cbindgen::Config::from_file("cbindgen.toml").unwrap();
+        let header = Path::new(&std::env::var("OUT_DIR").unwrap()).join("hanabi.h");
+        cbindgen::Builder::new()
+            .with_config(config)
+            .with_src("src/capi.rs")
+            .generate()
+            .unwrap()
+            .write_to_file(&header);
+        if std::fs::read(&header).ok() != std::fs::read("include/hanabi.h").ok() {
+            println!(
+                "cargo:warning=include/hanabi.h differs from the header of src/capi.rs ({})",
+                header.display()
+            );
+        }
+        println!("cargo:rerun-if-changed=src/capi.rs");
+        println!("cargo:rerun-if-changed=cbindgen.toml");
+        println!("cargo:rerun-if-changed=include/hanabi.h");
+    }
+}
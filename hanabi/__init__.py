@@ -1,4 +1,2 @@
 # pylint: disable=missing-docstring
-from .hanabi import Game  # pylint: disable=no-name-in-module
-
-###
+from .hanabi import ExternalBot, Game, GameDriver, HanabiAEC, Observation, ProbeSet, VisitCounts, anonymize, build_info, clue_efficiency, convert_slot, daily_seed, dataset, dataset_stats, diff_observations, endgame, evaluate_lineups, evaluate_policy, fingerprint_distance, imitation_accuracy, mlp_quantization_report, negotiate, probe_suite, protocol_hello, run_experiment, score_histogram, serve, verify  # pylint: disable=no-name-in-module
@@ -0,0 +1,254 @@
+# pylint: disable=missing-docstring, invalid-name
+import argparse
+import csv
+import glob
+import os
+import sys
+
+import numpy as np
+
+from . import Game, anonymize, clue_efficiency, daily_seed, dataset, dataset_stats, endgame, imitation_accuracy, run_experiment, serve, verify
+from .batch import Client
+
+
+def cmd_dataset(args):
+    paths = sorted(glob.glob(os.path.join(args.input, "*.json")))
+    os.makedirs(args.out, exist_ok=True)
+
+    for i in range(0, len(paths), args.shard_size):
+        data = dataset(paths[i:i + args.shard_size], args.gamma, args.encoder)
+        out = os.path.join(args.out, "shard_{:05d}.npz".format(i // args.shard_size))
+        np.savez_compressed(out, encoder=args.encoder, **data)
+        print("{} games -> {} ({} turns)".format(len(paths[i:i + args.shard_size]), out, len(data['actions'])))
+
+
+def cmd_stats(args):
+    paths = sorted(glob.glob(os.path.join(args.input, "*.json")))
+    report = dataset_stats(paths)
+    print("{} games, {} turns, mean score {:.2f}, {} perfect".format(report['games'], report['turns'], report['mean_score'], report['perfect']))
+    print("scores: " + " ".join("{}:{}".format(score, n) for score, n in sorted(report['scores'].items())))
+    print("clues: {} color, {} value ({:.1%} value)".format(report['color_clues'], report['value_clues'], report['value_clue_ratio']))
+
+    print("{:6} {:>8} {:>8} {:>8} {:>8} {:>8}".format("seat", "plays", "misplays", "discards", "color", "value"))
+    for i, seat in enumerate(report['seats']):
+        print("{:6d} {:8d} {:8d} {:8d} {:8d} {:8d}".format(i, seat['plays'], seat['misplays'], seat['discards'], seat['color_clues'], seat['value_clues']))
+
+    for anomaly in report['anomalies']:
+        if anomaly['type'] == 'invalid':
+            print("{}: invalid, {}".format(anomaly['path'], anomaly['error']))
+        elif anomaly['type'] == 'truncated':
+            print("{}: truncated after {} turns".format(anomaly['path'], anomaly['turns']))
+        else:
+            print("{}: duplicate of {}".format(anomaly['path'], anomaly['of']))
+    if report['anomalies'] and args.strict:
+        sys.exit(1)
+
+
+def cmd_imitation(args):
+    paths = sorted(glob.glob(os.path.join(args.input, "*.json")))
+    report = imitation_accuracy(paths, args.agent)
+    print("{} decisions, {:.1%} of the recorded actions taken by {}".format(report['decisions'], report['accuracy'], args.agent))
+    for name, kind in report['kinds'].items():
+        print("{:12} {:8d} {:8.1%}".format(name, kind['decisions'], kind['accuracy']))
+
+
+def cmd_run(args):
+    results = run_experiment(args.experiment)
+    scores = [score for _, score in results]
+    print("{} games, mean score {:.2f}".format(len(scores), sum(scores) / max(len(scores), 1)))
+
+
+def cmd_endgame(args):
+    with open(args.replay) as f:
+        deals, values = endgame(f.read(), args.turn, args.limit)
+    print("{} deals consistent with what the player to move knows".format(deals))
+    for name, expected, worst, best in sorted(values, key=lambda x: -x[1]):
+        print("{:12} {:6.3f}  (worst {}, best {})".format(name, expected, worst, best))
+
+
+def cmd_daily(args):
+    seed = daily_seed(args.date)
+    print("seed {}".format(seed))
+    game = Game(args.players, seed=seed, variant=args.variant)
+    print(game)
+
+
+def cmd_anonymize(args):
+    paths = sorted(glob.glob(os.path.join(args.input, "*.json")))
+    os.makedirs(args.out, exist_ok=True)
+    for path in paths:
+        with open(path) as f:
+            replay = anonymize(f.read(), args.names, args.keep_notes)
+        with open(os.path.join(args.out, os.path.basename(path)), "w") as f:
+            f.write(replay)
+    print("{} replays -> {}".format(len(paths), args.out))
+
+
+def cmd_verify(args):
+    rejected = 0
+    for path in args.claims:
+        with open(path) as f:
+            try:
+                score, turns = verify(f.read())
+                print("{}: verified, score {} in {} turns".format(path, score, turns))
+            except ValueError as err:
+                rejected += 1
+                print("{}: rejected, {}".format(path, err))
+    if rejected:
+        sys.exit(1)
+
+
+def cmd_serve(args):
+    print("serving on {} with {} workers".format(args.address, args.workers))
+    serve(args.address, args.workers, args.root)
+
+
+def cmd_submit(args):
+    client = Client(args.address)
+    with open(args.experiment) as f:
+        job = client.submit(f.read())
+    print("job {}".format(job))
+    if args.wait:
+        def progress(reply):
+            if reply['type'] == 'running':
+                eta = "?" if reply['eta'] is None else "{:.0f}s".format(reply['eta'])
+                print("{}/{} games, eta {}".format(reply['done'], reply['total'], eta))
+        reply = client.wait(job, poll=5.0, callback=progress)
+        if reply['type'] == 'failed':
+            print("failed: {}".format(reply['error']))
+        else:
+            scores = [x['score'] for x in reply['results']]
+            print("{} games, mean score {:.2f}".format(len(scores), sum(scores) / max(len(scores), 1)))
+    client.close()
+
+
+def cmd_claim(args):
+    client = Client(args.address)
+    for path in args.claims:
+        with open(path) as f:
+            try:
+                print("{}: stored as game {}".format(path, client.submit_game(args.submitter, f.read())))
+            except ValueError as err:
+                print("{}: rejected, {}".format(path, err))
+    client.close()
+
+
+def cmd_ranking(args):
+    client = Client(args.address)
+    entries = client.leaderboard(args.variant, args.players, args.modifiers, args.limit)
+    client.close()
+    print("{:>4} {:20} {:>6} {:>6} {:>6}".format("", "submitter", "score", "turns", "game"))
+    for entry in entries:
+        print("{:4d} {:20} {:6d} {:6d} {:6d}".format(entry['rank'], entry['submitter'], entry['score'], entry['turns'], entry['game']))
+
+
+LEADERBOARD = ["games", "mean_score", "clue_efficiency", "bad_touch_rate", "critical_discard_rate"]
+
+
+def cmd_leaderboard(args):
+    rows = []
+    for path in args.paths:
+        paths = sorted(glob.glob(os.path.join(path, "*.json"))) if os.path.isdir(path) else [path]
+        rows.append(dict(name=path, **clue_efficiency(paths)))
+    # a high clue efficiency is good, high rates are bad
+    rows.sort(key=lambda row: -row[args.sort] if args.sort in ["mean_score", "clue_efficiency"] else row[args.sort])
+
+    print("{:30} {:>6} {:>6} {:>10} {:>10} {:>10}".format("", "games", "score", "plays/clue", "bad touch", "crit disc"))
+    for row in rows:
+        print("{:30} {:6d} {:6.2f} {:10.3f} {:10.3f} {:10.3f}".format(row['name'], *[row[key] for key in LEADERBOARD]))
+
+    if args.out:
+        with open(args.out, "w", newline="") as f:
+            writer = csv.DictWriter(f, fieldnames=["name"] + LEADERBOARD, extrasaction="ignore")
+            writer.writeheader()
+            writer.writerows(rows)
+
+
+def main():
+    parser = argparse.ArgumentParser(prog="hanabi")
+    subparsers = parser.add_subparsers(dest="command")
+    subparsers.required = True
+
+    p = subparsers.add_parser("dataset", help="convert a directory of json replays into training shards")
+    p.add_argument("--in", dest="input", type=str, required=True)
+    p.add_argument("--out", type=str, required=True)
+    p.add_argument("--encoder", type=str, default="v1", choices=["v1", "hle", "v2"])
+    p.add_argument("--gamma", type=float, default=1.0)
+    p.add_argument("--shard_size", type=int, default=1000)
+    p.set_defaults(func=cmd_dataset)
+
+    p = subparsers.add_parser("run", help="run the games described by an experiment file")
+    p.add_argument("experiment", type=str)
+    p.set_defaults(func=cmd_run)
+
+    p = subparsers.add_parser("endgame", help="exact value of every action at a turn of a replay")
+    p.add_argument("replay", type=str)
+    p.add_argument("--turn", type=int, required=True)
+    p.add_argument("--limit", type=int, default=100000, help="maximum number of deals")
+    p.set_defaults(func=cmd_endgame)
+
+    p = subparsers.add_parser("stats", help="summarize a directory of json replays and flag invalid, truncated or duplicated games")
+    p.add_argument("--in", dest="input", type=str, required=True)
+    p.add_argument("--strict", action="store_true", help="exit with 1 if a game is flagged")
+    p.set_defaults(func=cmd_stats)
+
+    p = subparsers.add_parser("imitation", help="how often an agent takes the action of the players of a directory of json replays")
+    p.add_argument("--in", dest="input", type=str, required=True)
+    p.add_argument("--agent", type=str, default="convention")
+    p.set_defaults(func=cmd_imitation)
+
+    p = subparsers.add_parser("leaderboard", help="rank directories of replays by clue and discard quality")
+    p.add_argument("paths", type=str, nargs="+", help="directories of json replays (e.g. one per checkpoint) or single replays")
+    p.add_argument("--sort", type=str, default="clue_efficiency", choices=LEADERBOARD[1:])
+    p.add_argument("--out", type=str, help="also write the table as csv")
+    p.set_defaults(func=cmd_leaderboard)
+
+    p = subparsers.add_parser("daily", help="seed and deal of the day, the same for everyone")
+    p.add_argument("--date", type=str, help="YYYY-MM-DD, today (utc) by default")
+    p.add_argument("--players", type=int, default=4)
+    p.add_argument("--variant", type=str, default="standard")
+    p.set_defaults(func=cmd_daily)
+
+    p = subparsers.add_parser("anonymize", help="strip the names and notes of a directory of json replays before sharing them")
+    p.add_argument("--in", dest="input", type=str, required=True)
+    p.add_argument("--out", type=str, required=True)
+    p.add_argument("--names", type=str, default="seats", choices=["seats", "remove", "keep"])
+    p.add_argument("--keep_notes", action="store_true")
+    p.set_defaults(func=cmd_anonymize)
+
+    p = subparsers.add_parser("verify", help="replay the actions of claimed scores and check the scores")
+    p.add_argument("claims", type=str, nargs="+", help="json files with the players, the seed or the deck, the actions and the score")
+    p.set_defaults(func=cmd_verify)
+
+    p = subparsers.add_parser("serve", help="run experiments submitted over the network")
+    p.add_argument("--address", type=str, default="127.0.0.1:7878")
+    p.add_argument("--workers", type=int, default=1)
+    p.add_argument("--root", type=str, default="jobs", help="directory of the jobs, unfinished jobs are resumed")
+    p.set_defaults(func=cmd_serve)
+
+    p = subparsers.add_parser("submit", help="submit an experiment file to a server")
+    p.add_argument("experiment", type=str)
+    p.add_argument("--address", type=str, default="127.0.0.1:7878")
+    p.add_argument("--wait", action="store_true", help="wait for the results")
+    p.set_defaults(func=cmd_submit)
+
+    p = subparsers.add_parser("claim", help="submit claimed games to the leaderboard of a server")
+    p.add_argument("claims", type=str, nargs="+")
+    p.add_argument("--submitter", type=str, required=True)
+    p.add_argument("--address", type=str, default="127.0.0.1:7878")
+    p.set_defaults(func=cmd_claim)
+
+    p = subparsers.add_parser("ranking", help="leaderboard of the verified games of a server")
+    p.add_argument("--variant", type=str, default="standard")
+    p.add_argument("--players", type=int, default=2)
+    p.add_argument("--modifiers", type=str, nargs="*", default=[])
+    p.add_argument("--limit", type=int, default=20)
+    p.add_argument("--address", type=str, default="127.0.0.1:7878")
+    p.set_defaults(func=cmd_ranking)
+
+    args = parser.parse_args()
+    args.func(args)
+
+
+if __name__ == "__main__":
+    main()
@@ -0,0 +1,66 @@
+# pylint: disable=missing-docstring
+import json
+import socket
+import time
+
+from .hanabi import negotiate, protocol_hello  # pylint: disable=no-name-in-module
+
+
+class Client:
+    """connection to a server started with `hanabi.serve` or `python -m hanabi serve`"""
+
+    def __init__(self, address):
+        host, port = address.rsplit(":", 1)
+        self.sock = socket.create_connection((host, int(port)))
+        self.file = self.sock.makefile("rw")
+        negotiate(self.file.readline())
+        self._write(protocol_hello())
+
+    def _write(self, line):
+        self.file.write(line + "\n")
+        self.file.flush()
+
+    def request(self, **message):
+        self._write(json.dumps(message))
+        reply = json.loads(self.file.readline())
+        if reply['type'] == 'error':
+            raise ValueError(reply['message'])
+        return reply
+
+    def submit(self, experiment):
+        """`experiment` is the toml of an experiment, returns the job id"""
+        return self.request(type="submit", experiment=experiment)['job']
+
+    def status(self, job):
+        """`queued`, `running` (with `done`, `total` and `eta` in seconds), `done` or `failed`"""
+        return self.request(type="status", job=job)
+
+    def replay(self, job, seed):
+        """json of the replay of a game played by the job"""
+        return self.request(type="replay", job=job, seed=seed)['replay']
+
+    def submit_game(self, submitter, claim):
+        """`claim` is the json of a claimed game (see `hanabi.verify`), returns its id once verified
+        (servers built with the `leaderboard` feature)"""
+        return self.request(type="submit_game", submitter=submitter, claim=claim)['game']
+
+    def leaderboard(self, variant="standard", players=2, modifiers=(), limit=100):
+        """best game of each submitter, ranked by score, then turns, then submission time"""
+        return self.request(type="leaderboard", variant=variant, players=players, modifiers=list(modifiers), limit=limit)['entries']
+
+    def game(self, game):
+        """json of the claim of a stored game"""
+        return self.request(type="game", game=game)['claim']
+
+    def wait(self, job, poll=1.0, callback=None):
+        while True:
+            reply = self.status(job)
+            if reply['type'] in ['done', 'failed']:
+                return reply
+            if callback is not None:
+                callback(reply)
+            time.sleep(poll)
+
+    def close(self):
+        self.file.close()
+        self.sock.close()
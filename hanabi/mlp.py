@@ -0,0 +1,23 @@
+# pylint: disable=missing-docstring
+# writes the weights of a small network for the rust agent "mlp:<path>", see `src/mlp.rs`
+import numpy as np
+
+
+def save_mlp(path, layers):
+    """writes `layers`, a list of (weight of shape (inputs, outputs), bias of shape (outputs,)) with a relu
+    between the layers; the input is `Game.encode()` and the output one logit per action id"""
+    arrays = {}
+    for i, (weight, bias) in enumerate(layers):
+        arrays["w{}".format(i)] = np.ascontiguousarray(weight, dtype=np.float32)
+        arrays["b{}".format(i)] = np.ascontiguousarray(bias, dtype=np.float32)
+    # the rust side only reads uncompressed archives
+    np.savez(path, **arrays)
+
+
+def torch_layers(module):
+    """the layers of a torch module made of `Linear` layers and relus, e.g. a `Sequential`"""
+    return [
+        (x.weight.detach().cpu().numpy().T, x.bias.detach().cpu().numpy())
+        for x in module.modules()
+        if type(x).__name__ == "Linear"
+    ]
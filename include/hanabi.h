@@ -0,0 +1,64 @@
+#ifndef HANABI_H
+#define HANABI_H
+
+/* generated by cbindgen from src/capi.rs, do not edit */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+// Bumped on every incompatible change of this api.
+#define HANABI_API_VERSION 1
+
+#define HANABI_OK 0
+
+// The action is not legal, the game is unchanged.
+#define HANABI_ILLEGAL 1
+
+// A pointer is null, an index is out of range or a buffer has the wrong length.
+#define HANABI_INVALID 2
+
+// A game, opaque.
+typedef struct HanabiGame HanabiGame;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+// A game of 2 to 5 players dealt from `seed`; `variant` is a name like "standard" (the default
+// when null). Null if the arguments are invalid.
+struct HanabiGame *hanabi_new(size_t players, const char *variant, uint64_t seed);
+
+void hanabi_free(struct HanabiGame *game);
+
+// Number of action ids, the length of the legal mask.
+size_t hanabi_num_actions(void);
+
+// Plays the action id of the player to move, writes the score increase to `reward` if not null.
+int32_t hanabi_step(struct HanabiGame *game, size_t action, float *reward);
+
+// Length of the observations of the game.
+size_t hanabi_encoding_size(const struct HanabiGame *game);
+
+// Writes the observation of `player` (see `hanabi_encoding_size` for `len`).
+int32_t hanabi_encode(const struct HanabiGame *game, size_t player, float *out, size_t len);
+
+// Writes 1 for the legal action ids of the player to move and 0 for the others (`len` is
+// `hanabi_num_actions()`).
+int32_t hanabi_legal_mask(const struct HanabiGame *game, float *out, size_t len);
+
+// The player to move.
+size_t hanabi_player(const struct HanabiGame *game);
+
+size_t hanabi_score(const struct HanabiGame *game);
+
+// True once the game is over, and for a null game.
+bool hanabi_gameover(const struct HanabiGame *game);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* HANABI_H */
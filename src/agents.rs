@@ -0,0 +1,565 @@
+#![allow(dead_code)]
+
+use crate::conventions::{Conventions, HGroupBot};
+use crate::encoder::Encoder;
+use crate::hat::HatBot;
+use crate::mlp::{self, MlpBot};
+use crate::rules::DrawPosition;
+use crate::search::{self, MctsBot, RolloutBot};
+use crate::slots::SlotOrder;
+use crate::state::{
+    Action, ActionRequest, Card, Knowledge, State, Value, MAXCARDS, MAXCLUES, MAXMISTAKES, NACTIONS,
+};
+use crate::variant::Variant;
+use ndarray::Array1;
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+use std::collections::VecDeque;
+
+pub trait Agent {
+    fn act(&mut self, obs: &Observation) -> ActionRequest;
+
+    // why the agent gave up the game with its last action, e.g. an external bot out of time
+    fn forfeit(&mut self) -> Option<String> {
+        None
+    }
+}
+
+// what the player to move sees: every hand but their own, of which they only know the clues
+pub struct Observation<'a> {
+    state: &'a State,
+    player: usize,
+}
+
+impl<'a> Observation<'a> {
+    pub fn new(state: &'a State) -> Observation<'a> {
+        Observation {
+            state,
+            player: state.turn() % state.players().len(),
+        }
+    }
+
+    // what `player` sees, who may be waiting for their turn; the legal actions are still those of
+    // the player to move
+    pub fn of(state: &'a State, player: usize) -> Observation<'a> {
+        Observation { state, player }
+    }
+
+    pub fn player(&self) -> usize {
+        self.player
+    }
+
+    pub fn to_move(&self) -> bool {
+        self.player == self.state.turn() % self.state.players().len()
+    }
+
+    pub fn players(&self) -> usize {
+        self.state.players().len()
+    }
+
+    // none for the own hand
+    pub fn hand(&self, player: usize) -> Option<&[Card]> {
+        if player == self.player {
+            None
+        } else {
+            Some(&self.state.players()[player])
+        }
+    }
+
+    pub fn hand_size(&self, player: usize) -> usize {
+        self.state.players()[player].len()
+    }
+
+    pub fn knowledge(&self, player: usize) -> &[Knowledge] {
+        &self.state.knowledge()[player]
+    }
+
+    pub fn table(&self) -> &[usize] {
+        self.state.table()
+    }
+
+    pub fn discard(&self) -> &[Card] {
+        self.state.discard()
+    }
+
+    pub fn clues(&self) -> usize {
+        *self.state.clues()
+    }
+
+    pub fn mistakes(&self) -> usize {
+        *self.state.mistakes()
+    }
+
+    pub fn deck_size(&self) -> usize {
+        self.state.deck().len()
+    }
+
+    pub fn variant(&self) -> &Variant {
+        self.state.variant()
+    }
+
+    pub fn turn(&self) -> usize {
+        *self.state.turn()
+    }
+
+    pub fn encode(&self) -> Array1<f32> {
+        self.state.encode_for(self.player)
+    }
+
+    // the observation of `encoder`, panics if it does not support the game
+    pub fn encode_with(&self, encoder: Encoder) -> Array1<f32> {
+        encoder.encode(self.state, self.player)
+    }
+
+    pub fn legal_mask(&self) -> Array1<f32> {
+        self.state.legal_mask()
+    }
+
+    // from the table and the discard pile, see the functions of the same name
+    pub fn is_playable(&self, card: &Card) -> bool {
+        is_playable(self.state, card)
+    }
+
+    pub fn is_useless(&self, card: &Card) -> bool {
+        is_useless(self.state, card)
+    }
+
+    pub fn is_critical(&self, card: &Card) -> bool {
+        is_critical(self.state, card)
+    }
+
+    // see `State::chop` and `State::clue_focus`, from what every player was told
+    pub fn chop(&self, player: usize) -> Option<usize> {
+        self.state.chop(player)
+    }
+
+    pub fn clue_focus(&self, clue: &ActionRequest) -> Option<usize> {
+        self.state.clue_focus(clue)
+    }
+
+    // the conversion from the engine order to `order`, see `SlotOrder::for_state`
+    pub fn slots(&self, order: SlotOrder) -> SlotOrder {
+        order.for_state(self.state)
+    }
+
+    // the actions so far, public: the cards played and discarded are shown to everyone
+    pub fn history(&self) -> &VecDeque<Action> {
+        self.state.history()
+    }
+
+    // the number of cards in every hand before each action of the history
+    pub fn hands_before(&self) -> Vec<Vec<usize>> {
+        self.state.hands_before()
+    }
+
+    // the hands before each action of the history, None for the own hand; rebuilt backwards from
+    // the hands seen now, putting back the cards played or discarded and taking out those drawn
+    pub fn past_hands(&self) -> Vec<Vec<Option<Vec<Card>>>> {
+        let mut hands: Vec<Option<Vec<Card>>> = (0..self.players())
+            .map(|p| self.hand(p).map(|x| x.to_vec()))
+            .collect();
+        let sizes = self.state.hands_before();
+        let draw = self.state.rules().draw_position;
+        let mut past = Vec::new();
+        for (action, before) in self.history().iter().zip(&sizes).rev() {
+            let p = action.player();
+            if let (Some(hand), Action::Play { position, card, .. })
+            | (Some(hand), Action::Discard { position, card, .. }) = (&mut hands[p], action)
+            {
+                if hand.len() == before[p] {
+                    match draw {
+                        DrawPosition::First => hand.remove(0),
+                        DrawPosition::Last => hand.pop().unwrap(),
+                    };
+                }
+                hand.insert(*position, *card);
+            }
+            past.push(hands.clone());
+        }
+        past.reverse();
+        past
+    }
+
+    // everything, hidden cards included: private to this module, for `CheatBot` alone
+    fn full_state(&self) -> &State {
+        self.state
+    }
+
+    // a whole game agreeing with what the player sees, their own hand and the deck dealt again
+    // (see `State::sample_deal`), for the searches that play out the game
+    pub fn sample_world<R: Rng>(&self, rng: &mut R) -> Option<State> {
+        let (hand, deck) = self.state.sample_deal(self.player, rng)?;
+        Some(self.state.with_hidden(self.player, hand, deck))
+    }
+
+    // the positions a clue touches in the hand of a teammate, None for the own hand
+    pub fn touched_by(&self, clue: &ActionRequest) -> Option<Vec<usize>> {
+        match *clue {
+            ActionRequest::ColorClue { target, .. } | ActionRequest::ValueClue { target, .. }
+                if target == self.player =>
+            {
+                None
+            }
+            _ => Some(self.state.touched_by(clue)),
+        }
+    }
+
+    // legality only depends on public information and on the hands of the others
+    pub fn is_legal(&self, action: &ActionRequest) -> bool {
+        self.state.check(action).is_ok()
+    }
+
+    // the identities a card of the own hand can still have given its clues and the visible cards
+    pub fn candidates(&self, position: usize) -> Vec<Card> {
+        let knowledge = self.knowledge(self.player)[position];
+        let mut candidates = Vec::new();
+        for &suit in self.variant().suits() {
+            for value in Value::all() {
+                let card = Card::new(value, suit);
+                if !knowledge.allows(&card, self.variant()) {
+                    continue;
+                }
+                if self.visible(&card) < value.copies() {
+                    candidates.push(card);
+                }
+            }
+        }
+        candidates
+    }
+
+    // copies of a card on the table, in the discard pile and in the other hands
+    fn visible(&self, card: &Card) -> usize {
+        let played =
+            (self.table()[self.variant().suit_index(card.color())] > card.value().index()) as usize;
+        let discarded = self.discard().iter().filter(|c| *c == card).count();
+        let held: usize = (0..self.players())
+            .filter_map(|p| self.hand(p))
+            .map(|hand| hand.iter().filter(|c| *c == card).count())
+            .sum();
+        played + discarded + held
+    }
+}
+
+// picks uniformly among the legal actions
+pub struct RandomBot {
+    rng: StdRng,
+}
+
+impl RandomBot {
+    pub fn new(seed: u64) -> RandomBot {
+        RandomBot {
+            rng: StdRng::seed_from_u64(seed),
+        }
+    }
+}
+
+impl Agent for RandomBot {
+    fn act(&mut self, obs: &Observation) -> ActionRequest {
+        let legal: Vec<usize> = (0..NACTIONS)
+            .filter(|&id| obs.is_legal(&ActionRequest::from_id(id)))
+            .collect();
+        match legal.choose(&mut self.rng) {
+            Some(&id) => ActionRequest::from_id(id),
+            None => ActionRequest::Play { position: 0 },
+        }
+    }
+}
+
+// sees every hand, used as an upper bound baseline for learned policies
+#[derive(Default)]
+pub struct CheatBot;
+
+impl Agent for CheatBot {
+    fn act(&mut self, obs: &Observation) -> ActionRequest {
+        self.best_move(obs.full_state())
+    }
+}
+
+impl CheatBot {
+    pub fn best_move(&self, state: &State) -> ActionRequest {
+        let player = state.turn() % state.players().len();
+        let hand = &state.players()[player];
+
+        // playable cards, the 5s first while they give back a clue
+        let playable = (0..hand.len())
+            .filter(|&i| is_playable(state, &hand[i]))
+            .min_by_key(|&i| {
+                let value = hand[i].value().index();
+                if value == 4 && *state.clues() < MAXCLUES {
+                    0
+                } else {
+                    1 + value
+                }
+            });
+        if let Some(position) = playable {
+            return ActionRequest::Play { position };
+        }
+
+        let clue = (2 * MAXCARDS..NACTIONS)
+            .map(ActionRequest::from_id)
+            .find(|action| state.check(action).is_ok());
+        let can_discard = state.check(&ActionRequest::Discard { position: 0 }).is_ok();
+
+        // cards that will never be played, or of which another copy is held
+        if can_discard {
+            if let Some(position) = (0..hand.len()).find(|&i| is_useless(state, &hand[i])) {
+                return ActionRequest::Discard { position };
+            }
+            if let Some(position) = (0..hand.len()).find(|&i| is_duplicate(state, player, i)) {
+                return ActionRequest::Discard { position };
+            }
+        }
+
+        // stall while a teammate has something to do or every discard hurts
+        let teammate_can_play = state
+            .players()
+            .iter()
+            .enumerate()
+            .any(|(i, cards)| i != player && cards.iter().any(|c| is_playable(state, c)));
+        let safe = (0..hand.len()).filter(|&i| !is_critical(state, &hand[i]));
+        let safe = safe.max_by_key(|&i| hand[i].value().index());
+        if let Some(clue) = clue {
+            if !can_discard || teammate_can_play || safe.is_none() {
+                return clue;
+            }
+        }
+
+        if can_discard {
+            let position = safe.unwrap_or_else(|| {
+                (0..hand.len())
+                    .max_by_key(|&i| hand[i].value().index())
+                    .unwrap()
+            });
+            return ActionRequest::Discard { position };
+        }
+        // no clue token to spend and nothing to discard: the hand only holds unplayable cards
+        ActionRequest::Play { position: 0 }
+    }
+}
+
+pub fn is_playable(state: &State, card: &Card) -> bool {
+    state.table()[state.variant().suit_index(card.color())] == card.value().index()
+}
+
+// already played, or a lower card of its suit is lost
+pub fn is_useless(state: &State, card: &Card) -> bool {
+    let suit = state.variant().suit_index(card.color());
+    let played = state.table()[suit];
+    if card.value().index() < played {
+        return true;
+    }
+    (played..card.value().index()).any(|value| {
+        let lost = state
+            .discard()
+            .iter()
+            .filter(|c| c.color() == card.color() && c.value().index() == value)
+            .count();
+        lost == crate::state::Value::new(value).copies()
+    })
+}
+
+pub fn is_critical(state: &State, card: &Card) -> bool {
+    let lost = state.discard().iter().filter(|c| **c == *card).count();
+    lost + 1 == card.value().copies() && !is_useless(state, card)
+}
+
+fn is_duplicate(state: &State, player: usize, position: usize) -> bool {
+    let card = state.players()[player][position];
+    state.players().iter().enumerate().any(|(i, cards)| {
+        cards
+            .iter()
+            .enumerate()
+            .any(|(j, c)| *c == card && (i, j) != (player, position))
+    })
+}
+
+// plays cards it knows to be playable or that were clued, discards its oldest unclued card and
+// clues playable cards to the others
+#[derive(Default)]
+pub struct ConventionBot;
+
+impl Agent for ConventionBot {
+    fn act(&mut self, obs: &Observation) -> ActionRequest {
+        let me = obs.player();
+        let knowledge = obs.knowledge(me);
+        let playable = |card: &Card| {
+            obs.table()[obs.variant().suit_index(card.color())] == card.value().index()
+        };
+
+        let certain = (0..knowledge.len()).find(|&i| {
+            let candidates = obs.candidates(i);
+            !candidates.is_empty() && candidates.iter().all(playable)
+        });
+        if let Some(position) = certain {
+            return ActionRequest::Play { position };
+        }
+        // by convention clues only touch playable cards, this stops being trusted at the last strike
+        if obs.mistakes() + 1 < MAXMISTAKES {
+            let clued = (0..knowledge.len())
+                .find(|&i| is_clued(&knowledge[i]) && obs.candidates(i).iter().any(playable));
+            if let Some(position) = clued {
+                return ActionRequest::Play { position };
+            }
+        }
+
+        if let Some(clue) = self.clue(obs) {
+            return clue;
+        }
+
+        // the oldest card when every card is clued
+        let oldest = SlotOrder::OldestFirst
+            .for_state(obs.state)
+            .convert(0, knowledge.len());
+        let discard = ActionRequest::Discard {
+            position: obs.state.chop(me).unwrap_or(oldest),
+        };
+        if obs.is_legal(&discard) {
+            return discard;
+        }
+        (2 * MAXCARDS..NACTIONS)
+            .map(ActionRequest::from_id)
+            .find(|action| obs.is_legal(action))
+            .unwrap_or(discard)
+    }
+}
+
+impl ConventionBot {
+    // a clue whose newly touched cards are all playable and not touched twice, to the next players
+    // first, value clues before color clues
+    fn clue(&self, obs: &Observation) -> Option<ActionRequest> {
+        if obs.clues() == 0 {
+            return None;
+        }
+        for offset in 1..obs.players() {
+            let target = (obs.player() + offset) % obs.players();
+            let clues = Value::all()
+                .into_iter()
+                .map(|value| ActionRequest::ValueClue { target, value })
+                .chain(
+                    obs.variant()
+                        .colors()
+                        .into_iter()
+                        .map(|color| ActionRequest::ColorClue { target, color }),
+                );
+            for clue in clues {
+                if obs.is_legal(&clue) && only_playable(obs, &clue) {
+                    return Some(clue);
+                }
+            }
+        }
+        None
+    }
+}
+
+fn is_clued(knowledge: &Knowledge) -> bool {
+    knowledge.value.is_some() || knowledge.color.is_some()
+}
+
+fn only_playable(obs: &Observation, clue: &ActionRequest) -> bool {
+    let target = match *clue {
+        ActionRequest::ValueClue { target, .. } | ActionRequest::ColorClue { target, .. } => target,
+        _ => return false,
+    };
+    let touches = |card: &Card| match *clue {
+        ActionRequest::ValueClue { value, .. } => card.value() == value,
+        ActionRequest::ColorClue { color, .. } => obs.variant().touches(color, card),
+        _ => false,
+    };
+    let hand = obs.hand(target).unwrap();
+    let knowledge = obs.knowledge(target);
+    let touched: Vec<&Card> = (0..hand.len())
+        .filter(|&i| touches(&hand[i]) && !is_clued(&knowledge[i]))
+        .map(|i| &hand[i])
+        .collect();
+    !touched.is_empty()
+        && touched.iter().enumerate().all(|(i, card)| {
+            obs.table()[obs.variant().suit_index(card.color())] == card.value().index()
+                && !touched[..i].contains(card)
+                && !clued_elsewhere(obs, card)
+        })
+}
+
+fn clued_elsewhere(obs: &Observation, card: &Card) -> bool {
+    (0..obs.players()).any(|p| match obs.hand(p) {
+        Some(hand) => hand
+            .iter()
+            .zip(obs.knowledge(p))
+            .any(|(c, k)| c == card && is_clued(k)),
+        None => false,
+    })
+}
+
+pub fn names() -> Vec<&'static str> {
+    vec![
+        "random",
+        "cheat",
+        "convention",
+        "hat",
+        "hgroup",
+        "rollout",
+        "mcts",
+    ]
+}
+
+// `seed` only matters to the random and search agents; "rollout:<agent>" is the rollout agent
+// with `<agent>` as the model of its teammates, e.g. "rollout:random", "mcts:<agent>" the same for
+// the tree search, and "mlp:<path>" plays the network of a `.npz` file (see `mlp`), "mlp8:<path>"
+// its int8 quantization; "hgroup" follows the h-group beginner conventions, "hgroup:<conventions>"
+// only those named, e.g. "hgroup:play_clues"
+pub fn from_name(name: &str, seed: u64) -> Option<Box<dyn Agent>> {
+    with_encoder(name, seed, Encoder::V1)
+}
+
+// whether the agent of `name` loads a file, "mlp:<path>", "mlp8:<path>" or a search agent with
+// one of them as the model of its teammates
+pub fn reads_files(name: &str) -> bool {
+    let model = name
+        .strip_prefix("rollout:")
+        .or_else(|| name.strip_prefix("mcts:"))
+        .unwrap_or(name);
+    model.starts_with("mlp:") || model.starts_with("mlp8:")
+}
+
+// `from_name`, the "mlp:<path>" and "mlp8:<path>" agents taking the observation of `encoder`
+pub fn with_encoder(name: &str, seed: u64, encoder: Encoder) -> Option<Box<dyn Agent>> {
+    match name {
+        "random" => Some(Box::new(RandomBot::new(seed))),
+        "cheat" => Some(Box::new(CheatBot)),
+        "convention" => Some(Box::new(ConventionBot)),
+        "hat" => Some(Box::new(HatBot)),
+        "hgroup" => Some(Box::new(HGroupBot::new(Conventions::level_one()))),
+        "rollout" => Some(Box::new(RolloutBot::new(16, None, seed))),
+        "mcts" => Some(Box::new(MctsBot::new(200, seed))),
+        _ if name.starts_with("mlp:") => {
+            let mlp = mlp::cached(&name["mlp:".len()..], false).ok()?;
+            Some(Box::new(MlpBot { mlp, encoder }))
+        }
+        _ if name.starts_with("mlp8:") => {
+            let mlp = mlp::cached(&name["mlp8:".len()..], true).ok()?;
+            Some(Box::new(MlpBot { mlp, encoder }))
+        }
+        _ if name.starts_with("hgroup:") => {
+            let conventions = Conventions::from_names(&name["hgroup:".len()..])?;
+            Some(Box::new(HGroupBot::new(conventions)))
+        }
+        _ if name.starts_with("mcts:") => {
+            let teammates = &name["mcts:".len()..];
+            if !search::is_teammate_model(teammates) {
+                return None;
+            }
+            let mut bot = MctsBot::new(200, seed);
+            bot.teammates = teammates.to_string();
+            Some(Box::new(bot))
+        }
+        _ => {
+            let teammates = name.strip_prefix("rollout:")?;
+            if !search::is_teammate_model(teammates) {
+                return None;
+            }
+            let mut bot = RolloutBot::new(16, None, seed);
+            bot.teammates = teammates.to_string();
+            Some(Box::new(bot))
+        }
+    }
+}
@@ -0,0 +1,49 @@
+// the board as arrays indexed like the beliefs, suits in the order of the variant and values 1 to 5
+use crate::slots::SlotOrder;
+use crate::state::{State, MAXCARDS};
+use ndarray::{Array1, Array2, Array3};
+
+impl State {
+    // number of discarded copies of every card, shape (suits, 5)
+    pub fn discard_matrix(&self) -> Array2<i32> {
+        let mut x = Array2::zeros((self.variant().suits().len(), 5));
+        for card in self.discard() {
+            x[self.index(card)] += 1;
+        }
+        x
+    }
+
+    // highest value played in every suit, shape (suits,)
+    pub fn fireworks(&self) -> Array1<i32> {
+        self.table().iter().map(|&x| x as i32).collect()
+    }
+
+    // (suit, value - 1) of every card in the hands in the slot order `slots`, -1 for the empty
+    // slots, shape (players, MAXCARDS, 2)
+    pub fn hands_matrix(&self, slots: SlotOrder) -> Array3<i32> {
+        let slots = slots.for_state(self);
+        let mut x = Array3::from_elem((self.players().len(), MAXCARDS, 2), -1);
+        for (player, hand) in self.players().iter().enumerate() {
+            for (position, card) in hand.iter().enumerate() {
+                let [suit, value] = self.index(card);
+                let slot = slots.convert(position, hand.len());
+                x[[player, slot, 0]] = suit as i32;
+                x[[player, slot, 1]] = value as i32;
+            }
+        }
+        x
+    }
+
+    // `Card::index` of every card in the hands in the slot order `slots`, 255 for the empty slots,
+    // shape (players, MAXCARDS)
+    pub fn hands_codes(&self, slots: SlotOrder) -> Array2<u8> {
+        let slots = slots.for_state(self);
+        let mut x = Array2::from_elem((self.players().len(), MAXCARDS), u8::MAX);
+        for (player, hand) in self.players().iter().enumerate() {
+            for (position, card) in hand.iter().enumerate() {
+                x[[player, slots.convert(position, hand.len())]] = card.index();
+            }
+        }
+        x
+    }
+}
@@ -0,0 +1,157 @@
+// many games stepped together, in parallel with rayon: every game of a batch has its deal and its
+// own rng stream drawn from the seed of the batch, so that a batch plays the same whatever the
+// number of threads
+use crate::bench;
+use crate::rules::IllegalMoves;
+use crate::state::{ActionRequest, State, NACTIONS};
+use crate::variant::Variant;
+use ndarray::{Array1, Array2, ArrayViewMut1};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use rayon::prelude::*;
+
+pub struct Batch {
+    games: Vec<Game>,
+    // draws the deals and the streams, then the deals of the games started again
+    master: StdRng,
+}
+
+struct Game {
+    state: State,
+    rng: StdRng,
+}
+
+impl Batch {
+    pub fn new(games: usize, players: usize, variant: &Variant, seed: u64) -> Batch {
+        let mut master = StdRng::seed_from_u64(seed);
+        let games = (0..games)
+            .map(|_| Game {
+                state: State::with_variant(players, variant.clone(), Some(master.gen())),
+                rng: StdRng::seed_from_u64(master.gen()),
+            })
+            .collect();
+        Batch { games, master }
+    }
+
+    pub fn len(&self) -> usize {
+        self.games.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.games.is_empty()
+    }
+
+    pub fn states(&self) -> impl Iterator<Item = &State> {
+        self.games.iter().map(|game| &game.state)
+    }
+
+    pub fn done(&self) -> Vec<bool> {
+        self.states().map(State::gameover).collect()
+    }
+
+    pub fn scores(&self) -> Vec<usize> {
+        self.states().map(State::final_score).collect()
+    }
+
+    // one row per game, that of a finished game is its last observation
+    pub fn observations(&mut self) -> Array2<f32> {
+        let width = match self.games.first() {
+            Some(game) => game.state.encode().len(),
+            None => return Array2::zeros((0, 0)),
+        };
+        let mut rows = Array2::zeros((self.games.len(), width));
+        rows.as_slice_mut()
+            .unwrap()
+            .par_chunks_mut(width)
+            .zip(self.games.par_iter_mut())
+            .for_each(|(row, game)| game.state.encode_into(&mut ArrayViewMut1::from(row)));
+        rows
+    }
+
+    // the legal actions of every game, none once it is over
+    pub fn legal_masks(&mut self) -> Array2<f32> {
+        let mut rows = Array2::zeros((self.games.len(), NACTIONS));
+        rows.as_slice_mut()
+            .unwrap()
+            .par_chunks_mut(NACTIONS)
+            .zip(self.games.par_iter_mut())
+            .for_each(|(row, game)| ArrayViewMut1::from(row).assign(&game.state.legal_mask()));
+        rows
+    }
+
+    // plays one action id in every game still running, the actions of finished games are ignored;
+    // returns the score increase of every game, or the first game whose action is illegal, in
+    // which case no game is stepped
+    pub fn step(&mut self, actions: &[usize]) -> Result<Array1<f32>, (usize, IllegalMoves)> {
+        assert_eq!(actions.len(), self.games.len());
+        let illegal = self
+            .games
+            .par_iter_mut()
+            .zip(actions)
+            .enumerate()
+            .filter(|(_, (game, _))| !game.state.gameover())
+            .find_map_first(|(i, (game, &action))| {
+                if action >= NACTIONS {
+                    return Some((i, IllegalMoves::InvalidActionId { id: action }));
+                }
+                game.state
+                    .check(&ActionRequest::from_id(action))
+                    .err()
+                    .map(|err| (i, err))
+            });
+        if let Some(illegal) = illegal {
+            return Err(illegal);
+        }
+        let rewards = self
+            .games
+            .par_iter_mut()
+            .zip(actions)
+            .map(|(game, &action)| {
+                if game.state.gameover() {
+                    return 0.0;
+                }
+                let score = game.state.score();
+                game.state.apply(&ActionRequest::from_id(action)).unwrap();
+                (game.state.score() - score) as f32
+            })
+            .collect::<Vec<f32>>();
+        Ok(Array1::from(rewards))
+    }
+
+    // deals a new game in place of every finished one, its next deal drawn from the seed of the
+    // batch, and returns their indices; the games keep their buffers (`State::redeal`), so that
+    // running many episodes does not allocate
+    pub fn reset_done(&mut self) -> Vec<usize> {
+        let done: Vec<usize> = (0..self.games.len())
+            .filter(|&i| self.games[i].state.gameover())
+            .collect();
+        let mut seeds = vec![None; self.games.len()];
+        for &i in &done {
+            seeds[i] = Some(self.master.gen());
+        }
+        self.games
+            .par_iter_mut()
+            .zip(seeds)
+            .for_each(|(game, seed)| {
+                if let Some(seed) = seed {
+                    game.state.redeal(seed);
+                }
+            });
+        done
+    }
+
+    // keeps at most the last `capacity` actions of every game, `State::set_history_capacity`, so
+    // that the memory of a batch stays flat however many episodes it runs
+    pub fn set_history_capacity(&mut self, capacity: Option<usize>) {
+        for game in &mut self.games {
+            game.state.set_history_capacity(capacity);
+        }
+    }
+
+    // plays uniformly random legal actions to the end of every game, each from its own stream
+    pub fn random_rollouts(&mut self) {
+        self.games
+            .par_iter_mut()
+            .for_each(|game| bench::random_moves(&mut game.state, usize::MAX, &mut game.rng));
+    }
+}
@@ -0,0 +1,297 @@
+use crate::agents;
+use crate::endgame::{self, Deal};
+use crate::state::{Card, State, Value, MAXCARDS};
+use ndarray::{Array2, Array3, Array4, Axis};
+use rand::seq::SliceRandom;
+use rand::Rng;
+
+// draws of `State::sample_deal` before giving up, each restarted at its first dead end
+pub const SAMPLE_ATTEMPTS: usize = 1000;
+
+impl State {
+    // for each slot of the hand of `player`, the probability of every (suit, value) given the
+    // clues received and the cards visible to that player; empty slots are all zero
+    pub fn beliefs(&self, player: usize) -> Array3<f32> {
+        self.slot_beliefs(player, &self.unseen_by(player))
+    }
+
+    // for each slot of the hand of `player`, 1 for every (suit, value) that agrees with the clues,
+    // those that touched the card and those that did not, whatever the card counts; empty slots
+    // are all zero, shape (slots, suits, 5)
+    pub fn constraints(&self, player: usize) -> Array3<f32> {
+        let suits = self.variant().suits();
+        let mut x = Array3::zeros((MAXCARDS, suits.len(), 5));
+        for (slot, knowledge) in self.knowledge()[player].iter().enumerate() {
+            for (s, &suit) in suits.iter().enumerate() {
+                for v in 0..5 {
+                    if knowledge.allows(&Card::new(Value::new(v), suit), self.variant()) {
+                        x[[slot, s, v]] = 1.0;
+                    }
+                }
+            }
+        }
+        x
+    }
+
+    // card counting: the share of the copies of each (suit, value) that `player` cannot see, in
+    // the deck or in their own hand, shape (suits, 5)
+    pub fn unseen_counts(&self, player: usize) -> Array2<f32> {
+        let mut counts = self.unseen_by(player);
+        for (v, mut column) in counts.axis_iter_mut(Axis(1)).enumerate() {
+            column /= Value::new(v).copies() as f32;
+        }
+        counts
+    }
+
+    // beliefs of every player using only the clues, the discard pile and the table,
+    // shape (players, slots, suits, 5)
+    pub fn public_beliefs(&self) -> Array4<f32> {
+        let counts = self.unseen();
+        let nsuits = self.variant().suits().len();
+        let mut x = Array4::zeros((self.players().len(), MAXCARDS, nsuits, 5));
+        for (player, mut view) in x.axis_iter_mut(Axis(0)).enumerate() {
+            view.assign(&self.slot_beliefs(player, &counts));
+        }
+        x
+    }
+
+    // for each slot of the hand of `player`, the probability under the public beliefs that the
+    // card is playable, dead (it can no longer be played) or critical (the last copy of a card
+    // still needed), shape (slots, 3); empty slots are all zero
+    pub fn playability(&self, player: usize) -> Array2<f32> {
+        let beliefs = self.slot_beliefs(player, &self.unseen());
+        let suits = self.variant().suits();
+        let mut discarded = Array2::<usize>::zeros((suits.len(), 5));
+        for card in self.discard() {
+            discarded[self.index(card)] += 1;
+        }
+        let max_values = self.max_values();
+        let mut x = Array2::zeros((MAXCARDS, 3));
+        for s in 0..suits.len() {
+            let table = self.table()[s];
+            // the first value of the suit of which every copy is gone
+            let lost = max_values[s];
+            for v in 0..5 {
+                let dead = v < table || v >= lost;
+                let critical = !dead && Value::new(v).copies() - discarded[[s, v]] == 1;
+                let features = [v == table && !dead, dead, critical];
+                for slot in 0..MAXCARDS {
+                    for (f, &feature) in features.iter().enumerate() {
+                        if feature {
+                            x[[slot, f]] += beliefs[[slot, s, v]];
+                        }
+                    }
+                }
+            }
+        }
+        x
+    }
+
+    // for each teammate of `player`, in seat order after them, the number of playable cards in
+    // their hand, whether the card on their chop (the oldest unclued card) is critical, and the
+    // number of cards whose color and value were both clued, shape (players - 1, 3)
+    pub fn teammate_summary(&self, player: usize) -> Array2<f32> {
+        let n = self.players().len();
+        let mut x = Array2::zeros((n - 1, 3));
+        for offset in 1..n {
+            let teammate = (player + offset) % n;
+            let hand = &self.players()[teammate];
+            let knowledge = &self.knowledge()[teammate];
+            let chop = self.chop(teammate);
+            x[[offset - 1, 0]] =
+                hand.iter().filter(|c| agents::is_playable(self, c)).count() as f32;
+            x[[offset - 1, 1]] =
+                chop.is_some_and(|i| agents::is_critical(self, &hand[i])) as u8 as f32;
+            x[[offset - 1, 2]] = knowledge
+                .iter()
+                .filter(|k| k.value.is_some() && k.color.is_some())
+                .count() as f32;
+        }
+        x
+    }
+
+    // a hand for `player` drawn from the cards they cannot see and agreeing with their clues
+    pub fn sample_hidden<R: Rng>(&self, player: usize, rng: &mut R) -> Option<Vec<Card>> {
+        self.sample_deal(player, rng).map(|x| x.0)
+    }
+
+    // the same with the deck made of the remaining unseen cards in random order; the true hand
+    // agrees with everything, so a draw that runs into a dead end is restarted, None after
+    // `SAMPLE_ATTEMPTS` dead ends, the knowledge being too tight for rejection sampling
+    pub fn sample_deal<R: Rng>(&self, player: usize, rng: &mut R) -> Option<Deal> {
+        (0..SAMPLE_ATTEMPTS).find_map(|_| self.try_sample_deal(player, rng))
+    }
+
+    // the clued slots are filled first, they are the most constrained
+    fn try_sample_deal<R: Rng>(&self, player: usize, rng: &mut R) -> Option<Deal> {
+        let mut pool: Vec<Card> = endgame::unseen(self, player)
+            .into_iter()
+            .flat_map(|(card, count)| vec![card; count])
+            .collect();
+        let knowledge = &self.knowledge()[player];
+        let mut order: Vec<usize> = (0..knowledge.len()).collect();
+        order.sort_by_key(|&i| {
+            knowledge[i].value.is_none() as usize + knowledge[i].color.is_none() as usize
+        });
+        let mut hand = vec![None; knowledge.len()];
+        for position in order {
+            let choices: Vec<usize> = (0..pool.len())
+                .filter(|&i| endgame::consistent(self, player, position, &pool[i]))
+                .collect();
+            let &i = choices.choose(rng)?;
+            hand[position] = Some(pool.swap_remove(i));
+        }
+        pool.shuffle(rng);
+        Some((hand.into_iter().map(Option::unwrap).collect(), pool))
+    }
+
+    fn slot_beliefs(&self, player: usize, counts: &Array2<f32>) -> Array3<f32> {
+        let suits = self.variant().suits();
+        let mut x = Array3::zeros((MAXCARDS, suits.len(), 5));
+        for (slot, knowledge) in self.knowledge()[player].iter().enumerate() {
+            let mut total = 0.0;
+            for (s, &suit) in suits.iter().enumerate() {
+                for v in 0..5 {
+                    let card = Card::new(Value::new(v), suit);
+                    if knowledge.allows(&card, self.variant()) {
+                        x[[slot, s, v]] = counts[[s, v]];
+                        total += counts[[s, v]];
+                    }
+                }
+            }
+            if total > 0.0 {
+                x.slice_mut(ndarray::s![slot, .., ..])
+                    .mapv_inplace(|p| p / total);
+            }
+        }
+        x
+    }
+
+    // copies of each (suit, value) that are neither discarded nor on the table
+    fn unseen(&self) -> Array2<f32> {
+        let suits = self.variant().suits();
+        let mut counts = Array2::zeros((suits.len(), 5));
+        for s in 0..suits.len() {
+            for v in 0..5 {
+                counts[[s, v]] = Value::new(v).copies() as f32;
+                if v < self.table()[s] {
+                    counts[[s, v]] -= 1.0;
+                }
+            }
+        }
+        for card in self.discard() {
+            counts[self.index(card)] -= 1.0;
+        }
+        counts
+    }
+
+    // the same without the cards in the hands of the others
+    fn unseen_by(&self, player: usize) -> Array2<f32> {
+        let mut counts = self.unseen();
+        for (i, cards) in self.players().iter().enumerate() {
+            if i != player {
+                for card in cards {
+                    counts[self.index(card)] -= 1.0;
+                }
+            }
+        }
+        counts
+    }
+
+    // (suit, value - 1) of a card
+    pub fn index(&self, card: &Card) -> [usize; 2] {
+        [
+            self.variant().suit_index(card.color()),
+            card.value().index(),
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::ActionRequest;
+    use crate::variant::Variant;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    // the deck in order: 1r 1r 1r 2r 2r for the first player, 3r 3r 4r 4r 5r for the second
+    fn dealt() -> State {
+        State::from_deck(2, Variant::standard().deck())
+    }
+
+    // the 5 clued to the second player, then the 1s to the first
+    fn clued() -> State {
+        let mut state = dealt();
+        let clue = |target, value| ActionRequest::ValueClue {
+            target,
+            value: Value::new(value),
+        };
+        state.apply(&clue(1, 4)).unwrap();
+        state.apply(&clue(0, 0)).unwrap();
+        state
+    }
+
+    #[test]
+    fn beliefs_count_the_cards_seen() {
+        let state = dealt();
+        let beliefs = state.beliefs(0);
+        // 45 cards unseen by the first player, the red 3s are all in the other hand
+        assert_eq!(beliefs[[0, 0, 0]], 3.0 / 45.0);
+        assert_eq!(beliefs[[0, 0, 2]], 0.0);
+        assert_eq!(beliefs[[0, 1, 0]], 3.0 / 45.0);
+        for slot in 0..MAXCARDS {
+            let total: f32 = beliefs.slice(ndarray::s![slot, .., ..]).sum();
+            assert!((total - 1.0).abs() < 1e-5);
+        }
+
+        // the 1s clued: one of the 15 ones, red as likely as the others
+        let beliefs = clued().beliefs(0);
+        assert!((beliefs[[0, 0, 0]] - 0.2).abs() < 1e-6);
+        assert_eq!(beliefs[[0, 0, 1]], 0.0);
+        assert_eq!(beliefs[[3, 0, 0]], 0.0);
+    }
+
+    #[test]
+    fn constraints_follow_the_clues() {
+        let state = clued();
+        let first = state.constraints(0);
+        // a 1 of any suit, anything but a 1
+        assert_eq!(first.slice(ndarray::s![0, .., ..]).sum(), 5.0);
+        assert_eq!(first.slice(ndarray::s![0, .., 0]).sum(), 5.0);
+        assert_eq!(first.slice(ndarray::s![3, .., ..]).sum(), 20.0);
+        assert_eq!(first.slice(ndarray::s![3, .., 0]).sum(), 0.0);
+        let second = state.constraints(1);
+        assert_eq!(second.slice(ndarray::s![4, .., 4]).sum(), 5.0);
+        assert_eq!(second.slice(ndarray::s![4, .., ..]).sum(), 5.0);
+        // whatever the card counts: the red 3s are all seen but still allowed
+        assert_eq!(dealt().constraints(0)[[0, 0, 2]], 1.0);
+    }
+
+    #[test]
+    fn teammate_summary_of_a_known_deal() {
+        let state = dealt();
+        // the second player holds no playable card and the 5 on their chop
+        assert_eq!(
+            state.teammate_summary(0).row(0).to_vec(),
+            vec![0.0, 1.0, 0.0]
+        );
+        // the first holds three playable red 1s, a 2 on their chop
+        assert_eq!(
+            state.teammate_summary(1).row(0).to_vec(),
+            vec![3.0, 0.0, 0.0]
+        );
+    }
+
+    #[test]
+    fn sampled_deals_agree_with_the_clues() {
+        let state = clued();
+        let mut rng = StdRng::seed_from_u64(0);
+        for _ in 0..20 {
+            let (hand, deck) = state.sample_deal(0, &mut rng).unwrap();
+            assert!(hand[..3].iter().all(|card| card.value() == Value::new(0)));
+            assert!(hand[3..].iter().all(|card| card.value() != Value::new(0)));
+            assert_eq!(deck.len(), state.deck().len());
+        }
+    }
+}
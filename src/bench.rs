@@ -0,0 +1,51 @@
+// throughput of the engine alone, to compare versions (see also `benches/core.rs`)
+use crate::state::{ActionRequest, State, NACTIONS};
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+use std::time::Instant;
+
+pub struct Throughput {
+    pub games: usize,
+    pub actions: usize,
+    pub seconds: f64,
+}
+
+// plays up to `turns` uniformly random legal actions
+pub fn random_moves(state: &mut State, turns: usize, rng: &mut StdRng) {
+    let mut legal = Vec::with_capacity(NACTIONS);
+    for _ in 0..turns {
+        if state.gameover() {
+            return;
+        }
+        legal.clear();
+        legal.extend(
+            (0..NACTIONS)
+                .map(ActionRequest::from_id)
+                .filter(|action| state.check(action).is_ok()),
+        );
+        match legal.choose(rng) {
+            Some(action) => state.apply(action).unwrap(),
+            None => return,
+        }
+    }
+}
+
+pub fn random_game(players: usize, rng: &mut StdRng) -> State {
+    let mut state = State::with_seed(players, rng.gen());
+    random_moves(&mut state, usize::MAX, rng);
+    state
+}
+
+pub fn random_games(games: usize, players: usize, seed: u64) -> Throughput {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let start = Instant::now();
+    let actions = (0..games)
+        .map(|_| *random_game(players, &mut rng).turn())
+        .sum();
+    Throughput {
+        games,
+        actions,
+        seconds: start.elapsed().as_secs_f64(),
+    }
+}
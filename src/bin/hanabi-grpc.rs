@@ -0,0 +1,16 @@
+// the environment over grpc, see `proto/hanabi.proto`
+//
+//     hanabi-grpc [address]  # 127.0.0.1:50051 by default, or unix:path for a unix socket
+use std::env;
+use std::process;
+
+fn main() {
+    let address = env::args()
+        .nth(1)
+        .unwrap_or_else(|| "127.0.0.1:50051".to_string());
+    println!("serving on {}", address);
+    if let Err(err) = hanabi::grpc::serve(&address) {
+        eprintln!("{}", err);
+        process::exit(1);
+    }
+}
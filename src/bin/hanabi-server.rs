@@ -0,0 +1,78 @@
+// multiplayer games over websocket, see `src/rooms.rs`
+//
+//     hanabi-server [address] [games]  # 127.0.0.1:9001 by default, finished games saved in `games`
+//
+// with `--collect <dir>` the games are also exported anonymized to `dir` for imitation learning,
+// `--consent <file>` is the banner the players accept before joining, `--tag key=value` (repeated)
+// labels every game and `--names keep|seats|remove` and `--keep_notes` set the anonymization
+use hanabi::replay::{Anonymization, Names};
+use hanabi::rooms::Collection;
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use std::process;
+
+fn fail(message: String) -> ! {
+    eprintln!("{}", message);
+    process::exit(1);
+}
+
+fn main() {
+    let mut positional = Vec::new();
+    let mut dir = None;
+    let mut consent = None;
+    let mut tags = BTreeMap::new();
+    let mut anonymization = Anonymization::default();
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let mut value = || {
+            args.next()
+                .unwrap_or_else(|| fail(format!("{} expects a value", arg)))
+        };
+        match arg.as_str() {
+            "--collect" => dir = Some(PathBuf::from(value())),
+            "--consent" => {
+                let path = value();
+                let text = fs::read_to_string(&path)
+                    .unwrap_or_else(|err| fail(format!("cannot read {}: {}", path, err)));
+                consent = Some(text);
+            }
+            "--tag" => {
+                let tag = value();
+                match tag.split_once('=') {
+                    Some((key, value)) => tags.insert(key.to_string(), value.to_string()),
+                    None => fail(format!("tag {:?} is not key=value", tag)),
+                };
+            }
+            "--names" => {
+                let names = value();
+                anonymization.names = Names::from_name(&names)
+                    .unwrap_or_else(|| fail(format!("unknown names {:?}", names)));
+            }
+            "--keep_notes" => anonymization.keep_notes = true,
+            _ => positional.push(arg),
+        }
+    }
+    let address = positional
+        .first()
+        .cloned()
+        .unwrap_or_else(|| "127.0.0.1:9001".to_string());
+    let games = positional.get(1).map(PathBuf::from);
+    let collection = match dir {
+        Some(dir) => Some(Collection {
+            consent,
+            tags,
+            anonymization,
+            dir,
+        }),
+        None if consent.is_some() || !tags.is_empty() => {
+            fail("--consent and --tag need --collect".to_string())
+        }
+        None => None,
+    };
+    println!("serving on ws://{}", address);
+    if let Err(err) = hanabi::rooms::serve(&address, games, collection) {
+        fail(format!("{}", err));
+    }
+}
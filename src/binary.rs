@@ -0,0 +1,223 @@
+// compact binary log of a game, to archive self-play at a fraction of the size of a json replay
+//
+//     version          u8, 1
+//     players          u8
+//     variant          u8, index in `Variant::names()`
+//     modifiers        u8 count, then a u8 index in `modifier::names()` each
+//     deal             u8 0 then the deck, a u8 count and `color * 5 + value` per card,
+//                      or u8 1 then the seed, u64 little endian
+//     actions          u16 count little endian, then a u8 action id each (engine slot order)
+//
+// a game dealt from a seed takes 15 bytes plus one per action; records are self-delimiting and
+// can be concatenated, see `State::read_bytes`
+use crate::modifier;
+use crate::replay::ReplayError;
+use crate::state::{ActionRequest, Card, Color, State, Value, NACTIONS};
+use crate::variant::Variant;
+use std::convert::TryInto;
+
+const VERSION: u8 = 1;
+
+fn invalid(message: &str) -> ReplayError {
+    ReplayError::Invalid(format!("binary game: {}", message))
+}
+
+// reads the bytes of a record one field at a time
+struct Reader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, n: usize) -> Result<&'a [u8], ReplayError> {
+        let bytes = self
+            .bytes
+            .get(self.position..self.position + n)
+            .ok_or_else(|| invalid("truncated"))?;
+        self.position += n;
+        Ok(bytes)
+    }
+
+    fn u8(&mut self) -> Result<u8, ReplayError> {
+        Ok(self.take(1)?[0])
+    }
+}
+
+impl State {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = vec![VERSION, self.players().len() as u8];
+        let variant = Variant::names()
+            .iter()
+            .position(|&name| name == self.variant().name())
+            .unwrap();
+        bytes.push(variant as u8);
+        let modifiers = self.modifiers().names();
+        bytes.push(modifiers.len() as u8);
+        for name in &modifiers {
+            let i = modifier::names().iter().position(|x| x == name).unwrap();
+            bytes.push(i as u8);
+        }
+        match self.seed() {
+            Some(seed) => {
+                bytes.push(1);
+                bytes.extend(&seed.to_le_bytes());
+            }
+            None => {
+                bytes.push(0);
+                bytes.push(self.initial_deck().len() as u8);
+                for card in self.initial_deck() {
+                    bytes.push((card.color().index() * 5 + card.value().index()) as u8);
+                }
+            }
+        }
+        let actions = self.requests();
+        bytes.extend(&(actions.len() as u16).to_le_bytes());
+        bytes.extend(actions.iter().map(|x| x.id() as u8));
+        bytes
+    }
+
+    // the game of `to_bytes`, its actions played again
+    pub fn from_bytes(bytes: &[u8]) -> Result<State, ReplayError> {
+        let (state, n) = State::read_bytes(bytes)?;
+        if n != bytes.len() {
+            return Err(invalid("trailing bytes"));
+        }
+        Ok(state)
+    }
+
+    // the first game of `bytes` and the length of its record
+    pub fn read_bytes(bytes: &[u8]) -> Result<(State, usize), ReplayError> {
+        let mut reader = Reader { bytes, position: 0 };
+        if reader.u8()? != VERSION {
+            return Err(invalid("unknown version"));
+        }
+        let players = reader.u8()? as usize;
+        if !(2..=5).contains(&players) {
+            return Err(invalid("unsupported player count"));
+        }
+        let variant = Variant::names()
+            .get(reader.u8()? as usize)
+            .and_then(|name| Variant::from_name(name))
+            .ok_or_else(|| invalid("unknown variant"))?;
+        let mut modifiers = Vec::new();
+        for _ in 0..reader.u8()? {
+            let name = modifier::names()
+                .get(reader.u8()? as usize)
+                .copied()
+                .ok_or_else(|| invalid("unknown rule modifier"))?;
+            modifiers.push(modifier::from_name(name).unwrap());
+        }
+        let state = match reader.u8()? {
+            1 => {
+                let seed = u64::from_le_bytes(reader.take(8)?.try_into().unwrap());
+                State::with_variant(players, variant, Some(seed))
+            }
+            0 => {
+                let n = reader.u8()? as usize;
+                let mut deck = Vec::new();
+                for &x in reader.take(n)? {
+                    let (color, value) = (x as usize / 5, x as usize % 5);
+                    if !variant.suits().iter().any(|suit| suit.index() == color) {
+                        return Err(invalid("card out of the variant"));
+                    }
+                    deck.push(Card::new(Value::new(value), Color::new(color)));
+                }
+                let mut counts = variant.deck();
+                for card in &deck {
+                    match counts.iter().position(|x| x == card) {
+                        Some(i) => counts.swap_remove(i),
+                        None => return Err(invalid("too many copies of a card")),
+                    };
+                }
+                if !counts.is_empty() {
+                    return Err(invalid("incomplete deck"));
+                }
+                State::from_deck_variant(players, variant, deck)
+            }
+            _ => return Err(invalid("unknown deal")),
+        };
+        let mut state = state.modified(modifiers);
+        let n = u16::from_le_bytes(reader.take(2)?.try_into().unwrap()) as usize;
+        for (turn, &id) in reader.take(n)?.iter().enumerate() {
+            let id = id as usize;
+            if id >= NACTIONS {
+                return Err(invalid("unknown action"));
+            }
+            state
+                .apply(&ActionRequest::from_id(id))
+                .map_err(|error| ReplayError::Illegal { turn, error })?;
+        }
+        Ok((state, reader.position))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agents::{Agent, ConventionBot, Observation};
+
+    fn played(mut state: State) -> State {
+        while !state.gameover() {
+            let action = ConventionBot.act(&Observation::new(&state));
+            state.apply(&action).unwrap();
+        }
+        state
+    }
+
+    #[test]
+    fn seeded_games_round_trip() {
+        let state = played(State::with_seed(3, 7));
+        let bytes = state.to_bytes();
+        assert_eq!(bytes.len(), 15 + state.requests().len());
+        let read = State::from_bytes(&bytes).unwrap();
+        assert_eq!(read.requests(), state.requests());
+        assert_eq!(read.score(), state.score());
+        assert_eq!(read.to_bytes(), bytes);
+    }
+
+    #[test]
+    fn dealt_games_with_modifiers_round_trip() {
+        let variant = Variant::from_name("six_suits").unwrap();
+        let mut deck = variant.deck();
+        deck.reverse();
+        let modifiers = modifier::resolve(&["draw_last"]).unwrap();
+        let state = played(State::from_deck_variant(2, variant, deck).modified(modifiers));
+        let read = State::from_bytes(&state.to_bytes()).unwrap();
+        assert_eq!(read.variant(), state.variant());
+        assert_eq!(read.modifiers().names(), vec!["draw_last"]);
+        assert_eq!(read.initial_deck(), state.initial_deck());
+        assert_eq!(read.requests(), state.requests());
+        assert_eq!(read.score(), state.score());
+    }
+
+    #[test]
+    fn concatenated_records_are_read_one_by_one() {
+        let first = played(State::with_seed(2, 0));
+        let second = played(State::with_seed(4, 1));
+        let bytes = [first.to_bytes(), second.to_bytes()].concat();
+        let (read, n) = State::read_bytes(&bytes).unwrap();
+        assert_eq!(read.requests(), first.requests());
+        assert_eq!(
+            State::from_bytes(&bytes[n..]).unwrap().requests(),
+            second.requests()
+        );
+        assert!(State::from_bytes(&bytes).is_err());
+    }
+
+    #[test]
+    fn corrupt_records_are_errors() {
+        let bytes = played(State::with_seed(2, 0)).to_bytes();
+        assert!(State::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+        let mut version = bytes.clone();
+        version[0] = 2;
+        assert!(State::from_bytes(&version).is_err());
+        // the first player discards at the maximum of clues
+        let mut illegal = State::with_seed(2, 0).to_bytes();
+        illegal.truncate(illegal.len() - 2);
+        illegal.extend(&[1, 0, ActionRequest::Discard { position: 0 }.id() as u8]);
+        assert!(matches!(
+            State::from_bytes(&illegal),
+            Err(ReplayError::Illegal { turn: 0, .. })
+        ));
+    }
+}
@@ -0,0 +1,172 @@
+// the arguments of the python `Game` turned into engine requests, in the slot order of the caller;
+// kept out of pyo3 so that the tests comparing them with the engine run without python
+use crate::encoder::{EncoderBuilder, Feature};
+use crate::rules::IllegalMoves;
+use crate::slots::SlotOrder;
+use crate::state::{ActionRequest, Color, State, Value, NACTIONS};
+use std::convert::TryFrom;
+
+// the info of a clue as passed from python: a value from 1 to 5, a color letter, or anything else
+pub enum ClueInfo<'a> {
+    Value(usize),
+    Color(&'a str),
+    Other,
+}
+
+// None when the info is neither a value nor a color letter
+pub fn clue_request(target: usize, info: ClueInfo) -> Option<ActionRequest> {
+    match info {
+        ClueInfo::Value(value) => (1..=5).contains(&value).then(|| ActionRequest::ValueClue {
+            target,
+            value: Value::new(value - 1),
+        }),
+        ClueInfo::Color(color) => {
+            let color = Color::try_from(color.to_string()).ok()?;
+            Some(ActionRequest::ColorClue { target, color })
+        }
+        ClueInfo::Other => None,
+    }
+}
+
+// a position of the current hand in `slots` to the engine order
+pub fn position(state: &State, slots: SlotOrder, position: usize) -> usize {
+    slots
+        .for_state(state)
+        .convert(position, state.current_hand())
+}
+
+// the action of `id` in `slots`, an id out of range refused like the engine does
+pub fn step_request(
+    state: &State,
+    slots: SlotOrder,
+    id: usize,
+) -> Result<ActionRequest, IllegalMoves> {
+    if id >= NACTIONS {
+        return Err(IllegalMoves::InvalidActionId { id });
+    }
+    let id = slots.for_state(state).convert_id(id, state.current_hand());
+    Ok(ActionRequest::from_id(id))
+}
+
+// the features of the v1 observation in `slots`
+pub fn observation_builder(slots: SlotOrder) -> EncoderBuilder {
+    EncoderBuilder::new()
+        .feature(Feature::Core)
+        .feature(Feature::History)
+        .slots(slots)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::MAXCARDS;
+    use crate::variant::Variant;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    fn error(result: Result<(), IllegalMoves>) -> Option<String> {
+        result
+            .err()
+            .map(|err| format!("{} {:?}", err.kind(), err.fields().collect::<Vec<_>>()))
+    }
+
+    // the game seen through the bindings and the engine driven with the same actions
+    #[test]
+    fn steps_match_the_engine() {
+        for seed in 0..20 {
+            for &slots in &[SlotOrder::NewestFirst, SlotOrder::OldestFirst] {
+                let nplayer = 2 + seed as usize % 4;
+                let mut state = State::with_variant(nplayer, Variant::standard(), Some(seed));
+                let mut game = state.fork();
+                let encoder = observation_builder(slots).build();
+                let mut rng = StdRng::seed_from_u64(seed);
+                while !state.gameover() {
+                    let player = state.turn() % nplayer;
+                    assert_eq!(
+                        encoder.encode(&game, player),
+                        state.encode_in(player, slots)
+                    );
+
+                    // illegal ids included, both sides must refuse them the same way
+                    let id = rng.gen_range(0, NACTIONS + 2);
+                    let expected = if id < NACTIONS {
+                        let id = slots.convert_id(id, state.current_hand());
+                        error(state.apply(&ActionRequest::from_id(id)))
+                    } else {
+                        error(Err(IllegalMoves::InvalidActionId { id }))
+                    };
+                    let outcome = step_request(&game, slots, id).and_then(|x| game.apply(&x));
+                    assert_eq!(error(outcome), expected);
+                    assert_eq!(game.turn(), state.turn());
+                    assert_eq!(game.score(), state.score());
+                    assert_eq!(game.final_score(), state.final_score());
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn moves_match_the_engine() {
+        let mut state = State::with_variant(3, Variant::standard(), Some(1));
+        let mut game = state.fork();
+        let target = 1;
+        let clues = vec![
+            (ClueInfo::Value(0), None),
+            (ClueInfo::Value(6), None),
+            (ClueInfo::Color("x"), None),
+            (ClueInfo::Color("red"), None),
+            (ClueInfo::Other, None),
+            (
+                ClueInfo::Value(3),
+                Some(ActionRequest::ValueClue {
+                    target,
+                    value: Value::new(2),
+                }),
+            ),
+            (
+                ClueInfo::Color("g"),
+                Some(ActionRequest::ColorClue {
+                    target,
+                    color: Color::from_letter("g"),
+                }),
+            ),
+        ];
+        for (info, action) in clues {
+            let request = clue_request(target, info);
+            assert_eq!(request, action);
+            if let Some(action) = request {
+                assert_eq!(error(game.apply(&action)), error(state.apply(&action)));
+            }
+        }
+        // to the player to move, out of range
+        let player = state.turn() % 3;
+        let action = clue_request(player, ClueInfo::Value(1)).unwrap();
+        assert_eq!(error(game.apply(&action)), error(state.apply(&action)));
+        // the bindings in the oldest first order, their position 0 is the last of the engine
+        let hand = state.current_hand();
+        let at = |x| position(&game, SlotOrder::OldestFirst, x);
+        let requests = [
+            (
+                ActionRequest::Play {
+                    position: at(MAXCARDS),
+                },
+                ActionRequest::Play { position: MAXCARDS },
+            ),
+            (
+                ActionRequest::Discard { position: at(0) },
+                ActionRequest::Discard { position: hand - 1 },
+            ),
+            (
+                ActionRequest::Play { position: at(2) },
+                ActionRequest::Play { position: hand - 3 },
+            ),
+        ];
+        for (action, engine) in requests.iter() {
+            assert_eq!(error(game.apply(action)), error(state.apply(engine)));
+        }
+        let text =
+            |x: &State| -> Vec<String> { x.history().iter().map(|x| x.to_string()).collect() };
+        assert_eq!(text(&game), text(&state));
+        assert_eq!(game.state_hash(), state.state_hash());
+    }
+}
@@ -0,0 +1,58 @@
+use crate::agents;
+use crate::encoder::Encoder;
+use crate::modifier;
+use crate::variant::Variant;
+
+// what the installed build can do, for tools that adapt to it
+pub struct BuildInfo {
+    pub version: &'static str,
+    pub git_hash: Option<&'static str>,
+    // the enabled cargo features
+    pub features: Vec<&'static str>,
+    pub encoders: Vec<&'static str>,
+    pub variants: Vec<&'static str>,
+    pub modifiers: Vec<&'static str>,
+    pub agents: Vec<&'static str>,
+}
+
+pub fn build_info() -> BuildInfo {
+    BuildInfo {
+        version: env!("CARGO_PKG_VERSION"),
+        git_hash: option_env!("HANABI_GIT_HASH"),
+        features: features(),
+        encoders: Encoder::names(),
+        variants: Variant::names(),
+        modifiers: modifier::names(),
+        agents: agents::names(),
+    }
+}
+
+// one entry per optional cargo feature of the crate
+fn features() -> Vec<&'static str> {
+    let mut features = Vec::new();
+    if cfg!(feature = "python") {
+        features.push("python");
+    }
+    if cfg!(feature = "leaderboard") {
+        features.push("leaderboard");
+    }
+    if cfg!(feature = "capi") {
+        features.push("capi");
+    }
+    if cfg!(feature = "grpc") {
+        features.push("grpc");
+    }
+    if cfg!(feature = "websocket") {
+        features.push("websocket");
+    }
+    if cfg!(feature = "storage") {
+        features.push("storage");
+    }
+    if cfg!(feature = "wasm") {
+        features.push("wasm");
+    }
+    if cfg!(feature = "extension-module") {
+        features.push("extension-module");
+    }
+    features
+}
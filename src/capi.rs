@@ -0,0 +1,154 @@
+// c api for frameworks embedding the engine, built with the `capi` feature; the header
+// `include/hanabi.h` is generated from this file by cbindgen at build time
+//
+// a game is an opaque pointer from `hanabi_new`, released by `hanabi_free`; every function takes
+// a pointer returned by `hanabi_new` and not freed yet, and output buffers of at least `len` floats
+#![allow(clippy::missing_safety_doc)]
+
+use crate::state::{ActionRequest, State, NACTIONS};
+use crate::variant::Variant;
+use ndarray::ArrayViewMut1;
+use std::ffi::CStr;
+use std::os::raw::c_char;
+
+/// Bumped on every incompatible change of this api.
+pub const HANABI_API_VERSION: u32 = 1;
+
+pub const HANABI_OK: i32 = 0;
+/// The action is not legal, the game is unchanged.
+pub const HANABI_ILLEGAL: i32 = 1;
+/// A pointer is null, an index is out of range or a buffer has the wrong length.
+pub const HANABI_INVALID: i32 = 2;
+
+/// A game, opaque.
+pub struct HanabiGame {
+    state: State,
+}
+
+/// A game of 2 to 5 players dealt from `seed`; `variant` is a name like "standard" (the default
+/// when null). Null if the arguments are invalid.
+#[no_mangle]
+pub unsafe extern "C" fn hanabi_new(
+    players: usize,
+    variant: *const c_char,
+    seed: u64,
+) -> *mut HanabiGame {
+    if !(2..=5).contains(&players) {
+        return std::ptr::null_mut();
+    }
+    let variant = if variant.is_null() {
+        Some(Variant::standard())
+    } else {
+        CStr::from_ptr(variant)
+            .to_str()
+            .ok()
+            .and_then(Variant::from_name)
+    };
+    match variant {
+        Some(variant) => Box::into_raw(Box::new(HanabiGame {
+            state: State::with_variant(players, variant, Some(seed)),
+        })),
+        None => std::ptr::null_mut(),
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn hanabi_free(game: *mut HanabiGame) {
+    if !game.is_null() {
+        drop(Box::from_raw(game));
+    }
+}
+
+/// Number of action ids, the length of the legal mask.
+#[no_mangle]
+pub extern "C" fn hanabi_num_actions() -> usize {
+    NACTIONS
+}
+
+/// Plays the action id of the player to move, writes the score increase to `reward` if not null.
+#[no_mangle]
+pub unsafe extern "C" fn hanabi_step(
+    game: *mut HanabiGame,
+    action: usize,
+    reward: *mut f32,
+) -> i32 {
+    let game = match game.as_mut() {
+        Some(game) => game,
+        None => return HANABI_INVALID,
+    };
+    if action >= NACTIONS {
+        return HANABI_INVALID;
+    }
+    let score = game.state.score();
+    if game.state.apply(&ActionRequest::from_id(action)).is_err() {
+        return HANABI_ILLEGAL;
+    }
+    if !reward.is_null() {
+        *reward = (game.state.score() - score) as f32;
+    }
+    HANABI_OK
+}
+
+/// Length of the observations of the game.
+#[no_mangle]
+pub unsafe extern "C" fn hanabi_encoding_size(game: *const HanabiGame) -> usize {
+    game.as_ref().map_or(0, |game| game.state.encoding_size())
+}
+
+/// Writes the observation of `player` (see `hanabi_encoding_size` for `len`).
+#[no_mangle]
+pub unsafe extern "C" fn hanabi_encode(
+    game: *const HanabiGame,
+    player: usize,
+    out: *mut f32,
+    len: usize,
+) -> i32 {
+    let game = match game.as_ref() {
+        Some(game) => game,
+        None => return HANABI_INVALID,
+    };
+    if out.is_null() || player >= game.state.players().len() || len != game.state.encoding_size() {
+        return HANABI_INVALID;
+    }
+    let mut x = ArrayViewMut1::from(std::slice::from_raw_parts_mut(out, len));
+    game.state.encode_for_into(player, &mut x);
+    HANABI_OK
+}
+
+/// Writes 1 for the legal action ids of the player to move and 0 for the others (`len` is
+/// `hanabi_num_actions()`).
+#[no_mangle]
+pub unsafe extern "C" fn hanabi_legal_mask(
+    game: *const HanabiGame,
+    out: *mut f32,
+    len: usize,
+) -> i32 {
+    let game = match game.as_ref() {
+        Some(game) => game,
+        None => return HANABI_INVALID,
+    };
+    if out.is_null() || len != NACTIONS {
+        return HANABI_INVALID;
+    }
+    let out = std::slice::from_raw_parts_mut(out, len);
+    out.copy_from_slice(game.state.legal_mask().as_slice().unwrap());
+    HANABI_OK
+}
+
+/// The player to move.
+#[no_mangle]
+pub unsafe extern "C" fn hanabi_player(game: *const HanabiGame) -> usize {
+    game.as_ref()
+        .map_or(0, |game| game.state.turn() % game.state.players().len())
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn hanabi_score(game: *const HanabiGame) -> usize {
+    game.as_ref().map_or(0, |game| game.state.score())
+}
+
+/// True once the game is over, and for a null game.
+#[no_mangle]
+pub unsafe extern "C" fn hanabi_gameover(game: *const HanabiGame) -> bool {
+    game.as_ref().is_none_or(|game| game.state.gameover())
+}
@@ -0,0 +1,161 @@
+#![allow(dead_code)]
+
+use crate::slots::SlotOrder;
+use crate::state::{ActionRequest, State, NACTIONS};
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SeatTime {
+    pub moves: usize,
+    pub used: Duration,
+    pub longest: Duration,
+    pub timeouts: usize,
+}
+
+// what happens to a move over budget: the seat gives up the game, or the move is replaced by
+// `default_action`
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OnTimeout {
+    #[default]
+    Forfeit,
+    Default,
+}
+
+impl OnTimeout {
+    pub fn from_name(name: &str) -> Option<OnTimeout> {
+        match name {
+            "forfeit" => Some(OnTimeout::Forfeit),
+            "default" => Some(OnTimeout::Default),
+            _ => None,
+        }
+    }
+}
+
+// the budgets of every seat of a game and what a timeout costs
+#[derive(Clone, Copy, Debug, Default)]
+pub struct TimeControl {
+    pub per_move: Option<Duration>,
+    pub total: Option<Duration>,
+    pub on_timeout: OnTimeout,
+}
+
+// per-seat move timing; a move slower than `per_move`, or one that exhausts `total`, counts as a timeout
+#[derive(Debug)]
+pub struct Clock {
+    per_move: Option<Duration>,
+    total: Option<Duration>,
+    seats: Vec<SeatTime>,
+    // when the move being timed was requested, None between moves
+    started: Option<Instant>,
+}
+
+impl Clock {
+    pub fn new(nplayer: usize, per_move: Option<Duration>, total: Option<Duration>) -> Clock {
+        Clock {
+            per_move,
+            total,
+            seats: vec![SeatTime::default(); nplayer],
+            started: None,
+        }
+    }
+
+    pub fn with_control(nplayer: usize, control: TimeControl) -> Clock {
+        Clock::new(nplayer, control.per_move, control.total)
+    }
+
+    // to call when the move is asked for, so that only the time of the agent is counted
+    pub fn start(&mut self) {
+        self.started = Some(Instant::now());
+    }
+
+    // returns false if the move was over budget; a move that was not started is not timed
+    pub fn stop(&mut self, seat: usize) -> bool {
+        match self.started.take() {
+            Some(started) => self.record(seat, started.elapsed()),
+            None => true,
+        }
+    }
+
+    pub fn record(&mut self, seat: usize, elapsed: Duration) -> bool {
+        let time = &mut self.seats[seat];
+        time.moves += 1;
+        time.used += elapsed;
+        time.longest = time.longest.max(elapsed);
+
+        let mut ok = true;
+        if let Some(per_move) = self.per_move {
+            ok &= elapsed <= per_move;
+        }
+        if let Some(total) = self.total {
+            ok &= time.used <= total;
+        }
+        if !ok {
+            time.timeouts += 1;
+        }
+        self.started = None;
+        ok
+    }
+
+    pub fn remaining(&self, seat: usize) -> Option<Duration> {
+        self.total
+            .map(|x| x.checked_sub(self.seats[seat].used).unwrap_or_default())
+    }
+
+    pub fn seats(&self) -> &[SeatTime] {
+        &self.seats
+    }
+}
+
+// the move played for a seat out of time: a discard of its chop (its oldest card if every card is
+// clued), or the first legal clue when no discard is allowed
+pub fn default_action(state: &State) -> ActionRequest {
+    let player = state.turn() % state.players().len();
+    let position = state.chop(player).unwrap_or_else(|| {
+        SlotOrder::OldestFirst
+            .for_state(state)
+            .convert(0, state.current_hand())
+    });
+    let discard = ActionRequest::Discard { position };
+    if state.check(&discard).is_ok() {
+        return discard;
+    }
+    (0..NACTIONS)
+        .map(ActionRequest::from_id)
+        .find(|x| !matches!(x, ActionRequest::Play { .. }) && state.check(x).is_ok())
+        .unwrap_or(ActionRequest::Play { position: 0 })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{default_action, Clock};
+    use crate::state::{ActionRequest, State};
+    use std::time::Duration;
+
+    #[test]
+    fn moves_over_budget_are_timeouts() {
+        let ms = Duration::from_millis;
+        let mut clock = Clock::new(2, Some(ms(100)), Some(ms(250)));
+        // a move that was not requested is not timed
+        assert!(clock.stop(0));
+        assert!(clock.record(0, ms(100)));
+        assert!(!clock.record(0, ms(101)));
+        assert!(clock.record(1, ms(50)));
+        // under the move budget, over the total
+        assert!(!clock.record(0, ms(50)));
+        assert_eq!(clock.seats()[0].timeouts, 2);
+        assert_eq!(clock.remaining(0), Some(Duration::default()));
+        assert_eq!(clock.remaining(1), Some(ms(200)));
+    }
+
+    #[test]
+    fn the_default_action_is_legal() {
+        let mut state = State::with_seed(3, 0);
+        while !state.gameover() {
+            let action = default_action(&state);
+            assert!(!matches!(action, ActionRequest::Play { .. }));
+            state.apply(&action).unwrap();
+        }
+    }
+}
@@ -0,0 +1,89 @@
+use crate::state::{Card, Color, Knowledge, State};
+
+// all the orderings of 0..n
+fn permutations(n: usize) -> Vec<Vec<usize>> {
+    if n == 0 {
+        return vec![Vec::new()];
+    }
+    let mut out = Vec::new();
+    for p in permutations(n - 1) {
+        for i in 0..n {
+            let mut p = p.clone();
+            p.insert(i, n - 1);
+            out.push(p);
+        }
+    }
+    out
+}
+
+fn sorted_discard<F: Fn(Color) -> Color>(state: &State, f: &F) -> Vec<(usize, usize)> {
+    let mut discard: Vec<(usize, usize)> = state
+        .discard()
+        .iter()
+        .map(|card| (f(card.color()).index(), card.value().index()))
+        .collect();
+    discard.sort();
+    discard
+}
+
+impl State {
+    // same position once the suits of `self` are renamed by `f`; the history and the order
+    // of the discard pile are ignored
+    fn same_position<F: Fn(Color) -> Color>(&self, other: &State, f: F) -> bool {
+        let card = |x: &Card| Card::new(x.value(), f(x.color()));
+        let knowledge = |x: &Knowledge| Knowledge {
+            color: x.color.map(&f),
+            not_colors: (0..8)
+                .filter(|&i| x.not_colors & (1 << i) != 0)
+                .fold(0, |bits, i| bits | 1 << f(Color::new(i)).index()),
+            ..*x
+        };
+        let mut table = vec![0; self.table().len()];
+        for (i, &suit) in self.variant().suits().iter().enumerate() {
+            table[other.variant().suit_index(f(suit))] = self.table()[i];
+        }
+
+        self.variant() == other.variant()
+            && self.modifiers().names() == other.modifiers().names()
+            && self.turn() == other.turn()
+            && self.final_round() == other.final_round()
+            && self.clues() == other.clues()
+            && self.mistakes() == other.mistakes()
+            && &table == other.table()
+            && self
+                .players()
+                .iter()
+                .map(|hand| hand.iter().map(card).collect::<Vec<Card>>())
+                .eq(other.players().iter().cloned())
+            && self
+                .knowledge()
+                .iter()
+                .map(|hand| hand.iter().map(knowledge).collect::<Vec<Knowledge>>())
+                .eq(other.knowledge().iter().cloned())
+            && self
+                .deck()
+                .iter()
+                .map(card)
+                .eq(other.deck().iter().cloned())
+            && sorted_discard(self, &f) == sorted_discard(other, &|x| x)
+    }
+
+    // equal up to a renaming of the suits, only when every suit has its own clue color
+    pub fn eq_up_to_colors(&self, other: &State) -> bool {
+        let suits = self.variant().suits();
+        if suits.to_vec() != self.variant().colors() {
+            return self == other;
+        }
+        permutations(suits.len())
+            .iter()
+            .any(|p| self.same_position(other, |x| suits[p[self.variant().suit_index(x)]]))
+    }
+}
+
+impl PartialEq for State {
+    fn eq(&self, other: &State) -> bool {
+        self.same_position(other, |x| x)
+    }
+}
+
+impl Eq for State {}
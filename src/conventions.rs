@@ -0,0 +1,563 @@
+// helpers of the h-group conventions, positions in the engine order
+use crate::agents::{Agent, Observation};
+use crate::slots::SlotOrder;
+use crate::state::{
+    Action, ActionRequest, Card, Color, State, Value, MAXCARDS, MAXMISTAKES, NACTIONS,
+};
+use crate::variant::Variant;
+
+impl State {
+    // the oldest card of `player` that no clue touched, None if every card was clued
+    pub fn chop(&self, player: usize) -> Option<usize> {
+        let knowledge = &self.knowledge()[player];
+        let by_age = SlotOrder::OldestFirst.for_state(self);
+        (0..knowledge.len())
+            .map(|i| by_age.convert(i, knowledge.len()))
+            .find(|&i| knowledge[i].value.is_none() && knowledge[i].color.is_none())
+    }
+
+    // positions of the hand of the target that `clue` would touch, empty if it is not a clue
+    pub fn touched_by(&self, clue: &ActionRequest) -> Vec<usize> {
+        let target = match *clue {
+            ActionRequest::ColorClue { target, .. } | ActionRequest::ValueClue { target, .. } => {
+                target
+            }
+            _ => return Vec::new(),
+        };
+        let hand = &self.players()[target];
+        (0..hand.len())
+            .filter(|&i| match *clue {
+                ActionRequest::ColorClue { color, .. } => self.variant().touches(color, &hand[i]),
+                ActionRequest::ValueClue { value, .. } => hand[i].value() == value,
+                _ => false,
+            })
+            .collect()
+    }
+
+    // the card `clue` is about: the chop if it touches it, else the newest card it newly touches,
+    // else the newest card it touches; None if it touches nothing
+    pub fn clue_focus(&self, clue: &ActionRequest) -> Option<usize> {
+        let target = match *clue {
+            ActionRequest::ColorClue { target, .. } | ActionRequest::ValueClue { target, .. } => {
+                target
+            }
+            _ => return None,
+        };
+        let touched = self.touched_by(clue);
+        let chop = self.chop(target);
+        if chop.is_some_and(|i| touched.contains(&i)) {
+            return chop;
+        }
+        let knowledge = &self.knowledge()[target];
+        let by_age = SlotOrder::NewestFirst.for_state(self);
+        let newest: Vec<usize> = (0..knowledge.len())
+            .map(|i| by_age.convert(i, knowledge.len()))
+            .filter(|i| touched.contains(i))
+            .collect();
+        newest
+            .iter()
+            .copied()
+            .find(|&i| knowledge[i].value.is_none() && knowledge[i].color.is_none())
+            .or_else(|| newest.first().copied())
+    }
+}
+
+// the h-group beginner conventions an `HGroupBot` follows
+#[derive(Clone, Debug, PartialEq)]
+pub struct Conventions {
+    // a clue touching new cards is about its focus, which is playable
+    pub play_clues: bool,
+    // value clues on a chop holding a 5, a 2 not seen elsewhere or the last copy of a card, the
+    // receiver keeps the card rather than playing it
+    pub five_saves: bool,
+    pub two_saves: bool,
+    pub critical_saves: bool,
+    // a clued card of a suit whose next card was clued to someone else is the playable one
+    pub prompts: bool,
+}
+
+impl Conventions {
+    // level 1, all of them
+    pub fn level_one() -> Conventions {
+        Conventions {
+            play_clues: true,
+            five_saves: true,
+            two_saves: true,
+            critical_saves: true,
+            prompts: true,
+        }
+    }
+
+    pub fn names() -> Vec<&'static str> {
+        vec![
+            "play_clues",
+            "five_saves",
+            "two_saves",
+            "critical_saves",
+            "prompts",
+        ]
+    }
+
+    // the conventions named in `names`, separated by commas, None if one is unknown
+    pub fn from_names(names: &str) -> Option<Conventions> {
+        let mut conventions = Conventions {
+            play_clues: false,
+            five_saves: false,
+            two_saves: false,
+            critical_saves: false,
+            prompts: false,
+        };
+        for name in names.split(',').filter(|x| !x.is_empty()) {
+            match name {
+                "play_clues" => conventions.play_clues = true,
+                "five_saves" => conventions.five_saves = true,
+                "two_saves" => conventions.two_saves = true,
+                "critical_saves" => conventions.critical_saves = true,
+                "prompts" => conventions.prompts = true,
+                _ => return None,
+            }
+        }
+        Some(conventions)
+    }
+
+    // whether a clue on the chop of `card` would be a save, unless it is playable
+    fn saves(&self, obs: &Observation, card: &Card) -> bool {
+        !obs.is_useless(card)
+            && (self.five_saves && card.value().index() == 4
+                || self.two_saves && card.value().index() == 1
+                || self.critical_saves && obs.is_critical(card))
+    }
+}
+
+// what the player to move read from the clues about a card of their hand
+#[derive(Clone, Default)]
+struct Note {
+    // the focus of a clue, on the chop or not
+    focus: bool,
+    chop: bool,
+    // the cards it could be that were playable when it was clued
+    playable: Vec<Card>,
+}
+
+// plays the cards it knows to be playable and the focus of the play clues it gets, saves the chop
+// of the next player, gives play clues that only touch new useful cards, and discards its chop
+pub struct HGroupBot {
+    pub conventions: Conventions,
+}
+
+impl HGroupBot {
+    pub fn new(conventions: Conventions) -> HGroupBot {
+        HGroupBot { conventions }
+    }
+
+    // the notes of the own hand, following its cards through the history
+    fn notes(&self, obs: &Observation) -> Vec<Note> {
+        let me = obs.player();
+        let sizes = obs.hands_before();
+        let newest = obs.slots(SlotOrder::NewestFirst);
+        let by_age = obs.slots(SlotOrder::OldestFirst);
+        let first = newest == SlotOrder::NewestFirst;
+        let mut notes = vec![Note::default(); sizes.first().map_or(obs.hand_size(me), |x| x[me])];
+        let mut clued = vec![false; notes.len()];
+        let variant = obs.variant();
+        let mut table = vec![0; variant.suits().len()];
+        for (turn, action) in obs.history().iter().enumerate() {
+            let after = sizes.get(turn + 1).map_or(obs.hand_size(me), |x| x[me]);
+            if let Action::Play {
+                card,
+                success: true,
+                ..
+            } = *action
+            {
+                table[variant.suit_index(card.color())] += 1;
+            }
+            match *action {
+                Action::Play {
+                    player, position, ..
+                }
+                | Action::Discard {
+                    player, position, ..
+                } if player == me => {
+                    notes.remove(position);
+                    clued.remove(position);
+                    if after > notes.len() {
+                        let i = if first { 0 } else { notes.len() };
+                        notes.insert(i, Note::default());
+                        clued.insert(i, false);
+                    }
+                }
+                Action::ColorClue { target, .. } | Action::ValueClue { target, .. }
+                    if target == me =>
+                {
+                    let touched = action.touched();
+                    let n = notes.len();
+                    let chop = (0..n).map(|i| by_age.convert(i, n)).find(|&i| !clued[i]);
+                    let newest: Vec<usize> = (0..n)
+                        .map(|i| newest.convert(i, n))
+                        .filter(|i| touched.contains(i))
+                        .collect();
+                    // a clue touching no new card is a stall, it has no focus
+                    let focus = match chop {
+                        Some(chop) if touched.contains(&chop) => Some(chop),
+                        _ => newest.iter().copied().find(|&i| !clued[i]),
+                    };
+                    if let Some(focus) = focus {
+                        let playable = variant
+                            .suits()
+                            .iter()
+                            .zip(&table)
+                            .filter(|&(_, &n)| n < 5)
+                            .map(|(&suit, &n)| Card::new(Value::new(n), suit))
+                            .filter(|card| match *action {
+                                Action::ColorClue { color, .. } => variant.touches(color, card),
+                                Action::ValueClue { value, .. } => card.value() == value,
+                                _ => false,
+                            })
+                            .collect();
+                        notes[focus] = Note {
+                            focus: true,
+                            chop: Some(focus) == chop,
+                            playable,
+                        };
+                    }
+                    for &i in touched {
+                        clued[i] = true;
+                    }
+                }
+                _ => {}
+            }
+        }
+        notes
+    }
+
+    // the identities of a clued card, without those useless or clued in another hand
+    fn candidates(&self, obs: &Observation, position: usize) -> Vec<Card> {
+        let candidates = obs.candidates(position);
+        let good: Vec<Card> = candidates
+            .iter()
+            .copied()
+            .filter(|card| !obs.is_useless(card) && !clued_elsewhere(obs, card))
+            .collect();
+        if good.is_empty() {
+            candidates
+        } else {
+            good
+        }
+    }
+
+    fn play(&self, obs: &Observation) -> Option<ActionRequest> {
+        let me = obs.player();
+        let knowledge = obs.knowledge(me);
+        let hand = knowledge.len();
+        let clued = |i: usize| knowledge[i].value.is_some() || knowledge[i].color.is_some();
+        let candidates: Vec<Vec<Card>> = (0..hand)
+            .map(|i| {
+                if clued(i) {
+                    self.candidates(obs, i)
+                } else {
+                    obs.candidates(i)
+                }
+            })
+            .collect();
+        // good touch is not enough to be sure, a copy may have been played since the clue
+        let certain = (0..hand).find(|&i| {
+            let candidates = obs.candidates(i);
+            !candidates.is_empty() && candidates.iter().all(|c| obs.is_playable(c))
+        });
+        if let Some(position) = certain {
+            return Some(ActionRequest::Play { position });
+        }
+        if obs.mistakes() + 1 >= MAXMISTAKES {
+            return None;
+        }
+        let notes = self.notes(obs);
+        if self.conventions.play_clues {
+            // a card playable at the time of the clue that nobody played since
+            let focus = (0..hand).find(|&i| {
+                let note = &notes[i];
+                note.focus
+                    && note
+                        .playable
+                        .iter()
+                        .any(|c| obs.is_playable(c) && obs.candidates(i).contains(c))
+                    && !(note.chop
+                        && obs
+                            .candidates(i)
+                            .iter()
+                            .any(|c| self.conventions.saves(obs, c) && !obs.is_playable(c)))
+            });
+            if let Some(position) = focus {
+                return Some(ActionRequest::Play { position });
+            }
+        }
+        if self.conventions.prompts {
+            let newest = obs.slots(SlotOrder::NewestFirst);
+            let prompted = (0..hand).map(|i| newest.convert(i, hand)).find(|&i| {
+                let color = match knowledge[i].color {
+                    Some(color) => color,
+                    None => return false,
+                };
+                let suits: Vec<Color> = obs
+                    .variant()
+                    .suits()
+                    .iter()
+                    .copied()
+                    .filter(|&suit| {
+                        obs.variant()
+                            .touches(color, &Card::new(Value::new(0), suit))
+                    })
+                    .collect();
+                suits.len() == 1 && {
+                    let next = obs.table()[obs.variant().suit_index(suits[0])];
+                    next < 4
+                        && clued_elsewhere(obs, &Card::new(Value::new(next + 1), suits[0]))
+                        && !clued_elsewhere(obs, &Card::new(Value::new(next), suits[0]))
+                        && candidates[i].iter().any(|c| obs.is_playable(c))
+                }
+            });
+            if let Some(position) = prompted {
+                return Some(ActionRequest::Play { position });
+            }
+        }
+        None
+    }
+
+    // a value clue on the chop of `target` if it holds a card to save
+    fn save(&self, obs: &Observation, target: usize) -> Option<ActionRequest> {
+        let hand = obs.hand(target)?;
+        let chop = obs.chop(target)?;
+        let card = hand[chop];
+        let seen_twice = (0..obs.players()).any(|p| match obs.hand(p) {
+            Some(cards) => cards
+                .iter()
+                .enumerate()
+                .any(|(i, c)| *c == card && (p, i) != (target, chop)),
+            None => false,
+        });
+        let save = !obs.is_useless(&card)
+            && (self.conventions.five_saves && card.value().index() == 4
+                || self.conventions.two_saves && card.value().index() == 1 && !seen_twice
+                || self.conventions.critical_saves && obs.is_critical(&card));
+        let clue = ActionRequest::ValueClue {
+            target,
+            value: card.value(),
+        };
+        (save && obs.is_legal(&clue)).then_some(clue)
+    }
+
+    // the legal clue whose focus is playable and whose new cards are all useful and clued nowhere
+    // else, touching the most playable cards, to the next players first
+    fn play_clue(&self, obs: &Observation) -> Option<ActionRequest> {
+        let mut best: Option<(usize, ActionRequest)> = None;
+        for offset in 1..obs.players() {
+            let target = (obs.player() + offset) % obs.players();
+            let hand = obs.hand(target).unwrap();
+            let knowledge = obs.knowledge(target);
+            let clues = Value::all()
+                .into_iter()
+                .map(|value| ActionRequest::ValueClue { target, value })
+                .chain(
+                    obs.variant()
+                        .colors()
+                        .into_iter()
+                        .map(|color| ActionRequest::ColorClue { target, color }),
+                );
+            for clue in clues.filter(|clue| obs.is_legal(clue)) {
+                let focus = match obs.clue_focus(&clue) {
+                    Some(focus) => focus,
+                    None => continue,
+                };
+                let new: Vec<Card> = obs
+                    .touched_by(&clue)
+                    .unwrap_or_default()
+                    .into_iter()
+                    .filter(|&i| knowledge[i].value.is_none() && knowledge[i].color.is_none())
+                    .map(|i| hand[i])
+                    .collect();
+                let good = new.iter().enumerate().all(|(i, card)| {
+                    !obs.is_useless(card) && !clued_elsewhere(obs, card) && !new[..i].contains(card)
+                });
+                let fresh = knowledge[focus].value.is_none() && knowledge[focus].color.is_none();
+                if !fresh || !good || !obs.is_playable(&hand[focus]) {
+                    continue;
+                }
+                // on the chop, a card the target could read as one to save
+                let save = Some(focus) == obs.chop(target)
+                    && obs.variant().deck().iter().any(|card| {
+                        clue_touches(&clue, obs.variant(), card)
+                            && knowledge[focus].allows(card, obs.variant())
+                            && self.conventions.saves(obs, card)
+                            && !obs.is_playable(card)
+                    });
+                if save {
+                    continue;
+                }
+                let playable = new.iter().filter(|card| obs.is_playable(card)).count();
+                if best.as_ref().is_none_or(|&(n, _)| playable > n) {
+                    best = Some((playable, clue));
+                }
+            }
+        }
+        best.map(|(_, clue)| clue)
+    }
+}
+
+impl Agent for HGroupBot {
+    fn act(&mut self, obs: &Observation) -> ActionRequest {
+        if let Some(play) = self.play(obs) {
+            return play;
+        }
+        let me = obs.player();
+        let n = obs.players();
+        if obs.clues() > 0 {
+            let next = (me + 1) % n;
+            if let Some(save) = self.save(obs, next) {
+                return save;
+            }
+            if self.conventions.play_clues {
+                if let Some(clue) = self.play_clue(obs) {
+                    return clue;
+                }
+            }
+            for offset in 2..n {
+                if let Some(save) = self.save(obs, (me + offset) % n) {
+                    return save;
+                }
+            }
+        }
+        let hand = obs.hand_size(me);
+        let oldest = obs.slots(SlotOrder::OldestFirst).convert(0, hand);
+        let discard = ActionRequest::Discard {
+            position: obs.chop(me).unwrap_or(oldest),
+        };
+        if obs.chop(me).is_some() && obs.is_legal(&discard) {
+            return discard;
+        }
+        // every card is clued, or no discard at the maximum of tokens: a clue to stall, that
+        // touches no new card if possible
+        let clues: Vec<ActionRequest> = (2 * MAXCARDS..NACTIONS)
+            .map(ActionRequest::from_id)
+            .filter(|action| obs.is_legal(action))
+            .collect();
+        let stall = clues.iter().find(|clue| {
+            let target = match **clue {
+                ActionRequest::ColorClue { target, .. }
+                | ActionRequest::ValueClue { target, .. } => target,
+                _ => return false,
+            };
+            let knowledge = obs.knowledge(target);
+            obs.touched_by(clue).is_some_and(|touched| {
+                touched
+                    .iter()
+                    .all(|&i| knowledge[i].value.is_some() || knowledge[i].color.is_some())
+            })
+        });
+        // else a value no suit needs, its focus cannot be read as playable
+        let unplayable = clues.iter().find(|clue| match **clue {
+            ActionRequest::ValueClue { value, .. } => !obs.table().contains(&value.index()),
+            _ => false,
+        });
+        if let Some(&stall) = stall.or(unplayable).or_else(|| clues.first()) {
+            return stall;
+        }
+        (0..2 * MAXCARDS)
+            .map(ActionRequest::from_id)
+            .find(|action| obs.is_legal(action) && !matches!(action, ActionRequest::Play { .. }))
+            .unwrap_or(discard)
+    }
+}
+
+fn clue_touches(clue: &ActionRequest, variant: &Variant, card: &Card) -> bool {
+    match *clue {
+        ActionRequest::ColorClue { color, .. } => variant.touches(color, card),
+        ActionRequest::ValueClue { value, .. } => card.value() == value,
+        _ => false,
+    }
+}
+
+fn clued_elsewhere(obs: &Observation, card: &Card) -> bool {
+    (0..obs.players()).any(|p| match obs.hand(p) {
+        Some(hand) => hand
+            .iter()
+            .zip(obs.knowledge(p))
+            .any(|(c, k)| c == card && (k.value.is_some() || k.color.is_some())),
+        None => false,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // the first player holds 1r 1r 1r 2r 2r and the second 3r 3r 4r 4r 5r, newest first
+    fn dealt(swaps: &[(usize, usize)]) -> State {
+        let mut deck = Variant::standard().deck();
+        for &(i, j) in swaps {
+            deck.swap(i, j);
+        }
+        State::from_deck(2, deck)
+    }
+
+    fn value(target: usize, value: usize) -> ActionRequest {
+        ActionRequest::ValueClue {
+            target,
+            value: Value::new(value - 1),
+        }
+    }
+
+    #[test]
+    fn names_give_the_conventions() {
+        let all = Conventions::names().join(",");
+        assert_eq!(
+            Conventions::from_names(&all),
+            Some(Conventions::level_one())
+        );
+        let some = Conventions::from_names("five_saves,prompts").unwrap();
+        assert!(some.five_saves && some.prompts && !some.play_clues && !some.two_saves);
+        assert_eq!(Conventions::from_names("five_save"), None);
+    }
+
+    #[test]
+    fn chop_and_focus_follow_the_clues() {
+        let mut state = dealt(&[]);
+        assert_eq!(state.chop(1), Some(4));
+        assert_eq!(state.touched_by(&value(1, 5)), vec![4]);
+        assert_eq!(state.clue_focus(&value(1, 5)), Some(4));
+        state.apply(&value(1, 5)).unwrap();
+        assert_eq!(state.chop(1), Some(3));
+        state.apply(&value(0, 1)).unwrap();
+        // off the chop, the newest card newly touched
+        assert_eq!(state.touched_by(&value(1, 3)), vec![0, 1]);
+        assert_eq!(state.clue_focus(&value(1, 3)), Some(0));
+        let red = ActionRequest::ColorClue {
+            target: 1,
+            color: Color::from_letter("r"),
+        };
+        assert_eq!(state.clue_focus(&red), Some(3));
+        assert_eq!(state.clue_focus(&ActionRequest::Play { position: 0 }), None);
+    }
+
+    #[test]
+    fn five_on_the_chop_is_saved() {
+        let state = dealt(&[]);
+        let obs = Observation::new(&state);
+        let mut bot = HGroupBot::new(Conventions::level_one());
+        assert_eq!(bot.act(&obs), value(1, 5));
+        let mut bot = HGroupBot::new(Conventions::from_names("play_clues").unwrap());
+        assert_ne!(bot.act(&obs), value(1, 5));
+    }
+
+    #[test]
+    fn play_clue_is_played() {
+        // the second player holds 1r 3r 4r 4r 1y
+        let mut state = dealt(&[(0, 5), (9, 10)]);
+        let mut bot = HGroupBot::new(Conventions::level_one());
+        let clue = bot.act(&Observation::new(&state));
+        assert_eq!(clue, value(1, 1));
+        state.apply(&clue).unwrap();
+        let play = bot.act(&Observation::new(&state));
+        assert!(matches!(play, ActionRequest::Play { position: 0 | 4 }));
+        state.apply(&play).unwrap();
+        assert_eq!(state.score(), 1);
+    }
+}
@@ -0,0 +1,98 @@
+// the deal of the day: a date maps to a seed, so that everyone playing that date (people, bots,
+// experiments) gets the same deck for a given variant and can compare scores
+use crate::manifest::fnv1a;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// `date` is "YYYY-MM-DD"
+pub fn seed(date: &str) -> Result<u64, String> {
+    let (year, month, day) = parse(date).ok_or_else(|| format!("invalid date {:?}", date))?;
+    let canonical = format!("hanabi daily {:04}-{:02}-{:02}", year, month, day);
+    Ok(fnv1a(canonical.as_bytes()))
+}
+
+// the current date in utc
+pub fn today() -> String {
+    let secs = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|x| x.as_secs())
+        .unwrap_or(0);
+    let (year, month, day) = civil((secs / 86400) as i64);
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+fn parse(date: &str) -> Option<(i64, u32, u32)> {
+    let parts: Vec<&str> = date.trim().split('-').collect();
+    if parts.len() != 3 || parts[0].len() != 4 {
+        return None;
+    }
+    let year: i64 = parts[0].parse().ok()?;
+    let month: u32 = parts[1].parse().ok()?;
+    let day: u32 = parts[2].parse().ok()?;
+    if !(1..=12).contains(&month) || day == 0 || day > days_in_month(year, month) {
+        return None;
+    }
+    Some((year, month, day))
+}
+
+fn days_in_month(year: i64, month: u32) -> u32 {
+    let leap = (year % 4 == 0 && year % 100 != 0) || year % 400 == 0;
+    match month {
+        2 if leap => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+// days since 1970-01-01 to (year, month, day), from Howard Hinnant's `civil_from_days`
+fn civil(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn seeds_do_not_change() {
+        // published seeds, a change breaks the comparison with the scores of past days
+        assert_eq!(seed("2024-02-29"), Ok(13_343_287_574_632_962_000));
+        assert_eq!(seed(" 2024-2-29\n"), seed("2024-02-29"));
+        assert_ne!(seed("2024-03-01"), seed("2024-02-29"));
+    }
+
+    #[test]
+    fn invalid_dates_are_refused() {
+        for date in [
+            "2023-02-29",
+            "1900-02-29",
+            "2024-04-31",
+            "2024-13-01",
+            "2024-00-10",
+            "24-01-01",
+            "2024-01",
+            "today",
+        ] {
+            assert!(seed(date).is_err(), "{}", date);
+        }
+        assert!(seed("2000-02-29").is_ok());
+    }
+
+    #[test]
+    fn days_since_the_epoch_give_the_date() {
+        assert_eq!(civil(0), (1970, 1, 1));
+        assert_eq!(civil(-1), (1969, 12, 31));
+        assert_eq!(civil(19782), (2024, 2, 29));
+        assert_eq!(civil(47541), (2100, 3, 1));
+        assert!(seed(&today()).is_ok());
+    }
+}
@@ -0,0 +1,100 @@
+use crate::encoder::Encoder;
+use crate::replay::{Replay, ReplayError};
+use crate::state::NACTIONS;
+use crate::variant::Variant;
+use ndarray::{Array1, Array2};
+use rayon::prelude::*;
+
+pub struct Dataset {
+    pub observations: Array2<f32>,
+    pub masks: Array2<f32>,
+    pub actions: Array1<i64>,
+    pub returns: Array1<f32>,
+    pub episodes: Array1<i64>,
+}
+
+struct Episode {
+    observations: Vec<f32>,
+    masks: Vec<f32>,
+    actions: Vec<i64>,
+    returns: Vec<f32>,
+}
+
+impl Episode {
+    fn record(replay: &Replay, gamma: f32, encoder: Encoder) -> Result<Episode, ReplayError> {
+        let mut episode = Episode {
+            observations: Vec::new(),
+            masks: Vec::new(),
+            actions: Vec::new(),
+            returns: Vec::new(),
+        };
+        if encoder == Encoder::Hle
+            && (replay.variant != Variant::standard() || !replay.modifiers.is_empty())
+        {
+            return Err(ReplayError::Invalid(
+                "the hle encoder only supports the standard variant without modifiers".to_string(),
+            ));
+        }
+        let mut scores = Vec::new();
+        let state = replay.play(|state, action| {
+            let player = state.turn() % state.players().len();
+            episode
+                .observations
+                .extend(encoder.encode(state, player).iter());
+            episode.masks.extend(state.legal_mask().iter());
+            episode.actions.push(action.id() as i64);
+            scores.push(state.score());
+        })?;
+        scores.push(state.score());
+
+        let mut ret = 0.0;
+        for t in (0..episode.actions.len()).rev() {
+            ret = (scores[t + 1] - scores[t]) as f32 + gamma * ret;
+            episode.returns.push(ret);
+        }
+        episode.returns.reverse();
+        Ok(episode)
+    }
+}
+
+pub fn from_replays(
+    replays: &[Replay],
+    gamma: f32,
+    encoder: Encoder,
+) -> Result<Dataset, ReplayError> {
+    let episodes = replays
+        .par_iter()
+        .map(|replay| Episode::record(replay, gamma, encoder))
+        .collect::<Result<Vec<Episode>, ReplayError>>()?;
+
+    let n: usize = episodes.iter().map(|x| x.actions.len()).sum();
+    let mut observations = Vec::new();
+    let mut masks = Vec::new();
+    let mut actions = Vec::new();
+    let mut returns = Vec::new();
+    let mut ids = Vec::new();
+    for (i, episode) in episodes.into_iter().enumerate() {
+        ids.extend(episode.actions.iter().map(|_| i as i64));
+        observations.extend(episode.observations);
+        masks.extend(episode.masks);
+        actions.extend(episode.actions);
+        returns.extend(episode.returns);
+    }
+    let dim = observations.len().checked_div(n).unwrap_or(0);
+
+    Ok(Dataset {
+        observations: Array2::from_shape_vec((n, dim), observations).unwrap(),
+        masks: Array2::from_shape_vec((n, NACTIONS), masks).unwrap(),
+        actions: Array1::from(actions),
+        returns: Array1::from(returns),
+        episodes: Array1::from(ids),
+    })
+}
+
+pub fn from_files(paths: &[String], gamma: f32, encoder: Encoder) -> Result<Dataset, ReplayError> {
+    let replays = paths
+        .par_iter()
+        .map(Replay::load)
+        .collect::<Result<Vec<Replay>, ReplayError>>()?;
+    from_replays(&replays, gamma, encoder)
+}
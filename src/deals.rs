@@ -0,0 +1,191 @@
+// shuffled decks under constraints on where the cards are, for a curriculum or to test given
+// situations (e.g. the endgames of a deck with no 5 at the bottom); the hands are dealt from the
+// front of the deck and the cards are drawn from its end
+use crate::rules;
+use crate::state::{Card, State};
+use crate::variant::Variant;
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+
+#[derive(Debug)]
+pub enum DealError {
+    // none of the shuffles satisfied the constraints
+    Unsatisfied { attempts: usize },
+    Invalid(String),
+}
+
+#[derive(Clone, Debug)]
+pub struct DealGenerator {
+    players: usize,
+    hand_size: usize,
+    variant: Variant,
+    // no 5 among the last `n` cards drawn
+    no_five_at_bottom: usize,
+    // at most this many cards with a single copy (the 5s) in the first hands
+    max_criticals: Option<usize>,
+    // at most this many cards of a suit in a first hand
+    max_per_suit: Option<usize>,
+    attempts: usize,
+}
+
+impl DealGenerator {
+    pub fn new(players: usize, variant: Variant) -> DealGenerator {
+        DealGenerator {
+            players,
+            hand_size: rules::hand_size(players),
+            variant,
+            no_five_at_bottom: 0,
+            max_criticals: None,
+            max_per_suit: None,
+            attempts: 100_000,
+        }
+    }
+
+    // the size of the first hands, see `Modifiers::hand_size`
+    pub fn hand_size(mut self, size: usize) -> DealGenerator {
+        self.hand_size = size;
+        self
+    }
+
+    pub fn no_five_at_bottom(mut self, n: usize) -> DealGenerator {
+        self.no_five_at_bottom = n;
+        self
+    }
+
+    pub fn max_criticals(mut self, k: usize) -> DealGenerator {
+        self.max_criticals = Some(k);
+        self
+    }
+
+    pub fn max_per_suit(mut self, k: usize) -> DealGenerator {
+        self.max_per_suit = Some(k);
+        self
+    }
+
+    // shuffles tried before giving up
+    pub fn attempts(mut self, n: usize) -> DealGenerator {
+        self.attempts = n;
+        self
+    }
+
+    pub fn satisfies(&self, deck: &[Card]) -> bool {
+        let dealt = self.players * self.hand_size;
+        let bottom = &deck[dealt..(dealt + self.no_five_at_bottom).min(deck.len())];
+        if bottom.iter().any(|card| card.value().index() == 4) {
+            return false;
+        }
+        let hands = &deck[..dealt];
+        if let Some(k) = self.max_criticals {
+            if hands
+                .iter()
+                .filter(|card| card.value().copies() == 1)
+                .count()
+                > k
+            {
+                return false;
+            }
+        }
+        if let Some(k) = self.max_per_suit {
+            for hand in hands.chunks(self.hand_size) {
+                for &suit in self.variant.suits() {
+                    if hand.iter().filter(|card| card.color() == suit).count() > k {
+                        return false;
+                    }
+                }
+            }
+        }
+        true
+    }
+
+    // a uniform deck among those that satisfy the constraints, by rejection
+    pub fn generate<R: Rng>(&self, rng: &mut R) -> Result<Vec<Card>, DealError> {
+        let mut deck = self.variant.deck();
+        if self.hand_size == 0 || self.players * self.hand_size > deck.len() {
+            return Err(DealError::Invalid(format!(
+                "{} hands of {} cards from a deck of {}",
+                self.players,
+                self.hand_size,
+                deck.len()
+            )));
+        }
+        for _ in 0..self.attempts {
+            deck.shuffle(rng);
+            if self.satisfies(&deck) {
+                return Ok(deck);
+            }
+        }
+        Err(DealError::Unsatisfied {
+            attempts: self.attempts,
+        })
+    }
+
+    // the same seed gives the same deal
+    pub fn state(&self, seed: u64) -> Result<State, DealError> {
+        let deck = self.generate(&mut StdRng::seed_from_u64(seed))?;
+        Ok(State::from_deck_variant(
+            self.players,
+            self.variant.clone(),
+            deck,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sorted(mut cards: Vec<Card>) -> Vec<Card> {
+        cards.sort_by_key(|card| (card.color().index(), card.value().index()));
+        cards
+    }
+
+    #[test]
+    fn generated_decks_satisfy_the_constraints() {
+        let generator = DealGenerator::new(3, Variant::standard())
+            .no_five_at_bottom(10)
+            .max_criticals(0)
+            .max_per_suit(2);
+        let mut rng = StdRng::seed_from_u64(0);
+        for _ in 0..20 {
+            let deck = generator.generate(&mut rng).unwrap();
+            assert!(generator.satisfies(&deck));
+            assert_eq!(sorted(deck.clone()), sorted(Variant::standard().deck()));
+            assert!(deck[..15].iter().all(|card| card.value().copies() > 1));
+            assert!(deck[15..25].iter().all(|card| card.value().index() != 4));
+        }
+    }
+
+    #[test]
+    fn the_bottom_is_drawn_last() {
+        let generator = DealGenerator::new(2, Variant::standard()).no_five_at_bottom(20);
+        for seed in 0..5 {
+            let state = generator.state(seed).unwrap();
+            assert_eq!(state.deck().len(), 40);
+            assert!(state.deck()[..20]
+                .iter()
+                .all(|card| card.value().index() != 4));
+            let again = generator.state(seed).unwrap();
+            assert_eq!(state.players(), again.players());
+            assert_eq!(state.deck(), again.deck());
+        }
+    }
+
+    #[test]
+    fn impossible_deals_are_errors() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let generator = DealGenerator::new(2, Variant::standard());
+        assert!(matches!(
+            generator.clone().hand_size(0).generate(&mut rng),
+            Err(DealError::Invalid(_))
+        ));
+        assert!(matches!(
+            generator.clone().hand_size(30).generate(&mut rng),
+            Err(DealError::Invalid(_))
+        ));
+        assert!(matches!(
+            generator.max_per_suit(0).attempts(10).generate(&mut rng),
+            Err(DealError::Unsatisfied { attempts: 10 })
+        ));
+    }
+}
@@ -0,0 +1,131 @@
+use crate::agents::{Agent, Observation};
+use crate::clock::{self, Clock, OnTimeout, SeatTime, TimeControl};
+use crate::modifier::{self, Modifiers};
+use crate::replay::Replay;
+use crate::state::{IllegalMoves, State};
+use crate::variant::Variant;
+use std::time::{Duration, Instant};
+
+// seats one agent per player and plays full games
+pub struct GameDriver {
+    pub variant: Variant,
+    // resolved from their names by `with_modifiers`
+    modifiers: Modifiers,
+    pub seats: Vec<Box<dyn Agent>>,
+    // the move budgets, enforced after each move: the agent cannot be interrupted, a move over
+    // budget forfeits the game or is replaced by `clock::default_action`
+    pub time_control: Option<TimeControl>,
+}
+
+pub struct GameRecord {
+    pub score: usize,
+    pub turns: usize,
+    pub mistakes: usize,
+    // the game stops at the first illegal action
+    pub illegal: Option<(usize, IllegalMoves)>,
+    // seat and reason of the agent that gave up the game
+    pub forfeit: Option<(usize, String)>,
+    pub replay: Replay,
+    // one line per action, e.g. "P2 clues P3 about 4's"
+    pub transcript: Vec<String>,
+    // time taken by the agent of every turn to choose its action
+    pub move_times: Vec<Duration>,
+    // the time used and the timeouts of every seat, empty without a time control
+    pub clock: Vec<SeatTime>,
+}
+
+impl GameDriver {
+    pub fn new(variant: Variant, seats: Vec<Box<dyn Agent>>) -> GameDriver {
+        GameDriver {
+            variant,
+            modifiers: Modifiers::default(),
+            seats,
+            time_control: None,
+        }
+    }
+
+    // the games are played with the rule modifiers of `names`, an unknown name is an error
+    pub fn with_modifiers<S: AsRef<str>>(
+        variant: Variant,
+        seats: Vec<Box<dyn Agent>>,
+        names: &[S],
+    ) -> Result<GameDriver, String> {
+        Ok(GameDriver {
+            modifiers: Modifiers(modifier::resolve(names)?),
+            ..GameDriver::new(variant, seats)
+        })
+    }
+
+    pub fn modifiers(&self) -> Vec<String> {
+        self.modifiers.names()
+    }
+
+    pub fn play(&mut self, seed: Option<u64>) -> GameRecord {
+        let mut state = State::with_variant(self.seats.len(), self.variant.clone(), seed)
+            .modified(self.modifiers.clone().0);
+        let mut illegal = None;
+        let mut forfeit = None;
+        let mut move_times = Vec::new();
+        let mut clock = self
+            .time_control
+            .map(|control| Clock::with_control(self.seats.len(), control));
+        while !state.gameover() {
+            let seat = state.turn() % self.seats.len();
+            let observation = Observation::new(&state);
+            // timed from the request of the move, the observation already built
+            let start = Instant::now();
+            let mut action = self.seats[seat].act(&observation);
+            let elapsed = start.elapsed();
+            move_times.push(elapsed);
+            if let (Some(clock), Some(control)) = (clock.as_mut(), self.time_control) {
+                if !clock.record(seat, elapsed) {
+                    match control.on_timeout {
+                        OnTimeout::Forfeit => {
+                            let reason = format!("out of time, {:?} for the move", elapsed);
+                            forfeit = Some((seat, reason));
+                            break;
+                        }
+                        OnTimeout::Default => action = clock::default_action(&state),
+                    }
+                }
+            }
+            if let Err(error) = state.apply(&action) {
+                illegal = Some((*state.turn(), error));
+                forfeit = self.seats[seat].forfeit().map(|reason| (seat, reason));
+                break;
+            }
+        }
+        GameRecord {
+            score: state.final_score(),
+            turns: *state.turn(),
+            mistakes: *state.mistakes(),
+            illegal,
+            forfeit,
+            replay: Replay::from_state(&state),
+            transcript: state.history().iter().map(|x| format!("{}", x)).collect(),
+            move_times,
+            clock: clock.map(|x| x.seats().to_vec()).unwrap_or_default(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agents::ConventionBot;
+
+    fn seats() -> Vec<Box<dyn Agent>> {
+        vec![Box::new(ConventionBot), Box::new(ConventionBot)]
+    }
+
+    #[test]
+    fn unknown_modifiers_are_refused() {
+        let err = GameDriver::with_modifiers(Variant::standard(), seats(), &["draw_lats"]);
+        assert!(err.is_err());
+        let mut driver =
+            GameDriver::with_modifiers(Variant::standard(), seats(), &["draw_last"]).unwrap();
+        assert_eq!(driver.modifiers(), vec!["draw_last"]);
+        let record = driver.play(Some(0));
+        assert_eq!(record.replay.modifiers, vec!["draw_last"]);
+    }
+}
@@ -0,0 +1,170 @@
+use crate::agents::{is_critical, is_useless};
+use crate::replay::{Replay, ReplayError};
+use crate::state::{ActionRequest, Card, State};
+use rayon::prelude::*;
+
+// clue and discard quality over a set of games
+#[derive(Clone, Debug, Default)]
+pub struct Efficiency {
+    pub games: usize,
+    pub score: usize,
+    pub clues: usize,
+    // cards touched for the first time by a clue, and those already played, dead or touched elsewhere
+    pub touched: usize,
+    pub bad_touches: usize,
+    pub discards: usize,
+    pub critical_discards: usize,
+}
+
+impl Efficiency {
+    pub fn of_replay(replay: &Replay) -> Result<Efficiency, ReplayError> {
+        let mut stats = Efficiency {
+            games: 1,
+            ..Efficiency::default()
+        };
+        let state = replay.play(|state, action| match *action {
+            ActionRequest::Discard { position } => {
+                let card = &state.players()[state.turn() % state.players().len()][position];
+                stats.discards += 1;
+                if is_critical(state, card) {
+                    stats.critical_discards += 1;
+                }
+            }
+            ActionRequest::ColorClue { target, color } => {
+                stats.clues += 1;
+                stats.touch(state, target, |card| state.variant().touches(color, card));
+            }
+            ActionRequest::ValueClue { target, value } => {
+                stats.clues += 1;
+                stats.touch(state, target, |card| card.value() == value);
+            }
+            ActionRequest::Play { .. } => {}
+        })?;
+        stats.score = state.score();
+        Ok(stats)
+    }
+
+    pub(crate) fn touch<F>(&mut self, state: &State, target: usize, f: F)
+    where
+        F: Fn(&Card) -> bool,
+    {
+        let touched = |player: usize, i: usize| {
+            let knowledge = state.knowledge()[player][i];
+            knowledge.value.is_some() || knowledge.color.is_some()
+        };
+        let mut seen: Vec<Card> = Vec::new();
+        for (player, hand) in state.players().iter().enumerate() {
+            for (i, card) in hand.iter().enumerate() {
+                if touched(player, i) {
+                    seen.push(*card);
+                }
+            }
+        }
+        for (i, card) in state.players()[target].iter().enumerate() {
+            if !f(card) || touched(target, i) {
+                continue;
+            }
+            self.touched += 1;
+            if is_useless(state, card) || seen.contains(card) {
+                self.bad_touches += 1;
+            }
+            seen.push(*card);
+        }
+    }
+
+    pub fn merge(mut self, other: &Efficiency) -> Efficiency {
+        self.games += other.games;
+        self.score += other.score;
+        self.clues += other.clues;
+        self.touched += other.touched;
+        self.bad_touches += other.bad_touches;
+        self.discards += other.discards;
+        self.critical_discards += other.critical_discards;
+        self
+    }
+
+    pub fn mean_score(&self) -> f64 {
+        ratio(self.score, self.games)
+    }
+
+    // cards played per clue given
+    pub fn clue_efficiency(&self) -> f64 {
+        ratio(self.score, self.clues)
+    }
+
+    pub fn bad_touch_rate(&self) -> f64 {
+        ratio(self.bad_touches, self.touched)
+    }
+
+    pub fn critical_discard_rate(&self) -> f64 {
+        ratio(self.critical_discards, self.discards)
+    }
+}
+
+fn ratio(a: usize, b: usize) -> f64 {
+    if b == 0 {
+        0.0
+    } else {
+        a as f64 / b as f64
+    }
+}
+
+pub fn from_files(paths: &[String]) -> Result<Efficiency, ReplayError> {
+    let games = paths
+        .par_iter()
+        .map(|path| Efficiency::of_replay(&Replay::load(path)?))
+        .collect::<Result<Vec<Efficiency>, ReplayError>>()?;
+    Ok(games
+        .iter()
+        .fold(Efficiency::default(), |total, game| total.merge(game)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::Value;
+    use crate::variant::Variant;
+
+    // the deck in order: 1r 1r 1r 2r 2r for the first player, 3r 3r 4r 4r 5r for the second
+    fn replay() -> Replay {
+        let mut state = State::from_deck(2, Variant::standard().deck());
+        let position = |state: &State, player: usize, value: usize| {
+            state.players()[player]
+                .iter()
+                .position(|card| card.value() == Value::new(value))
+                .unwrap()
+        };
+        let clue = |target, value| ActionRequest::ValueClue {
+            target,
+            value: Value::new(value),
+        };
+        // the 5 is a good touch, two of the three red 1s are bad touches
+        state.apply(&clue(1, 4)).unwrap();
+        state.apply(&clue(0, 0)).unwrap();
+        let play = position(&state, 0, 0);
+        state
+            .apply(&ActionRequest::Play { position: play })
+            .unwrap();
+        // the only red 5
+        let discard = position(&state, 1, 4);
+        state
+            .apply(&ActionRequest::Discard { position: discard })
+            .unwrap();
+        Replay::from_state(&state)
+    }
+
+    #[test]
+    fn clue_efficiency_of_a_known_game() {
+        let stats = Efficiency::of_replay(&replay()).unwrap();
+        assert_eq!(stats.score, 1);
+        assert_eq!(stats.clues, 2);
+        assert_eq!((stats.touched, stats.bad_touches), (4, 2));
+        assert_eq!((stats.discards, stats.critical_discards), (1, 1));
+        assert_eq!(stats.clue_efficiency(), 0.5);
+        assert_eq!(stats.bad_touch_rate(), 0.5);
+        assert_eq!(stats.critical_discard_rate(), 1.0);
+        let both = stats.clone().merge(&stats);
+        assert_eq!(both.mean_score(), 1.0);
+        assert_eq!(both.clue_efficiency(), 0.5);
+    }
+}
@@ -0,0 +1,373 @@
+use crate::layout::Block;
+use crate::modifier;
+use crate::slots::SlotOrder;
+use crate::state::{State, MAXCARDS, MAXPLAYERS};
+use crate::variant::Variant;
+use ndarray::{Array1, Array2};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Encoder {
+    V1,
+    // layout of the hanabi-learning-environment, standard variant only
+    Hle,
+    // the v1 observation followed by the knowledge, the card counts, the playability and the
+    // variant info blocks, see `EncoderBuilder::v2`
+    V2,
+}
+
+impl Encoder {
+    pub fn names() -> Vec<&'static str> {
+        vec!["v1", "hle", "v2"]
+    }
+
+    pub fn from_name(name: &str) -> Option<Encoder> {
+        match name {
+            "v1" => Some(Encoder::V1),
+            "hle" => Some(Encoder::Hle),
+            "v2" => Some(Encoder::V2),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        Encoder::names()[self as usize]
+    }
+
+    // observation of `player`, panics if the encoder does not support the game
+    pub fn encode(self, state: &State, player: usize) -> Array1<f32> {
+        match self {
+            Encoder::V1 => state.encode_for(player),
+            Encoder::Hle => state.encode_hle(player).unwrap(),
+            Encoder::V2 => EncoderBuilder::v2().build().encode(state, player),
+        }
+    }
+}
+
+// a named part of the observation, see `EncoderBuilder`
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Feature {
+    // the v1 observation up to the rules: tokens, deck, discard, hands, table
+    Core,
+    // the moves of the v1 observation, last first
+    History,
+    // the value and the color clued to each slot of every seat, one-hot in `Variant::colors`
+    Knowledge,
+    // `State::beliefs` of the observer
+    Beliefs,
+    // `State::unseen_counts` of the observer
+    CardCounting,
+    // `State::playability` of the observer
+    Playability,
+    // `State::teammate_summary` of the observer, padded to `MAXPLAYERS - 1` teammates
+    Teammates,
+    // the variant, one-hot in `Variant::names()`, and the rule modifiers in `modifier::names()`;
+    // a variant or a user-defined modifier missing from them is left out
+    VariantInfo,
+}
+
+impl Feature {
+    pub fn names() -> Vec<&'static str> {
+        vec![
+            "core",
+            "history",
+            "knowledge",
+            "beliefs",
+            "card_counting",
+            "playability",
+            "teammates",
+            "variant_info",
+        ]
+    }
+
+    pub fn from_name(name: &str) -> Option<Feature> {
+        match name {
+            "core" => Some(Feature::Core),
+            "history" => Some(Feature::History),
+            "knowledge" => Some(Feature::Knowledge),
+            "beliefs" => Some(Feature::Beliefs),
+            "card_counting" => Some(Feature::CardCounting),
+            "playability" => Some(Feature::Playability),
+            "teammates" => Some(Feature::Teammates),
+            "variant_info" => Some(Feature::VariantInfo),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        Feature::names()[self as usize]
+    }
+}
+
+// an observation made of features in the order they are added, e.g.
+//
+//     EncoderBuilder::new().feature(Feature::Core).feature(Feature::Beliefs).build()
+//
+// core followed by history is the v1 observation
+#[derive(Clone, Debug, Default)]
+pub struct EncoderBuilder {
+    features: Vec<Feature>,
+    slots: SlotOrder,
+}
+
+impl EncoderBuilder {
+    pub fn new() -> EncoderBuilder {
+        EncoderBuilder::default()
+    }
+
+    // the blocks of the v2 observation, for every variant and rule modifier
+    pub fn v2() -> EncoderBuilder {
+        [
+            Feature::Core,
+            Feature::History,
+            Feature::Knowledge,
+            Feature::CardCounting,
+            Feature::Playability,
+            Feature::VariantInfo,
+        ]
+        .iter()
+        .fold(EncoderBuilder::new(), |builder, &x| builder.feature(x))
+    }
+
+    // the features named in `names`, None if one is unknown
+    pub fn from_names<S: AsRef<str>>(names: &[S]) -> Option<EncoderBuilder> {
+        let mut builder = EncoderBuilder::new();
+        for name in names {
+            builder = builder.feature(Feature::from_name(name.as_ref())?);
+        }
+        Some(builder)
+    }
+
+    pub fn feature(mut self, feature: Feature) -> EncoderBuilder {
+        self.features.push(feature);
+        self
+    }
+
+    // order of the slots of the hands, of the positions of the history and of the per slot features
+    pub fn slots(mut self, slots: SlotOrder) -> EncoderBuilder {
+        self.slots = slots;
+        self
+    }
+
+    pub fn build(self) -> FeatureEncoder {
+        FeatureEncoder {
+            features: self.features,
+            slots: self.slots,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct FeatureEncoder {
+    features: Vec<Feature>,
+    slots: SlotOrder,
+}
+
+impl FeatureEncoder {
+    pub fn features(&self) -> &[Feature] {
+        &self.features
+    }
+
+    // observation of `player`
+    pub fn encode(&self, state: &State, player: usize) -> Array1<f32> {
+        let mut x = Vec::new();
+        let v1 = if self.features.contains(&Feature::Core)
+            || self.features.contains(&Feature::History)
+        {
+            state.encode_in(player, self.slots)
+        } else {
+            Array1::zeros(0)
+        };
+        let core = core_size(state);
+        for &feature in &self.features {
+            match feature {
+                Feature::Core => x.extend(v1.slice(ndarray::s![..core]).iter()),
+                Feature::History => x.extend(v1.slice(ndarray::s![core..]).iter()),
+                Feature::Knowledge => x.extend(self.knowledge(state).iter()),
+                Feature::Beliefs => {
+                    let b = state.beliefs(player);
+                    let n = b.len() / MAXCARDS;
+                    let b =
+                        Array2::from_shape_vec((MAXCARDS, n), b.iter().copied().collect()).unwrap();
+                    x.extend(self.reorder(state, player, b).iter());
+                }
+                Feature::CardCounting => x.extend(state.unseen_counts(player).iter()),
+                Feature::Playability => x.extend(
+                    self.reorder(state, player, state.playability(player))
+                        .iter(),
+                ),
+                Feature::Teammates => {
+                    // zeros for the seats missing from the game, as for the other blocks
+                    let missing = MAXPLAYERS - state.players().len();
+                    x.extend(state.teammate_summary(player).iter());
+                    x.extend(std::iter::repeat_n(0.0, missing * 3));
+                }
+                Feature::VariantInfo => x.extend(variant_info(state).iter()),
+            }
+        }
+        Array1::from(x)
+    }
+
+    pub fn size(&self, state: &State) -> usize {
+        self.layout(state).last().map_or(0, |x| x.start + x.len)
+    }
+
+    // blocks of `encode` in order, those of `State::encoding_layout` for the core and the history
+    pub fn layout(&self, state: &State) -> Vec<Block> {
+        let v1 = state.encoding_layout();
+        let core = core_size(state);
+        let width = 5 + state.variant().ncolors();
+        let nsuits = state.variant().suits().len();
+        let mut blocks: Vec<Block> = Vec::new();
+        let mut push = |name: String, len: usize| {
+            let start = blocks.last().map_or(0, |x| x.start + x.len);
+            blocks.push(Block { name, start, len });
+        };
+        for &feature in &self.features {
+            match feature {
+                Feature::Core | Feature::History => {
+                    let history = feature == Feature::History;
+                    for block in v1.iter().filter(|x| (x.start >= core) == history) {
+                        push(block.name.clone(), block.len);
+                    }
+                }
+                Feature::Knowledge => {
+                    for i in 0..MAXPLAYERS {
+                        push(format!("knowledge {}", i), MAXCARDS * width);
+                    }
+                }
+                Feature::Beliefs => push("beliefs".to_string(), MAXCARDS * nsuits * 5),
+                Feature::CardCounting => push("card_counting".to_string(), nsuits * 5),
+                Feature::Playability => push("playability".to_string(), MAXCARDS * 3),
+                Feature::Teammates => push("teammates".to_string(), (MAXPLAYERS - 1) * 3),
+                Feature::VariantInfo => push(
+                    "variant_info".to_string(),
+                    Variant::names().len() + modifier::names().len(),
+                ),
+            }
+        }
+        blocks
+    }
+
+    // rows of the slots of the hand of `player` in the slot order
+    fn reorder(&self, state: &State, player: usize, x: Array2<f32>) -> Array2<f32> {
+        let slots = self.slots.for_state(state);
+        let hand = state.players()[player].len();
+        let mut y = x.clone();
+        for j in 0..hand {
+            y.row_mut(slots.convert(j, hand)).assign(&x.row(j));
+        }
+        y
+    }
+
+    fn knowledge(&self, state: &State) -> Array1<f32> {
+        let slots = self.slots.for_state(state);
+        let colors = state.variant().colors();
+        let width = 5 + colors.len();
+        let mut x = Array2::zeros((MAXPLAYERS * MAXCARDS, width));
+        for (i, hand) in state.knowledge().iter().enumerate() {
+            for (j, knowledge) in hand.iter().enumerate() {
+                let mut row = x.row_mut(i * MAXCARDS + slots.convert(j, hand.len()));
+                if let Some(value) = knowledge.value {
+                    row[value.index()] = 1.0;
+                }
+                if let Some(color) = knowledge.color {
+                    row[5 + colors.iter().position(|&c| c == color).unwrap()] = 1.0;
+                }
+            }
+        }
+        x.iter().copied().collect()
+    }
+}
+
+// entries of the v1 observation before the history
+fn core_size(state: &State) -> usize {
+    let layout = state.encoding_layout();
+    let rules = layout.iter().find(|x| x.name == "rules").unwrap();
+    rules.start + rules.len
+}
+
+fn variant_info(state: &State) -> Array1<f32> {
+    let variants = Variant::names();
+    let modifiers = modifier::names();
+    let mut x = Array1::zeros(variants.len() + modifiers.len());
+    if let Some(i) = variants.iter().position(|&x| x == state.variant().name()) {
+        x[i] = 1.0;
+    }
+    for name in state.modifiers().names() {
+        if let Some(i) = modifiers.iter().position(|&x| x == name) {
+            x[variants.len() + i] = 1.0;
+        }
+    }
+    x
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Encoder, EncoderBuilder, Feature};
+    use crate::modifier::{self, RuleModifier};
+    use crate::state::{State, MAXPLAYERS};
+    use crate::variant::Variant;
+    use ndarray::s;
+
+    #[test]
+    fn v2_extends_v1_in_every_variant() {
+        for name in Variant::names() {
+            let state = State::with_variant(3, Variant::from_name(name).unwrap(), Some(2));
+            let v1 = Encoder::V1.encode(&state, 1);
+            let v2 = Encoder::V2.encode(&state, 1);
+            assert_eq!(v2.len(), EncoderBuilder::v2().build().size(&state));
+            assert_eq!(v2.slice(s![..v1.len()]), v1);
+            assert_eq!(Encoder::from_name(Encoder::V2.name()), Some(Encoder::V2));
+        }
+    }
+
+    #[test]
+    fn widths_do_not_depend_on_the_player_count() {
+        let encoder = EncoderBuilder::new()
+            .feature(Feature::Teammates)
+            .feature(Feature::Playability)
+            .build();
+        let widths: Vec<usize> = [2, MAXPLAYERS]
+            .iter()
+            .map(|&players| {
+                let state = State::with_seed(players, 0);
+                let x = encoder.encode(&state, 0);
+                assert_eq!(x.len(), encoder.size(&state));
+                x.len()
+            })
+            .collect();
+        assert_eq!(widths[0], widths[1]);
+    }
+
+    #[derive(Clone)]
+    struct Custom;
+
+    impl RuleModifier for Custom {
+        fn name(&self) -> &str {
+            "custom"
+        }
+
+        fn box_clone(&self) -> Box<dyn RuleModifier> {
+            Box::new(self.clone())
+        }
+
+        fn fresh(&self) -> Box<dyn RuleModifier> {
+            Box::new(self.clone())
+        }
+    }
+
+    #[test]
+    fn user_defined_modifiers_are_left_out_of_variant_info() {
+        let mut modifiers = modifier::resolve(&["clue_starved"]).unwrap();
+        modifiers.push(Box::new(Custom));
+        let state = State::with_seed(2, 0).modified(modifiers);
+        let x = Encoder::V2.encode(&state, 0);
+        assert_eq!(x.len(), EncoderBuilder::v2().build().size(&state));
+        let info = EncoderBuilder::new()
+            .feature(Feature::VariantInfo)
+            .build()
+            .encode(&state, 0);
+        // the standard variant and clue_starved
+        assert_eq!(info.sum(), 2.0);
+    }
+}
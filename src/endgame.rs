@@ -0,0 +1,414 @@
+#![allow(dead_code)]
+
+use crate::manifest::fnv1a;
+use crate::state::{ActionRequest, Card, State, Value, MAXCARDS, MAXMISTAKES, NACTIONS};
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use rayon::prelude::*;
+use std::collections::HashMap;
+
+#[derive(Debug)]
+pub enum EndgameError {
+    GameOver,
+    TooManyDeals(usize),
+    // the number of cards in the deck, above the limit of `State::solve_endgame`
+    DeckTooLarge(usize),
+}
+
+// value of an action of the player to move over all the consistent deals
+#[derive(Debug)]
+pub struct ActionValue {
+    // with every card known the clues are all worth the same, one of them stands for all
+    pub action: ActionRequest,
+    pub expected: f64,
+    pub worst: usize,
+    pub best: usize,
+}
+
+// best final score when every card is known
+pub fn solve(state: &State) -> usize {
+    search(state, &mut HashMap::new())
+}
+
+// what matters for the rest of the game once every card is known, the deck order being fixed
+fn key(state: &State) -> u64 {
+    let mut bytes = vec![
+        (state.turn() % state.players().len()) as u8,
+        state.final_round().map_or(u8::MAX, |x| x as u8),
+        *state.clues() as u8,
+        *state.mistakes() as u8,
+        state.deck().len() as u8,
+    ];
+    bytes.extend(state.table().iter().map(|&x| x as u8));
+    for hand in state.players() {
+        bytes.push(hand.len() as u8);
+        for card in hand {
+            bytes.push(card.color().index() as u8);
+            bytes.push(card.value().index() as u8);
+        }
+    }
+    fnv1a(&bytes)
+}
+
+// score if every card still in a hand or in the deck was played in time, and at most one per turn
+// left once the deck is empty (a play draws, there are as many plays before the last card as
+// cards in the deck)
+fn upper_bound(state: &State) -> usize {
+    let plays = match *state.final_round() {
+        Some(left) => left,
+        None => state.deck().len() + state.players().len(),
+    };
+    let variant = state.variant();
+    let mut left: Vec<&Card> = state.deck().iter().collect();
+    for hand in state.players() {
+        left.extend(hand);
+    }
+    variant
+        .suits()
+        .iter()
+        .map(|&suit| {
+            let mut value = state.table()[variant.suit_index(suit)];
+            while value < 5
+                && left
+                    .iter()
+                    .any(|card| card.color() == suit && card.value().index() == value)
+            {
+                value += 1;
+            }
+            value
+        })
+        .sum::<usize>()
+        .min(state.score() + plays)
+}
+
+fn search(state: &State, memo: &mut HashMap<u64, usize>) -> usize {
+    if state.gameover() {
+        return state.score();
+    }
+    let key = key(state);
+    if let Some(&score) = memo.get(&key) {
+        return score;
+    }
+    let bound = upper_bound(state);
+    let mut best = state.score();
+    if best < bound {
+        for action in useful(state) {
+            let mut next = state.fork();
+            next.apply(&action).unwrap();
+            best = best.max(search(&next, memo));
+            if best == bound {
+                break;
+            }
+        }
+    }
+    memo.insert(key, best);
+    best
+}
+
+// legal plays and discards, and the first legal clue
+fn candidates(state: &State) -> Vec<ActionRequest> {
+    let legal = |action: &ActionRequest| state.check(action).is_ok();
+    let mut actions: Vec<ActionRequest> = (0..2 * MAXCARDS)
+        .map(ActionRequest::from_id)
+        .filter(legal)
+        .collect();
+    actions.extend(
+        (2 * MAXCARDS..NACTIONS)
+            .map(ActionRequest::from_id)
+            .find(legal),
+    );
+    actions
+}
+
+// candidates without the misplays, a discard of the same card is always as good
+fn useful(state: &State) -> Vec<ActionRequest> {
+    let hand = &state.players()[state.turn() % state.players().len()];
+    let variant = state.variant();
+    let can_discard = state.check(&ActionRequest::Discard { position: 0 }).is_ok();
+    let mut actions = candidates(state);
+    actions.retain(|action| match *action {
+        ActionRequest::Play { position } => {
+            let card = hand[position];
+            state.table()[variant.suit_index(card.color())] == card.value().index()
+                || !can_discard && state.mistakes() + 1 < MAXMISTAKES
+        }
+        _ => true,
+    });
+    actions
+}
+
+pub type Deal = (Vec<Card>, Vec<Card>);
+
+// (hand, deck) of the player to move consistent with the cards they see and their clues
+pub fn deals(state: &State, limit: usize) -> Result<Vec<Deal>, EndgameError> {
+    let n = state.players().len();
+    let player = state.turn() % n;
+    let mut unseen = unseen(state, player);
+
+    let hand = state.players()[player].len();
+    let slots = hand + state.deck().len();
+    let mut out = Vec::new();
+    let mut cards = Vec::new();
+    enumerate(
+        state,
+        player,
+        &mut unseen,
+        slots,
+        &mut cards,
+        &mut out,
+        limit,
+    )?;
+    Ok(out
+        .into_iter()
+        .map(|mut cards: Vec<Card>| {
+            let deck = cards.split_off(hand);
+            (cards, deck)
+        })
+        .collect())
+}
+
+// the cards `player` cannot see, with their number of copies
+pub fn unseen(state: &State, player: usize) -> Vec<(Card, usize)> {
+    let variant = state.variant();
+    let mut unseen: Vec<(Card, usize)> = Vec::new();
+    for card in variant.deck() {
+        match unseen.iter_mut().find(|(x, _)| *x == card) {
+            Some((_, count)) => *count += 1,
+            None => unseen.push((card, 1)),
+        }
+    }
+    let mut seen: Vec<Card> = state.discard().clone();
+    for (i, hand) in state.players().iter().enumerate() {
+        if i != player {
+            seen.extend(hand);
+        }
+    }
+    for &suit in variant.suits() {
+        for value in 0..state.table()[variant.suit_index(suit)] {
+            seen.push(Card::new(Value::new(value), suit));
+        }
+    }
+    for card in seen {
+        if let Some((_, count)) = unseen.iter_mut().find(|(x, _)| *x == card) {
+            *count -= 1;
+        }
+    }
+    unseen
+}
+
+// whether `card` agrees with the clues `player` received about their card at `position`
+pub fn consistent(state: &State, player: usize, position: usize, card: &Card) -> bool {
+    match state.knowledge()[player].get(position) {
+        Some(knowledge) => knowledge.allows(card, state.variant()),
+        None => true,
+    }
+}
+
+fn enumerate(
+    state: &State,
+    player: usize,
+    unseen: &mut Vec<(Card, usize)>,
+    slots: usize,
+    cards: &mut Vec<Card>,
+    out: &mut Vec<Vec<Card>>,
+    limit: usize,
+) -> Result<(), EndgameError> {
+    if cards.len() == slots {
+        if out.len() == limit {
+            return Err(EndgameError::TooManyDeals(limit));
+        }
+        out.push(cards.clone());
+        return Ok(());
+    }
+    for i in 0..unseen.len() {
+        let (card, count) = unseen[i];
+        if count == 0 {
+            continue;
+        }
+        if !consistent(state, player, cards.len(), &card) {
+            continue;
+        }
+        unseen[i].1 -= 1;
+        cards.push(card);
+        enumerate(state, player, unseen, slots, cards, out, limit)?;
+        cards.pop();
+        unseen[i].1 += 1;
+    }
+    Ok(())
+}
+
+// exact value of every action of the player to move, averaged over the deals consistent with
+// what they know; returns the values and the number of deals
+pub fn evaluate(state: &State, limit: usize) -> Result<(Vec<ActionValue>, usize), EndgameError> {
+    if state.gameover() {
+        return Err(EndgameError::GameOver);
+    }
+    let deals = deals(state, limit)?;
+    let n = deals.len();
+    Ok((values(state, deals), n))
+}
+
+// every candidate action solved with full information on each deal of the player to move
+fn values(state: &State, deals: Vec<Deal>) -> Vec<ActionValue> {
+    let player = state.turn() % state.players().len();
+    let actions = candidates(state);
+    let deals: Vec<State> = deals
+        .into_iter()
+        .map(|(hand, deck)| state.with_hidden(player, hand, deck))
+        .collect();
+    let scores: Vec<Vec<usize>> = deals
+        .into_par_iter()
+        .map(|deal| {
+            let mut memo = HashMap::new();
+            actions
+                .iter()
+                .map(|action| {
+                    let mut next = deal.fork();
+                    next.apply(action).unwrap();
+                    search(&next, &mut memo)
+                })
+                .collect()
+        })
+        .collect();
+
+    actions
+        .iter()
+        .enumerate()
+        .map(|(i, &action)| {
+            let column = scores.iter().map(|x| x[i]);
+            ActionValue {
+                action,
+                expected: column.clone().sum::<usize>() as f64 / scores.len().max(1) as f64,
+                worst: column.clone().min().unwrap_or(0),
+                best: column.max().unwrap_or(0),
+            }
+        })
+        .collect()
+}
+
+// deals enumerated before falling back to sampled ones, and the number of samples
+pub const EXACT_DEALS: usize = 2_000;
+pub const SAMPLED_DEALS: usize = 200;
+
+// the values of `evaluate` and the best expected score
+#[derive(Debug)]
+pub struct Solution {
+    pub values: Vec<ActionValue>,
+    pub expected: f64,
+    pub deals: usize,
+    // every consistent deal was solved, else `SAMPLED_DEALS` drawn with `State::sample_deal`
+    pub exact: bool,
+}
+
+impl State {
+    // the maximum expected final score of the player to move with at most `k` cards in the deck,
+    // each deal of their hand and the deck solved with full information; the samples are seeded by
+    // the public state, the same position gives the same solution; the search grows quickly with
+    // the deck, a few cards take milliseconds and five can take minutes
+    pub fn solve_endgame(&self, k: usize) -> Result<Solution, EndgameError> {
+        if self.gameover() {
+            return Err(EndgameError::GameOver);
+        }
+        if self.deck().len() > k {
+            return Err(EndgameError::DeckTooLarge(self.deck().len()));
+        }
+        let player = self.turn() % self.players().len();
+        let (deals, exact) = match deals(self, EXACT_DEALS) {
+            Ok(deals) => (deals, true),
+            Err(EndgameError::TooManyDeals(_)) => {
+                let mut rng = StdRng::seed_from_u64(self.public_hash());
+                let deals = (0..SAMPLED_DEALS)
+                    .filter_map(|_| self.sample_deal(player, &mut rng))
+                    .collect();
+                (deals, false)
+            }
+            Err(err) => return Err(err),
+        };
+        let n = deals.len();
+        let values = values(self, deals);
+        let expected = values.iter().map(|x| x.expected).fold(0.0, f64::max);
+        Ok(Solution {
+            values,
+            expected,
+            deals: n,
+            exact,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agents::{Agent, ConventionBot, Observation};
+
+    // a game of two convention bots stopped with at most `k` cards in the deck
+    fn near_end(seed: u64, k: usize) -> State {
+        let mut state = State::with_seed(2, seed);
+        while state.deck().len() > k && !state.gameover() {
+            let action = ConventionBot.act(&Observation::new(&state));
+            state.apply(&action).unwrap();
+        }
+        state
+    }
+
+    fn sorted(mut cards: Vec<Card>) -> Vec<Card> {
+        cards.sort_by_key(|card| (card.color().index(), card.value().index()));
+        cards
+    }
+
+    #[test]
+    fn solve_is_between_the_score_and_the_bound() {
+        for seed in 0..5 {
+            let state = near_end(seed, 2);
+            let best = solve(&state);
+            assert!(
+                state.score() <= best && best <= upper_bound(&state),
+                "seed {}",
+                seed
+            );
+        }
+        let mut state = near_end(0, 0);
+        while !state.gameover() {
+            let action = ConventionBot.act(&Observation::new(&state));
+            state.apply(&action).unwrap();
+        }
+        assert_eq!(solve(&state), state.score());
+    }
+
+    #[test]
+    fn deals_hold_the_true_one() {
+        for seed in 0..5 {
+            let state = near_end(seed, 1);
+            assert_eq!(state.deck().len(), 1);
+            let player = state.turn() % state.players().len();
+            let hand = state.players()[player].to_vec();
+            let deck = state.deck().clone();
+            let deals = deals(&state, EXACT_DEALS).unwrap();
+            assert!(
+                deals.contains(&(hand.clone(), deck.clone())),
+                "seed {}",
+                seed
+            );
+            let cards = sorted([hand, deck].concat());
+            for (hand, deck) in deals {
+                assert_eq!(sorted([hand, deck].concat()), cards);
+            }
+        }
+    }
+
+    #[test]
+    fn endgame_values_bound_the_full_information_score() {
+        let state = near_end(1, 1);
+        let solution = state.solve_endgame(1).unwrap();
+        assert!(solution.exact);
+        for value in &solution.values {
+            assert!(value.worst as f64 <= value.expected && value.expected <= value.best as f64);
+        }
+        let best = solution.values.iter().map(|x| x.best).max().unwrap();
+        assert!(solve(&state) <= best);
+        assert!(matches!(
+            state.solve_endgame(0),
+            Err(EndgameError::DeckTooLarge(1))
+        ));
+    }
+}
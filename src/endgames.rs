@@ -0,0 +1,140 @@
+// exact positions without their history (hands, deck, fireworks and tokens), to benchmark the
+// searches on endgames whose best score is known, see `State::from_position`
+use crate::state::{Card, State, Value, MAXCARDS, MAXCLUES, MAXMISTAKES, MAXPLAYERS};
+use crate::variant::Variant;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug)]
+pub enum PositionError {
+    Parse(String),
+    Invalid(String),
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Position {
+    #[serde(default)]
+    pub name: String,
+    #[serde(default = "Variant::standard")]
+    pub variant: Variant,
+    // in the order of the engine, newest card first
+    pub hands: Vec<Vec<Card>>,
+    // the cards still to draw, the next one first
+    #[serde(default)]
+    pub deck: Vec<Card>,
+    // the highest value played of each suit of the variant, in its order
+    pub fireworks: Vec<usize>,
+    pub clues: usize,
+    #[serde(default)]
+    pub mistakes: usize,
+    // the player to move
+    #[serde(default)]
+    pub turn: usize,
+    // turns left once the deck is empty, one per player by default
+    #[serde(default)]
+    pub final_round: Option<usize>,
+    // the best final score with every card known, for the bundled puzzles
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub solution: Option<usize>,
+}
+
+impl Position {
+    // the position of a game, its history and what was told dropped
+    pub fn of_state(state: &State) -> Position {
+        Position {
+            name: String::new(),
+            variant: state.variant().clone(),
+            hands: state.players().iter().map(|x| x.to_vec()).collect(),
+            deck: state.deck().iter().rev().copied().collect(),
+            fireworks: state.table().clone(),
+            clues: *state.clues(),
+            mistakes: *state.mistakes(),
+            turn: state.turn() % state.players().len(),
+            final_round: *state.final_round(),
+            solution: None,
+        }
+    }
+
+    pub fn from_json(json: &str) -> Result<Position, PositionError> {
+        serde_json::from_str(json).map_err(|err| PositionError::Parse(err.to_string()))
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).unwrap()
+    }
+
+    pub fn validate(&self) -> Result<(), PositionError> {
+        let invalid = |message: String| Err(PositionError::Invalid(message));
+        let players = self.hands.len();
+        if !(2..=MAXPLAYERS).contains(&players) {
+            return invalid(format!("{} players is not supported", players));
+        }
+        if let Some(hand) = self.hands.iter().find(|x| x.len() > MAXCARDS) {
+            return invalid(format!("a hand of {} cards", hand.len()));
+        }
+        if self.fireworks.len() != self.variant.suits().len() {
+            return invalid(format!(
+                "{} fireworks for {} suits",
+                self.fireworks.len(),
+                self.variant.suits().len()
+            ));
+        }
+        if self.fireworks.iter().any(|&x| x > 5) {
+            return invalid(format!("fireworks {:?} above 5", self.fireworks));
+        }
+        if self.clues > MAXCLUES || self.mistakes >= MAXMISTAKES {
+            return invalid(format!(
+                "{} clues and {} mistakes",
+                self.clues, self.mistakes
+            ));
+        }
+        if self.turn >= players {
+            return invalid(format!("no player {}", self.turn));
+        }
+        match self.final_round {
+            Some(_) if !self.deck.is_empty() => {
+                return invalid("a final round with cards left to draw".to_string())
+            }
+            Some(left) if left == 0 || left > players => {
+                return invalid(format!("{} turns left for {} players", left, players))
+            }
+            _ => {}
+        }
+        self.discard().map(|_| ())
+    }
+
+    pub fn played(&self) -> Vec<Card> {
+        self.variant
+            .suits()
+            .iter()
+            .zip(&self.fireworks)
+            .flat_map(|(&suit, &n)| (0..n).map(move |value| Card::new(Value::new(value), suit)))
+            .collect()
+    }
+
+    // the cards of the variant neither in a hand, nor in the deck nor played
+    pub fn discard(&self) -> Result<Vec<Card>, PositionError> {
+        let mut left = self.variant.deck();
+        let played = self.played();
+        for card in self.hands.iter().flatten().chain(&self.deck).chain(&played) {
+            match left.iter().position(|x| x == card) {
+                Some(i) => {
+                    left.remove(i);
+                }
+                None => {
+                    return Err(PositionError::Invalid(format!(
+                        "too many {} for the {} variant",
+                        card,
+                        self.variant.name()
+                    )))
+                }
+            }
+        }
+        Ok(left)
+    }
+}
+
+// the puzzles shipped with the crate, their solution is that of `endgame::solve`
+pub fn puzzles() -> Vec<Position> {
+    serde_json::from_str(include_str!("endgames.json")).unwrap()
+}
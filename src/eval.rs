@@ -0,0 +1,454 @@
+#![allow(dead_code)]
+
+use crate::agents::{self, Agent, Observation};
+use crate::driver::GameDriver;
+use crate::state::{ActionRequest, State, MAXMISTAKES, NACTIONS};
+use crate::variant::Variant;
+use ndarray::{Array2, ArrayView1};
+use rayon::prelude::*;
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Default)]
+pub struct Summary {
+    pub games: usize,
+    pub scores: Vec<usize>,
+    pub mean_score: f64,
+    pub std_score: f64,
+    pub perfect: f64,
+    pub bombed: f64,
+    pub mean_turns: f64,
+    // games stopped because the policy chose an illegal action
+    pub illegal: usize,
+    // games given up by each seat of a lineup, see `Agent::forfeit`, out of time included
+    pub forfeits: Vec<usize>,
+    // moves over budget of each seat of a lineup and the seconds it used over every game, with a
+    // time control, see `GameDriver::time_control`
+    pub timeouts: Vec<usize>,
+    pub seconds_used: Vec<f64>,
+    // 95% confidence intervals, normal for the mean score and wilson for the rates
+    pub score_ci: (f64, f64),
+    pub perfect_ci: (f64, f64),
+    pub bombed_ci: (f64, f64),
+    // time taken to choose each move, over every seat and then for each seat of a lineup
+    pub latency: Latency,
+    pub seat_latency: Vec<Latency>,
+}
+
+// percentiles of the time taken to choose a move, in seconds; online play with a clock depends on
+// the slowest moves more than on the mean
+#[derive(Clone, Debug, Default)]
+pub struct Latency {
+    pub moves: usize,
+    pub mean: f64,
+    pub p50: f64,
+    pub p90: f64,
+    pub p99: f64,
+    pub max: f64,
+}
+
+impl Latency {
+    pub fn of(times: &[Duration]) -> Latency {
+        let mut seconds: Vec<f64> = times.iter().map(|x| x.as_secs_f64()).collect();
+        seconds.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        let n = seconds.len();
+        // nearest rank
+        let percentile = |p: f64| match n {
+            0 => 0.0,
+            _ => seconds[((p * n as f64).ceil() as usize).clamp(1, n) - 1],
+        };
+        Latency {
+            moves: n,
+            mean: seconds.iter().sum::<f64>() / n.max(1) as f64,
+            p50: percentile(0.5),
+            p90: percentile(0.9),
+            p99: percentile(0.99),
+            max: seconds.last().copied().unwrap_or(0.0),
+        }
+    }
+}
+
+// plays `games` games seeded from `seed`, `policy` returns None to stop a game
+pub fn evaluate<F>(
+    games: usize,
+    players: usize,
+    variant: &Variant,
+    seed: u64,
+    mut policy: F,
+) -> Summary
+where
+    F: FnMut(&State) -> Option<ActionRequest>,
+{
+    let mut outcomes = Vec::new();
+    let mut times = Vec::new();
+    for i in 0..games {
+        let mut state = State::with_variant(players, variant.clone(), Some(seed + i as u64));
+        let mut illegal = false;
+        while !state.gameover() {
+            let start = Instant::now();
+            let action = policy(&state);
+            times.push(start.elapsed());
+            let legal = match action {
+                Some(action) => state.apply(&action).is_ok(),
+                None => false,
+            };
+            if !legal {
+                illegal = true;
+                break;
+            }
+        }
+        outcomes.push(Outcome::new(&state, illegal));
+    }
+    Summary {
+        latency: Latency::of(&times),
+        ..summarize(&outcomes, variant.max_score())
+    }
+}
+
+// the same with every game of the batch played in lockstep: at each turn `policy` gets the
+// observations and the legal masks of the games still running, one row per game, and returns one
+// logit per action for each row, of which the legal action of largest logit is played; a single
+// call for many games lets a network run on a gpu. None from `policy` stops the evaluation
+pub fn evaluate_batched<F>(
+    games: usize,
+    players: usize,
+    variant: &Variant,
+    seed: u64,
+    mut policy: F,
+) -> Option<Summary>
+where
+    F: FnMut(Array2<f32>, Array2<f32>) -> Option<Array2<f32>>,
+{
+    let mut states: Vec<State> = (0..games)
+        .map(|i| State::with_variant(players, variant.clone(), Some(seed + i as u64)))
+        .collect();
+    let mut illegal = vec![false; games];
+    let mut times = Vec::new();
+    loop {
+        let running: Vec<usize> = (0..games)
+            .filter(|&i| !states[i].gameover() && !illegal[i])
+            .collect();
+        let width = match running.first() {
+            Some(&i) => states[i].encode().len(),
+            None => break,
+        };
+        let mut observations = Array2::zeros((running.len(), width));
+        let mut masks = Array2::zeros((running.len(), NACTIONS));
+        for (row, &i) in running.iter().enumerate() {
+            states[i].encode_into(&mut observations.row_mut(row));
+            masks.row_mut(row).assign(&states[i].legal_mask());
+        }
+        let start = Instant::now();
+        let logits = policy(observations, masks.clone())?;
+        let elapsed = start.elapsed() / running.len() as u32;
+        times.extend(running.iter().map(|_| elapsed));
+        for (row, &i) in running.iter().enumerate() {
+            let action = best_legal(logits.row(row), masks.row(row)).map(ActionRequest::from_id);
+            illegal[i] = match action {
+                Some(action) => states[i].apply(&action).is_err(),
+                None => true,
+            };
+        }
+    }
+    let outcomes: Vec<Outcome> = states
+        .iter()
+        .zip(illegal)
+        .map(|(state, illegal)| Outcome::new(state, illegal))
+        .collect();
+    Some(Summary {
+        latency: Latency::of(&times),
+        ..summarize(&outcomes, variant.max_score())
+    })
+}
+
+// the legal action of largest logit, None if no action is legal
+pub fn best_legal(logits: ArrayView1<f32>, mask: ArrayView1<f32>) -> Option<usize> {
+    (0..NACTIONS)
+        .filter(|&id| mask[id] > 0.0)
+        .max_by(|&i, &j| logits[i].total_cmp(&logits[j]))
+}
+
+// the same for the agents seated in `driver`
+pub fn evaluate_lineup(driver: &mut GameDriver, games: usize, seed: u64) -> Summary {
+    let seats = driver.seats.len();
+    let mut forfeits = vec![0; seats];
+    // the records of a driver without time control have no clock
+    let clocked = if driver.time_control.is_some() {
+        seats
+    } else {
+        0
+    };
+    let mut timeouts = vec![0; clocked];
+    let mut seconds_used = vec![0.0; clocked];
+    let mut times = vec![Vec::new(); seats];
+    let outcomes: Vec<Outcome> = (0..games)
+        .map(|i| {
+            let record = driver.play(Some(seed + i as u64));
+            if let Some((seat, _)) = record.forfeit {
+                forfeits[seat] += 1;
+            }
+            for (turn, &time) in record.move_times.iter().enumerate() {
+                times[turn % seats].push(time);
+            }
+            for (seat, time) in record.clock.iter().enumerate() {
+                timeouts[seat] += time.timeouts;
+                seconds_used[seat] += time.used.as_secs_f64();
+            }
+            Outcome {
+                score: record.score,
+                turns: record.turns,
+                bombed: record.mistakes >= MAXMISTAKES,
+                illegal: record.illegal.is_some(),
+            }
+        })
+        .collect();
+    Summary {
+        forfeits,
+        timeouts,
+        seconds_used,
+        latency: Latency::of(&times.concat()),
+        seat_latency: times.iter().map(|x| Latency::of(x)).collect(),
+        ..summarize(&outcomes, driver.variant.max_score())
+    }
+}
+
+// mean score of every pairing of `agents` agents over `games` games: `scores[[i, j]]` with agent i
+// in one seat, which turns from game to game, and agent j in all the others, every pairing on the
+// same deals; `agent(k, seed)` seats agent k, None stops the evaluation
+pub fn cross_play<F>(
+    agents: usize,
+    players: usize,
+    variant: &Variant,
+    games: usize,
+    seed: u64,
+    mut agent: F,
+) -> Option<Array2<f64>>
+where
+    F: FnMut(usize, u64) -> Option<Box<dyn Agent>>,
+{
+    let mut scores = Array2::zeros((agents, agents));
+    for i in 0..agents {
+        for j in 0..agents {
+            let mut total = 0;
+            for game in 0..games {
+                let seed = seed.wrapping_add(game as u64);
+                let seats = (0..players)
+                    .map(|seat| {
+                        let k = if seat == game % players { i } else { j };
+                        agent(k, seed.wrapping_add(seat as u64))
+                    })
+                    .collect::<Option<Vec<Box<dyn Agent>>>>()?;
+                total += GameDriver::new(variant.clone(), seats)
+                    .play(Some(seed))
+                    .score;
+            }
+            scores[[i, j]] = total as f64 / games.max(1) as f64;
+        }
+    }
+    Some(scores)
+}
+
+// number of games of each final score, from 0 to the maximum of the variant, of `agent` (see
+// `agents::from_name`) in every seat; the games are played in parallel, a game stops at an illegal
+// action; None if the agent is unknown
+pub fn score_histogram(
+    agent: &str,
+    games: usize,
+    players: usize,
+    variant: &Variant,
+    seed: u64,
+) -> Option<Vec<usize>> {
+    agents::from_name(agent, seed)?;
+    let scores: Vec<usize> = (0..games)
+        .into_par_iter()
+        .map(|i| {
+            let seed = seed + i as u64;
+            let mut bot = agents::from_name(agent, seed).unwrap();
+            let mut state = State::with_variant(players, variant.clone(), Some(seed));
+            while !state.gameover() {
+                let action = bot.act(&Observation::new(&state));
+                if state.apply(&action).is_err() {
+                    break;
+                }
+            }
+            state.final_score()
+        })
+        .collect();
+    let mut histogram = vec![0; variant.max_score() + 1];
+    for score in scores {
+        histogram[score] += 1;
+    }
+    Some(histogram)
+}
+
+// mean score difference `b - a` of two evaluations on the same seeds, and its 95% confidence interval
+pub fn paired_difference(a: &Summary, b: &Summary) -> (f64, (f64, f64)) {
+    let diffs: Vec<f64> = a
+        .scores
+        .iter()
+        .zip(b.scores.iter())
+        .map(|(&x, &y)| y as f64 - x as f64)
+        .collect();
+    let (mean, std) = mean_std(&diffs);
+    (mean, normal_interval(mean, std, diffs.len()))
+}
+
+struct Outcome {
+    score: usize,
+    turns: usize,
+    bombed: bool,
+    illegal: bool,
+}
+
+impl Outcome {
+    fn new(state: &State, illegal: bool) -> Outcome {
+        Outcome {
+            score: state.final_score(),
+            turns: *state.turn(),
+            bombed: *state.mistakes() >= MAXMISTAKES,
+            illegal,
+        }
+    }
+}
+
+fn summarize(outcomes: &[Outcome], max_score: usize) -> Summary {
+    let games = outcomes.len();
+    let scores: Vec<usize> = outcomes.iter().map(|x| x.score).collect();
+    let (mean_score, std_score) = mean_std(&scores.iter().map(|&x| x as f64).collect::<Vec<f64>>());
+    let perfect = outcomes.iter().filter(|x| x.score == max_score).count();
+    let bombed = outcomes.iter().filter(|x| x.bombed).count();
+    let n = games.max(1) as f64;
+    Summary {
+        games,
+        scores,
+        mean_score,
+        std_score,
+        perfect: perfect as f64 / n,
+        bombed: bombed as f64 / n,
+        mean_turns: outcomes.iter().map(|x| x.turns).sum::<usize>() as f64 / n,
+        illegal: outcomes.iter().filter(|x| x.illegal).count(),
+        forfeits: Vec::new(),
+        timeouts: Vec::new(),
+        seconds_used: Vec::new(),
+        score_ci: normal_interval(mean_score, std_score, games),
+        perfect_ci: wilson_interval(perfect, games),
+        bombed_ci: wilson_interval(bombed, games),
+        latency: Latency::default(),
+        seat_latency: Vec::new(),
+    }
+}
+
+const Z95: f64 = 1.96;
+
+fn mean_std(xs: &[f64]) -> (f64, f64) {
+    let n = xs.len().max(1) as f64;
+    let mean = xs.iter().sum::<f64>() / n;
+    let var = xs.iter().map(|&x| (x - mean).powi(2)).sum::<f64>() / n;
+    (mean, var.sqrt())
+}
+
+fn normal_interval(mean: f64, std: f64, n: usize) -> (f64, f64) {
+    if n < 2 {
+        return (mean, mean);
+    }
+    // sample standard deviation
+    let half = Z95 * std * (n as f64 / (n as f64 - 1.0)).sqrt() / (n as f64).sqrt();
+    (mean - half, mean + half)
+}
+
+fn wilson_interval(k: usize, n: usize) -> (f64, f64) {
+    if n == 0 {
+        return (0.0, 1.0);
+    }
+    let (k, n) = (k as f64, n as f64);
+    let p = k / n;
+    let z2 = Z95 * Z95;
+    let center = (p + z2 / (2.0 * n)) / (1.0 + z2 / n);
+    let half = Z95 * (p * (1.0 - p) / n + z2 / (4.0 * n * n)).sqrt() / (1.0 + z2 / n);
+    ((center - half).max(0.0), (center + half).min(1.0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agents::ConventionBot;
+    use crate::clock::{OnTimeout, TimeControl};
+
+    fn outcomes(scores: &[usize]) -> Vec<Outcome> {
+        scores
+            .iter()
+            .map(|&score| Outcome {
+                score,
+                turns: 50,
+                bombed: false,
+                illegal: false,
+            })
+            .collect()
+    }
+
+    fn close(x: (f64, f64), y: (f64, f64)) -> bool {
+        (x.0 - y.0).abs() < 1e-4 && (x.1 - y.1).abs() < 1e-4
+    }
+
+    #[test]
+    fn confidence_intervals_of_known_scores() {
+        let constant = summarize(&outcomes(&[20; 10]), 25);
+        assert_eq!(constant.score_ci, (20.0, 20.0));
+        assert_eq!(constant.perfect_ci.0, 0.0);
+        // a sample standard deviation of sqrt(2)
+        let spread = summarize(&outcomes(&[0, 2]), 25);
+        assert!(close(spread.score_ci, (1.0 - 1.96, 1.0 + 1.96)));
+        let half = summarize(&outcomes(&[25, 0, 25, 0, 25, 0, 25, 0, 25, 0]), 25);
+        assert!(close(half.perfect_ci, (0.2366, 0.7634)));
+        assert_eq!(wilson_interval(0, 0), (0.0, 1.0));
+        assert_eq!(wilson_interval(10, 10).1, 1.0);
+    }
+
+    #[test]
+    fn paired_difference_of_shifted_scores() {
+        let a = summarize(&outcomes(&[10, 12, 17]), 25);
+        let b = summarize(&outcomes(&[11, 13, 18]), 25);
+        assert_eq!(paired_difference(&a, &b), (1.0, (1.0, 1.0)));
+        assert_eq!(paired_difference(&b, &a).0, -1.0);
+    }
+
+    #[test]
+    fn latency_percentiles_are_nearest_ranks() {
+        let times: Vec<Duration> = (1..=100).rev().map(Duration::from_millis).collect();
+        let latency = Latency::of(&times);
+        let ms = |x: u64| Duration::from_millis(x).as_secs_f64();
+        assert_eq!(latency.moves, 100);
+        assert_eq!(
+            (latency.p50, latency.p90, latency.p99, latency.max),
+            (ms(50), ms(90), ms(99), ms(100))
+        );
+        assert!((latency.mean - 0.0505).abs() < 1e-9);
+        assert_eq!(Latency::of(&[]).max, 0.0);
+    }
+
+    #[test]
+    fn timeouts_are_counted_by_seat() {
+        let seats =
+            || -> Vec<Box<dyn Agent>> { vec![Box::new(ConventionBot), Box::new(ConventionBot)] };
+        let mut driver = GameDriver::new(Variant::standard(), seats());
+        let summary = evaluate_lineup(&mut driver, 2, 0);
+        assert!(summary.timeouts.is_empty() && summary.seconds_used.is_empty());
+
+        let control = |on_timeout| TimeControl {
+            per_move: Some(Duration::from_nanos(1)),
+            total: None,
+            on_timeout,
+        };
+        driver.time_control = Some(control(OnTimeout::Default));
+        let summary = evaluate_lineup(&mut driver, 2, 0);
+        assert_eq!(summary.forfeits, vec![0, 0]);
+        let moves: Vec<usize> = summary.seat_latency.iter().map(|x| x.moves).collect();
+        assert_eq!(summary.timeouts, moves);
+        assert!(summary.seconds_used.iter().all(|&x| x > 0.0));
+
+        driver.time_control = Some(control(OnTimeout::Forfeit));
+        let summary = evaluate_lineup(&mut driver, 3, 0);
+        assert_eq!(summary.forfeits, vec![3, 0]);
+        assert_eq!(summary.timeouts, vec![3, 0]);
+        assert_eq!(summary.scores, vec![0, 0, 0]);
+    }
+}
@@ -0,0 +1,519 @@
+#![allow(dead_code)]
+
+use crate::agents::{self, Agent, Observation};
+use crate::clock::{OnTimeout, SeatTime, TimeControl};
+use crate::daily;
+use crate::driver::GameDriver;
+use crate::encoder::Encoder;
+use crate::manifest::Manifest;
+use crate::modifier;
+use crate::replay::{Replay, ReplayError};
+use crate::state::{ActionRequest, NACTIONS};
+use crate::variant::Variant;
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::SeedableRng;
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::time::Duration;
+
+#[derive(Debug)]
+pub enum ExperimentError {
+    Io(std::io::Error),
+    Toml(toml::de::Error),
+    Invalid(String),
+    Replay(ReplayError),
+}
+
+impl From<std::io::Error> for ExperimentError {
+    fn from(err: std::io::Error) -> ExperimentError {
+        ExperimentError::Io(err)
+    }
+}
+
+impl From<ReplayError> for ExperimentError {
+    fn from(err: ReplayError) -> ExperimentError {
+        ExperimentError::Replay(err)
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum Seeds {
+    List(Vec<u64>),
+    Range { start: u64, count: u64 },
+    // the deals of the day of each date, see `daily`
+    Daily { daily: Vec<String> },
+}
+
+// games of an experiment at most, its seeds and results are held in memory
+pub const MAX_SEEDS: u64 = 1_000_000;
+
+impl Seeds {
+    // checked by `Experiment::validate`, at most `MAX_SEEDS` and a range that does not overflow
+    pub fn to_vec(&self) -> Vec<u64> {
+        match self {
+            Seeds::List(seeds) => seeds.clone(),
+            Seeds::Range { start, count } => (*start..start.saturating_add(*count)).collect(),
+            // the dates are checked by `Experiment::validate`
+            Seeds::Daily { daily } => daily.iter().filter_map(|x| daily::seed(x).ok()).collect(),
+        }
+    }
+
+    // the number of games, None if the range goes past `u64::MAX`
+    pub fn count(&self) -> Option<u64> {
+        match self {
+            Seeds::List(seeds) => Some(seeds.len() as u64),
+            Seeds::Range { start, count } => start.checked_add(*count).map(|_| *count),
+            Seeds::Daily { daily } => Some(daily.len() as u64),
+        }
+    }
+}
+
+fn default_encoder() -> String {
+    "v1".to_string()
+}
+
+// the move budgets of every seat in seconds, see `clock::TimeControl`
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct TimeBudget {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub per_move: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub total: Option<f64>,
+    #[serde(default)]
+    pub on_timeout: OnTimeout,
+}
+
+impl TimeBudget {
+    // checked by `Experiment::validate`
+    pub fn control(&self) -> TimeControl {
+        TimeControl {
+            per_move: self.per_move.map(Duration::from_secs_f64),
+            total: self.total.map(Duration::from_secs_f64),
+            on_timeout: self.on_timeout,
+        }
+    }
+}
+
+// an experiment file, e.g.
+//
+//     name = "random-baseline"
+//     players = 4
+//     seeds = { start = 0, count = 100 }
+//     agents = ["random"]  # or "cheat", which sees every hand, "convention", "hat" or "hgroup"
+//     output = "results/random-baseline"
+//     time_control = { per_move = 0.5, total = 60.0, on_timeout = "forfeit" }  # optional
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Experiment {
+    pub name: String,
+    pub players: usize,
+    #[serde(default = "Variant::standard")]
+    pub variant: Variant,
+    #[serde(default)]
+    pub modifiers: Vec<String>,
+    pub seeds: Seeds,
+    // the observation of the "mlp:<path>" agents
+    #[serde(default = "default_encoder")]
+    pub encoder: String,
+    // one agent per seat, or a single agent for every seat
+    pub agents: Vec<String>,
+    pub output: PathBuf,
+    // none for games without a clock
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub time_control: Option<TimeBudget>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct GameResult {
+    pub seed: u64,
+    pub score: usize,
+    pub turns: usize,
+    // seat and reason of the agent whose illegal action stopped the game
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub forfeit: Option<(usize, String)>,
+    // the time used by every seat, empty without a time control
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub clock: Vec<SeatTime>,
+}
+
+impl Experiment {
+    pub fn from_toml(text: &str) -> Result<Experiment, ExperimentError> {
+        let experiment: Experiment = toml::from_str(text).map_err(ExperimentError::Toml)?;
+        experiment.validate()?;
+        Ok(experiment)
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Experiment, ExperimentError> {
+        Experiment::from_toml(&fs::read_to_string(path)?)
+    }
+
+    // `from_toml` for an experiment sent by someone else (see `server`): the agents that read a
+    // file are refused before any file is opened
+    pub fn from_remote_toml(text: &str) -> Result<Experiment, ExperimentError> {
+        let experiment: Experiment = toml::from_str(text).map_err(ExperimentError::Toml)?;
+        if let Some(agent) = experiment.agents.iter().find(|x| agents::reads_files(x)) {
+            return Err(ExperimentError::Invalid(format!(
+                "agent {:?} reads a file, not allowed remotely",
+                agent
+            )));
+        }
+        experiment.validate()?;
+        Ok(experiment)
+    }
+
+    fn validate(&self) -> Result<(), ExperimentError> {
+        if self.players < 2 || self.players > 5 {
+            return Err(ExperimentError::Invalid(format!(
+                "{} players is not supported",
+                self.players
+            )));
+        }
+        if self.agents.len() != 1 && self.agents.len() != self.players {
+            return Err(ExperimentError::Invalid(format!(
+                "{} agents for {} players",
+                self.agents.len(),
+                self.players
+            )));
+        }
+        match self.seeds.count() {
+            Some(n) if n <= MAX_SEEDS => {}
+            Some(n) => {
+                return Err(ExperimentError::Invalid(format!(
+                    "{} seeds, at most {}",
+                    n, MAX_SEEDS
+                )))
+            }
+            None => {
+                return Err(ExperimentError::Invalid(
+                    "the seed range goes past the last seed".to_string(),
+                ))
+            }
+        }
+        if let Seeds::Daily { daily } = &self.seeds {
+            for date in daily {
+                daily::seed(date).map_err(ExperimentError::Invalid)?;
+            }
+        }
+        for name in &self.modifiers {
+            if modifier::from_name(name).is_none() {
+                return Err(ExperimentError::Invalid(format!(
+                    "unknown rule modifier {:?}",
+                    name
+                )));
+            }
+        }
+        for agent in &self.agents {
+            if agents::from_name(agent, 0).is_none() {
+                return Err(ExperimentError::Invalid(format!(
+                    "unknown agent {:?}",
+                    agent
+                )));
+            }
+        }
+        match Encoder::from_name(&self.encoder) {
+            None => {
+                return Err(ExperimentError::Invalid(format!(
+                    "unknown encoder {:?}",
+                    self.encoder
+                )))
+            }
+            Some(Encoder::Hle)
+                if self.variant != Variant::standard() || !self.modifiers.is_empty() =>
+            {
+                return Err(ExperimentError::Invalid(
+                    "the hle encoder only supports the standard variant without modifiers"
+                        .to_string(),
+                ))
+            }
+            _ => {}
+        }
+        if let Some(budget) = &self.time_control {
+            let budgets = budget.per_move.iter().chain(&budget.total);
+            if let Some(seconds) = budgets.into_iter().find(|x| !x.is_finite() || **x <= 0.0) {
+                return Err(ExperimentError::Invalid(format!(
+                    "invalid time budget of {} seconds",
+                    seconds
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    pub fn agent(&self, seat: usize) -> &str {
+        &self.agents[seat % self.agents.len()]
+    }
+
+    // plays every seed, writes one replay per game, `results.json` and `manifest.json` in the output directory
+    pub fn run(&self) -> Result<Vec<GameResult>, ExperimentError> {
+        self.run_with(false, |_, _| {})
+    }
+
+    // like `run`, with `resume` the games whose replay is already in the output directory are
+    // not played again, provided its manifest has the config hash of this experiment;
+    // `progress(done, total)` is called once with the resumed games and then after every game
+    pub fn run_with<F>(
+        &self,
+        resume: bool,
+        mut progress: F,
+    ) -> Result<Vec<GameResult>, ExperimentError>
+    where
+        F: FnMut(usize, usize),
+    {
+        fs::create_dir_all(&self.output)?;
+        let manifest = Manifest::new(self);
+        if resume {
+            self.check_resumable(&manifest)?;
+        }
+        fs::write(self.output.join("manifest.json"), manifest.to_json())?;
+        let seeds = self.seeds.to_vec();
+        let mut results: Vec<Option<GameResult>> = seeds
+            .iter()
+            .map(|&seed| if resume { self.resumed(seed) } else { None })
+            .collect();
+        let mut done = results.iter().filter(|x| x.is_some()).count();
+        progress(done, seeds.len());
+        for (i, &seed) in seeds.iter().enumerate() {
+            if results[i].is_some() {
+                continue;
+            }
+            let result = self.play(seed)?;
+            results[i] = Some(result);
+            done += 1;
+            progress(done, seeds.len());
+        }
+        let results: Vec<GameResult> = results.into_iter().flatten().collect();
+        fs::write(
+            self.output.join("results.json"),
+            serde_json::to_string_pretty(&results).unwrap(),
+        )?;
+        Ok(results)
+    }
+
+    pub fn replay_path(&self, seed: u64) -> PathBuf {
+        self.output.join(format!("{}.json", seed))
+    }
+
+    // the replays of the output directory can only be those of this experiment
+    fn check_resumable(&self, manifest: &Manifest) -> Result<(), ExperimentError> {
+        let previous = match fs::read_to_string(self.output.join("manifest.json")) {
+            Ok(json) => json,
+            Err(_) => return Ok(()),
+        };
+        let previous: Manifest = serde_json::from_str(&previous).map_err(|err| {
+            ExperimentError::Invalid(format!("cannot read the manifest to resume: {}", err))
+        })?;
+        if previous.config_hash != manifest.config_hash {
+            return Err(ExperimentError::Invalid(format!(
+                "{} holds the games of another config ({}, not {}), not resuming",
+                self.output.display(),
+                previous.config_hash,
+                manifest.config_hash
+            )));
+        }
+        Ok(())
+    }
+
+    // result of a game already written in the output directory, None if its replay is missing
+    // or was cut short
+    fn resumed(&self, seed: u64) -> Option<GameResult> {
+        let replay = Replay::load(self.replay_path(seed)).ok()?;
+        let forfeit = replay.tags.get(FORFEIT).and_then(|x| parse_forfeit(x));
+        let clock = match replay.tags.get(CLOCK) {
+            Some(json) => serde_json::from_str(json).ok()?,
+            None => Vec::new(),
+        };
+        let state = replay.play(|_, _| {}).ok()?;
+        if !state.gameover() && forfeit.is_none() {
+            return None;
+        }
+        Some(GameResult {
+            seed,
+            score: state.final_score(),
+            turns: *state.turn(),
+            forfeit,
+            clock,
+        })
+    }
+
+    // plays the game of `seed` under the time control and saves its replay; the first illegal
+    // action of an agent, or a move out of time with `on_timeout = "forfeit"`, forfeits the game
+    fn play(&self, seed: u64) -> Result<GameResult, ExperimentError> {
+        // the random seats share one generator
+        let rng = Rc::new(RefCell::new(StdRng::seed_from_u64(seed.wrapping_add(1))));
+        // checked by `validate`
+        let encoder = Encoder::from_name(&self.encoder).unwrap();
+        let seats: Vec<Box<dyn Agent>> = (0..self.players)
+            .map(|seat| match self.agent(seat) {
+                "random" => Box::new(SharedRandom(rng.clone())) as Box<dyn Agent>,
+                name => {
+                    agents::with_encoder(name, seed.wrapping_add(seat as u64), encoder).unwrap()
+                }
+            })
+            .collect();
+        let mut driver = GameDriver::with_modifiers(self.variant.clone(), seats, &self.modifiers)
+            .map_err(ExperimentError::Invalid)?;
+        driver.time_control = self.time_control.as_ref().map(TimeBudget::control);
+        let record = driver.play(Some(seed));
+        let illegal = record.illegal;
+        let forfeit = record.forfeit.or_else(|| {
+            illegal
+                .map(|(turn, error)| (turn % self.players, format!("illegal action: {:?}", error)))
+        });
+        let mut replay = record.replay;
+        if let Some((seat, reason)) = &forfeit {
+            replay
+                .tags
+                .insert(FORFEIT.to_string(), forfeit_tag(*seat, reason));
+        }
+        if !record.clock.is_empty() {
+            replay.tags.insert(
+                CLOCK.to_string(),
+                serde_json::to_string(&record.clock).unwrap(),
+            );
+        }
+        replay.save(self.replay_path(seed))?;
+        Ok(GameResult {
+            seed,
+            score: record.score,
+            turns: record.turns,
+            forfeit,
+            clock: record.clock,
+        })
+    }
+}
+
+// a random seat drawing from the generator of its game
+struct SharedRandom(Rc<RefCell<StdRng>>);
+
+impl Agent for SharedRandom {
+    fn act(&mut self, obs: &Observation) -> ActionRequest {
+        let mask = obs.legal_mask();
+        let legal: Vec<usize> = (0..NACTIONS).filter(|&id| mask[id] > 0.0).collect();
+        // the driver asks only while the game goes on, there is always a legal action
+        ActionRequest::from_id(*legal.choose(&mut *self.0.borrow_mut()).unwrap())
+    }
+}
+
+// the tag of the replay of a forfeited game, "<seat>: <reason>"
+const FORFEIT: &str = "forfeit";
+
+fn forfeit_tag(seat: usize, reason: &str) -> String {
+    format!("{}: {}", seat, reason)
+}
+
+// the tag of the replay of a game with a time control, the json of the time of every seat
+const CLOCK: &str = "clock";
+
+fn parse_forfeit(tag: &str) -> Option<(usize, String)> {
+    let (seat, reason) = tag.split_once(": ")?;
+    Some((seat.parse().ok()?, reason.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resume_refuses_the_replays_of_another_config() {
+        let output = std::env::temp_dir().join(format!("hanabi-resume-{}", std::process::id()));
+        let text = |count: u64| {
+            format!(
+                "name = \"resume\"\nplayers = 2\nseeds = {{ start = 0, count = {} }}\n\
+                 agents = [\"convention\"]\noutput = {:?}\n",
+                count, output
+            )
+        };
+        let experiment = Experiment::from_toml(&text(2)).unwrap();
+        let results = experiment.run().unwrap();
+        let resumed = experiment.run_with(true, |_, _| {}).unwrap();
+        assert_eq!(
+            serde_json::to_string(&resumed).unwrap(),
+            serde_json::to_string(&results).unwrap()
+        );
+        let other = Experiment::from_toml(&text(3)).unwrap();
+        assert!(matches!(
+            other.run_with(true, |_, _| {}),
+            Err(ExperimentError::Invalid(_))
+        ));
+        fs::remove_dir_all(&output).unwrap();
+    }
+
+    #[test]
+    fn seeds_and_agents_are_bounded() {
+        let text = |seeds: &str, agent: &str| {
+            format!(
+                "name = \"bounds\"\nplayers = 2\nseeds = {}\nagents = [{:?}]\noutput = \"out\"\n",
+                seeds, agent
+            )
+        };
+        let invalid = |result| matches!(result, Err(ExperimentError::Invalid(_)));
+        let overflow = text("{ start = 1, count = 18446744073709551615 }", "random");
+        assert!(invalid(Experiment::from_toml(&overflow)));
+        let many = text(
+            &format!("{{ start = 0, count = {} }}", MAX_SEEDS + 1),
+            "random",
+        );
+        assert!(invalid(Experiment::from_toml(&many)));
+        let last = text("{ start = 18446744073709551614, count = 1 }", "random");
+        assert_eq!(
+            Experiment::from_toml(&last).unwrap().seeds.to_vec(),
+            vec![u64::MAX - 1]
+        );
+        // refused before the file is looked for
+        for agent in ["mlp:/etc/passwd", "mlp8:model.npz", "rollout:mlp:model.npz"] {
+            assert!(invalid(Experiment::from_remote_toml(&text("[0]", agent))));
+        }
+        assert!(Experiment::from_remote_toml(&text("[0]", "rollout:hat")).is_ok());
+    }
+
+    #[test]
+    fn time_control_is_enforced_and_recorded() {
+        let output = std::env::temp_dir().join(format!("hanabi-clock-{}", std::process::id()));
+        let text = |on_timeout: &str| {
+            format!(
+                "name = \"clock\"\nplayers = 3\nseeds = [0, 1]\nagents = [\"convention\"]\n\
+                 output = {:?}\ntime_control = {{ per_move = 1e-9, on_timeout = {:?} }}\n",
+                output, on_timeout
+            )
+        };
+        let experiment = Experiment::from_toml(&text("default")).unwrap();
+        let results = experiment.run().unwrap();
+        for result in &results {
+            assert_eq!(result.forfeit, None);
+            assert_eq!(result.clock.len(), 3);
+            let moves: usize = result.clock.iter().map(|x| x.moves).sum();
+            assert_eq!(moves, result.turns);
+            assert!(result
+                .clock
+                .iter()
+                .all(|x| x.timeouts == x.moves && x.longest <= x.used));
+        }
+        let resumed = experiment.run_with(true, |_, _| {}).unwrap();
+        assert_eq!(
+            serde_json::to_string(&resumed).unwrap(),
+            serde_json::to_string(&results).unwrap()
+        );
+        fs::remove_dir_all(&output).unwrap();
+
+        let results = Experiment::from_toml(&text("forfeit"))
+            .unwrap()
+            .run()
+            .unwrap();
+        for result in &results {
+            let (seat, reason) = result.forfeit.clone().unwrap();
+            assert_eq!((seat, result.turns), (0, 0));
+            assert!(reason.starts_with("out of time"), "{}", reason);
+        }
+        fs::remove_dir_all(&output).unwrap();
+
+        let negative = text("default").replace("1e-9", "-1.0");
+        assert!(matches!(
+            Experiment::from_toml(&negative),
+            Err(ExperimentError::Invalid(_))
+        ));
+    }
+}
@@ -0,0 +1,230 @@
+// bots running in their own process, one json message per line over stdin and stdout
+//
+// the engine sends its `Hello` and reads the one of the bot, then sends an observation at every
+// turn of the bot and reads back an action id; a bot that exceeds its limits, exits or answers
+// garbage is killed and forfeits the game, it is started again for the next game
+use crate::agents::{Agent, Observation};
+use crate::protocol::Hello;
+use crate::state::{ActionRequest, Card, Color, Value, MAXCARDS, NACTIONS};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Write};
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::sync::mpsc::{channel, Receiver, RecvTimeoutError};
+use std::thread;
+use std::time::Duration;
+
+#[derive(Clone, Debug)]
+pub struct Limits {
+    // to answer the hello and every observation
+    pub move_time: Duration,
+    // address space of the process in bytes, through `ulimit -v`
+    pub memory: Option<u64>,
+}
+
+// what the bot receives at its turn, also the observation of the browser bindings and of the
+// seats of the websocket server
+#[derive(Serialize)]
+pub(crate) struct Message {
+    player: usize,
+    turn: usize,
+    // null for the own hand
+    hands: Vec<Option<Vec<Card>>>,
+    // [color, value] per slot, null when not told
+    knowledge: Vec<Vec<(Option<Color>, Option<Value>)>>,
+    fireworks: HashMap<String, usize>,
+    clues: usize,
+    mistakes: usize,
+    discard: Vec<Card>,
+    deck_size: usize,
+    legal_actions: Vec<usize>,
+}
+
+impl Message {
+    pub(crate) fn new(obs: &Observation) -> Message {
+        let mask = obs.legal_mask();
+        Message {
+            player: obs.player(),
+            turn: obs.turn(),
+            hands: (0..obs.players())
+                .map(|i| obs.hand(i).map(|x| x.to_vec()))
+                .collect(),
+            knowledge: (0..obs.players())
+                .map(|i| {
+                    obs.knowledge(i)
+                        .iter()
+                        .map(|k| (k.color, k.value))
+                        .collect()
+                })
+                .collect(),
+            fireworks: obs
+                .variant()
+                .suits()
+                .iter()
+                .zip(obs.table())
+                .map(|(suit, &n)| (format!("{}", suit), n))
+                .collect(),
+            clues: obs.clues(),
+            mistakes: obs.mistakes(),
+            discard: obs.discard().to_vec(),
+            deck_size: obs.deck_size(),
+            // none while waiting for the turn
+            legal_actions: (0..NACTIONS)
+                .filter(|&id| obs.to_move() && mask[id] > 0.0)
+                .collect(),
+        }
+    }
+}
+
+struct Process {
+    child: Child,
+    stdin: ChildStdin,
+    lines: Receiver<String>,
+}
+
+pub struct ExternalBot {
+    command: Vec<String>,
+    limits: Limits,
+    process: Option<Process>,
+    forfeit: Option<String>,
+}
+
+impl ExternalBot {
+    // `command` is the program and its arguments, nothing is started before the first action
+    pub fn new(command: Vec<String>, limits: Limits) -> ExternalBot {
+        assert!(!command.is_empty());
+        ExternalBot {
+            command,
+            limits,
+            process: None,
+            forfeit: None,
+        }
+    }
+
+    fn spawn(&self) -> Result<Process, String> {
+        let mut command = match self.limits.memory {
+            Some(bytes) => {
+                let mut command = Command::new("sh");
+                command
+                    .arg("-c")
+                    .arg(format!("ulimit -v {} && exec \"$0\" \"$@\"", bytes / 1024))
+                    .args(&self.command);
+                command
+            }
+            None => {
+                let mut command = Command::new(&self.command[0]);
+                command.args(&self.command[1..]);
+                command
+            }
+        };
+        let mut child = command
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .map_err(|err| format!("cannot start {:?}: {}", self.command[0], err))?;
+        let stdin = child.stdin.take().unwrap();
+        let stdout = child.stdout.take().unwrap();
+        // reads in the background so that a silent bot cannot block the game
+        let (sender, lines) = channel();
+        thread::spawn(move || {
+            for line in BufReader::new(stdout).lines() {
+                let sent = line.map(|line| sender.send(line).is_ok());
+                if !matches!(sent, Ok(true)) {
+                    return;
+                }
+            }
+        });
+        let mut process = Process {
+            child,
+            stdin,
+            lines,
+        };
+        let theirs = process.exchange(&Hello::local().to_json(), self.limits.move_time);
+        let handshake = theirs.and_then(|theirs| {
+            Hello::from_json(&theirs)
+                .and_then(|theirs| Hello::local().negotiate(&theirs))
+                .map_err(|err| format!("handshake: {}", err))
+        });
+        if let Err(reason) = handshake {
+            process.kill();
+            return Err(reason);
+        }
+        Ok(process)
+    }
+
+    fn request(&mut self, obs: &Observation) -> Result<ActionRequest, String> {
+        if self.process.is_none() {
+            self.process = Some(self.spawn()?);
+        }
+        let message = serde_json::to_string(&Message::new(obs)).unwrap();
+        let line = self
+            .process
+            .as_mut()
+            .unwrap()
+            .exchange(&message, self.limits.move_time)?;
+        match line.trim().parse::<usize>() {
+            Ok(id) if id < NACTIONS => Ok(ActionRequest::from_id(id)),
+            _ => Err(format!("invalid action {:?}", line)),
+        }
+    }
+
+    fn kill(&mut self) {
+        if let Some(mut process) = self.process.take() {
+            process.kill();
+        }
+    }
+}
+
+impl Process {
+    // sends a line and waits for the answer
+    fn exchange(&mut self, line: &str, timeout: Duration) -> Result<String, String> {
+        if writeln!(self.stdin, "{}", line)
+            .and_then(|_| self.stdin.flush())
+            .is_err()
+        {
+            return Err(self.exited());
+        }
+        match self.lines.recv_timeout(timeout) {
+            Ok(line) => Ok(line),
+            Err(RecvTimeoutError::Timeout) => Err(format!("no answer within {:?}", timeout)),
+            Err(RecvTimeoutError::Disconnected) => Err(self.exited()),
+        }
+    }
+
+    fn exited(&mut self) -> String {
+        match self.child.wait() {
+            Ok(status) => format!("exited with {}", status),
+            Err(err) => format!("exited: {}", err),
+        }
+    }
+
+    fn kill(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+impl Agent for ExternalBot {
+    fn act(&mut self, obs: &Observation) -> ActionRequest {
+        match self.request(obs) {
+            Ok(action) => action,
+            Err(reason) => {
+                self.kill();
+                self.forfeit = Some(reason);
+                // out of any hand, stops the game
+                ActionRequest::Play { position: MAXCARDS }
+            }
+        }
+    }
+
+    fn forfeit(&mut self) -> Option<String> {
+        self.forfeit.take()
+    }
+}
+
+impl Drop for ExternalBot {
+    fn drop(&mut self) {
+        self.kill();
+    }
+}
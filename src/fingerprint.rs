@@ -0,0 +1,146 @@
+use crate::replay::{Replay, ReplayError};
+use crate::state::{State, NACTIONS};
+use ndarray::{Array1, ArrayView1, ArrayView2, Axis};
+
+// the positions every `stride` turns of the replays, the fixed probes on which checkpoints are compared
+pub fn probes(replays: &[Replay], stride: usize) -> Result<Vec<State>, ReplayError> {
+    let stride = stride.max(1);
+    let mut states = Vec::new();
+    for replay in replays {
+        replay.play(|state, _| {
+            if state.turn() % stride == 0 {
+                states.push(state.fork());
+            }
+        })?;
+    }
+    Ok(states)
+}
+
+// softmax of the logits over the legal actions
+pub fn softmax(logits: &[f32], mask: &ArrayView1<f32>) -> Array1<f32> {
+    let max = (0..NACTIONS)
+        .filter(|&id| mask[id] > 0.0)
+        .map(|id| logits[id])
+        .fold(f32::NEG_INFINITY, f32::max);
+    let mut p: Array1<f32> = (0..NACTIONS)
+        .map(|id| {
+            if mask[id] > 0.0 {
+                (logits[id] - max).exp()
+            } else {
+                0.0
+            }
+        })
+        .collect();
+    let total = p.sum();
+    if total > 0.0 {
+        p /= total;
+    }
+    p
+}
+
+pub fn one_hot(id: usize) -> Array1<f32> {
+    let mut p = Array1::zeros(NACTIONS);
+    if id < NACTIONS {
+        p[id] = 1.0;
+    }
+    p
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Metric {
+    TotalVariation,
+    JensenShannon,
+    // fraction of probes on which the most likely actions differ
+    Disagreement,
+}
+
+impl Metric {
+    pub fn from_name(name: &str) -> Option<Metric> {
+        match name {
+            "tv" => Some(Metric::TotalVariation),
+            "js" => Some(Metric::JensenShannon),
+            "disagreement" => Some(Metric::Disagreement),
+            _ => None,
+        }
+    }
+}
+
+// mean distance between two fingerprints, one action distribution per probe
+pub fn distance(a: &ArrayView2<f32>, b: &ArrayView2<f32>, metric: Metric) -> f32 {
+    let n = a.len_of(Axis(0));
+    if n == 0 {
+        return 0.0;
+    }
+    let total: f32 = a
+        .outer_iter()
+        .zip(b.outer_iter())
+        .map(|(p, q)| match metric {
+            Metric::TotalVariation => 0.5 * (&p - &q).mapv(f32::abs).sum(),
+            Metric::JensenShannon => {
+                let m = (&p + &q) / 2.0;
+                0.5 * kl(&p, &m.view()) + 0.5 * kl(&q, &m.view())
+            }
+            Metric::Disagreement => (argmax(&p) != argmax(&q)) as usize as f32,
+        })
+        .sum();
+    total / n as f32
+}
+
+fn kl(p: &ArrayView1<f32>, q: &ArrayView1<f32>) -> f32 {
+    p.iter()
+        .zip(q.iter())
+        .filter(|(&x, _)| x > 0.0)
+        .map(|(&x, &y)| x * (x / y).ln())
+        .sum()
+}
+
+fn argmax(p: &ArrayView1<f32>) -> usize {
+    (0..p.len())
+        .max_by(|&i, &j| p[i].partial_cmp(&p[j]).unwrap())
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agents::{Agent, ConventionBot, Observation};
+    use crate::driver::GameDriver;
+    use crate::variant::Variant;
+    use ndarray::Array2;
+
+    fn fingerprint<F>(states: &[State], policy: F) -> Array2<f32>
+    where
+        F: Fn(&State) -> Array1<f32>,
+    {
+        let mut rows = Array2::zeros((states.len(), NACTIONS));
+        for (state, mut row) in states.iter().zip(rows.outer_iter_mut()) {
+            row.assign(&policy(state));
+        }
+        rows
+    }
+
+    #[test]
+    fn an_agent_is_at_zero_distance_of_itself() {
+        let seats: Vec<Box<dyn Agent>> = vec![Box::new(ConventionBot), Box::new(ConventionBot)];
+        let replay = GameDriver::new(Variant::standard(), seats)
+            .play(Some(0))
+            .replay;
+        let states = probes(&[replay], 3).unwrap();
+        assert!(!states.is_empty());
+        let convention = |state: &State| one_hot(ConventionBot.act(&Observation::new(state)).id());
+        let a = fingerprint(&states, convention);
+        let b = fingerprint(&states, convention);
+        for metric in ["tv", "js", "disagreement"] {
+            let metric = Metric::from_name(metric).unwrap();
+            assert_eq!(distance(&a.view(), &b.view(), metric), 0.0);
+        }
+        // uniform over the legal actions, never the single action of the agent
+        let uniform = fingerprint(&states, |state| {
+            softmax(&[0.0; NACTIONS], &state.legal_mask().view())
+        });
+        let tv = distance(&a.view(), &uniform.view(), Metric::TotalVariation);
+        let js = distance(&a.view(), &uniform.view(), Metric::JensenShannon);
+        assert!(tv > 0.0 && tv < 1.0);
+        assert!(js > 0.0 && js <= 2f32.ln());
+    }
+}
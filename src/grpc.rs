@@ -0,0 +1,317 @@
+// the environment over grpc for ml stacks without python, built with the `grpc` feature and
+// served by the `hanabi-grpc` binary; the schema is `proto/hanabi.proto`
+use crate::agents::Observation;
+use crate::encoder::Encoder;
+use crate::protocol::{self, Agreement};
+use crate::state::{ActionRequest, Card, Color, State, Value, NACTIONS};
+use crate::variant::Variant;
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::io;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use tonic::transport::Server;
+use tonic::{Request, Response, Status};
+
+pub mod proto {
+    tonic::include_proto!("hanabi");
+}
+
+use proto::action::Action as Kind;
+use proto::environment_server::{Environment, EnvironmentServer};
+
+fn card(card: &Card) -> proto::Card {
+    proto::Card {
+        color: card.color().to_string(),
+        value: card.value().index() as u32 + 1,
+    }
+}
+
+fn observation(env: u64, state: &State, player: usize) -> proto::Observation {
+    let obs = Observation::of(state, player);
+    let mask = state.legal_mask();
+    proto::Observation {
+        env,
+        player: player as u32,
+        turn: obs.turn() as u32,
+        hands: (0..obs.players())
+            .map(|i| proto::Hand {
+                cards: obs.hand(i).unwrap_or(&[]).iter().map(card).collect(),
+                knowledge: obs
+                    .knowledge(i)
+                    .iter()
+                    .map(|k| proto::Knowledge {
+                        color: k.color.map(|c| c.to_string()),
+                        value: k.value.map(|v| v.index() as u32 + 1),
+                    })
+                    .collect(),
+            })
+            .collect(),
+        fireworks: obs
+            .variant()
+            .suits()
+            .iter()
+            .zip(obs.table())
+            .map(|(suit, &n)| proto::Firework {
+                color: suit.to_string(),
+                value: n as u32,
+            })
+            .collect(),
+        clues: obs.clues() as u32,
+        mistakes: obs.mistakes() as u32,
+        discard: obs.discard().iter().map(card).collect(),
+        deck_size: obs.deck_size() as u32,
+        legal_actions: (0..NACTIONS)
+            .filter(|&id| mask[id] > 0.0)
+            .map(|id| id as u32)
+            .collect(),
+        score: state.score() as u32,
+        gameover: state.gameover(),
+    }
+}
+
+fn action(action: Option<proto::Action>) -> Result<ActionRequest, Status> {
+    let kind = action
+        .and_then(|x| x.action)
+        .ok_or_else(|| Status::invalid_argument("no action"))?;
+    let invalid = |err: String| Status::invalid_argument(err);
+    Ok(match kind {
+        Kind::Id(id) if (id as usize) < NACTIONS => ActionRequest::from_id(id as usize),
+        Kind::Id(id) => return Err(invalid(format!("no action {}", id))),
+        Kind::Play(x) => ActionRequest::Play {
+            position: x.position as usize,
+        },
+        Kind::Discard(x) => ActionRequest::Discard {
+            position: x.position as usize,
+        },
+        Kind::ColorClue(x) => ActionRequest::ColorClue {
+            target: x.target as usize,
+            color: Color::try_from(x.color).map_err(invalid)?,
+        },
+        Kind::ValueClue(x) => ActionRequest::ValueClue {
+            target: x.target as usize,
+            value: Value::try_from(x.value as usize).map_err(invalid)?,
+        },
+    })
+}
+
+// the negotiation of the hello of a `Reset`
+fn agreement(hello: Option<proto::Hello>) -> Result<Agreement, Status> {
+    let hello = hello.ok_or_else(|| Status::invalid_argument("no hello"))?;
+    let theirs = protocol::Hello {
+        protocol_version: hello.protocol_version,
+        engine_version: hello.engine_version,
+        variants: hello.variants,
+        encoders: hello.encoders,
+    };
+    protocol::Hello::local()
+        .negotiate(&theirs)
+        .map_err(|err| Status::failed_precondition(format!("{}", err)))
+}
+
+// an environment and the encoders its client agreed on
+struct Env {
+    state: State,
+    encoders: Vec<String>,
+}
+
+#[derive(Default)]
+pub struct Service {
+    envs: Mutex<HashMap<u64, Env>>,
+    next: AtomicU64,
+}
+
+impl Service {
+    fn player(state: &State) -> usize {
+        state.turn() % state.players().len()
+    }
+}
+
+#[tonic::async_trait]
+impl Environment for Service {
+    async fn reset(
+        &self,
+        request: Request<proto::ResetRequest>,
+    ) -> Result<Response<proto::Observation>, Status> {
+        let request = request.into_inner();
+        let players = request.players as usize;
+        if !(2..=5).contains(&players) {
+            return Err(Status::invalid_argument(format!(
+                "{} players is not supported",
+                players
+            )));
+        }
+        let agreement = agreement(request.hello)?;
+        let variant = match request.variant.as_str() {
+            "" => Variant::standard(),
+            name => Variant::from_name(name)
+                .ok_or_else(|| Status::invalid_argument(format!("unknown variant {:?}", name)))?,
+        };
+        if !agreement.variants.iter().any(|x| x == variant.name()) {
+            return Err(Status::failed_precondition(format!(
+                "variant {} was not agreed on",
+                variant.name()
+            )));
+        }
+        let mut envs = self.envs.lock().unwrap();
+        let env = match request.env {
+            Some(env) if envs.contains_key(&env) => env,
+            Some(env) => return Err(Status::not_found(format!("no environment {}", env))),
+            None => self.next.fetch_add(1, Ordering::Relaxed),
+        };
+        let state = State::with_variant(players, variant, request.seed);
+        let reply = observation(env, &state, Service::player(&state));
+        let encoders = agreement.encoders;
+        envs.insert(env, Env { state, encoders });
+        Ok(Response::new(reply))
+    }
+
+    async fn step(
+        &self,
+        request: Request<proto::StepRequest>,
+    ) -> Result<Response<proto::StepReply>, Status> {
+        let proto::StepRequest {
+            env,
+            action: request,
+        } = request.into_inner();
+        let action = action(request)?;
+        let mut envs = self.envs.lock().unwrap();
+        let state = &mut envs
+            .get_mut(&env)
+            .ok_or_else(|| Status::not_found(format!("no environment {}", env)))?
+            .state;
+        let score = state.score();
+        state
+            .apply(&action)
+            .map_err(|err| Status::failed_precondition(format!("{:?}", err)))?;
+        Ok(Response::new(proto::StepReply {
+            observation: Some(observation(env, state, Service::player(state))),
+            reward: (state.score() - score) as f32,
+            done: state.gameover(),
+        }))
+    }
+
+    async fn encode(
+        &self,
+        request: Request<proto::EncodeRequest>,
+    ) -> Result<Response<proto::Encoding>, Status> {
+        let request = request.into_inner();
+        let envs = self.envs.lock().unwrap();
+        let env = envs
+            .get(&request.env)
+            .ok_or_else(|| Status::not_found(format!("no environment {}", request.env)))?;
+        let state = &env.state;
+        let player = request
+            .player
+            .map_or(Service::player(state), |x| x as usize);
+        if player >= state.players().len() {
+            return Err(Status::invalid_argument(format!("no player {}", player)));
+        }
+        let encoder = match request.encoder.as_str() {
+            "" => Encoder::V1,
+            name => Encoder::from_name(name)
+                .ok_or_else(|| Status::invalid_argument(format!("unknown encoder {:?}", name)))?,
+        };
+        if !env.encoders.iter().any(|x| x == encoder.name()) {
+            return Err(Status::failed_precondition(format!(
+                "encoder {} was not agreed on",
+                encoder.name()
+            )));
+        }
+        let values = match encoder {
+            Encoder::V1 | Encoder::V2 => encoder.encode(state, player),
+            Encoder::Hle => state.encode_hle(player).ok_or_else(|| {
+                Status::failed_precondition("the hle encoder only supports the standard game")
+            })?,
+        };
+        Ok(Response::new(proto::Encoding {
+            values: values.to_vec(),
+            legal_mask: state.legal_mask().to_vec(),
+        }))
+    }
+}
+
+// serves forever on `address`, "host:port" or "unix:path" for a unix socket
+pub fn serve(address: &str) -> io::Result<()> {
+    let runtime = tokio::runtime::Runtime::new()?;
+    runtime.block_on(async {
+        let service = EnvironmentServer::new(Service::default());
+        let served = match address.strip_prefix("unix:") {
+            Some(path) => {
+                let listener = tokio::net::UnixListener::bind(path)?;
+                let incoming = tokio_stream::wrappers::UnixListenerStream::new(listener);
+                Server::builder()
+                    .add_service(service)
+                    .serve_with_incoming(incoming)
+                    .await
+            }
+            None => {
+                let address = address
+                    .parse()
+                    .map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err))?;
+                Server::builder().add_service(service).serve(address).await
+            }
+        };
+        served.map_err(io::Error::other)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hello(protocol_version: u32, encoders: &[&str]) -> Option<proto::Hello> {
+        let local = protocol::Hello::local();
+        Some(proto::Hello {
+            protocol_version,
+            engine_version: local.engine_version,
+            variants: local.variants,
+            encoders: encoders.iter().map(|x| x.to_string()).collect(),
+        })
+    }
+
+    fn reset(hello: Option<proto::Hello>) -> proto::ResetRequest {
+        proto::ResetRequest {
+            env: None,
+            players: 2,
+            variant: String::new(),
+            seed: Some(0),
+            hello,
+        }
+    }
+
+    fn encode(encoder: &str) -> proto::EncodeRequest {
+        proto::EncodeRequest {
+            env: 0,
+            encoder: encoder.to_string(),
+            player: None,
+        }
+    }
+
+    #[test]
+    fn reset_negotiates_the_hello() {
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        runtime.block_on(async {
+            let service = Service::default();
+            let refused = |status: Status| status.code() == tonic::Code::FailedPrecondition;
+            let err = service.reset(Request::new(reset(None))).await.unwrap_err();
+            assert_eq!(err.code(), tonic::Code::InvalidArgument);
+            let other = hello(protocol::PROTOCOL_VERSION + 1, &["v1"]);
+            let err = service.reset(Request::new(reset(other))).await.unwrap_err();
+            assert!(refused(err));
+            let none = hello(protocol::PROTOCOL_VERSION, &["v0"]);
+            let err = service.reset(Request::new(reset(none))).await.unwrap_err();
+            assert!(refused(err));
+
+            let v1 = hello(protocol::PROTOCOL_VERSION, &["v1"]);
+            let obs = service.reset(Request::new(reset(v1))).await.unwrap();
+            assert_eq!(obs.into_inner().env, 0);
+            assert!(service.encode(Request::new(encode("v1"))).await.is_ok());
+            let err = service
+                .encode(Request::new(encode("v2")))
+                .await
+                .unwrap_err();
+            assert!(refused(err));
+        });
+    }
+}
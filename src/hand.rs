@@ -0,0 +1,166 @@
+// a hand, or what its holder was told about it, in `MAXCARDS` fixed slots: a play or a discard
+// shifts the cards in place instead of moving them on the heap, and a fork of the state copies the
+// hands with the rest of the position; it reads as a slice of the cards held
+use crate::rules::MAXCARDS;
+use std::borrow::Borrow;
+use std::fmt;
+use std::ops::{Deref, DerefMut};
+
+#[derive(Clone, Copy, Default)]
+pub struct Hand<T> {
+    slots: [T; MAXCARDS],
+    len: u8,
+}
+
+impl<T: Copy + Default> Hand<T> {
+    pub fn new() -> Hand<T> {
+        Hand::default()
+    }
+
+    pub fn from_slice(cards: &[T]) -> Hand<T> {
+        assert!(cards.len() <= MAXCARDS, "a hand of {} cards", cards.len());
+        let mut hand = Hand::default();
+        hand.slots[..cards.len()].copy_from_slice(cards);
+        hand.len = cards.len() as u8;
+        hand
+    }
+
+    // `n` slots of `value`
+    pub fn filled(n: usize, value: T) -> Hand<T> {
+        Hand::from_slice(&[value; MAXCARDS][..n])
+    }
+
+    pub fn push(&mut self, card: T) {
+        self.insert(self.len(), card);
+    }
+
+    pub fn pop(&mut self) -> Option<T> {
+        if self.is_empty() {
+            return None;
+        }
+        Some(self.remove(self.len() - 1))
+    }
+
+    pub fn insert(&mut self, position: usize, card: T) {
+        let len = self.len();
+        assert!(len < MAXCARDS, "a hand of more than {} cards", MAXCARDS);
+        assert!(
+            position <= len,
+            "position {} in a hand of {}",
+            position,
+            len
+        );
+        self.slots.copy_within(position..len, position + 1);
+        self.slots[position] = card;
+        self.len += 1;
+    }
+
+    pub fn remove(&mut self, position: usize) -> T {
+        let len = self.len();
+        assert!(position < len, "position {} in a hand of {}", position, len);
+        let card = self.slots[position];
+        self.slots.copy_within(position + 1..len, position);
+        self.slots[len - 1] = T::default();
+        self.len -= 1;
+        card
+    }
+
+    pub fn clear(&mut self) {
+        *self = Hand::default();
+    }
+}
+
+impl<T> Deref for Hand<T> {
+    type Target = [T];
+
+    fn deref(&self) -> &[T] {
+        &self.slots[..self.len as usize]
+    }
+}
+
+impl<T> DerefMut for Hand<T> {
+    fn deref_mut(&mut self) -> &mut [T] {
+        &mut self.slots[..self.len as usize]
+    }
+}
+
+// for `concat` and `join` of the hands
+impl<T> Borrow<[T]> for Hand<T> {
+    fn borrow(&self) -> &[T] {
+        self
+    }
+}
+
+impl<'a, T> IntoIterator for &'a Hand<T> {
+    type Item = &'a T;
+    type IntoIter = std::slice::Iter<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'a, T> IntoIterator for &'a mut Hand<T> {
+    type Item = &'a mut T;
+    type IntoIter = std::slice::IterMut<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter_mut()
+    }
+}
+
+// only the cards held count, not what is left in the empty slots
+impl<T: PartialEq> PartialEq for Hand<T> {
+    fn eq(&self, other: &Hand<T>) -> bool {
+        **self == **other
+    }
+}
+
+impl<T: PartialEq> PartialEq<Vec<T>> for Hand<T> {
+    fn eq(&self, other: &Vec<T>) -> bool {
+        **self == other[..]
+    }
+}
+
+impl<T: PartialEq> PartialEq<Hand<T>> for Vec<T> {
+    fn eq(&self, other: &Hand<T>) -> bool {
+        self[..] == **other
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for Hand<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl<T: Copy + Default> From<&[T]> for Hand<T> {
+    fn from(cards: &[T]) -> Hand<T> {
+        Hand::from_slice(cards)
+    }
+}
+
+impl<T: Copy + Default> From<Vec<T>> for Hand<T> {
+    fn from(cards: Vec<T>) -> Hand<T> {
+        Hand::from_slice(&cards)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Hand;
+
+    #[test]
+    fn cards_shift_in_their_slots() {
+        let mut hand = Hand::from_slice(&[1u8, 2, 3]);
+        hand.insert(0, 0);
+        assert_eq!(hand, vec![0, 1, 2, 3]);
+        assert_eq!(hand.remove(2), 2);
+        hand.push(4);
+        assert_eq!(hand, vec![0, 1, 3, 4]);
+        assert_eq!(hand.pop(), Some(4));
+        // the emptied slots are reset, two hands holding the same cards are equal
+        assert_eq!(hand, Hand::from_slice(&[0, 1, 3]));
+        assert_eq!(format!("{:?}", hand), "[0, 1, 3]");
+    }
+}
@@ -0,0 +1,55 @@
+use crate::manifest::fnv1a;
+use crate::state::{Card, State};
+
+fn card_bytes(card: &Card) -> [u8; 2] {
+    [card.color().index() as u8, card.value().index() as u8]
+}
+
+impl State {
+    // stable hash of what every player can see: tokens, fireworks, discard pile, deck size and
+    // clues; the order of the discard pile is ignored
+    pub fn public_hash(&self) -> u64 {
+        fnv1a(&self.public_bytes())
+    }
+
+    // stable hash of the full position, hands and deck order included
+    pub fn state_hash(&self) -> u64 {
+        let mut bytes = self.public_bytes();
+        for hand in self.players() {
+            for card in hand {
+                bytes.extend(&card_bytes(card));
+            }
+        }
+        for card in self.deck() {
+            bytes.extend(&card_bytes(card));
+        }
+        fnv1a(&bytes)
+    }
+
+    fn public_bytes(&self) -> Vec<u8> {
+        let mut bytes = vec![
+            self.players().len() as u8,
+            (self.turn() % self.players().len()) as u8,
+            *self.clues() as u8,
+            *self.mistakes() as u8,
+            self.deck().len() as u8,
+        ];
+        bytes.extend(self.table().iter().map(|&x| x as u8));
+        let mut discard: Vec<[u8; 2]> = self.discard().iter().map(card_bytes).collect();
+        discard.sort();
+        bytes.push(discard.len() as u8);
+        for card in discard {
+            bytes.extend(&card);
+        }
+        for hand in self.knowledge() {
+            bytes.push(hand.len() as u8);
+            for knowledge in hand {
+                bytes.push(knowledge.value.map_or(0xff, |x| x.index() as u8));
+                bytes.push(knowledge.color.map_or(0xff, |x| x.index() as u8));
+                bytes.push(knowledge.not_values);
+                bytes.push(knowledge.not_colors);
+            }
+        }
+        bytes
+    }
+}
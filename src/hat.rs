@@ -0,0 +1,279 @@
+// the recommendation strategy of hat guessing (Cox et al., "How to make the perfect fireworks
+// display"): a clue encodes the sum of what every other player should do, and each of them reads
+// their own by subtracting what they see the others should do; near perfect with 5 players
+use crate::agents::{Agent, Observation};
+use crate::slots::SlotOrder;
+use crate::state::{Action, ActionRequest, Card, Value, MAXCARDS, MAXMISTAKES, NACTIONS};
+use crate::variant::Variant;
+
+#[derive(Default)]
+pub struct HatBot;
+
+// what was played and discarded before an action, all the recommendations depend on
+struct Public {
+    table: Vec<usize>,
+    discard: Vec<Card>,
+}
+
+impl Public {
+    fn playable(&self, variant: &Variant, card: &Card) -> bool {
+        self.table[variant.suit_index(card.color())] == card.value().index()
+    }
+
+    // already played, or a lower card of its suit is lost
+    fn dead(&self, variant: &Variant, card: &Card) -> bool {
+        let suit = variant.suit_index(card.color());
+        self.table[suit] > card.value().index()
+            || (self.table[suit]..card.value().index()).any(|value| {
+                let lower = Card::new(Value::new(value), card.color());
+                let lost = self.discard.iter().filter(|x| **x == lower).count();
+                lost == lower.value().copies()
+            })
+    }
+
+    // the last copy
+    fn critical(&self, card: &Card) -> bool {
+        let lost = self.discard.iter().filter(|x| *x == card).count();
+        lost + 1 == card.value().copies()
+    }
+}
+
+// what the players should do, by the first `slots` cards from the oldest: 0..slots to play a card,
+// slots..2 * slots to discard one
+struct Code {
+    slots: usize,
+    order: SlotOrder,
+}
+
+impl Code {
+    fn new(obs: &Observation) -> Code {
+        Code {
+            // a clue tells one of `2 * (players - 1)`, a target and a value or color clue
+            slots: (obs.players() - 1).min(MAXCARDS),
+            order: obs.slots(SlotOrder::OldestFirst),
+        }
+    }
+
+    fn size(&self) -> usize {
+        2 * self.slots
+    }
+
+    // a playable 5, else the playable card of lowest value, else a dead card, else the highest card
+    // that is not the last copy, else the oldest card
+    fn recommend(&self, public: &Public, variant: &Variant, hand: &[Card]) -> usize {
+        let slots: Vec<(usize, &Card)> = (0..self.slots.min(hand.len()))
+            .map(|slot| (slot, &hand[self.order.convert(slot, hand.len())]))
+            .collect();
+        if let Some(&(slot, _)) = slots
+            .iter()
+            .filter(|(_, card)| public.playable(variant, card))
+            .min_by_key(|(slot, card)| (card.value().index() != 4, card.value().index(), *slot))
+        {
+            return slot;
+        }
+        if let Some(&(slot, _)) = slots.iter().find(|(_, card)| public.dead(variant, card)) {
+            return self.slots + slot;
+        }
+        if let Some(&(slot, _)) = slots
+            .iter()
+            .filter(|(_, card)| !public.critical(card))
+            .max_by_key(|(slot, card)| (card.value().index(), usize::MAX - slot))
+        {
+            return self.slots + slot;
+        }
+        self.slots
+    }
+
+    // the recommended action in the engine order
+    fn action(&self, code: usize, hand: usize) -> ActionRequest {
+        let position = self.order.convert(code % self.slots, hand);
+        if code < self.slots {
+            ActionRequest::Play { position }
+        } else {
+            ActionRequest::Discard { position }
+        }
+    }
+}
+
+// the recommendation of a player and the number of plays before the clue that gave it
+struct Recommendation {
+    code: usize,
+    plays: usize,
+}
+
+impl HatBot {
+    // the recommendation of the player to move, from every clue since their last play or discard
+    fn recommendation(&self, obs: &Observation, code: &Code) -> Option<Recommendation> {
+        let me = obs.player();
+        let n = obs.players();
+        let variant = obs.variant();
+        let mut public = Public {
+            table: vec![0; variant.suits().len()],
+            discard: Vec::new(),
+        };
+        let mut plays = 0;
+        let mut current = None;
+        for (action, hands) in obs.history().iter().zip(obs.past_hands()) {
+            let player = action.player();
+            match *action {
+                Action::Play { card, success, .. } => {
+                    plays += 1;
+                    if success {
+                        public.table[variant.suit_index(card.color())] += 1;
+                    } else {
+                        public.discard.push(card);
+                    }
+                    if player == me {
+                        current = None;
+                    }
+                }
+                Action::Discard { card, .. } => {
+                    public.discard.push(card);
+                    if player == me {
+                        current = None;
+                    }
+                }
+                Action::ColorClue { target, .. } | Action::ValueClue { target, .. } => {
+                    if player == me {
+                        continue;
+                    }
+                    let kind = matches!(action, Action::ColorClue { .. }) as usize;
+                    let told = (2 * ((target + n - player) % n - 1) + kind) % code.size();
+                    let others: usize = (0..n)
+                        .filter(|&p| p != player && p != me)
+                        .map(|p| {
+                            let hand = hands[p].as_ref().unwrap();
+                            code.recommend(&public, variant, hand)
+                        })
+                        .sum();
+                    current = Some(Recommendation {
+                        code: (told + code.size() - others % code.size()) % code.size(),
+                        plays,
+                    });
+                }
+            }
+        }
+        current.map(|x| Recommendation {
+            code: x.code,
+            plays: plays - x.plays,
+        })
+    }
+
+    // the clue that tells everyone else their recommendation
+    fn clue(&self, obs: &Observation, code: &Code) -> Option<ActionRequest> {
+        let me = obs.player();
+        let n = obs.players();
+        let variant = obs.variant();
+        let public = Public {
+            table: obs.table().to_vec(),
+            discard: obs.discard().to_vec(),
+        };
+        let sum: usize = (0..n)
+            .filter(|&p| p != me)
+            .map(|p| code.recommend(&public, variant, obs.hand(p).unwrap()))
+            .sum();
+        let told = sum % code.size();
+        let target = (me + told / 2 + 1) % n;
+        let hand = obs.hand(target).unwrap();
+        let clues: Vec<ActionRequest> = if told.is_multiple_of(2) {
+            hand.iter()
+                .map(|card| ActionRequest::ValueClue {
+                    target,
+                    value: card.value(),
+                })
+                .collect()
+        } else {
+            variant
+                .colors()
+                .into_iter()
+                .map(|color| ActionRequest::ColorClue { target, color })
+                .collect()
+        };
+        clues.into_iter().find(|clue| obs.is_legal(clue))
+    }
+}
+
+impl Agent for HatBot {
+    // plays its recommended card unless two cards were played since the clue (or one, at the last
+    // strike), else clues, else follows a recommended discard, else discards its oldest card
+    fn act(&mut self, obs: &Observation) -> ActionRequest {
+        let hand = obs.hand_size(obs.player());
+        let code = Code::new(obs);
+        let recommendation = self.recommendation(obs, &code);
+        if let Some(Recommendation { code: play, plays }) = recommendation {
+            let safe = plays == 0 || (plays == 1 && obs.mistakes() + 1 < MAXMISTAKES);
+            let action = code.action(play, hand);
+            if play < code.slots && safe && obs.is_legal(&action) {
+                return action;
+            }
+        }
+        if let Some(clue) = self.clue(obs, &code) {
+            return clue;
+        }
+        if let Some(Recommendation { code: discard, .. }) = recommendation {
+            let action = code.action(discard, hand);
+            if discard >= code.slots && obs.is_legal(&action) {
+                return action;
+            }
+        }
+        let oldest = ActionRequest::Discard {
+            position: code.order.convert(0, hand),
+        };
+        if obs.is_legal(&oldest) {
+            return oldest;
+        }
+        (0..NACTIONS)
+            .map(ActionRequest::from_id)
+            .find(|action| obs.is_legal(action))
+            .unwrap_or(oldest)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::State;
+
+    fn scores(nplayer: usize, seeds: std::ops::Range<u64>) -> Vec<usize> {
+        seeds
+            .map(|seed| {
+                let mut state = State::with_seed(nplayer, seed);
+                while !state.gameover() {
+                    let action = HatBot.act(&Observation::new(&state));
+                    state.apply(&action).unwrap();
+                }
+                state.score()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn every_player_reads_their_recommendation_from_the_clue() {
+        for seed in 0..20 {
+            let mut state = State::with_seed(5, seed);
+            let obs = Observation::new(&state);
+            let code = Code::new(&obs);
+            let public = Public {
+                table: obs.table().to_vec(),
+                discard: obs.discard().to_vec(),
+            };
+            let told: Vec<usize> = (1..5)
+                .map(|p| code.recommend(&public, obs.variant(), obs.hand(p).unwrap()))
+                .collect();
+            let clue = HatBot.clue(&obs, &code).unwrap();
+            state.apply(&clue).unwrap();
+            for p in 1..5 {
+                let obs = Observation::of(&state, p);
+                let read = HatBot.recommendation(&obs, &Code::new(&obs)).unwrap();
+                assert_eq!((read.code, read.plays), (told[p - 1], 0), "seed {}", seed);
+            }
+        }
+    }
+
+    // a change of the strategy shows here, update the total if it is meant
+    #[test]
+    fn scores_of_fixed_seeds() {
+        let scores = scores(5, 0..20);
+        assert_eq!(scores.iter().sum::<usize>(), 448, "{:?}", scores);
+    }
+}
@@ -0,0 +1,363 @@
+// observation of DeepMind's hanabi-learning-environment (CanonicalObservationEncoder), 658
+// entries for 2 players; hands are ordered oldest card first, players relative to the observer
+// and the colors as R Y G W B with our purple standing for white
+use crate::replay::Replay;
+use crate::state::{Action, ActionRequest, Card, Color, State, MAXCLUES, MAXMISTAKES};
+use crate::variant::Variant;
+use ndarray::Array1;
+
+const NCOLORS: usize = 5;
+const NRANKS: usize = 5;
+const CARD: usize = NCOLORS * NRANKS;
+
+fn color_index(color: Color) -> usize {
+    [0, 2, 4, 1, 3][color.index()]
+}
+
+fn card_index(card: &Card) -> usize {
+    color_index(card.color()) * NRANKS + card.value().index()
+}
+
+fn hand_size(players: usize) -> usize {
+    if players <= 3 {
+        5
+    } else {
+        4
+    }
+}
+
+pub fn size(players: usize) -> usize {
+    let hand = hand_size(players);
+    let deck = Variant::standard().deck().len();
+    (players - 1) * hand * CARD
+        + players
+        + (deck - hand * players)
+        + NCOLORS * NRANKS
+        + MAXCLUES
+        + MAXMISTAKES
+        + deck
+        + (players + 4 + players + NCOLORS + NRANKS + hand + hand + CARD + 2)
+        + players * hand * (CARD + NCOLORS + NRANKS)
+}
+
+pub fn supports(state: &State) -> bool {
+    *state.variant() == Variant::standard() && state.modifiers().0.is_empty()
+}
+
+// what HLE tracks about a card, negative clues included
+#[derive(Clone)]
+struct CardKnowledge {
+    colors: [bool; NCOLORS],
+    ranks: [bool; NRANKS],
+    color: Option<usize>,
+    rank: Option<usize>,
+}
+
+impl CardKnowledge {
+    fn new() -> CardKnowledge {
+        CardKnowledge {
+            colors: [true; NCOLORS],
+            ranks: [true; NRANKS],
+            color: None,
+            rank: None,
+        }
+    }
+
+    fn clue_color(&mut self, color: usize, touched: bool) {
+        if touched {
+            self.colors = [false; NCOLORS];
+            self.colors[color] = true;
+            self.color = Some(color);
+        } else {
+            self.colors[color] = false;
+        }
+    }
+
+    fn clue_rank(&mut self, rank: usize, touched: bool) {
+        if touched {
+            self.ranks = [false; NRANKS];
+            self.ranks[rank] = true;
+            self.rank = Some(rank);
+        } else {
+            self.ranks[rank] = false;
+        }
+    }
+}
+
+struct LastMove {
+    action: ActionRequest,
+    player: usize,
+    clues: usize,
+    hand: usize,
+}
+
+impl State {
+    // None unless the game is the standard variant without rule modifiers
+    pub fn encode_hle(&self, observer: usize) -> Option<Array1<f32>> {
+        if !supports(self) {
+            return None;
+        }
+        let n = self.players().len();
+        let hand = hand_size(n);
+        let deck = Variant::standard().deck().len();
+
+        // knowledge of every hand, newest card first like the engine
+        let mut knowledge = vec![vec![CardKnowledge::new(); hand]; n];
+        let mut last = None;
+        Replay::from_state(self)
+            .play(|state, action| {
+                let p = state.turn() % n;
+                match *action {
+                    ActionRequest::Play { position } | ActionRequest::Discard { position } => {
+                        knowledge[p].remove(position);
+                        if !state.deck().is_empty() {
+                            knowledge[p].insert(0, CardKnowledge::new());
+                        }
+                    }
+                    ActionRequest::ColorClue { target, color } => {
+                        for (card, k) in state.players()[target].iter().zip(&mut knowledge[target])
+                        {
+                            k.clue_color(color_index(color), card.color() == color);
+                        }
+                    }
+                    ActionRequest::ValueClue { target, value } => {
+                        for (card, k) in state.players()[target].iter().zip(&mut knowledge[target])
+                        {
+                            k.clue_rank(value.index(), card.value() == value);
+                        }
+                    }
+                }
+                last = Some(LastMove {
+                    action: *action,
+                    player: p,
+                    clues: *state.clues(),
+                    hand: state.players()[p].len(),
+                });
+            })
+            .ok()?;
+
+        let mut x = Array1::zeros(size(n));
+        let mut off = 0;
+
+        // hands of the other players
+        for rel in 1..n {
+            let cards = &self.players()[(observer + rel) % n];
+            for (i, card) in cards.iter().rev().enumerate() {
+                x[off + i * CARD + card_index(card)] = 1.0;
+            }
+            off += hand * CARD;
+        }
+        for rel in 0..n {
+            if self.players()[(observer + rel) % n].len() < hand {
+                x[off + rel] = 1.0;
+            }
+        }
+        off += n;
+
+        // board
+        for i in 0..self.deck().len() {
+            x[off + i] = 1.0;
+        }
+        off += deck - hand * n;
+        for &suit in self.variant().suits() {
+            let played = self.table()[self.variant().suit_index(suit)];
+            if played > 0 {
+                x[off + color_index(suit) * NRANKS + played - 1] = 1.0;
+            }
+        }
+        off += NCOLORS * NRANKS;
+        for i in 0..*self.clues() {
+            x[off + i] = 1.0;
+        }
+        off += MAXCLUES;
+        for i in 0..MAXMISTAKES - self.mistakes() {
+            x[off + i] = 1.0;
+        }
+        off += MAXMISTAKES;
+
+        // discard pile
+        let mut counts = [0; CARD];
+        for card in self.discard() {
+            counts[card_index(card)] += 1;
+        }
+        for color in 0..NCOLORS {
+            for rank in 0..NRANKS {
+                for i in 0..counts[color * NRANKS + rank] {
+                    x[off + i] = 1.0;
+                }
+                off += [3, 2, 2, 2, 1][rank];
+            }
+        }
+
+        // last move
+        let start = off;
+        if let (Some(last), Some(action)) = (last, self.history().back()) {
+            let relative = |player: usize| (player + n - observer) % n;
+            x[off + relative(last.player)] = 1.0;
+            off += n;
+            let kind = match last.action {
+                ActionRequest::Play { .. } => 0,
+                ActionRequest::Discard { .. } => 1,
+                ActionRequest::ColorClue { .. } => 2,
+                ActionRequest::ValueClue { .. } => 3,
+            };
+            x[off + kind] = 1.0;
+            off += 4;
+            let touched: Option<(usize, Vec<bool>)> = match last.action {
+                ActionRequest::ColorClue { target, color } => {
+                    x[off + relative(target)] = 1.0;
+                    x[off + n + color_index(color)] = 1.0;
+                    let cards = &self.players()[target];
+                    Some((target, cards.iter().map(|c| c.color() == color).collect()))
+                }
+                ActionRequest::ValueClue { target, value } => {
+                    x[off + relative(target)] = 1.0;
+                    x[off + n + NCOLORS + value.index()] = 1.0;
+                    let cards = &self.players()[target];
+                    Some((target, cards.iter().map(|c| c.value() == value).collect()))
+                }
+                _ => None,
+            };
+            off += n + NCOLORS + NRANKS;
+            if let Some((_, touched)) = touched {
+                for (i, &t) in touched.iter().rev().enumerate() {
+                    if t {
+                        x[off + i] = 1.0;
+                    }
+                }
+            }
+            off += hand;
+            match *action {
+                Action::Play {
+                    position,
+                    card,
+                    success,
+                    ..
+                } => {
+                    x[off + last.hand - 1 - position] = 1.0;
+                    x[off + hand + card_index(&card)] = 1.0;
+                    if success {
+                        x[off + hand + CARD] = 1.0;
+                        if card.value().index() == NRANKS - 1 && last.clues < MAXCLUES {
+                            x[off + hand + CARD + 1] = 1.0;
+                        }
+                    }
+                }
+                Action::Discard { position, card, .. } => {
+                    x[off + last.hand - 1 - position] = 1.0;
+                    x[off + hand + card_index(&card)] = 1.0;
+                }
+                _ => {}
+            }
+        }
+        off = start + n + 4 + n + NCOLORS + NRANKS + hand + hand + CARD + 2;
+
+        // what every player knows about their cards
+        for rel in 0..n {
+            let player = (observer + rel) % n;
+            for (i, k) in knowledge[player].iter().rev().enumerate() {
+                let o = off + i * (CARD + NCOLORS + NRANKS);
+                for color in 0..NCOLORS {
+                    for rank in 0..NRANKS {
+                        if k.colors[color] && k.ranks[rank] {
+                            x[o + color * NRANKS + rank] = 1.0;
+                        }
+                    }
+                }
+                if let Some(color) = k.color {
+                    x[o + CARD + color] = 1.0;
+                }
+                if let Some(rank) = k.rank {
+                    x[o + CARD + NCOLORS + rank] = 1.0;
+                }
+            }
+            off += hand * (CARD + NCOLORS + NRANKS);
+        }
+        debug_assert_eq!(off, x.len());
+        Some(x)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::modifier;
+    use crate::state::Value;
+
+    // the first player holds 1r 1r 1r 2r 2r and the second 3r 3r 4r 4r 5r, newest first
+    fn dealt() -> State {
+        State::from_deck(2, Variant::standard().deck())
+    }
+
+    // the offsets of the sections for 2 players
+    const DECK: usize = 127;
+    const FIREWORKS: usize = 167;
+    const LAST: usize = 253;
+    const KNOWLEDGE: usize = 308;
+
+    #[test]
+    fn two_players_give_the_hle_size() {
+        assert_eq!(size(2), 658);
+        assert_eq!(dealt().encode_hle(0).unwrap().len(), 658);
+        let six = State::with_variant(2, Variant::from_name("six_suits").unwrap(), Some(0));
+        assert!(six.encode_hle(0).is_none());
+        let draw_last = dealt().modified(modifier::resolve(&["draw_last"]).unwrap());
+        assert!(draw_last.encode_hle(0).is_none());
+    }
+
+    #[test]
+    fn hands_and_board_of_the_deal() {
+        let x = dealt().encode_hle(0).unwrap();
+        // 5r 4r 4r 3r 3r, oldest first
+        for (i, &value) in [4, 3, 3, 2, 2].iter().enumerate() {
+            assert_eq!(x[i * CARD + value], 1.0);
+        }
+        assert_eq!(x.slice(ndarray::s![..5 * CARD]).sum(), 5.0);
+        assert_eq!(x.slice(ndarray::s![DECK..FIREWORKS]).sum(), 40.0);
+        assert_eq!(x.slice(ndarray::s![FIREWORKS..LAST]).sum(), 11.0);
+        assert_eq!(x.slice(ndarray::s![LAST..KNOWLEDGE]).sum(), 0.0);
+    }
+
+    #[test]
+    fn last_clue_and_knowledge() {
+        let mut state = dealt();
+        let clue = ActionRequest::ValueClue {
+            target: 1,
+            value: Value::new(4),
+        };
+        state.apply(&clue).unwrap();
+        let x = state.encode_hle(1).unwrap();
+        // by the other player, a rank clue to the observer touching their oldest card, a 5
+        for i in [
+            LAST + 1,
+            LAST + 2 + 3,
+            LAST + 6,
+            LAST + 8 + 5 + 4,
+            LAST + 18,
+        ] {
+            assert_eq!(x[i], 1.0, "{}", i);
+        }
+        assert_eq!(x.slice(ndarray::s![LAST..KNOWLEDGE]).sum(), 5.0);
+        let width = CARD + NCOLORS + NRANKS;
+        for color in 0..NCOLORS {
+            assert_eq!(x[KNOWLEDGE + color * NRANKS + 4], 1.0);
+            assert_eq!(x[KNOWLEDGE + color * NRANKS], 0.0);
+            assert_eq!(x[KNOWLEDGE + width + color * NRANKS + 4], 0.0);
+        }
+        assert_eq!(x[KNOWLEDGE + CARD + NCOLORS + 4], 1.0);
+    }
+
+    #[test]
+    fn last_play() {
+        let mut state = dealt();
+        state.apply(&ActionRequest::Play { position: 0 }).unwrap();
+        let x = state.encode_hle(0).unwrap();
+        // the newest card of five is the last oldest first, a red 1 played
+        let reveal = LAST + 2 + 4 + 2 + NCOLORS + NRANKS + 5;
+        assert_eq!(x[reveal + 4], 1.0);
+        assert_eq!(x[reveal + 5], 1.0);
+        assert_eq!(x[reveal + 5 + CARD], 1.0);
+        assert_eq!(x[reveal + 5 + CARD + 1], 0.0);
+        assert_eq!(x[FIREWORKS], 1.0);
+    }
+}
@@ -0,0 +1,106 @@
+use crate::replay::{Replay, ReplayError};
+use crate::state::{ActionRequest, State};
+
+pub const KINDS: [&str; 4] = ["play", "discard", "color_clue", "value_clue"];
+
+pub fn kind(action: &ActionRequest) -> usize {
+    match action {
+        ActionRequest::Play { .. } => 0,
+        ActionRequest::Discard { .. } => 1,
+        ActionRequest::ColorClue { .. } => 2,
+        ActionRequest::ValueClue { .. } => 3,
+    }
+}
+
+// how often a policy takes the action of the recorded player, by kind of the recorded action
+#[derive(Clone, Debug, Default)]
+pub struct Accuracy {
+    pub decisions: [usize; 4],
+    pub matches: [usize; 4],
+    // decisions of each recorded kind (rows) where the policy chose each kind (columns)
+    pub confusion: [[usize; 4]; 4],
+    // decisions where the policy gave no action
+    pub abstained: usize,
+}
+
+impl Accuracy {
+    pub fn total(&self) -> usize {
+        self.decisions.iter().sum()
+    }
+
+    pub fn accuracy(&self) -> f64 {
+        ratio(self.matches.iter().sum(), self.total())
+    }
+
+    pub fn accuracy_of(&self, kind: usize) -> f64 {
+        ratio(self.matches[kind], self.decisions[kind])
+    }
+
+    fn record(&mut self, recorded: &ActionRequest, action: Option<ActionRequest>) {
+        let k = kind(recorded);
+        self.decisions[k] += 1;
+        match action {
+            Some(action) => {
+                self.confusion[k][kind(&action)] += 1;
+                if action == *recorded {
+                    self.matches[k] += 1;
+                }
+            }
+            None => self.abstained += 1,
+        }
+    }
+}
+
+fn ratio(a: usize, b: usize) -> f64 {
+    if b == 0 {
+        0.0
+    } else {
+        a as f64 / b as f64
+    }
+}
+
+// asks `policy` for its action at every decision point of the replays, where the recorded
+// player acted; None counts as a miss
+pub fn evaluate<F>(replays: &[Replay], mut policy: F) -> Result<Accuracy, ReplayError>
+where
+    F: FnMut(&State) -> Option<ActionRequest>,
+{
+    let mut accuracy = Accuracy::default();
+    for replay in replays {
+        replay.play(|state, recorded| {
+            let action = policy(state);
+            accuracy.record(recorded, action);
+        })?;
+    }
+    Ok(accuracy)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agents::{Agent, ConventionBot, Observation};
+    use crate::driver::GameDriver;
+    use crate::variant::Variant;
+
+    #[test]
+    fn an_agent_imitates_its_own_games() {
+        let seats: Vec<Box<dyn Agent>> = vec![Box::new(ConventionBot), Box::new(ConventionBot)];
+        let record = GameDriver::new(Variant::standard(), seats).play(Some(0));
+        let replays = [record.replay];
+        let accuracy = evaluate(&replays, |state| {
+            Some(ConventionBot.act(&Observation::new(state)))
+        })
+        .unwrap();
+        assert_eq!(accuracy.total(), record.turns);
+        assert_eq!(accuracy.accuracy(), 1.0);
+        assert_eq!(accuracy.abstained, 0);
+        for k in 0..KINDS.len() {
+            assert_eq!(accuracy.confusion[k][k], accuracy.decisions[k]);
+        }
+
+        let silent = evaluate(&replays, |_| None).unwrap();
+        assert_eq!(silent.total(), record.turns);
+        assert_eq!(silent.accuracy(), 0.0);
+        assert_eq!(silent.abstained, record.turns);
+    }
+}
@@ -0,0 +1,126 @@
+#![allow(dead_code)]
+
+use crate::manifest::fnv1a;
+use crate::replay::{Replay, ReplayError};
+use crate::state::State;
+use rayon::prelude::*;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+// bonus added to the score increase of an action, evaluated on the state after the action
+pub trait IntrinsicReward: Send {
+    fn reward(&mut self, state: &State) -> f32;
+}
+
+// hash of the observation of the player to move
+pub fn fingerprint(state: &State) -> u64 {
+    let bytes: Vec<u8> = state
+        .encode()
+        .iter()
+        .flat_map(|x| x.to_bits().to_le_bytes().to_vec())
+        .collect();
+    fnv1a(&bytes)
+}
+
+// visit counts, shared between games so that novelty persists across episodes
+#[derive(Clone, Default)]
+pub struct Counts(Arc<Mutex<HashMap<u64, u64>>>);
+
+impl Counts {
+    pub fn visit(&self, key: u64) -> u64 {
+        let mut counts = self.0.lock().unwrap();
+        let count = counts.entry(key).or_insert(0);
+        *count += 1;
+        *count
+    }
+
+    pub fn get(&self, key: u64) -> u64 {
+        *self.0.lock().unwrap().get(&key).unwrap_or(&0)
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.lock().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn total(&self) -> u64 {
+        self.0.lock().unwrap().values().sum()
+    }
+
+    // counts the public states met in the replays, returns how many were visited
+    pub fn add_replays(&self, replays: &[Replay]) -> Result<usize, ReplayError> {
+        let keys = replays
+            .par_iter()
+            .map(|replay| {
+                let mut keys = Vec::new();
+                let state = replay.play(|state, _| keys.push(state.public_hash()))?;
+                keys.push(state.public_hash());
+                Ok(keys)
+            })
+            .collect::<Result<Vec<Vec<u64>>, ReplayError>>()?;
+
+        let mut counts = self.0.lock().unwrap();
+        let mut n = 0;
+        for key in keys.into_iter().flatten() {
+            *counts.entry(key).or_insert(0) += 1;
+            n += 1;
+        }
+        Ok(n)
+    }
+
+    pub fn clear(&self) {
+        self.0.lock().unwrap().clear();
+    }
+}
+
+// scale / sqrt(n) where n is the number of visits of the fingerprint
+pub struct CountBonus {
+    pub counts: Counts,
+    pub scale: f32,
+}
+
+impl IntrinsicReward for CountBonus {
+    fn reward(&mut self, state: &State) -> f32 {
+        let n = self.counts.visit(fingerprint(state));
+        self.scale / (n as f32).sqrt()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::{ActionRequest, Value};
+    use crate::variant::Variant;
+
+    #[test]
+    fn count_bonus_decays_with_the_visits() {
+        let dealt = State::from_deck(2, Variant::standard().deck());
+        let mut clued = State::from_deck(2, Variant::standard().deck());
+        clued
+            .apply(&ActionRequest::ValueClue {
+                target: 1,
+                value: Value::new(4),
+            })
+            .unwrap();
+        let mut bonus = CountBonus {
+            counts: Counts::default(),
+            scale: 0.5,
+        };
+        for n in 1..=4 {
+            let expected = 0.5 / (n as f32).sqrt();
+            assert!((bonus.reward(&dealt) - expected).abs() < 1e-6);
+        }
+        assert_eq!(bonus.reward(&clued), 0.5);
+        // the counts are shared with the clones, across games
+        let mut other = CountBonus {
+            counts: bonus.counts.clone(),
+            scale: 1.0,
+        };
+        assert_eq!(other.reward(&clued), 1.0 / 2f32.sqrt());
+        assert_eq!(bonus.counts.get(fingerprint(&dealt)), 4);
+        assert_eq!((bonus.counts.len(), bonus.counts.total()), (2, 6));
+    }
+}
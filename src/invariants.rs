@@ -0,0 +1,160 @@
+// consistency checks of a state, to debug the engine, the rule modifiers and the code that builds
+// positions; the rules never break them, whatever the actions tried
+use crate::state::{Card, State, MAXCARDS, MAXCLUES, MAXMISTAKES, MAXPLAYERS};
+
+impl State {
+    // the first broken invariant: every card of the variant is in exactly one of the hands, the
+    // deck, the discard pile and the fireworks, the tokens are in their bounds, the cards agree
+    // with what their holders were told, and the history accounts for the turns; that the turn
+    // only increases is for the caller to check between actions
+    pub fn check_invariants(&self) -> Result<(), String> {
+        let players = self.players().len();
+        if !(2..=MAXPLAYERS).contains(&players) {
+            return Err(format!("{} players", players));
+        }
+        if let Some(hand) = self.players().iter().find(|x| x.len() > MAXCARDS) {
+            return Err(format!("a hand of {} cards", hand.len()));
+        }
+        if *self.clues() > MAXCLUES || *self.mistakes() > MAXMISTAKES {
+            return Err(format!(
+                "{} clues and {} mistakes",
+                self.clues(),
+                self.mistakes()
+            ));
+        }
+        let suits = self.variant().suits().len();
+        if self.table().len() != suits || self.table().iter().any(|&x| x > 5) {
+            return Err(format!("fireworks {:?} for {} suits", self.table(), suits));
+        }
+
+        let mut cards: Vec<u8> = self
+            .players()
+            .iter()
+            .flatten()
+            .chain(self.deck())
+            .chain(self.discard())
+            .chain(&self.played())
+            .map(Card::index)
+            .collect();
+        let mut deck: Vec<u8> = self.variant().deck().iter().map(Card::index).collect();
+        cards.sort_unstable();
+        deck.sort_unstable();
+        if cards != deck {
+            return Err(format!(
+                "{} cards in the game instead of the {} of the deck, or other cards",
+                cards.len(),
+                deck.len()
+            ));
+        }
+
+        if self.knowledge().len() != players {
+            return Err(format!(
+                "knowledge of {} players for {}",
+                self.knowledge().len(),
+                players
+            ));
+        }
+        for (player, (hand, knowledge)) in self.players().iter().zip(self.knowledge()).enumerate() {
+            if hand.len() != knowledge.len() {
+                return Err(format!(
+                    "player {} holds {} cards and knows about {}",
+                    player,
+                    hand.len(),
+                    knowledge.len()
+                ));
+            }
+            for (position, (card, k)) in hand.iter().zip(knowledge).enumerate() {
+                if !k.allows(card, self.variant()) {
+                    return Err(format!(
+                        "player {} was told wrong about their {} at position {}",
+                        player, card, position
+                    ));
+                }
+            }
+        }
+
+        match *self.final_round() {
+            Some(_) if !self.deck().is_empty() => {
+                return Err(format!(
+                    "a final round with {} cards to draw",
+                    self.deck().len()
+                ))
+            }
+            Some(left) if left > players => {
+                return Err(format!("{} turns left for {} players", left, players))
+            }
+            _ => {}
+        }
+
+        let actions = self.compacted().len() + self.history().len();
+        if actions > *self.turn() {
+            return Err(format!("{} actions in {} turns", actions, self.turn()));
+        }
+        if let Some(action) = self.history().back() {
+            if action.player() != (self.turn() + players - 1) % players {
+                return Err(format!(
+                    "the last action is of player {} at turn {}",
+                    action.player(),
+                    self.turn()
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::modifier;
+    use crate::state::{ActionRequest, State, NACTIONS};
+    use crate::variant::Variant;
+    use proptest::prelude::*;
+
+    fn game() -> impl Strategy<Value = (usize, String, Option<String>, u64)> {
+        let variants: Vec<String> = Variant::names().iter().map(|x| x.to_string()).collect();
+        let modifiers: Vec<String> = modifier::names().iter().map(|x| x.to_string()).collect();
+        (
+            2..=5usize,
+            proptest::sample::select(variants),
+            proptest::option::of(proptest::sample::select(modifiers)),
+            any::<u64>(),
+        )
+    }
+
+    proptest! {
+        #![proptest_config(ProptestConfig::with_cases(64))]
+
+        // `picks` choose among the legal actions and `tries` are any ids, refused or not
+        #[test]
+        fn random_games_keep_the_invariants(
+            (players, variant, modifier, seed) in game(),
+            picks in proptest::collection::vec(any::<usize>(), 100),
+            tries in proptest::collection::vec(0..NACTIONS, 100),
+        ) {
+            let variant = Variant::from_name(&variant).unwrap();
+            let mut state = State::with_variant(players, variant, Some(seed))
+                .modified(modifier.iter().filter_map(|x| modifier::from_name(x)).collect());
+            prop_assert_eq!(state.check_invariants(), Ok(()));
+            for (&pick, &id) in picks.iter().zip(&tries) {
+                if state.gameover() {
+                    break;
+                }
+                let turn = *state.turn();
+                let refused = state.apply(&ActionRequest::from_id(id)).is_err();
+                prop_assert_eq!(*state.turn(), if refused { turn } else { turn + 1 });
+                prop_assert_eq!(state.check_invariants(), Ok(()));
+                if state.gameover() {
+                    break;
+                }
+                let legal: Vec<usize> = (0..NACTIONS)
+                    .filter(|&id| state.check(&ActionRequest::from_id(id)).is_ok())
+                    .collect();
+                prop_assert!(!legal.is_empty());
+                let turn = *state.turn();
+                state.apply(&ActionRequest::from_id(legal[pick % legal.len()])).unwrap();
+                prop_assert_eq!(*state.turn(), turn + 1);
+                prop_assert_eq!(state.check_invariants(), Ok(()));
+            }
+        }
+    }
+}
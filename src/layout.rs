@@ -0,0 +1,74 @@
+use crate::rules::NRULES;
+use crate::state::{State, MAXCARDS, MAXCLUES, MAXMISTAKES, MAXPLAYERS};
+use ndarray::ArrayView1;
+
+// a named range of the observation
+#[derive(Debug, Clone)]
+pub struct Block {
+    pub name: String,
+    pub start: usize,
+    pub len: usize,
+}
+
+#[derive(Debug)]
+pub struct BlockDiff {
+    pub name: String,
+    // (index in the block, first value, second value)
+    pub entries: Vec<(usize, f32, f32)>,
+}
+
+impl State {
+    // blocks of `encode` in order, the history starts with the last move
+    pub fn encoding_layout(&self) -> Vec<Block> {
+        let (width, cwidth) = self.encoding_widths();
+        let nsuits = self.variant().suits().len();
+        let ncards = self.variant().deck_size();
+        let mut blocks: Vec<Block> = Vec::new();
+        let mut push = |name: String, len: usize| {
+            let start = blocks.last().map_or(0, |x| x.start + x.len);
+            blocks.push(Block { name, start, len });
+            start + len
+        };
+
+        push("players".to_string(), MAXPLAYERS - 1);
+        push("seat".to_string(), MAXPLAYERS);
+        push("clues".to_string(), MAXCLUES);
+        push("mistakes".to_string(), MAXMISTAKES);
+        push("deck".to_string(), ncards);
+        push("discard".to_string(), ncards);
+        for i in 0..MAXPLAYERS {
+            push(format!("hand {}", i), MAXCARDS * width);
+        }
+        push("table".to_string(), 5 * nsuits);
+        // the `Rules::flags` of `State::rules`
+        let mut start = push("rules".to_string(), NRULES);
+
+        let size = self.encoding_size();
+        // kind, position or target, value, color and the positions touched by a clue
+        let step = 4 + MAXPLAYERS + 5 + cwidth + MAXCARDS;
+        let mut i = 0;
+        while start < size {
+            start = push(format!("move -{}", i + 1), step);
+            i += 1;
+        }
+        blocks
+    }
+}
+
+// blocks in which the observations differ
+pub fn diff(layout: &[Block], a: &ArrayView1<f32>, b: &ArrayView1<f32>) -> Vec<BlockDiff> {
+    let mut diffs = Vec::new();
+    for block in layout {
+        let entries: Vec<(usize, f32, f32)> = (0..block.len)
+            .map(|i| (i, a[block.start + i], b[block.start + i]))
+            .filter(|&(_, x, y)| x != y)
+            .collect();
+        if !entries.is_empty() {
+            diffs.push(BlockDiff {
+                name: block.name.clone(),
+                entries,
+            });
+        }
+    }
+    diffs
+}
@@ -0,0 +1,149 @@
+// verified games of a community in sqlite, built with the `leaderboard` feature
+//
+// a submission is a claim (see `verify`) with the name of its submitter, it is stored only once
+// its score is verified; a leaderboard ranks the submitters of a configuration (variant, players
+// and rule modifiers) by their best game: higher score, then fewer turns, then earlier submission
+use crate::verify::{Claim, VerifyError};
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+#[derive(Debug)]
+pub enum LeaderboardError {
+    Sql(rusqlite::Error),
+    Verify(VerifyError),
+}
+
+impl From<rusqlite::Error> for LeaderboardError {
+    fn from(err: rusqlite::Error) -> LeaderboardError {
+        LeaderboardError::Sql(err)
+    }
+}
+
+impl From<VerifyError> for LeaderboardError {
+    fn from(err: VerifyError) -> LeaderboardError {
+        LeaderboardError::Verify(err)
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Entry {
+    pub rank: usize,
+    pub submitter: String,
+    // id of the game in the database
+    pub game: i64,
+    pub seed: Option<u64>,
+    pub score: usize,
+    pub turns: usize,
+    // unix time
+    pub submitted: u64,
+}
+
+pub struct Leaderboard {
+    conn: Connection,
+}
+
+impl Leaderboard {
+    // opens the database, creating it if needed
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Leaderboard, LeaderboardError> {
+        let conn = Connection::open(path)?;
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS games (
+                id INTEGER PRIMARY KEY,
+                submitter TEXT NOT NULL,
+                variant TEXT NOT NULL,
+                players INTEGER NOT NULL,
+                modifiers TEXT NOT NULL,
+                seed INTEGER,
+                claim TEXT NOT NULL,
+                score INTEGER NOT NULL,
+                turns INTEGER NOT NULL,
+                submitted INTEGER NOT NULL
+            );
+            CREATE INDEX IF NOT EXISTS games_config ON games (variant, players, modifiers);",
+        )?;
+        Ok(Leaderboard { conn })
+    }
+
+    // verifies the claim (json) and stores it, returns the id of the game
+    pub fn submit(&self, submitter: &str, json: &str) -> Result<i64, LeaderboardError> {
+        let claim = Claim::from_json(json)?;
+        let state = claim.verify()?;
+        let submitted = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |x| x.as_secs());
+        self.conn.execute(
+            "INSERT INTO games (submitter, variant, players, modifiers, seed, claim, score, turns, submitted)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+            params![
+                submitter,
+                claim.variant.name(),
+                claim.players as i64,
+                claim.modifiers.join(","),
+                // sqlite integers are signed, the bits of the seed are kept
+                claim.seed.map(|seed| seed as i64),
+                json,
+                state.score() as i64,
+                *state.turn() as i64,
+                submitted as i64,
+            ],
+        )?;
+        Ok(self.conn.last_insert_rowid())
+    }
+
+    // the best game of each submitter of the configuration, at most `limit` of them
+    pub fn ranking(
+        &self,
+        variant: &str,
+        players: usize,
+        modifiers: &[String],
+        limit: usize,
+    ) -> Result<Vec<Entry>, LeaderboardError> {
+        let mut statement = self.conn.prepare(
+            "SELECT id, submitter, seed, score, turns, submitted FROM games
+            WHERE variant = ?1 AND players = ?2 AND modifiers = ?3
+            ORDER BY score DESC, turns ASC, submitted ASC, id ASC",
+        )?;
+        let rows = statement.query_map(
+            params![variant, players as i64, modifiers.join(",")],
+            |row| {
+                Ok(Entry {
+                    rank: 0,
+                    game: row.get(0)?,
+                    submitter: row.get(1)?,
+                    seed: row.get::<_, Option<i64>>(2)?.map(|seed| seed as u64),
+                    score: row.get::<_, i64>(3)? as usize,
+                    turns: row.get::<_, i64>(4)? as usize,
+                    submitted: row.get::<_, i64>(5)? as u64,
+                })
+            },
+        )?;
+        let mut seen = HashSet::new();
+        let mut entries = Vec::new();
+        for entry in rows {
+            let entry = entry?;
+            if entries.len() == limit {
+                break;
+            }
+            if seen.insert(entry.submitter.clone()) {
+                entries.push(Entry {
+                    rank: entries.len() + 1,
+                    ..entry
+                });
+            }
+        }
+        Ok(entries)
+    }
+
+    // the claim (json) of a stored game
+    pub fn claim(&self, game: i64) -> Result<Option<String>, LeaderboardError> {
+        let mut statement = self.conn.prepare("SELECT claim FROM games WHERE id = ?1")?;
+        let mut rows = statement.query(params![game])?;
+        Ok(match rows.next()? {
+            Some(row) => Some(row.get(0)?),
+            None => None,
+        })
+    }
+}
@@ -1,110 +1,67 @@
+// the engine, in pure rust; the python bindings are built with the `python` feature (on by
+// default), depend on the crate with `default-features = false` to leave them out
 extern crate ndarray;
 extern crate rand;
 
-mod state;
+pub mod agents;
+pub mod arrays;
+pub mod batch;
+pub mod belief;
+pub mod bench;
+pub mod binary;
+pub mod bindings;
+pub mod build_info;
+#[cfg(feature = "capi")]
+pub mod capi;
+pub mod clock;
+pub mod compare;
+pub mod conventions;
+pub mod daily;
+pub mod dataset;
+pub mod deals;
+pub mod driver;
+pub mod efficiency;
+pub mod encoder;
+pub mod endgame;
+pub mod endgames;
+pub mod eval;
+pub mod experiment;
+pub mod external;
+pub mod fingerprint;
+#[cfg(feature = "grpc")]
+pub mod grpc;
+pub mod hand;
+pub mod hash;
+pub mod hat;
+pub mod hle;
+pub mod imitation;
+pub mod intrinsic;
+pub mod invariants;
+pub mod layout;
+#[cfg(feature = "leaderboard")]
+pub mod leaderboard;
+pub mod manifest;
+pub mod mlp;
+pub mod modifier;
+pub mod probes;
+pub mod protocol;
+pub mod recorder;
+pub mod render;
+pub mod replay;
+#[cfg(feature = "websocket")]
+pub mod rooms;
+pub mod rules;
+pub mod search;
+pub mod server;
+pub mod slots;
+pub mod state;
+pub mod stats;
+#[cfg(feature = "storage")]
+pub mod storage;
+pub mod variant;
+pub mod verify;
 
-use ndarray::ArrayView1;
-use numpy::{IntoPyArray, PyArray1};
-use pyo3::prelude::{
-    pyclass, pymethods, pymodule, Py, PyModule, PyObject, PyRawObject, PyResult, Python,
-};
-use state::{Color, IllegalMoves, State, Value};
-
-#[pymodule]
-fn hanabi(_py: Python, m: &PyModule) -> PyResult<()> {
-    m.add_class::<Game>()?;
-    Ok(())
-}
-
-#[pyclass]
-struct Game {
-    state: State,
-}
-
-#[pymethods]
-impl Game {
-    #[new]
-    fn new(obj: &PyRawObject, nplayer: usize) {
-        obj.init({
-            Game {
-                state: State::new(nplayer),
-            }
-        });
-    }
-
-    fn play(&mut self, position: usize) -> Option<String> {
-        match self.state.play(position) {
-            Ok(_) => None,
-            Err(err) => Some(format!("{:?}", err)),
-        }
-    }
-
-    fn discard(&mut self, position: usize) -> Option<String> {
-        match self.state.play_discard(position) {
-            Ok(_) => None,
-            Err(err) => Some(format!("{:?}", err)),
-        }
-    }
-
-    fn clue(&mut self, py: Python, target: usize, info: PyObject) -> Option<String> {
-        match if let Ok(value) = info.extract::<usize>(py) {
-            if 1 <= value && value <= 5 {
-                self.state.clue_value(target, Value::new(value - 1))
-            } else {
-                Err(IllegalMoves::Error)
-            }
-        } else if let Ok(color) = info.extract::<&str>(py) {
-            if "rgbyp".to_string().contains(color) {
-                self.state.clue_color(target, Color::from_str(color))
-            } else {
-                Err(IllegalMoves::Error)
-            }
-        } else {
-            Ok(())
-        } {
-            Ok(_) => None,
-            Err(err) => Some(format!("{:?}", err)),
-        }
-    }
-
-    fn encode(&self, py: Python) -> Py<PyArray1<f32>> {
-        self.state.encode().into_pyarray(py).to_owned()
-    }
-
-    fn decode(&mut self, x: &PyArray1<f32>) -> Option<String> {
-        let x: ArrayView1<f32> = x.as_array();
-        match self.state.decode(&x) {
-            Ok(_) => None,
-            Err(err) => Some(format!("{:?}", err)),
-        }
-    }
-
-    #[getter]
-    fn get_gameover(&self) -> bool {
-        self.state.gameover()
-    }
-
-    #[getter]
-    fn get_turn(&self) -> usize {
-        *self.state.turn()
-    }
-
-    #[getter]
-    fn get_score(&self) -> usize {
-        self.state.score()
-    }
-
-    #[getter]
-    fn get_deck(&self) -> Vec<String> {
-        self.state.deck().iter().map(|x| format!("{}", x)).collect()
-    }
-
-    #[getter]
-    fn get_history(&self) -> Vec<String> {
-        self.state
-            .history()
-            .iter()
-            .map(|x| format!("{}", x))
-            .collect()
-    }
-}
+#[cfg(feature = "python")]
+mod python;
+#[cfg(feature = "wasm")]
+mod wasm;
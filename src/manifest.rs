@@ -0,0 +1,114 @@
+use crate::experiment::Experiment;
+use crate::variant::Variant;
+use serde::{Deserialize, Serialize};
+
+// 64-bit FNV-1a, stable across platforms and compiler versions
+pub fn fnv1a(bytes: &[u8]) -> u64 {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for &byte in bytes {
+        hash ^= u64::from(byte);
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
+    }
+    hash
+}
+
+// written next to every set of results to trace them back to the engine that produced them
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Manifest {
+    pub crate_version: String,
+    pub git_hash: Option<String>,
+    pub config_hash: String,
+    pub encoder: String,
+    pub variant: String,
+    pub modifiers: Vec<String>,
+    pub players: usize,
+    pub agents: Vec<String>,
+    pub seeds: Vec<u64>,
+}
+
+impl Manifest {
+    pub fn new(experiment: &Experiment) -> Manifest {
+        let mut config = serde_json::to_value(experiment).unwrap();
+        // where the results are written does not change them
+        config.as_object_mut().unwrap().remove("output");
+        Manifest::with_config(
+            &config,
+            &experiment.encoder,
+            &experiment.variant,
+            &experiment.modifiers,
+            experiment.players,
+            &experiment.agents,
+            experiment.seeds.to_vec(),
+        )
+    }
+
+    // the manifest of games played without an experiment file, e.g. an evaluation from python,
+    // whose config is what the manifest records
+    pub fn for_run(
+        encoder: &str,
+        variant: &Variant,
+        modifiers: &[String],
+        players: usize,
+        agents: &[String],
+        seeds: Vec<u64>,
+    ) -> Manifest {
+        let config = serde_json::json!({
+            "encoder": encoder,
+            "variant": variant.name(),
+            "modifiers": modifiers,
+            "players": players,
+            "agents": agents,
+            "seeds": seeds,
+        });
+        Manifest::with_config(&config, encoder, variant, modifiers, players, agents, seeds)
+    }
+
+    fn with_config(
+        config: &serde_json::Value,
+        encoder: &str,
+        variant: &Variant,
+        modifiers: &[String],
+        players: usize,
+        agents: &[String],
+        seeds: Vec<u64>,
+    ) -> Manifest {
+        Manifest {
+            crate_version: env!("CARGO_PKG_VERSION").to_string(),
+            git_hash: option_env!("HANABI_GIT_HASH").map(|x| x.to_string()),
+            config_hash: format!("{:016x}", fnv1a(config.to_string().as_bytes())),
+            encoder: encoder.to_string(),
+            variant: variant.name().to_string(),
+            modifiers: modifiers.to_vec(),
+            players,
+            agents: agents.to_vec(),
+            seeds,
+        }
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Manifest;
+    use crate::experiment::Experiment;
+
+    #[test]
+    fn the_output_is_not_part_of_the_config() {
+        let experiment = |output: &str| {
+            Experiment::from_toml(&format!(
+                "name = \"x\"\nplayers = 2\nseeds = [1, 2]\nagents = [\"random\"]\noutput = \"{}\"",
+                output
+            ))
+            .unwrap()
+        };
+        let a = Manifest::new(&experiment("results/a"));
+        let b = Manifest::new(&experiment("results/b"));
+        assert_eq!(a.config_hash, b.config_hash);
+        let mut other = experiment("results/a");
+        other.players = 3;
+        assert_ne!(Manifest::new(&other).config_hash, a.config_hash);
+    }
+}
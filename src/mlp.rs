@@ -0,0 +1,448 @@
+// small multilayer perceptron run in pure rust, for distilled policies where onnx cannot be
+// shipped (rollouts, wasm, embedded)
+//
+// the weights are read from a `.npz` of `numpy.savez` (not `savez_compressed`) holding `w0, b0,
+// w1, b1, ...`: `wi` of shape (inputs, outputs) and `bi` of shape (outputs,), float32 or float64,
+// with a relu between the layers; the input is the v1 encoding of the player to move (or that of
+// the encoder of `MlpBot`) and the output one logit per action id, see `hanabi/mlp.py` to write
+// them; `Mlp::quantized` runs in int8
+use crate::agents::{Agent, Observation};
+use crate::encoder::Encoder;
+use crate::probes::Probe;
+use crate::replay::ReplayError;
+use crate::state::{ActionRequest, State, NACTIONS};
+use ndarray::{Array1, Array2, Axis};
+use std::collections::HashMap;
+use std::convert::TryInto;
+use std::fs;
+use std::path::Path;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+#[derive(Debug)]
+pub enum MlpError {
+    Io(std::io::Error),
+    Invalid(String),
+}
+
+impl From<std::io::Error> for MlpError {
+    fn from(err: std::io::Error) -> MlpError {
+        MlpError::Io(err)
+    }
+}
+
+fn invalid(message: &str) -> MlpError {
+    MlpError::Invalid(message.to_string())
+}
+
+enum Layer {
+    Float(Array2<f32>, Array1<f32>),
+    // int8 weights of every output in a row, the scale of each output and the float bias
+    Int8 {
+        inputs: usize,
+        weights: Vec<i8>,
+        scales: Vec<f32>,
+        bias: Array1<f32>,
+    },
+}
+
+impl Layer {
+    fn inputs(&self) -> usize {
+        match self {
+            Layer::Float(w, _) => w.nrows(),
+            Layer::Int8 { inputs, .. } => *inputs,
+        }
+    }
+
+    fn outputs(&self) -> usize {
+        match self {
+            Layer::Float(_, b) => b.len(),
+            Layer::Int8 { bias, .. } => bias.len(),
+        }
+    }
+
+    fn forward(&self, x: &Array1<f32>) -> Array1<f32> {
+        match self {
+            Layer::Float(w, b) => x.dot(w) + b,
+            Layer::Int8 {
+                inputs,
+                weights,
+                scales,
+                bias,
+            } => {
+                // the input is quantized too, on its own scale
+                let (xq, scale) = quantize(x.iter().copied());
+                let mut y = bias.clone();
+                for (j, row) in weights.chunks(*inputs).enumerate() {
+                    let dot: i32 = row
+                        .iter()
+                        .zip(&xq)
+                        .map(|(&w, &x)| w as i32 * x as i32)
+                        .sum();
+                    y[j] += dot as f32 * scale * scales[j];
+                }
+                y
+            }
+        }
+    }
+}
+
+// symmetric int8 quantization, the values are about `q * scale`
+fn quantize<I: Iterator<Item = f32> + Clone>(values: I) -> (Vec<i8>, f32) {
+    let max = values.clone().fold(0.0f32, |m, x| m.max(x.abs()));
+    let scale = if max > 0.0 { max / 127.0 } else { 1.0 };
+    let q = values
+        .map(|x| (x / scale).round().clamp(-127.0, 127.0) as i8)
+        .collect();
+    (q, scale)
+}
+
+pub struct Mlp {
+    layers: Vec<Layer>,
+}
+
+impl Mlp {
+    pub fn new(layers: Vec<(Array2<f32>, Array1<f32>)>) -> Result<Mlp, MlpError> {
+        if layers.is_empty() {
+            return Err(invalid("no layer"));
+        }
+        for (i, (w, b)) in layers.iter().enumerate() {
+            if w.ncols() != b.len() {
+                return Err(MlpError::Invalid(format!(
+                    "layer {}: bias of the wrong size",
+                    i
+                )));
+            }
+            if i > 0 && layers[i - 1].0.ncols() != w.nrows() {
+                return Err(MlpError::Invalid(format!("layer {}: wrong input size", i)));
+            }
+        }
+        Ok(Mlp {
+            layers: layers
+                .into_iter()
+                .map(|(w, b)| Layer::Float(w, b))
+                .collect(),
+        })
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Mlp, MlpError> {
+        Mlp::from_npz(&fs::read(path)?)
+    }
+
+    // the content of a `.npz` file, e.g. fetched by a web page
+    pub fn from_npz(bytes: &[u8]) -> Result<Mlp, MlpError> {
+        let arrays = read_npz(bytes)?;
+        let mut layers = Vec::new();
+        while let Some(w) = arrays.get(&format!("w{}", layers.len())) {
+            let b = arrays
+                .get(&format!("b{}", layers.len()))
+                .ok_or_else(|| invalid("weight without bias"))?;
+            let w = match w.shape[..] {
+                [rows, cols] => Array2::from_shape_vec((rows, cols), w.data.clone()).unwrap(),
+                _ => return Err(invalid("weight of more than two dimensions")),
+            };
+            if b.shape.len() != 1 {
+                return Err(invalid("bias of more than one dimension"));
+            }
+            layers.push((w, Array1::from(b.data.clone())));
+        }
+        Mlp::new(layers)
+    }
+
+    // the same network with int8 weights, one scale per output, and int8 activations: faster
+    // for playouts, at the cost of exactness
+    pub fn quantized(&self) -> Mlp {
+        let layers = self
+            .layers
+            .iter()
+            .map(|layer| match layer {
+                Layer::Float(w, b) => {
+                    let (mut weights, mut scales) = (Vec::new(), Vec::new());
+                    for column in w.axis_iter(Axis(1)) {
+                        let (q, scale) = quantize(column.iter().copied());
+                        weights.extend(q);
+                        scales.push(scale);
+                    }
+                    Layer::Int8 {
+                        inputs: w.nrows(),
+                        weights,
+                        scales,
+                        bias: b.clone(),
+                    }
+                }
+                Layer::Int8 {
+                    inputs,
+                    weights,
+                    scales,
+                    bias,
+                } => Layer::Int8 {
+                    inputs: *inputs,
+                    weights: weights.clone(),
+                    scales: scales.clone(),
+                    bias: bias.clone(),
+                },
+            })
+            .collect();
+        Mlp { layers }
+    }
+
+    pub fn input_size(&self) -> usize {
+        self.layers[0].inputs()
+    }
+
+    pub fn output_size(&self) -> usize {
+        self.layers.last().unwrap().outputs()
+    }
+
+    pub fn forward(&self, input: &Array1<f32>) -> Array1<f32> {
+        let mut x = input.clone();
+        for (i, layer) in self.layers.iter().enumerate() {
+            x = layer.forward(&x);
+            if i + 1 < self.layers.len() {
+                x.mapv_inplace(|x| x.max(0.0));
+            }
+        }
+        x
+    }
+
+    // the legal action of largest logit
+    pub fn act(&self, state: &State) -> ActionRequest {
+        let player = state.turn() % state.players().len();
+        self.choose(&state.encode_for(player), &state.legal_mask())
+    }
+
+    // the action of largest logit among those of `mask`, given the encoding of the player to move
+    pub fn choose(&self, input: &Array1<f32>, mask: &Array1<f32>) -> ActionRequest {
+        assert_eq!(
+            input.len(),
+            self.input_size(),
+            "the network does not take the encoding of this game"
+        );
+        let logits = self.forward(input);
+        (0..NACTIONS.min(logits.len()))
+            .filter(|&id| mask[id] > 0.0)
+            .max_by(|&i, &j| logits[i].partial_cmp(&logits[j]).unwrap())
+            .map_or(ActionRequest::from_id(0), ActionRequest::from_id)
+    }
+}
+
+// the networks of the agents "mlp:<path>" and "mlp8:<path>" (quantized), read once per process
+pub fn cached(path: &str, quantized: bool) -> Result<Arc<Mlp>, MlpError> {
+    static CACHE: OnceLock<Mutex<HashMap<String, Arc<Mlp>>>> = OnceLock::new();
+    let mut cache = CACHE
+        .get_or_init(|| Mutex::new(HashMap::new()))
+        .lock()
+        .unwrap();
+    // the name of the agent
+    let key = format!("{}:{}", if quantized { "mlp8" } else { "mlp" }, path);
+    if let Some(mlp) = cache.get(&key) {
+        return Ok(mlp.clone());
+    }
+    let mut mlp = Mlp::load(path)?;
+    if quantized {
+        mlp = mlp.quantized();
+    }
+    let mlp = Arc::new(mlp);
+    cache.insert(key, mlp.clone());
+    Ok(mlp)
+}
+
+// plays the legal action of largest logit, given the observation of `encoder`
+pub struct MlpBot {
+    pub mlp: Arc<Mlp>,
+    pub encoder: Encoder,
+}
+
+impl Agent for MlpBot {
+    fn act(&mut self, obs: &Observation) -> ActionRequest {
+        self.mlp
+            .choose(&obs.encode_with(self.encoder), &obs.legal_mask())
+    }
+}
+
+// the quantized network against the float one on the probes it takes (of its player count)
+#[derive(Clone, Debug, Default)]
+pub struct QuantizationReport {
+    pub probes: usize,
+    pub float_passed: usize,
+    pub int8_passed: usize,
+    // probes where both choose the same action
+    pub agreement: usize,
+    pub max_logit_error: f32,
+    // seconds per forward pass
+    pub float_seconds: f64,
+    pub int8_seconds: f64,
+}
+
+// every forward pass is timed `repeats` times
+pub fn quantization_report(
+    mlp: &Mlp,
+    probes: &[Probe],
+    repeats: usize,
+) -> Result<QuantizationReport, ReplayError> {
+    let int8 = mlp.quantized();
+    let mut report = QuantizationReport::default();
+    let (mut float_time, mut int8_time) = (Duration::default(), Duration::default());
+    for probe in probes {
+        let state = probe.state()?;
+        let input = state.encode_for(state.turn() % state.players().len());
+        if input.len() != mlp.input_size() {
+            continue;
+        }
+        report.probes += 1;
+        let (a, b) = (mlp.act(&state), int8.act(&state));
+        report.float_passed += probe.good.contains(&a) as usize;
+        report.int8_passed += probe.good.contains(&b) as usize;
+        report.agreement += (a == b) as usize;
+        let start = Instant::now();
+        for _ in 0..repeats {
+            mlp.forward(&input);
+        }
+        float_time += start.elapsed();
+        let start = Instant::now();
+        for _ in 0..repeats {
+            int8.forward(&input);
+        }
+        int8_time += start.elapsed();
+        let error =
+            (mlp.forward(&input) - int8.forward(&input)).fold(0.0f32, |m, x| m.max(x.abs()));
+        report.max_logit_error = report.max_logit_error.max(error);
+    }
+    let n = (report.probes * repeats).max(1) as f64;
+    report.float_seconds = float_time.as_secs_f64() / n;
+    report.int8_seconds = int8_time.as_secs_f64() / n;
+    Ok(report)
+}
+
+struct Npy {
+    shape: Vec<usize>,
+    data: Vec<f32>,
+}
+
+fn u16_at(bytes: &[u8], i: usize) -> Result<usize, MlpError> {
+    let x = bytes.get(i..i + 2).ok_or_else(|| invalid("truncated"))?;
+    Ok(u16::from_le_bytes(x.try_into().unwrap()) as usize)
+}
+
+fn u32_at(bytes: &[u8], i: usize) -> Result<usize, MlpError> {
+    let x = bytes.get(i..i + 4).ok_or_else(|| invalid("truncated"))?;
+    Ok(u32::from_le_bytes(x.try_into().unwrap()) as usize)
+}
+
+fn u64_at(bytes: &[u8], i: usize) -> Result<usize, MlpError> {
+    let x = bytes.get(i..i + 8).ok_or_else(|| invalid("truncated"))?;
+    Ok(u64::from_le_bytes(x.try_into().unwrap()) as usize)
+}
+
+// the arrays of a zip archive of uncompressed `.npy` files, by name without the extension
+fn read_npz(bytes: &[u8]) -> Result<HashMap<String, Npy>, MlpError> {
+    // end of central directory, followed by a comment of at most 65535 bytes
+    let end = (0..bytes.len().saturating_sub(21))
+        .rev()
+        .find(|&i| bytes[i..].starts_with(&[0x50, 0x4b, 0x05, 0x06]))
+        .ok_or_else(|| invalid("not a zip archive"))?;
+    let mut entries = u16_at(bytes, end + 10)?;
+    let mut offset = u32_at(bytes, end + 16)?;
+    if entries == 0xffff || offset == 0xffff_ffff {
+        // zip64 end of central directory, found through its locator
+        let record = u64_at(
+            bytes,
+            end.checked_sub(20).ok_or_else(|| invalid("truncated"))? + 8,
+        )?;
+        entries = u64_at(bytes, record + 32)?;
+        offset = u64_at(bytes, record + 48)?;
+    }
+    let mut arrays = HashMap::new();
+    for _ in 0..entries {
+        if bytes.get(offset..offset + 4) != Some(&[0x50, 0x4b, 0x01, 0x02]) {
+            return Err(invalid("corrupted central directory"));
+        }
+        if u16_at(bytes, offset + 10)? != 0 {
+            return Err(invalid("compressed archive, save it with numpy.savez"));
+        }
+        let mut size = u32_at(bytes, offset + 24)?;
+        let name_len = u16_at(bytes, offset + 28)?;
+        let extra_len = u16_at(bytes, offset + 30)?;
+        let comment_len = u16_at(bytes, offset + 32)?;
+        let mut local = u32_at(bytes, offset + 42)?;
+        let name = bytes
+            .get(offset + 46..offset + 46 + name_len)
+            .ok_or_else(|| invalid("truncated"))?;
+        let name = String::from_utf8_lossy(name).to_string();
+        // zip64 sizes and offset, in this order and only those that do not fit
+        let mut extra = offset + 46 + name_len;
+        while extra + 4 <= offset + 46 + name_len + extra_len {
+            let (id, len) = (u16_at(bytes, extra)?, u16_at(bytes, extra + 2)?);
+            if id == 1 {
+                let mut field = extra + 4;
+                if size == 0xffff_ffff {
+                    size = u64_at(bytes, field)?;
+                    field += 8;
+                }
+                if u32_at(bytes, offset + 20)? == 0xffff_ffff {
+                    field += 8;
+                }
+                if local == 0xffff_ffff {
+                    local = u64_at(bytes, field)?;
+                }
+            }
+            extra += 4 + len;
+        }
+        let start = local + 30 + u16_at(bytes, local + 26)? + u16_at(bytes, local + 28)?;
+        let data = bytes
+            .get(start..start + size)
+            .ok_or_else(|| invalid("truncated"))?;
+        if let Some(name) = name.strip_suffix(".npy") {
+            arrays.insert(name.to_string(), read_npy(data)?);
+        }
+        offset += 46 + name_len + extra_len + comment_len;
+    }
+    Ok(arrays)
+}
+
+// a little endian float array in c order
+fn read_npy(bytes: &[u8]) -> Result<Npy, MlpError> {
+    if !bytes.starts_with(b"\x93NUMPY") || bytes.len() < 10 {
+        return Err(invalid("not a npy array"));
+    }
+    let (len, start) = match bytes[6] {
+        1 => (u16_at(bytes, 8)?, 10),
+        _ => (u32_at(bytes, 8)?, 12),
+    };
+    let header = bytes
+        .get(start..start + len)
+        .ok_or_else(|| invalid("truncated"))?;
+    let header = String::from_utf8_lossy(header).replace(' ', "");
+    if header.contains("'fortran_order':True") {
+        return Err(invalid("array in fortran order"));
+    }
+    let shape = header
+        .split("'shape':(")
+        .nth(1)
+        .and_then(|x| x.split(')').next())
+        .ok_or_else(|| invalid("npy header without shape"))?;
+    let shape = shape
+        .split(',')
+        .filter(|x| !x.is_empty())
+        .map(|x| x.parse::<usize>())
+        .collect::<Result<Vec<usize>, _>>()
+        .map_err(|_| invalid("npy header with a wrong shape"))?;
+    let n: usize = shape.iter().product();
+    let body = &bytes[start + len..];
+    let data: Vec<f32> = if header.contains("'descr':'<f4'") {
+        body.get(..4 * n)
+            .ok_or_else(|| invalid("truncated"))?
+            .chunks(4)
+            .map(|x| f32::from_le_bytes(x.try_into().unwrap()))
+            .collect()
+    } else if header.contains("'descr':'<f8'") {
+        body.get(..8 * n)
+            .ok_or_else(|| invalid("truncated"))?
+            .chunks(8)
+            .map(|x| f64::from_le_bytes(x.try_into().unwrap()) as f32)
+            .collect()
+    } else {
+        return Err(invalid("array of neither float32 nor float64"));
+    };
+    Ok(Npy { shape, data })
+}
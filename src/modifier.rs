@@ -0,0 +1,358 @@
+#![allow(dead_code)]
+
+use crate::rules::{self, DrawPosition, Rules};
+use crate::state::{Card, IllegalMoves, MAXCARDS};
+use std::fmt;
+
+// hooks to implement extreme variants and detrimental characters without touching `State`
+pub trait RuleModifier: Send + Sync {
+    fn name(&self) -> &str;
+
+    // a copy with the state of the game so far, for the forks of a state
+    fn box_clone(&self) -> Box<dyn RuleModifier>;
+
+    // a copy as at the start of a game, for the new deals of `State::redeal`
+    fn fresh(&self) -> Box<dyn RuleModifier>;
+
+    fn hand_size(&self, _nplayer: usize, size: usize) -> usize {
+        size
+    }
+
+    // the options of the core set by this modifier, `rules` are those of the others
+    fn rules(&self, rules: Rules) -> Rules {
+        rules
+    }
+
+    // may forbid a clue, `touched` are the positions it touches in the hand of `target`
+    fn on_clue(
+        &self,
+        _player: usize,
+        _target: usize,
+        _touched: &[usize],
+    ) -> Result<(), IllegalMoves> {
+        Ok(())
+    }
+
+    // returns the number of clue tokens regained, `clues` is the number regained without this modifier
+    fn on_play(&mut self, _player: usize, _card: Card, _success: bool, clues: usize) -> usize {
+        clues
+    }
+
+    fn on_discard(&mut self, _player: usize, _card: Card, clues: usize) -> usize {
+        clues
+    }
+}
+
+#[derive(Default)]
+pub struct Modifiers(pub Vec<Box<dyn RuleModifier>>);
+
+impl fmt::Debug for Modifiers {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let names: Vec<&str> = self.0.iter().map(|x| x.name()).collect();
+        write!(f, "{:?}", names)
+    }
+}
+
+impl Clone for Modifiers {
+    fn clone(&self) -> Modifiers {
+        Modifiers(self.0.iter().map(|x| x.box_clone()).collect())
+    }
+}
+
+impl Modifiers {
+    pub fn names(&self) -> Vec<String> {
+        self.0.iter().map(|x| x.name().to_string()).collect()
+    }
+
+    // the number of cards dealt to each of `nplayer` players
+    pub fn hand_size(&self, nplayer: usize) -> usize {
+        self.0.iter().fold(rules::hand_size(nplayer), |size, x| {
+            x.hand_size(nplayer, size).clamp(1, MAXCARDS)
+        })
+    }
+}
+
+// half a clue token per discard or play that would give one
+#[derive(Clone, Default)]
+pub struct ClueStarved {
+    half: usize,
+}
+
+impl ClueStarved {
+    fn halve(&mut self, clues: usize) -> usize {
+        self.half += clues;
+        let clues = self.half / 2;
+        self.half %= 2;
+        clues
+    }
+}
+
+impl RuleModifier for ClueStarved {
+    fn name(&self) -> &str {
+        "clue_starved"
+    }
+
+    fn box_clone(&self) -> Box<dyn RuleModifier> {
+        Box::new(self.clone())
+    }
+
+    fn fresh(&self) -> Box<dyn RuleModifier> {
+        Box::new(ClueStarved::default())
+    }
+
+    fn on_play(&mut self, _player: usize, _card: Card, _success: bool, clues: usize) -> usize {
+        self.halve(clues)
+    }
+
+    fn on_discard(&mut self, _player: usize, _card: Card, clues: usize) -> usize {
+        self.halve(clues)
+    }
+}
+
+#[derive(Clone)]
+pub struct OneLessCard;
+
+impl RuleModifier for OneLessCard {
+    fn name(&self) -> &str {
+        "one_less_card"
+    }
+
+    fn box_clone(&self) -> Box<dyn RuleModifier> {
+        Box::new(self.clone())
+    }
+
+    fn fresh(&self) -> Box<dyn RuleModifier> {
+        Box::new(self.clone())
+    }
+
+    fn hand_size(&self, _nplayer: usize, size: usize) -> usize {
+        size - 1
+    }
+}
+
+// every clue must touch exactly one card
+#[derive(Clone)]
+pub struct Conservative;
+
+impl RuleModifier for Conservative {
+    fn name(&self) -> &str {
+        "conservative"
+    }
+
+    fn box_clone(&self) -> Box<dyn RuleModifier> {
+        Box::new(self.clone())
+    }
+
+    fn fresh(&self) -> Box<dyn RuleModifier> {
+        Box::new(self.clone())
+    }
+
+    fn on_clue(
+        &self,
+        _player: usize,
+        _target: usize,
+        touched: &[usize],
+    ) -> Result<(), IllegalMoves> {
+        if touched.len() == 1 {
+            Ok(())
+        } else {
+            Err(IllegalMoves::Forbidden)
+        }
+    }
+}
+
+// every clue must touch at least two cards
+#[derive(Clone)]
+pub struct Greedy;
+
+impl RuleModifier for Greedy {
+    fn name(&self) -> &str {
+        "greedy"
+    }
+
+    fn box_clone(&self) -> Box<dyn RuleModifier> {
+        Box::new(self.clone())
+    }
+
+    fn fresh(&self) -> Box<dyn RuleModifier> {
+        Box::new(self.clone())
+    }
+
+    fn on_clue(
+        &self,
+        _player: usize,
+        _target: usize,
+        touched: &[usize],
+    ) -> Result<(), IllegalMoves> {
+        if touched.len() >= 2 {
+            Ok(())
+        } else {
+            Err(IllegalMoves::Forbidden)
+        }
+    }
+}
+
+// a player may clue their own hand
+#[derive(Clone)]
+pub struct SelfClue;
+
+impl RuleModifier for SelfClue {
+    fn name(&self) -> &str {
+        "self_clue"
+    }
+
+    fn box_clone(&self) -> Box<dyn RuleModifier> {
+        Box::new(self.clone())
+    }
+
+    fn fresh(&self) -> Box<dyn RuleModifier> {
+        Box::new(self.clone())
+    }
+
+    fn rules(&self, rules: Rules) -> Rules {
+        Rules {
+            allow_self_clue: true,
+            ..rules
+        }
+    }
+}
+
+// a clue may touch no card
+#[derive(Clone)]
+pub struct EmptyClue;
+
+impl RuleModifier for EmptyClue {
+    fn name(&self) -> &str {
+        "empty_clue"
+    }
+
+    fn box_clone(&self) -> Box<dyn RuleModifier> {
+        Box::new(self.clone())
+    }
+
+    fn fresh(&self) -> Box<dyn RuleModifier> {
+        Box::new(self.clone())
+    }
+
+    fn rules(&self, rules: Rules) -> Rules {
+        Rules {
+            allow_empty_clue: true,
+            ..rules
+        }
+    }
+}
+
+// a game lost at the third strike scores 0
+#[derive(Clone)]
+pub struct StrikeoutZero;
+
+impl RuleModifier for StrikeoutZero {
+    fn name(&self) -> &str {
+        "strikeout_zero"
+    }
+
+    fn box_clone(&self) -> Box<dyn RuleModifier> {
+        Box::new(self.clone())
+    }
+
+    fn fresh(&self) -> Box<dyn RuleModifier> {
+        Box::new(self.clone())
+    }
+
+    fn rules(&self, rules: Rules) -> Rules {
+        Rules {
+            strikeout_score_zero: true,
+            ..rules
+        }
+    }
+}
+
+// playing the 5 of a suit gives no clue token back
+#[derive(Clone)]
+pub struct NoSuitBonus;
+
+impl RuleModifier for NoSuitBonus {
+    fn name(&self) -> &str {
+        "no_suit_bonus"
+    }
+
+    fn box_clone(&self) -> Box<dyn RuleModifier> {
+        Box::new(self.clone())
+    }
+
+    fn fresh(&self) -> Box<dyn RuleModifier> {
+        Box::new(self.clone())
+    }
+
+    fn rules(&self, rules: Rules) -> Rules {
+        Rules {
+            no_suit_bonus: true,
+            ..rules
+        }
+    }
+}
+
+// a drawn card goes after the last card, the hands are oldest card first
+#[derive(Clone)]
+pub struct DrawLast;
+
+impl RuleModifier for DrawLast {
+    fn name(&self) -> &str {
+        "draw_last"
+    }
+
+    fn box_clone(&self) -> Box<dyn RuleModifier> {
+        Box::new(self.clone())
+    }
+
+    fn fresh(&self) -> Box<dyn RuleModifier> {
+        Box::new(self.clone())
+    }
+
+    fn rules(&self, rules: Rules) -> Rules {
+        Rules {
+            draw_position: DrawPosition::Last,
+            ..rules
+        }
+    }
+}
+
+pub fn names() -> Vec<&'static str> {
+    vec![
+        "clue_starved",
+        "one_less_card",
+        "conservative",
+        "greedy",
+        "self_clue",
+        "empty_clue",
+        "strikeout_zero",
+        "no_suit_bonus",
+        "draw_last",
+    ]
+}
+
+// the modifiers of `names` in order, fails on the first unknown name
+pub fn resolve<S: AsRef<str>>(names: &[S]) -> Result<Vec<Box<dyn RuleModifier>>, String> {
+    names
+        .iter()
+        .map(|name| {
+            let name = name.as_ref();
+            from_name(name).ok_or_else(|| format!("unknown rule modifier {:?}", name))
+        })
+        .collect()
+}
+
+pub fn from_name(name: &str) -> Option<Box<dyn RuleModifier>> {
+    match name {
+        "clue_starved" => Some(Box::new(ClueStarved::default())),
+        "one_less_card" => Some(Box::new(OneLessCard)),
+        "conservative" => Some(Box::new(Conservative)),
+        "greedy" => Some(Box::new(Greedy)),
+        "self_clue" => Some(Box::new(SelfClue)),
+        "empty_clue" => Some(Box::new(EmptyClue)),
+        "strikeout_zero" => Some(Box::new(StrikeoutZero)),
+        "no_suit_bonus" => Some(Box::new(NoSuitBonus)),
+        "draw_last" => Some(Box::new(DrawLast)),
+        _ => None,
+    }
+}
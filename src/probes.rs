@@ -0,0 +1,49 @@
+use crate::replay::{Replay, ReplayError};
+use crate::state::{ActionRequest, State};
+use serde::{Deserialize, Serialize};
+
+// a position and the actions a reasonable player takes there
+#[derive(Serialize, Deserialize)]
+pub struct Probe {
+    pub name: String,
+    // "play", "save" or "endgame"
+    pub category: String,
+    pub replay: Replay,
+    pub good: Vec<ActionRequest>,
+}
+
+impl Probe {
+    pub fn state(&self) -> Result<State, ReplayError> {
+        self.replay.play(|_, _| {})
+    }
+}
+
+// the suite shipped with the crate, the good endgame actions are the best of `endgame::evaluate`
+pub fn suite() -> Vec<Probe> {
+    serde_json::from_str(include_str!("probes.json")).unwrap()
+}
+
+pub struct ProbeResult {
+    pub name: String,
+    pub category: String,
+    pub action: Option<ActionRequest>,
+    pub passed: bool,
+}
+
+// asks `policy` for its action on every probe, None counts as a failure
+pub fn run<F>(probes: &[Probe], mut policy: F) -> Result<Vec<ProbeResult>, ReplayError>
+where
+    F: FnMut(&State) -> Option<ActionRequest>,
+{
+    let mut results = Vec::new();
+    for probe in probes {
+        let action = policy(&probe.state()?);
+        results.push(ProbeResult {
+            name: probe.name.clone(),
+            category: probe.category.clone(),
+            action,
+            passed: matches!(action, Some(action) if probe.good.contains(&action)),
+        });
+    }
+    Ok(results)
+}
@@ -0,0 +1,188 @@
+use crate::build_info::build_info;
+use crate::state::{ActionRequest, IllegalMoves, State};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+// bumped on every incompatible change of the messages exchanged over the network
+pub const PROTOCOL_VERSION: u32 = 1;
+
+// first message sent by both ends of a connection
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Hello {
+    pub protocol_version: u32,
+    pub engine_version: String,
+    pub variants: Vec<String>,
+    pub encoders: Vec<String>,
+}
+
+// what both ends support, the game must stay within it
+#[derive(Debug, PartialEq)]
+pub struct Agreement {
+    pub variants: Vec<String>,
+    pub encoders: Vec<String>,
+}
+
+#[derive(Debug)]
+pub enum HandshakeError {
+    Json(serde_json::Error),
+    Version { ours: u32, theirs: u32 },
+    NoCommonVariant,
+    NoCommonEncoder,
+}
+
+impl Hello {
+    // the capabilities of this build
+    pub fn local() -> Hello {
+        let info = build_info();
+        Hello {
+            protocol_version: PROTOCOL_VERSION,
+            engine_version: info.version.to_string(),
+            variants: info.variants.iter().map(|x| x.to_string()).collect(),
+            encoders: info.encoders.iter().map(|x| x.to_string()).collect(),
+        }
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).unwrap()
+    }
+
+    pub fn from_json(json: &str) -> Result<Hello, HandshakeError> {
+        serde_json::from_str(json).map_err(HandshakeError::Json)
+    }
+
+    // fails on a different protocol version or when nothing can be played together
+    pub fn negotiate(&self, other: &Hello) -> Result<Agreement, HandshakeError> {
+        if self.protocol_version != other.protocol_version {
+            return Err(HandshakeError::Version {
+                ours: self.protocol_version,
+                theirs: other.protocol_version,
+            });
+        }
+        let common = |a: &[String], b: &[String]| -> Vec<String> {
+            a.iter().filter(|x| b.contains(x)).cloned().collect()
+        };
+        let variants = common(&self.variants, &other.variants);
+        if variants.is_empty() {
+            return Err(HandshakeError::NoCommonVariant);
+        }
+        let encoders = common(&self.encoders, &other.encoders);
+        if encoders.is_empty() {
+            return Err(HandshakeError::NoCommonEncoder);
+        }
+        Ok(Agreement { variants, encoders })
+    }
+}
+
+impl fmt::Display for HandshakeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HandshakeError::Json(err) => write!(f, "invalid hello message: {}", err),
+            HandshakeError::Version { ours, theirs } => write!(
+                f,
+                "protocol version {} on this side and {} on the other",
+                ours, theirs
+            ),
+            HandshakeError::NoCommonVariant => write!(f, "no variant supported by both sides"),
+            HandshakeError::NoCommonEncoder => write!(f, "no encoder supported by both sides"),
+        }
+    }
+}
+
+// messages of a game once the handshake is done, `seq` numbers the actions of the game from 0
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Message {
+    // client: the action of its seat, `seq` is the number of actions it has seen
+    Submit { seq: u64, action: ActionRequest },
+    // server: the action number `seq`
+    Update { seq: u64, action: ActionRequest },
+    // client: every update up to `seq` was received
+    Ack { seq: u64 },
+    // client, after a reconnection: resend the updates it did not acknowledge
+    Resume,
+}
+
+#[derive(Debug)]
+pub enum SubmitError {
+    // submitted for a position the game already left
+    Late { seq: u64, current: u64 },
+    // the client claims updates it cannot have received
+    Ahead { seq: u64, current: u64 },
+    NotYourTurn,
+    Illegal(IllegalMoves),
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Submitted {
+    Accepted(u64),
+    // the same action resent by the seat that played it, e.g. after a reconnection
+    Duplicate(u64),
+}
+
+// server side bookkeeping of one hosted game
+pub struct Session {
+    state: State,
+    log: Vec<ActionRequest>,
+    // per seat, the number of updates acknowledged
+    acked: Vec<u64>,
+}
+
+impl Session {
+    pub fn new(state: State) -> Session {
+        let players = state.players().len();
+        Session {
+            state,
+            log: Vec::new(),
+            acked: vec![0; players],
+        }
+    }
+
+    pub fn state(&self) -> &State {
+        &self.state
+    }
+
+    pub fn submit(
+        &mut self,
+        seat: usize,
+        seq: u64,
+        action: ActionRequest,
+    ) -> Result<Submitted, SubmitError> {
+        let current = self.log.len() as u64;
+        let players = self.state.players().len();
+        if seq < current {
+            let i = seq as usize;
+            return if self.log[i] == action && i % players == seat {
+                Ok(Submitted::Duplicate(seq))
+            } else {
+                Err(SubmitError::Late { seq, current })
+            };
+        }
+        if seq > current {
+            return Err(SubmitError::Ahead { seq, current });
+        }
+        if self.state.turn() % players != seat {
+            return Err(SubmitError::NotYourTurn);
+        }
+        self.state.apply(&action).map_err(SubmitError::Illegal)?;
+        self.log.push(action);
+        Ok(Submitted::Accepted(seq))
+    }
+
+    pub fn ack(&mut self, seat: usize, seq: u64) {
+        let seq = (seq + 1).min(self.log.len() as u64);
+        self.acked[seat] = self.acked[seat].max(seq);
+    }
+
+    // the updates `seat` did not acknowledge, to resend on `Resume`
+    pub fn missed(&self, seat: usize) -> Vec<Message> {
+        self.log
+            .iter()
+            .enumerate()
+            .skip(self.acked[seat] as usize)
+            .map(|(seq, &action)| Message::Update {
+                seq: seq as u64,
+                action,
+            })
+            .collect()
+    }
+}
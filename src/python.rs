@@ -0,0 +1,2375 @@
+// the python module, built with the `python` feature
+// the code generated by the pyo3 macros for `#[pyclass]` and `#[new]` trips these lints
+#![allow(clippy::manual_div_ceil, clippy::new_ret_no_self)]
+mod aec;
+mod batch;
+mod observation;
+mod view;
+
+use crate::agents::{Agent, CheatBot, ConventionBot, Observation};
+use crate::bindings::{self, ClueInfo};
+use crate::clock::{self, Clock, OnTimeout, SeatTime, TimeControl};
+use crate::encoder::{Encoder, EncoderBuilder, Feature};
+use crate::experiment::Experiment;
+use crate::intrinsic::{CountBonus, Counts, IntrinsicReward};
+use crate::manifest::Manifest;
+use crate::recorder::Recorder;
+use crate::replay::{Replay, ReplayError};
+use crate::slots::SlotOrder;
+use crate::state::{
+    ActionRequest, Card, IllegalMoves, State, TieBreak, MAXCARDS, MAXPLAYERS, NACTIONS,
+};
+#[cfg(feature = "storage")]
+use crate::storage;
+use crate::variant::Variant;
+use crate::{
+    agents, bench, build_info, daily, dataset, deals, driver, efficiency, endgame, endgames, eval,
+    external, fingerprint, imitation, layout, mlp, modifier, probes, protocol, replay, search,
+    server, stats, verify,
+};
+use ndarray::{Array1, Array2, ArrayView1, Axis};
+use numpy::{IntoPyArray, PyArray1, PyArray2, PyArray3, PyArray4};
+use pyo3::class::basic::{CompareOp, PyObjectProtocol};
+use pyo3::class::iter::PyIterProtocol;
+use pyo3::exceptions;
+use pyo3::prelude::{
+    pyclass, pyfunction, pymethods, pymodule, pyproto, Py, PyErr, PyModule, PyObject, PyRawObject,
+    PyRefMut, PyResult, Python, ToPyObject,
+};
+use pyo3::types::{PyAny, PyBytes, PyDict};
+use pyo3::wrap_pyfunction;
+use pyo3::{create_exception, ObjectProtocol, PyNativeType};
+use rayon::prelude::*;
+use std::cell::RefCell;
+use std::convert::TryFrom;
+use std::rc::Rc;
+use std::time::Duration;
+
+// raised for an illegal action, a ValueError with the reason as message, the variant of
+// `IllegalMoves` as `kind` (e.g. "PositionOutOfRange") and its fields as attributes (`position`,
+// `hand_size`, `target`, `id`, ...)
+create_exception!(hanabi, IllegalMove, exceptions::ValueError);
+
+#[pymodule]
+fn hanabi(py: Python, m: &PyModule) -> PyResult<()> {
+    m.add("IllegalMove", py.get_type::<IllegalMove>())?;
+    m.add_class::<Game>()?;
+    m.add_class::<ReplayIter>()?;
+    m.add_class::<VisitCounts>()?;
+    m.add_class::<aec::HanabiAEC>()?;
+    m.add_class::<batch::BatchGame>()?;
+    m.add_class::<GameDriver>()?;
+    m.add_class::<ExternalBot>()?;
+    m.add_class::<PyPolicyAgent>()?;
+    m.add_class::<ProbeSet>()?;
+    m.add_class::<observation::Observation>()?;
+    m.add_class::<view::Board>()?;
+    m.add_class::<view::PlayerView>()?;
+    m.add_class::<view::SpectatorView>()?;
+    m.add_class::<view::OmniscientView>()?;
+    #[cfg(feature = "storage")]
+    m.add_class::<GameDatabase>()?;
+    m.add_wrapped(wrap_pyfunction!(build_info))?;
+    m.add_wrapped(wrap_pyfunction!(dataset))?;
+    m.add_wrapped(wrap_pyfunction!(protocol_hello))?;
+    m.add_wrapped(wrap_pyfunction!(negotiate))?;
+    m.add_wrapped(wrap_pyfunction!(run_experiment))?;
+    m.add_wrapped(wrap_pyfunction!(daily_seed))?;
+    m.add_wrapped(wrap_pyfunction!(verify))?;
+    m.add_wrapped(wrap_pyfunction!(anonymize))?;
+    m.add_wrapped(wrap_pyfunction!(serve))?;
+    m.add_wrapped(wrap_pyfunction!(evaluate_policy))?;
+    m.add_wrapped(wrap_pyfunction!(evaluate_lineups))?;
+    m.add_wrapped(wrap_pyfunction!(score_histogram))?;
+    m.add_wrapped(wrap_pyfunction!(diff_observations))?;
+    m.add_wrapped(wrap_pyfunction!(convert_slot))?;
+    m.add_wrapped(wrap_pyfunction!(card_code))?;
+    m.add_wrapped(wrap_pyfunction!(card_from_code))?;
+    m.add_wrapped(wrap_pyfunction!(endgame))?;
+    m.add_wrapped(wrap_pyfunction!(endgame_puzzles))?;
+    m.add_wrapped(wrap_pyfunction!(clue_efficiency))?;
+    m.add_wrapped(wrap_pyfunction!(dataset_stats))?;
+    m.add_wrapped(wrap_pyfunction!(imitation_accuracy))?;
+    m.add_wrapped(wrap_pyfunction!(fingerprint_distance))?;
+    m.add_wrapped(wrap_pyfunction!(probe_suite))?;
+    m.add_wrapped(wrap_pyfunction!(mlp_quantization_report))?;
+    Ok(())
+}
+
+fn replay_err(err: ReplayError) -> PyErr {
+    match err {
+        ReplayError::Illegal { turn, error } => {
+            illegal_move(format!("turn {}: {}", turn, error), &error)
+        }
+        err => PyErr::new::<exceptions::ValueError, _>(format!("{:?}", err)),
+    }
+}
+
+pub(crate) fn illegal_err(err: IllegalMoves) -> PyErr {
+    illegal_move(err.to_string(), &err)
+}
+
+pub(crate) fn illegal_move(message: String, err: &IllegalMoves) -> PyErr {
+    let gil = Python::acquire_gil();
+    let py = gil.python();
+    let exception = || -> PyResult<PyErr> {
+        let exception = py.get_type::<IllegalMove>().call1((message,))?;
+        exception.setattr("kind", err.kind())?;
+        for (name, value) in err.fields() {
+            exception.setattr(name, value)?;
+        }
+        Ok(PyErr::from_instance(exception))
+    };
+    exception().unwrap_or_else(|err| err)
+}
+
+// the clue of a value 1 to 5 or a color letter, None for anything else
+fn clue_request(py: Python, target: usize, info: &PyObject) -> Option<ActionRequest> {
+    if let Ok(value) = info.extract::<usize>(py) {
+        bindings::clue_request(target, ClueInfo::Value(value))
+    } else if let Ok(color) = info.extract::<&str>(py) {
+        bindings::clue_request(target, ClueInfo::Color(color))
+    } else {
+        bindings::clue_request(target, ClueInfo::Other)
+    }
+}
+
+fn encoder_from_name(name: &str) -> PyResult<Encoder> {
+    Encoder::from_name(name).ok_or_else(|| {
+        PyErr::new::<exceptions::ValueError, _>(format!("unknown encoder {:?}", name))
+    })
+}
+
+// crate version, git commit, enabled features and the available encoders, variants, rule
+// modifiers and agents
+#[pyfunction]
+fn build_info(py: Python) -> PyResult<PyObject> {
+    let info = build_info::build_info();
+    let dict = PyDict::new(py);
+    dict.set_item("version", info.version)?;
+    dict.set_item("git_hash", info.git_hash)?;
+    dict.set_item("features", info.features)?;
+    dict.set_item("encoders", info.encoders)?;
+    dict.set_item("variants", info.variants)?;
+    dict.set_item("modifiers", info.modifiers)?;
+    dict.set_item("agents", info.agents)?;
+    Ok(dict.to_object(py))
+}
+
+// the json hello message that opens a connection, with the protocol version and the capabilities
+// of this build
+#[pyfunction]
+fn protocol_hello() -> String {
+    protocol::Hello::local().to_json()
+}
+
+// checks the hello message of the other side, returns the variants and encoders both support
+#[pyfunction]
+fn negotiate(hello: &str) -> PyResult<(Vec<String>, Vec<String>)> {
+    let err =
+        |err: protocol::HandshakeError| PyErr::new::<exceptions::ValueError, _>(format!("{}", err));
+    let other = protocol::Hello::from_json(hello).map_err(err)?;
+    let agreement = protocol::Hello::local().negotiate(&other).map_err(err)?;
+    Ok((agreement.variants, agreement.encoders))
+}
+
+#[pyfunction(encoder = "\"v1\"")]
+fn dataset(py: Python, paths: Vec<String>, gamma: f32, encoder: &str) -> PyResult<PyObject> {
+    let encoder = encoder_from_name(encoder)?;
+    let data = py
+        .allow_threads(|| dataset::from_files(&paths, gamma, encoder))
+        .map_err(replay_err)?;
+    Ok(dataset_dict(py, data)?.to_object(py))
+}
+
+fn dataset_dict(py: Python<'_>, data: dataset::Dataset) -> PyResult<&PyDict> {
+    let dict = PyDict::new(py);
+    dict.set_item("observations", data.observations.into_pyarray(py))?;
+    dict.set_item("masks", data.masks.into_pyarray(py))?;
+    dict.set_item("actions", data.actions.into_pyarray(py))?;
+    dict.set_item("returns", data.returns.into_pyarray(py))?;
+    dict.set_item("episodes", data.episodes.into_pyarray(py))?;
+    Ok(dict)
+}
+
+// clue and discard quality aggregated over the replays
+#[pyfunction]
+fn clue_efficiency(py: Python, paths: Vec<String>) -> PyResult<PyObject> {
+    let stats = py
+        .allow_threads(|| efficiency::from_files(&paths))
+        .map_err(replay_err)?;
+    let dict = PyDict::new(py);
+    dict.set_item("games", stats.games)?;
+    dict.set_item("mean_score", stats.mean_score())?;
+    dict.set_item("clues", stats.clues)?;
+    dict.set_item("clue_efficiency", stats.clue_efficiency())?;
+    dict.set_item("bad_touch_rate", stats.bad_touch_rate())?;
+    dict.set_item("critical_discard_rate", stats.critical_discard_rate())?;
+    Ok(dict.to_object(py))
+}
+
+// actions, clues, scores and seats of a dataset of replays, with its anomalies (invalid,
+// truncated or duplicated games) instead of failing on them
+#[pyfunction]
+fn dataset_stats(py: Python, paths: Vec<String>) -> PyResult<PyObject> {
+    let report = py.allow_threads(|| stats::Report::from_files(&paths));
+    let (color_clues, value_clues) = report.clues();
+    let dict = PyDict::new(py);
+    dict.set_item("games", report.games)?;
+    dict.set_item("turns", report.turns)?;
+    dict.set_item("mean_score", report.mean_score())?;
+    dict.set_item("perfect", report.perfect)?;
+    dict.set_item("scores", report.scores.clone())?;
+    dict.set_item("color_clues", color_clues)?;
+    dict.set_item("value_clues", value_clues)?;
+    dict.set_item("value_clue_ratio", report.value_clue_ratio())?;
+    let mut seats = Vec::new();
+    for seat in &report.seats {
+        let x = PyDict::new(py);
+        x.set_item("plays", seat.plays)?;
+        x.set_item("misplays", seat.misplays)?;
+        x.set_item("discards", seat.discards)?;
+        x.set_item("color_clues", seat.color_clues)?;
+        x.set_item("value_clues", seat.value_clues)?;
+        seats.push(x.to_object(py));
+    }
+    dict.set_item("seats", seats)?;
+    let mut anomalies = Vec::new();
+    for anomaly in &report.anomalies {
+        let x = PyDict::new(py);
+        match anomaly {
+            stats::Anomaly::Invalid { path, error } => {
+                x.set_item("type", "invalid")?;
+                x.set_item("path", path)?;
+                x.set_item("error", error)?;
+            }
+            stats::Anomaly::Truncated { path, turns } => {
+                x.set_item("type", "truncated")?;
+                x.set_item("path", path)?;
+                x.set_item("turns", turns)?;
+            }
+            stats::Anomaly::Duplicate { path, of } => {
+                x.set_item("type", "duplicate")?;
+                x.set_item("path", path)?;
+                x.set_item("of", of)?;
+            }
+        }
+        anomalies.push(x.to_object(py));
+    }
+    dict.set_item("anomalies", anomalies)?;
+    Ok(dict.to_object(py))
+}
+
+// returns the (seed, score) of every game
+#[pyfunction]
+fn run_experiment(py: Python, path: &str) -> PyResult<Vec<(u64, usize)>> {
+    let results = py
+        .allow_threads(|| Experiment::load(path).and_then(|experiment| experiment.run()))
+        .map_err(|err| PyErr::new::<exceptions::ValueError, _>(format!("{:?}", err)))?;
+    Ok(results.iter().map(|x| (x.seed, x.score)).collect())
+}
+
+// the seed of the deal of a date ("YYYY-MM-DD", today in utc by default), the same for everyone
+#[pyfunction]
+fn daily_seed(date: Option<&str>) -> PyResult<u64> {
+    let date = date.map_or_else(daily::today, str::to_string);
+    daily::seed(&date).map_err(PyErr::new::<exceptions::ValueError, _>)
+}
+
+// replays the actions of a claimed score (json, see `src/verify.rs`), returns the (score, turns)
+// of the game or raises a ValueError telling why the claim does not hold
+#[pyfunction]
+fn verify(json: &str) -> PyResult<(usize, usize)> {
+    let state = verify::Claim::from_json(json)
+        .and_then(|claim| claim.verify())
+        .map_err(|err| PyErr::new::<exceptions::ValueError, _>(format!("{:?}", err)))?;
+    Ok((state.score(), *state.turn()))
+}
+
+// the replay (json) without the names of its players ("seats" replaces them by "seat 0", ...,
+// "remove" drops them, "keep") and without their notes unless `keep_notes`
+#[pyfunction(names = "\"seats\"", keep_notes = "false")]
+fn anonymize(json: &str, names: &str, keep_notes: bool) -> PyResult<String> {
+    let names = replay::Names::from_name(names).ok_or_else(|| {
+        PyErr::new::<exceptions::ValueError, _>(format!("unknown names {:?}", names))
+    })?;
+    let replay = Replay::from_json(json).map_err(replay_err)?;
+    let anonymization = replay::Anonymization { names, keep_notes };
+    Ok(replay.anonymized(&anonymization).to_json())
+}
+
+// runs the evaluation server until the process is stopped, see `hanabi/batch.py` for the client
+#[pyfunction(workers = "1", root = "\"jobs\"")]
+fn serve(py: Python, address: &str, workers: usize, root: &str) -> PyResult<()> {
+    py.allow_threads(|| server::serve(address, workers, root))
+        .map_err(|err| PyErr::new::<exceptions::IOError, _>(format!("{}", err)))
+}
+
+// `policy(observation, legal_mask)` returns an action id or one logit per action id,
+// logits of illegal actions are ignored
+enum PolicyOutput {
+    Action(usize),
+    Logits(Vec<f32>),
+}
+
+fn call_policy(
+    py: Python,
+    policy: &PyObject,
+    observation: Array1<f32>,
+    mask: &Array1<f32>,
+) -> PyResult<PolicyOutput> {
+    let out = policy.call1(
+        py,
+        (observation.into_pyarray(py), mask.clone().into_pyarray(py)),
+    )?;
+    if let Ok(id) = out.extract::<usize>(py) {
+        return Ok(PolicyOutput::Action(id));
+    }
+    match out.extract::<Vec<f32>>(py) {
+        Ok(logits) if logits.len() == NACTIONS => Ok(PolicyOutput::Logits(logits)),
+        _ => Err(PyErr::new::<exceptions::TypeError, _>(
+            "the policy must return an action id or one logit per action",
+        )),
+    }
+}
+
+fn policy_action(
+    py: Python,
+    policy: &PyObject,
+    observation: Array1<f32>,
+    mask: Array1<f32>,
+) -> PyResult<Option<ActionRequest>> {
+    Ok(match call_policy(py, policy, observation, &mask)? {
+        PolicyOutput::Action(id) if id < NACTIONS => Some(ActionRequest::from_id(id)),
+        PolicyOutput::Action(_) => None,
+        PolicyOutput::Logits(logits) => {
+            eval::best_legal(ArrayView1::from(&logits[..]), mask.view()).map(ActionRequest::from_id)
+        }
+    })
+}
+
+// `policy(observations, masks)` of a `PyPolicyAgent`, one row per game, returns float32 logits of
+// shape (games, actions)
+fn call_batched(
+    py: Python,
+    policy: &PyObject,
+    observations: Array2<f32>,
+    masks: Array2<f32>,
+) -> PyResult<Array2<f32>> {
+    let rows = observations.nrows();
+    let out = policy.call1(py, (observations.into_pyarray(py), masks.into_pyarray(py)))?;
+    match out.extract::<&PyArray2<f32>>(py) {
+        Ok(logits) if logits.shape() == [rows, NACTIONS] => Ok(logits.as_array().to_owned()),
+        _ => Err(PyErr::new::<exceptions::TypeError, _>(format!(
+            "the policy must return float32 logits of shape ({}, {})",
+            rows, NACTIONS
+        ))),
+    }
+}
+
+// a python policy called on many games at once, e.g. a network on a gpu: `policy(observations,
+// masks)` gets the observations and legal masks of a batch, one row per game, and returns the
+// logits of every row; in a `GameDriver` seat it is called with a batch of one
+#[pyclass]
+struct PyPolicyAgent {
+    policy: PyObject,
+}
+
+#[pymethods]
+impl PyPolicyAgent {
+    #[new]
+    fn new(obj: &PyRawObject, policy: PyObject) {
+        obj.init(PyPolicyAgent { policy });
+    }
+
+    // plays `games` games with the policy in every seat, in lockstep: one call per turn for all
+    // the games still running; returns the same as `evaluate_policy`
+    #[args(players = "2", seed = "0", variant = "\"standard\"")]
+    fn evaluate(
+        &self,
+        py: Python,
+        games: usize,
+        players: usize,
+        seed: u64,
+        variant: &str,
+    ) -> PyResult<PyObject> {
+        let variant = Variant::from_name(variant).ok_or_else(|| {
+            PyErr::new::<exceptions::ValueError, _>(format!("unknown variant {:?}", variant))
+        })?;
+        if !(2..=MAXPLAYERS).contains(&players) {
+            return Err(PyErr::new::<exceptions::ValueError, _>(format!(
+                "{} players is not supported",
+                players
+            )));
+        }
+        let mut error = None;
+        let summary =
+            eval::evaluate_batched(games, players, &variant, seed, |observations, masks| {
+                call_batched(py, &self.policy, observations, masks)
+                    .map_err(|err| error = Some(err))
+                    .ok()
+            });
+        if let Some(err) = error {
+            return Err(err);
+        }
+        let seeds = (seed..seed + games as u64).collect();
+        let agents = ["python".to_string()];
+        let manifest = Manifest::for_run("v1", &variant, &[], players, &agents, seeds);
+        summary_dict(py, summary.unwrap(), &manifest)
+    }
+}
+
+#[pyfunction]
+fn evaluate_policy(
+    py: Python,
+    policy: PyObject,
+    games: usize,
+    players: usize,
+    seed: u64,
+) -> PyResult<PyObject> {
+    let mut error = None;
+    let summary = eval::evaluate(games, players, &Variant::standard(), seed, |state| {
+        if error.is_some() {
+            return None;
+        }
+        match policy_action(py, &policy, state.encode(), state.legal_mask()) {
+            Ok(action) => action,
+            Err(err) => {
+                error = Some(err);
+                None
+            }
+        }
+    });
+    if let Some(err) = error {
+        return Err(err);
+    }
+    let seeds = (seed..seed + games as u64).collect();
+    let agents = ["python".to_string()];
+    let variant = Variant::standard();
+    let manifest = Manifest::for_run("v1", &variant, &[], players, &agents, seeds);
+    summary_dict(py, summary, &manifest)
+}
+
+// number of games of each final score of the rust agent `agent` in every seat, an array indexed by
+// score from 0 to the maximum of the variant
+#[pyfunction(seed = "0", players = "2", variant = "\"standard\"")]
+fn score_histogram(
+    py: Python,
+    agent: &str,
+    games: usize,
+    seed: u64,
+    players: usize,
+    variant: &str,
+) -> PyResult<Py<PyArray1<i64>>> {
+    let variant = Variant::from_name(variant).ok_or_else(|| {
+        PyErr::new::<exceptions::ValueError, _>(format!("unknown variant {:?}", variant))
+    })?;
+    if !(2..=MAXPLAYERS).contains(&players) {
+        return Err(PyErr::new::<exceptions::ValueError, _>(format!(
+            "{} players is not supported",
+            players
+        )));
+    }
+    let histogram = py
+        .allow_threads(|| eval::score_histogram(agent, games, players, &variant, seed))
+        .ok_or_else(|| {
+            PyErr::new::<exceptions::ValueError, _>(format!("unknown agent {:?}", agent))
+        })?;
+    let histogram: Array1<i64> = histogram.into_iter().map(|x| x as i64).collect();
+    Ok(histogram.into_pyarray(py).to_owned())
+}
+
+// how often `agent` (a rust agent or a python policy) takes the action of the recorded players
+// of the replays, overall and by kind of the recorded action
+#[pyfunction]
+fn imitation_accuracy(py: Python, paths: Vec<String>, agent: PyObject) -> PyResult<PyObject> {
+    let replays = paths
+        .iter()
+        .map(Replay::load)
+        .collect::<Result<Vec<Replay>, ReplayError>>()
+        .map_err(replay_err)?;
+    let mut rust_agent = match agent.extract::<&str>(py) {
+        Ok(name) => Some(agents::from_name(name, 0).ok_or_else(|| {
+            PyErr::new::<exceptions::ValueError, _>(format!("unknown agent {:?}", name))
+        })?),
+        Err(_) => None,
+    };
+    let mut error = None;
+    let accuracy = imitation::evaluate(&replays, |state| {
+        if let Some(agent) = rust_agent.as_mut() {
+            return Some(agent.act(&Observation::new(state)));
+        }
+        if error.is_some() {
+            return None;
+        }
+        match policy_action(py, &agent, state.encode(), state.legal_mask()) {
+            Ok(action) => action,
+            Err(err) => {
+                error = Some(err);
+                None
+            }
+        }
+    })
+    .map_err(replay_err)?;
+    if let Some(err) = error {
+        return Err(err);
+    }
+    let dict = PyDict::new(py);
+    dict.set_item("decisions", accuracy.total())?;
+    dict.set_item("accuracy", accuracy.accuracy())?;
+    dict.set_item("abstained", accuracy.abstained)?;
+    let kinds = PyDict::new(py);
+    for (k, name) in imitation::KINDS.iter().enumerate() {
+        let x = PyDict::new(py);
+        x.set_item("decisions", accuracy.decisions[k])?;
+        x.set_item("matches", accuracy.matches[k])?;
+        x.set_item("accuracy", accuracy.accuracy_of(k))?;
+        kinds.set_item(*name, x)?;
+    }
+    dict.set_item("kinds", kinds)?;
+    dict.set_item(
+        "confusion",
+        accuracy
+            .confusion
+            .iter()
+            .map(|x| x.to_vec())
+            .collect::<Vec<_>>(),
+    )?;
+    Ok(dict.to_object(py))
+}
+
+// the manifest of the games as a dict, to trace the numbers back to the engine like the results of
+// an experiment
+fn manifest_dict(py: Python, manifest: &Manifest) -> PyResult<PyObject> {
+    Ok(py
+        .import("json")?
+        .call1("loads", (manifest.to_json(),))?
+        .to_object(py))
+}
+
+fn summary_dict(py: Python, summary: eval::Summary, manifest: &Manifest) -> PyResult<PyObject> {
+    let dict = PyDict::new(py);
+    dict.set_item("manifest", manifest_dict(py, manifest)?)?;
+    dict.set_item("games", summary.games)?;
+    dict.set_item("scores", summary.scores)?;
+    dict.set_item("mean_score", summary.mean_score)?;
+    dict.set_item("std_score", summary.std_score)?;
+    dict.set_item("perfect_rate", summary.perfect)?;
+    dict.set_item("bomb_rate", summary.bombed)?;
+    dict.set_item("mean_turns", summary.mean_turns)?;
+    dict.set_item("illegal", summary.illegal)?;
+    dict.set_item("forfeits", summary.forfeits)?;
+    dict.set_item("score_ci", summary.score_ci)?;
+    dict.set_item("perfect_rate_ci", summary.perfect_ci)?;
+    dict.set_item("bomb_rate_ci", summary.bombed_ci)?;
+    dict.set_item("latency", latency_dict(py, &summary.latency)?)?;
+    let mut seats = Vec::new();
+    for latency in &summary.seat_latency {
+        seats.push(latency_dict(py, latency)?);
+    }
+    dict.set_item("seat_latency", seats)?;
+    dict.set_item("timeouts", summary.timeouts)?;
+    dict.set_item("seconds_used", summary.seconds_used)?;
+    Ok(dict.to_object(py))
+}
+
+// the budgets of a driver in seconds, None if neither is set
+fn time_control(
+    per_move: Option<f64>,
+    total: Option<f64>,
+    on_timeout: &str,
+) -> PyResult<Option<TimeControl>> {
+    let on_timeout = OnTimeout::from_name(on_timeout).ok_or_else(|| {
+        PyErr::new::<exceptions::ValueError, _>(format!("unknown timeout {:?}", on_timeout))
+    })?;
+    if per_move.is_none() && total.is_none() {
+        return Ok(None);
+    }
+    Ok(Some(TimeControl {
+        per_move: per_move.map(Duration::from_secs_f64),
+        total: total.map(Duration::from_secs_f64),
+        on_timeout,
+    }))
+}
+
+fn seat_time_dict(py: Python, time: &SeatTime) -> PyResult<PyObject> {
+    let dict = PyDict::new(py);
+    dict.set_item("moves", time.moves)?;
+    dict.set_item("used", time.used.as_secs_f64())?;
+    dict.set_item("longest", time.longest.as_secs_f64())?;
+    dict.set_item("timeouts", time.timeouts)?;
+    Ok(dict.to_object(py))
+}
+
+// seconds per move
+fn latency_dict(py: Python, latency: &eval::Latency) -> PyResult<PyObject> {
+    let dict = PyDict::new(py);
+    dict.set_item("moves", latency.moves)?;
+    dict.set_item("mean", latency.mean)?;
+    dict.set_item("p50", latency.p50)?;
+    dict.set_item("p90", latency.p90)?;
+    dict.set_item("p99", latency.p99)?;
+    dict.set_item("max", latency.max)?;
+    Ok(dict.to_object(py))
+}
+
+// evaluates every lineup, a list of seats like for `GameDriver`, on `games` games; with `paired` the
+// lineups share the seeds and the score differences to the first lineup are reported; `per_move`
+// and `total` are budgets in seconds enforced like in `GameDriver`
+#[pyfunction(
+    seed = "0",
+    paired = "true",
+    variant = "\"standard\"",
+    per_move = "None",
+    total = "None",
+    on_timeout = "\"forfeit\""
+)]
+#[allow(clippy::too_many_arguments)]
+fn evaluate_lineups(
+    py: Python,
+    lineups: Vec<Vec<PyObject>>,
+    games: usize,
+    seed: u64,
+    paired: bool,
+    variant: &str,
+    per_move: Option<f64>,
+    total: Option<f64>,
+    on_timeout: &str,
+) -> PyResult<PyObject> {
+    let variant = Variant::from_name(variant).ok_or_else(|| {
+        PyErr::new::<exceptions::ValueError, _>(format!("unknown variant {:?}", variant))
+    })?;
+    let control = time_control(per_move, total, on_timeout)?;
+    let error = Rc::new(RefCell::new(None));
+    let mut summaries = Vec::new();
+    let mut manifests = Vec::new();
+    for (k, seats) in lineups.into_iter().enumerate() {
+        let names: Vec<String> = seats.iter().map(|x| seat_name(py, x)).collect();
+        let agents = seat_agents(py, seats, seed, &error)?;
+        let mut driver = driver::GameDriver::new(variant.clone(), agents);
+        driver.time_control = control;
+        let start = if paired {
+            seed
+        } else {
+            seed.wrapping_add((k * games) as u64)
+        };
+        summaries.push(eval::evaluate_lineup(&mut driver, games, start));
+        let seeds = (start..start + games as u64).collect();
+        let players = names.len();
+        manifests.push(Manifest::for_run(
+            "v1",
+            &variant,
+            &[],
+            players,
+            &names,
+            seeds,
+        ));
+        if let Some(err) = error.borrow_mut().take() {
+            return Err(err);
+        }
+    }
+    let dict = PyDict::new(py);
+    dict.set_item("paired", paired)?;
+    if paired && !summaries.is_empty() {
+        let differences: Vec<(f64, (f64, f64))> = summaries
+            .iter()
+            .map(|summary| eval::paired_difference(&summaries[0], summary))
+            .collect();
+        dict.set_item("differences", differences)?;
+    }
+    let mut results = Vec::new();
+    for (summary, manifest) in summaries.into_iter().zip(&manifests) {
+        results.push(summary_dict(py, summary, manifest)?);
+    }
+    dict.set_item("lineups", results)?;
+    Ok(dict.to_object(py))
+}
+
+type EndgameValues = (usize, Vec<(String, f64, usize, usize)>);
+
+// exact (expected, worst, best) final score of every action at `turn` of the replay, over all
+// the deals consistent with what the player to move knows; the clues are reported as one action
+#[pyfunction(limit = "100000")]
+fn endgame(py: Python, json: &str, turn: usize, limit: usize) -> PyResult<EndgameValues> {
+    let mut replay = Replay::from_json(json).map_err(replay_err)?;
+    replay.actions.truncate(turn);
+    let state = replay.play(|_, _| {}).map_err(replay_err)?;
+    let (values, deals) = py
+        .allow_threads(|| endgame::evaluate(&state, limit))
+        .map_err(|err| PyErr::new::<exceptions::ValueError, _>(format!("{:?}", err)))?;
+    let values = values
+        .into_iter()
+        .map(|x| {
+            let name = match x.action {
+                ActionRequest::Play { position } => format!("play #{}", position + 1),
+                ActionRequest::Discard { position } => format!("discard #{}", position + 1),
+                _ => "clue".to_string(),
+            };
+            (name, x.expected, x.worst, x.best)
+        })
+        .collect();
+    Ok((deals, values))
+}
+
+// the endgame positions shipped with the crate in json, with their best score
+#[pyfunction]
+fn endgame_puzzles() -> Vec<String> {
+    endgames::puzzles().iter().map(|x| x.to_json()).collect()
+}
+
+// the raw hands and deck of a game, read from its views instead, are on their way out
+fn deprecated(py: Python, accessor: &str, instead: &str) -> PyResult<()> {
+    let category = py.import("builtins")?.get("DeprecationWarning")?;
+    let message = format!("Game.{} is deprecated, use {}", accessor, instead);
+    PyErr::warn(py, category, &message, 1)
+}
+
+fn cards_object(py: Python, cards: &[Card], codes: bool) -> PyObject {
+    if codes {
+        let codes: Array1<u8> = cards.iter().map(Card::index).collect();
+        codes.into_pyarray(py).to_object(py)
+    } else {
+        let pairs: Vec<(String, usize)> = cards.iter().map(view::pair).collect();
+        pairs.to_object(py)
+    }
+}
+
+// the integer code of a card ("3r" is 2, "1g" is 5), see `Game.get_hands_codes`
+#[pyfunction]
+fn card_code(card: &str) -> PyResult<u8> {
+    Card::try_from(card.to_string())
+        .map(|card| card.index())
+        .map_err(PyErr::new::<exceptions::ValueError, _>)
+}
+
+#[pyfunction]
+fn card_from_code(code: u8) -> PyResult<String> {
+    Card::from_index(code)
+        .map(|card| card.to_string())
+        .ok_or_else(|| PyErr::new::<exceptions::ValueError, _>(format!("no card {}", code)))
+}
+
+// position in a hand of `hand` cards from the convention `src` to `dst`
+#[pyfunction]
+fn convert_slot(position: usize, hand: usize, src: &str, dst: &str) -> PyResult<usize> {
+    let order = |name: &str| {
+        SlotOrder::from_name(name).ok_or_else(|| {
+            PyErr::new::<exceptions::ValueError, _>(format!("unknown slot order {:?}", name))
+        })
+    };
+    let engine = order(src)?.convert(position, hand);
+    Ok(order(dst)?.convert(engine, hand))
+}
+
+type ObservationDiff = Vec<(String, Vec<(usize, f32, f32)>)>;
+
+// returns the blocks of the layout in which the observations differ,
+// with the (index in the block, value in a, value in b) of every difference
+#[pyfunction(variant = "\"standard\"")]
+fn diff_observations(
+    a: &PyArray1<f32>,
+    b: &PyArray1<f32>,
+    variant: &str,
+) -> PyResult<ObservationDiff> {
+    let variant = Variant::from_name(variant).ok_or_else(|| {
+        PyErr::new::<exceptions::ValueError, _>(format!("unknown variant {:?}", variant))
+    })?;
+    let state = State::from_deck_variant(2, variant.clone(), variant.deck());
+    let (a, b) = (a.as_array(), b.as_array());
+    if a.len() != state.encoding_size() || b.len() != state.encoding_size() {
+        return Err(PyErr::new::<exceptions::ValueError, _>(format!(
+            "observations of size {} expected",
+            state.encoding_size()
+        )));
+    }
+    Ok(layout::diff(&state.encoding_layout(), &a, &b)
+        .into_iter()
+        .map(|x| (x.name, x.entries))
+        .collect())
+}
+
+#[pyclass]
+struct VisitCounts {
+    counts: Counts,
+}
+
+#[pymethods]
+impl VisitCounts {
+    #[new]
+    fn new(obj: &PyRawObject) {
+        obj.init(VisitCounts {
+            counts: Counts::default(),
+        });
+    }
+
+    fn clear(&self) {
+        self.counts.clear();
+    }
+
+    // counts the public state of the game, returns its number of visits
+    fn visit(&self, game: &Game) -> u64 {
+        self.counts.visit(game.state.public_hash())
+    }
+
+    fn count(&self, game: &Game) -> u64 {
+        self.counts.get(game.state.public_hash())
+    }
+
+    // counts the public states of the replays, for the coverage of a dataset
+    fn add_files(&self, py: Python, paths: Vec<String>) -> PyResult<usize> {
+        let counts = &self.counts;
+        py.allow_threads(|| {
+            let replays = paths
+                .par_iter()
+                .map(Replay::load)
+                .collect::<Result<Vec<Replay>, ReplayError>>()?;
+            counts.add_replays(&replays)
+        })
+        .map_err(replay_err)
+    }
+
+    #[getter]
+    fn get_distinct(&self) -> usize {
+        self.counts.len()
+    }
+
+    #[getter]
+    fn get_total(&self) -> u64 {
+        self.counts.total()
+    }
+}
+
+// a python policy seated in a `GameDriver`, its exceptions are kept for the driver to raise;
+// `batched` for the policy of a `PyPolicyAgent`
+struct PyAgent {
+    policy: PyObject,
+    batched: bool,
+    error: Rc<RefCell<Option<PyErr>>>,
+}
+
+impl Agent for PyAgent {
+    fn act(&mut self, obs: &Observation) -> ActionRequest {
+        let gil = Python::acquire_gil();
+        let py = gil.python();
+        let action = if self.batched {
+            let mask = obs.legal_mask();
+            let observations = obs.encode().insert_axis(Axis(0));
+            let masks = mask.clone().insert_axis(Axis(0));
+            call_batched(py, &self.policy, observations, masks).map(|logits| {
+                eval::best_legal(logits.row(0), mask.view()).map(ActionRequest::from_id)
+            })
+        } else {
+            policy_action(py, &self.policy, obs.encode(), obs.legal_mask())
+        };
+        match action {
+            Ok(Some(action)) => return action,
+            Ok(None) => {}
+            Err(err) => *self.error.borrow_mut() = Some(err),
+        }
+        // out of any hand, stops the game
+        ActionRequest::Play { position: MAXCARDS }
+    }
+}
+
+fn seat_agents(
+    py: Python,
+    seats: Vec<PyObject>,
+    seed: u64,
+    error: &Rc<RefCell<Option<PyErr>>>,
+) -> PyResult<Vec<Box<dyn Agent>>> {
+    if seats.len() < 2 || seats.len() > MAXPLAYERS {
+        return Err(PyErr::new::<exceptions::ValueError, _>(format!(
+            "{} players is not supported",
+            seats.len()
+        )));
+    }
+    seats
+        .into_iter()
+        .enumerate()
+        .map(|(i, seat)| seat_agent(py, seat, seed.wrapping_add(i as u64), error))
+        .collect()
+}
+
+// how a seat is named in the manifests: the name of a rust agent, "external" or "python"
+fn seat_name(py: Python, seat: &PyObject) -> String {
+    if seat.extract::<&ExternalBot>(py).is_ok() {
+        "external".to_string()
+    } else if let Ok(name) = seat.extract::<&str>(py) {
+        name.to_string()
+    } else {
+        "python".to_string()
+    }
+}
+
+fn seat_agent(
+    py: Python,
+    seat: PyObject,
+    seed: u64,
+    error: &Rc<RefCell<Option<PyErr>>>,
+) -> PyResult<Box<dyn Agent>> {
+    if let Ok(agent) = seat.extract::<&PyPolicyAgent>(py) {
+        Ok(Box::new(PyAgent {
+            policy: agent.policy.clone_ref(py),
+            batched: true,
+            error: error.clone(),
+        }))
+    } else if let Ok(bot) = seat.extract::<&ExternalBot>(py) {
+        Ok(Box::new(external::ExternalBot::new(
+            bot.command.clone(),
+            bot.limits.clone(),
+        )))
+    } else if let Ok(name) = seat.extract::<&str>(py) {
+        agents::from_name(name, seed).ok_or_else(|| {
+            PyErr::new::<exceptions::ValueError, _>(format!("unknown agent {:?}", name))
+        })
+    } else {
+        Ok(Box::new(PyAgent {
+            policy: seat,
+            batched: false,
+            error: error.clone(),
+        }))
+    }
+}
+
+// finished games in sqlite, built with the `storage` feature, read back a page at a time
+#[cfg(feature = "storage")]
+#[pyclass]
+struct GameDatabase {
+    storage: storage::Storage,
+}
+
+#[cfg(feature = "storage")]
+fn storage_err(err: storage::StorageError) -> PyErr {
+    PyErr::new::<exceptions::ValueError, _>(format!("{:?}", err))
+}
+
+#[cfg(feature = "storage")]
+#[pymethods]
+impl GameDatabase {
+    #[new]
+    fn new(obj: &PyRawObject, path: &str) -> PyResult<()> {
+        let storage = storage::Storage::open(path).map_err(storage_err)?;
+        obj.init(GameDatabase { storage });
+        Ok(())
+    }
+
+    // stores a game, returns its id
+    fn add(&self, game: &Game) -> PyResult<i64> {
+        self.storage.insert(&game.state).map_err(storage_err)
+    }
+
+    #[args(seed = "None")]
+    fn add_replay(&self, json: &str, seed: Option<u64>) -> PyResult<i64> {
+        let replay = Replay::from_json(json).map_err(replay_err)?;
+        self.storage
+            .insert_replay(&replay, seed)
+            .map_err(storage_err)
+    }
+
+    #[args(variant = "None", players = "None", min_score = "None")]
+    fn count(
+        &self,
+        variant: Option<String>,
+        players: Option<usize>,
+        min_score: Option<usize>,
+    ) -> PyResult<usize> {
+        let query = storage::Query {
+            variant,
+            players,
+            min_score,
+            ..storage::Query::default()
+        };
+        self.storage.count(&query).map_err(storage_err)
+    }
+
+    // the (id, json) of the games of the query in the order of their ids, the next page starts
+    // after the last id
+    #[args(
+        variant = "None",
+        players = "None",
+        min_score = "None",
+        after = "None",
+        limit = "None"
+    )]
+    fn replays(
+        &self,
+        variant: Option<String>,
+        players: Option<usize>,
+        min_score: Option<usize>,
+        after: Option<i64>,
+        limit: Option<usize>,
+    ) -> PyResult<Vec<(i64, String)>> {
+        let query = storage::Query {
+            variant,
+            players,
+            min_score,
+            after,
+            limit,
+        };
+        let mut replays = Vec::new();
+        self.storage
+            .for_each(&query, |stored| {
+                replays.push((stored.id, stored.replay.to_json()));
+                Ok(())
+            })
+            .map_err(storage_err)?;
+        Ok(replays)
+    }
+
+    // the tensors of `hanabi.dataset` for the games of the query, with their `ids`
+    #[args(
+        encoder = "\"v1\"",
+        variant = "None",
+        players = "None",
+        min_score = "None",
+        after = "None",
+        limit = "None"
+    )]
+    #[allow(clippy::too_many_arguments)]
+    fn dataset(
+        &self,
+        py: Python,
+        gamma: f32,
+        encoder: &str,
+        variant: Option<String>,
+        players: Option<usize>,
+        min_score: Option<usize>,
+        after: Option<i64>,
+        limit: Option<usize>,
+    ) -> PyResult<PyObject> {
+        let encoder = encoder_from_name(encoder)?;
+        let query = storage::Query {
+            variant,
+            players,
+            min_score,
+            after,
+            limit,
+        };
+        let mut ids = Vec::new();
+        let mut replays = Vec::new();
+        self.storage
+            .for_each(&query, |stored| {
+                ids.push(stored.id);
+                replays.push(stored.replay);
+                Ok(())
+            })
+            .map_err(storage_err)?;
+        let data = dataset::from_replays(&replays, gamma, encoder).map_err(replay_err)?;
+        let dict = dataset_dict(py, data)?;
+        dict.set_item("ids", ids)?;
+        Ok(dict.to_object(py))
+    }
+}
+
+// a bot in its own process seated in a `GameDriver`, killed when it takes more than `move_time`
+// seconds to answer or goes over `memory_mb`
+#[pyclass]
+struct ExternalBot {
+    command: Vec<String>,
+    limits: external::Limits,
+}
+
+#[pymethods]
+impl ExternalBot {
+    #[new]
+    #[args(move_time = "1.0", memory_mb = "None")]
+    fn new(
+        obj: &PyRawObject,
+        command: Vec<String>,
+        move_time: f64,
+        memory_mb: Option<u64>,
+    ) -> PyResult<()> {
+        if command.is_empty() || move_time <= 0.0 {
+            return Err(PyErr::new::<exceptions::ValueError, _>(
+                "an external bot needs a command and a positive move time",
+            ));
+        }
+        let limits = external::Limits {
+            move_time: Duration::from_secs_f64(move_time),
+            memory: memory_mb.map(|x| x << 20),
+        };
+        obj.init(ExternalBot { command, limits });
+        Ok(())
+    }
+}
+
+// seats are agent names ("random", "cheat", "convention", "hat", "hgroup", "rollout") or python
+// policies called like in `evaluate_policy`, or `ExternalBot`s; with `per_move` or `total`, budgets
+// in seconds, a seat over budget forfeits the game ("forfeit") or has its chop discarded ("default")
+#[pyclass]
+struct GameDriver {
+    driver: driver::GameDriver,
+    error: Rc<RefCell<Option<PyErr>>>,
+}
+
+#[pymethods]
+impl GameDriver {
+    #[new]
+    #[args(
+        variant = "\"standard\"",
+        modifiers = "Vec::new()",
+        seed = "0",
+        per_move = "None",
+        total = "None",
+        on_timeout = "\"forfeit\""
+    )]
+    #[allow(clippy::too_many_arguments)]
+    fn new(
+        obj: &PyRawObject,
+        py: Python,
+        seats: Vec<PyObject>,
+        variant: &str,
+        modifiers: Vec<String>,
+        seed: u64,
+        per_move: Option<f64>,
+        total: Option<f64>,
+        on_timeout: &str,
+    ) -> PyResult<()> {
+        let variant = Variant::from_name(variant).ok_or_else(|| {
+            PyErr::new::<exceptions::ValueError, _>(format!("unknown variant {:?}", variant))
+        })?;
+        let error = Rc::new(RefCell::new(None));
+        let agents = seat_agents(py, seats, seed, &error)?;
+        let mut driver = driver::GameDriver::with_modifiers(variant, agents, &modifiers)
+            .map_err(PyErr::new::<exceptions::ValueError, _>)?;
+        driver.time_control = time_control(per_move, total, on_timeout)?;
+        obj.init(GameDriver { driver, error });
+        Ok(())
+    }
+
+    // returns a dict with the score, the number of turns, the illegal action that stopped the game
+    // if any, the json replay, the transcript, the seconds taken by every move and, with budgets,
+    // the clock of every seat
+    #[args(seed = "None")]
+    fn play(&mut self, py: Python, seed: Option<u64>) -> PyResult<PyObject> {
+        let record = self.driver.play(seed);
+        if let Some(err) = self.error.borrow_mut().take() {
+            return Err(err);
+        }
+        let dict = PyDict::new(py);
+        dict.set_item("score", record.score)?;
+        dict.set_item("turns", record.turns)?;
+        dict.set_item(
+            "illegal",
+            record
+                .illegal
+                .map(|(turn, error)| format!("turn {}: {:?}", turn, error)),
+        )?;
+        dict.set_item("forfeit", record.forfeit)?;
+        dict.set_item("replay", record.replay.to_json())?;
+        dict.set_item("transcript", record.transcript)?;
+        dict.set_item(
+            "move_times",
+            record
+                .move_times
+                .iter()
+                .map(|x| x.as_secs_f64())
+                .collect::<Vec<f64>>(),
+        )?;
+        let mut clock = Vec::new();
+        for time in &record.clock {
+            clock.push(seat_time_dict(py, time)?);
+        }
+        dict.set_item("clock", clock)?;
+        Ok(dict.to_object(py))
+    }
+
+    // mean score of every pairing of `agents`, seated like the seats of a driver: `scores[i, j]`
+    // with agent i in one seat, which turns from game to game, and agent j in all the others, every
+    // pairing on the same deals
+    #[staticmethod]
+    #[args(games = "100", players = "2", seed = "0", variant = "\"standard\"")]
+    fn cross_play(
+        py: Python,
+        agents: Vec<PyObject>,
+        games: usize,
+        players: usize,
+        seed: u64,
+        variant: &str,
+    ) -> PyResult<Py<PyArray2<f64>>> {
+        let variant = Variant::from_name(variant).ok_or_else(|| {
+            PyErr::new::<exceptions::ValueError, _>(format!("unknown variant {:?}", variant))
+        })?;
+        if !(2..=MAXPLAYERS).contains(&players) {
+            return Err(PyErr::new::<exceptions::ValueError, _>(format!(
+                "{} players is not supported",
+                players
+            )));
+        }
+        let error = Rc::new(RefCell::new(None));
+        for agent in &agents {
+            seat_agent(py, agent.clone_ref(py), seed, &error)?;
+        }
+        let scores = eval::cross_play(agents.len(), players, &variant, games, seed, |k, seed| {
+            if error.borrow().is_some() {
+                return None;
+            }
+            seat_agent(py, agents[k].clone_ref(py), seed, &error).ok()
+        });
+        if let Some(err) = error.borrow_mut().take() {
+            return Err(err);
+        }
+        Ok(scores.unwrap().into_pyarray(py).to_owned())
+    }
+}
+
+// fixed positions on which successive checkpoints are compared
+#[pyclass]
+struct ProbeSet {
+    states: Vec<State>,
+}
+
+#[pymethods]
+impl ProbeSet {
+    // the positions every `stride` turns of the replays
+    #[new]
+    #[args(stride = "5")]
+    fn new(obj: &PyRawObject, paths: Vec<String>, stride: usize) -> PyResult<()> {
+        let states = obj
+            .py()
+            .allow_threads(|| {
+                let replays = paths
+                    .par_iter()
+                    .map(Replay::load)
+                    .collect::<Result<Vec<Replay>, ReplayError>>()?;
+                fingerprint::probes(&replays, stride)
+            })
+            .map_err(replay_err)?;
+        obj.init(ProbeSet { states });
+        Ok(())
+    }
+
+    // the positions of the probe suite
+    #[staticmethod]
+    fn suite() -> PyResult<ProbeSet> {
+        let states = probes::suite()
+            .iter()
+            .map(probes::Probe::state)
+            .collect::<Result<Vec<State>, ReplayError>>()
+            .map_err(replay_err)?;
+        Ok(ProbeSet { states })
+    }
+
+    #[getter]
+    fn get_size(&self) -> usize {
+        self.states.len()
+    }
+
+    // the action distribution of `policy` on every probe, called like in `evaluate_policy`:
+    // logits are turned into a softmax over the legal actions, an action id into a one-hot
+    fn fingerprint(&self, py: Python, policy: PyObject) -> PyResult<Py<PyArray2<f32>>> {
+        let mut rows = Array2::zeros((self.states.len(), NACTIONS));
+        for (state, mut row) in self.states.iter().zip(rows.outer_iter_mut()) {
+            let mask = state.legal_mask();
+            row.assign(&match call_policy(py, &policy, state.encode(), &mask)? {
+                PolicyOutput::Action(id) => fingerprint::one_hot(id),
+                PolicyOutput::Logits(logits) => fingerprint::softmax(&logits, &mask.view()),
+            });
+        }
+        Ok(rows.into_pyarray(py).to_owned())
+    }
+}
+
+// scores `agent`, a rust agent name or a python policy called like in `evaluate_policy`, on the
+// probe positions shipped with the crate
+#[pyfunction]
+fn probe_suite(py: Python, agent: PyObject) -> PyResult<PyObject> {
+    let mut rust_agent = match agent.extract::<&str>(py) {
+        Ok(name) => Some(agents::from_name(name, 0).ok_or_else(|| {
+            PyErr::new::<exceptions::ValueError, _>(format!("unknown agent {:?}", name))
+        })?),
+        Err(_) => None,
+    };
+    let mut error = None;
+    let results = probes::run(&probes::suite(), |state| {
+        if let Some(agent) = rust_agent.as_mut() {
+            return Some(agent.act(&Observation::new(state)));
+        }
+        if error.is_some() {
+            return None;
+        }
+        match policy_action(py, &agent, state.encode(), state.legal_mask()) {
+            Ok(action) => action,
+            Err(err) => {
+                error = Some(err);
+                None
+            }
+        }
+    })
+    .map_err(replay_err)?;
+    if let Some(err) = error {
+        return Err(err);
+    }
+    let passed = results.iter().filter(|x| x.passed).count();
+    let dict = PyDict::new(py);
+    dict.set_item("passed", passed)?;
+    dict.set_item("total", results.len())?;
+    dict.set_item(
+        "results",
+        results
+            .into_iter()
+            .map(|x| (x.name, x.category, x.action.map(|a| a.id()), x.passed))
+            .collect::<Vec<_>>(),
+    )?;
+    Ok(dict.to_object(py))
+}
+
+// accuracy and speed of the int8 network of the `.npz` at `path` against the float one, on the
+// probes of its player count
+#[pyfunction(repeats = "100")]
+fn mlp_quantization_report(py: Python, path: &str, repeats: usize) -> PyResult<PyObject> {
+    let mlp = mlp::Mlp::load(path)
+        .map_err(|err| PyErr::new::<exceptions::ValueError, _>(format!("{:?}", err)))?;
+    let report = py
+        .allow_threads(|| mlp::quantization_report(&mlp, &probes::suite(), repeats))
+        .map_err(replay_err)?;
+    let dict = PyDict::new(py);
+    dict.set_item("probes", report.probes)?;
+    dict.set_item("float_passed", report.float_passed)?;
+    dict.set_item("int8_passed", report.int8_passed)?;
+    dict.set_item("agreement", report.agreement)?;
+    dict.set_item("max_logit_error", report.max_logit_error)?;
+    dict.set_item("float_seconds", report.float_seconds)?;
+    dict.set_item("int8_seconds", report.int8_seconds)?;
+    Ok(dict.to_object(py))
+}
+
+// mean distance between two fingerprints of the same probe set, `metric` is "tv" (total
+// variation), "js" (jensen-shannon) or "disagreement" (rate of different most likely actions)
+#[pyfunction(metric = "\"tv\"")]
+fn fingerprint_distance(a: &PyArray2<f32>, b: &PyArray2<f32>, metric: &str) -> PyResult<f32> {
+    let metric = fingerprint::Metric::from_name(metric).ok_or_else(|| {
+        PyErr::new::<exceptions::ValueError, _>(format!("unknown metric {:?}", metric))
+    })?;
+    let (a, b) = (a.as_array(), b.as_array());
+    if a.shape() != b.shape() || a.shape()[1] != NACTIONS {
+        return Err(PyErr::new::<exceptions::ValueError, _>(format!(
+            "fingerprints of shapes {:?} and {:?}",
+            a.shape(),
+            b.shape()
+        )));
+    }
+    Ok(fingerprint::distance(&a, &b, metric))
+}
+
+// python callable receiving the observation of the player to move
+struct PyIntrinsic(PyObject);
+
+impl IntrinsicReward for PyIntrinsic {
+    fn reward(&mut self, state: &State) -> f32 {
+        let gil = Python::acquire_gil();
+        let py = gil.python();
+        match self
+            .0
+            .call1(py, (state.encode().into_pyarray(py),))
+            .and_then(|x| x.extract::<f32>(py))
+        {
+            Ok(x) => x,
+            Err(err) => {
+                err.print(py);
+                0.0
+            }
+        }
+    }
+}
+
+// the (observation, action id) of every turn of `Game.iter_replay`, each action checked when
+// it is reached
+#[pyclass]
+struct ReplayIter {
+    state: State,
+    slots: SlotOrder,
+    actions: Vec<usize>,
+    turn: usize,
+}
+
+#[pyproto]
+impl PyIterProtocol for ReplayIter {
+    fn __iter__(slf: PyRefMut<Self>) -> PyResult<Py<ReplayIter>> {
+        Ok(slf.into())
+    }
+
+    fn __next__(mut slf: PyRefMut<Self>) -> PyResult<Option<(observation::Observation, usize)>> {
+        let id = match slf.actions.get(slf.turn) {
+            Some(&id) => id,
+            None => return Ok(None),
+        };
+        let player = slf.state.turn() % slf.state.players().len();
+        let obs = observation::Observation::new(&slf.state, player, slf.slots);
+        let turn = slf.turn;
+        let illegal = |err| illegal_move(format!("turn {}: {}", turn, err), &err);
+        if id >= NACTIONS {
+            return Err(illegal(IllegalMoves::InvalidActionId { id }));
+        }
+        let action = slf
+            .slots
+            .for_state(&slf.state)
+            .convert_id(id, slf.state.current_hand());
+        slf.state
+            .apply(&ActionRequest::from_id(action))
+            .map_err(illegal)?;
+        slf.turn += 1;
+        Ok(Some((obs, id)))
+    }
+}
+
+#[pyclass]
+struct Game {
+    state: State,
+    clock: Option<Clock>,
+    on_timeout: OnTimeout,
+    // the seat that ran out of time, the game is over for python
+    forfeit: Option<usize>,
+    intrinsic: Vec<Box<dyn IntrinsicReward>>,
+    // convention of the positions seen from python
+    slots: SlotOrder,
+    recorder: Option<Recorder>,
+    // whether what tells the cards still to draw may be read (the deck, the seed, the replays,
+    // every hand at once, the cheat bot), set once when the game is created
+    debug: bool,
+}
+
+impl Game {
+    fn wrap(state: State) -> Game {
+        Game {
+            state,
+            clock: None,
+            on_timeout: OnTimeout::Forfeit,
+            forfeit: None,
+            intrinsic: Vec::new(),
+            slots: SlotOrder::NewestFirst,
+            recorder: None,
+            debug: false,
+        }
+    }
+
+    fn with_debug(mut self, debug: bool) -> Game {
+        self.debug = debug;
+        self
+    }
+
+    // the error to raise when `what` is read outside debug mode
+    fn check_debug(&self, what: &str) -> Result<(), String> {
+        if self.debug {
+            Ok(())
+        } else {
+            Err(format!(
+                "{} is hidden, create the game with debug=True to read it",
+                what
+            ))
+        }
+    }
+
+    fn position(&self, position: usize) -> usize {
+        bindings::position(&self.state, self.slots, position)
+    }
+
+    // stops the clock of the player to move: raises TimeoutError if the seat forfeits, returns the
+    // action to play instead of theirs if the move was over budget and timeouts play a default
+    fn check_clock(&mut self) -> PyResult<Option<ActionRequest>> {
+        if let Some(seat) = self.forfeit {
+            return Err(PyErr::new::<exceptions::TimeoutError, _>(format!(
+                "player {} forfeited the game, out of time",
+                seat
+            )));
+        }
+        let seat = self.state.turn() % self.state.players().len();
+        let clock = match self.clock.as_mut() {
+            Some(clock) => clock,
+            None => return Ok(None),
+        };
+        if clock.stop(seat) {
+            return Ok(None);
+        }
+        match self.on_timeout {
+            OnTimeout::Forfeit => {
+                self.forfeit = Some(seat);
+                Err(PyErr::new::<exceptions::TimeoutError, _>(format!(
+                    "player {} is out of time and forfeits the game",
+                    seat
+                )))
+            }
+            OnTimeout::Default => Ok(Some(clock::default_action(&self.state))),
+        }
+    }
+
+    // every action goes through here to be timed and recorded
+    fn apply(&mut self, action: &ActionRequest) -> PyResult<()> {
+        let action = self.check_clock()?.unwrap_or(*action);
+        match self.recorder.as_mut() {
+            Some(recorder) => recorder.apply(&mut self.state, &action),
+            None => self.state.apply(&action),
+        }
+        .map_err(illegal_err)
+    }
+
+    // the features named in `blocks`, core and history (the v1 observation) if None
+    fn builder(&self, blocks: Option<Vec<String>>) -> PyResult<EncoderBuilder> {
+        let builder = match blocks {
+            Some(names) => EncoderBuilder::from_names(&names).ok_or_else(|| {
+                PyErr::new::<exceptions::ValueError, _>(format!(
+                    "unknown block in {:?}, the blocks are {:?}",
+                    names,
+                    Feature::names()
+                ))
+            })?,
+            None => return Ok(bindings::observation_builder(self.slots)),
+        };
+        Ok(builder.slots(self.slots))
+    }
+}
+
+#[pymethods]
+impl Game {
+    #[new]
+    #[args(
+        seed = "None",
+        variant = "\"standard\"",
+        modifiers = "Vec::new()",
+        slots = "\"newest_first\"",
+        debug = "false"
+    )]
+    fn new(
+        obj: &PyRawObject,
+        nplayer: usize,
+        seed: Option<u64>,
+        variant: &str,
+        modifiers: Vec<String>,
+        slots: &str,
+        debug: bool,
+    ) -> PyResult<()> {
+        let variant = Variant::from_name(variant).ok_or_else(|| {
+            PyErr::new::<exceptions::ValueError, _>(format!("unknown variant {:?}", variant))
+        })?;
+        let mut rules = Vec::new();
+        for name in &modifiers {
+            rules.push(modifier::from_name(name).ok_or_else(|| {
+                PyErr::new::<exceptions::ValueError, _>(format!("unknown rule modifier {:?}", name))
+            })?);
+        }
+        let slots = SlotOrder::from_name(slots).ok_or_else(|| {
+            PyErr::new::<exceptions::ValueError, _>(format!("unknown slot order {:?}", slots))
+        })?;
+        let mut game = Game::wrap(State::with_variant(nplayer, variant, seed).modified(rules));
+        game.slots = slots;
+        game.debug = debug;
+        obj.init(game);
+        Ok(())
+    }
+
+    // the actions raise IllegalMove when refused
+    fn play(&mut self, position: usize) -> PyResult<()> {
+        let position = self.position(position);
+        self.apply(&ActionRequest::Play { position })
+    }
+
+    fn discard(&mut self, position: usize) -> PyResult<()> {
+        let position = self.position(position);
+        self.apply(&ActionRequest::Discard { position })
+    }
+
+    fn clue(&mut self, py: Python, target: usize, info: PyObject) -> PyResult<()> {
+        match clue_request(py, target, &info) {
+            Some(action) => self.apply(&action),
+            // neither a value nor a color, an error like the engine's rather than a silent no-op
+            None => Err(illegal_err(IllegalMoves::InvalidClueToken)),
+        }
+    }
+
+    // the oldest unclued card of `player`, the player to move by default, in the slot order of
+    // the game; None if every card is clued
+    #[args(player = "None")]
+    fn chop(&self, player: Option<usize>) -> PyResult<Option<usize>> {
+        Ok(self.view(player)?.chop())
+    }
+
+    // the card a clue of `info` (a value 1 to 5 or a color letter) to `target` would be about
+    // under the h-group conventions, in the slot order of the game; None if it touches nothing
+    fn clue_focus(&self, py: Python, target: usize, info: PyObject) -> PyResult<Option<usize>> {
+        let clue = clue_request(py, target, &info)
+            .filter(|_| target < self.state.players().len())
+            .ok_or_else(|| PyErr::new::<exceptions::ValueError, _>("invalid clue"))?;
+        let hand = self.state.players()[target].len();
+        let order = self.slots.for_state(&self.state);
+        Ok(self.state.clue_focus(&clue).map(|i| order.convert(i, hand)))
+    }
+
+    // clues given, cards gotten per clue, misplays, critical discards and wasted turns of the game
+    // so far (`stats::Tempo`)
+    fn stats(&self, py: Python) -> PyResult<PyObject> {
+        let state = &self.state;
+        let tempo = py.allow_threads(|| stats::Tempo::of_state(state));
+        let dict = PyDict::new(py);
+        dict.set_item("turns", tempo.turns)?;
+        dict.set_item("score", self.state.final_score())?;
+        dict.set_item("clues", tempo.clues)?;
+        dict.set_item("cards_gotten", tempo.cards_gotten)?;
+        dict.set_item("efficiency", tempo.efficiency())?;
+        dict.set_item("misplays", tempo.misplays)?;
+        dict.set_item("critical_discards", tempo.critical_discards)?;
+        dict.set_item("wasted_turns", tempo.wasted_turns)?;
+        Ok(dict.to_object(py))
+    }
+
+    // the best expected final score of the player to move once the deck has at most `k` cards
+    // (`State::solve_endgame`), with the (action, expected, worst, best) of each action in the
+    // slot order of the game, the clues standing for one another
+    #[args(k = "3")]
+    fn solve_endgame(&self, py: Python, k: usize) -> PyResult<PyObject> {
+        let state = &self.state;
+        let solution = py
+            .allow_threads(|| state.solve_endgame(k))
+            .map_err(|err| PyErr::new::<exceptions::ValueError, _>(format!("{:?}", err)))?;
+        let order = self.slots.for_state(&self.state);
+        let hand = self.state.current_hand();
+        let values: Vec<(usize, f64, usize, usize)> = solution
+            .values
+            .iter()
+            .map(|x| {
+                let action = order.convert_id(x.action.id(), hand);
+                (action, x.expected, x.worst, x.best)
+            })
+            .collect();
+        let dict = PyDict::new(py);
+        dict.set_item("expected", solution.expected)?;
+        dict.set_item("exact", solution.exact)?;
+        dict.set_item("deals", solution.deals)?;
+        dict.set_item("values", values)?;
+        Ok(dict.to_object(py))
+    }
+
+    // returns the score increase plus the intrinsic rewards, raises IllegalMove if the action is
+    // illegal
+    fn step(&mut self, action: usize) -> PyResult<f32> {
+        let action =
+            bindings::step_request(&self.state, self.slots, action).map_err(illegal_err)?;
+        let score = self.state.score();
+        self.apply(&action)?;
+        let mut reward = (self.state.score() - score) as f32;
+        for intrinsic in self.intrinsic.iter_mut() {
+            let bonus = intrinsic.reward(&self.state);
+            if let Some(recorder) = self.recorder.as_mut() {
+                recorder.add_reward(bonus);
+            }
+            reward += bonus;
+        }
+        Ok(reward)
+    }
+
+    // plays the move of a bot that sees every hand, including its own, only in debug mode
+    fn play_cheat_bot_move(&mut self) -> PyResult<()> {
+        self.check_debug("the cheat bot")
+            .map_err(PyErr::new::<exceptions::ValueError, _>)?;
+        let action = CheatBot.best_move(&self.state);
+        self.apply(&action)
+    }
+
+    // plays the move of a bot that follows simple clue conventions
+    fn play_convention_bot_move(&mut self) -> PyResult<()> {
+        let action = ConventionBot.act(&Observation::new(&self.state));
+        self.apply(&action)
+    }
+
+    // plays the move of a monte carlo search over `samples` hidden hands, with rollouts of the
+    // convention bot limited to `depth` turns, the other players acting as the agent `teammates`
+    #[args(
+        samples = "16",
+        depth = "None",
+        threshold = "0.5",
+        seed = "0",
+        teammates = "\"convention\""
+    )]
+    fn play_rollout_bot_move(
+        &mut self,
+        py: Python,
+        samples: usize,
+        depth: Option<usize>,
+        threshold: f64,
+        seed: u64,
+        teammates: &str,
+    ) -> PyResult<()> {
+        if !search::is_teammate_model(teammates) {
+            return Err(PyErr::new::<exceptions::ValueError, _>(format!(
+                "unknown teammate model {:?}",
+                teammates
+            )));
+        }
+        let mut bot = search::RolloutBot::new(samples, depth, seed);
+        bot.threshold = threshold;
+        bot.teammates = teammates.to_string();
+        let state = &self.state;
+        let action = py.allow_threads(|| bot.act(&Observation::new(state)));
+        self.apply(&action)
+    }
+
+    #[args(scale = "0.1")]
+    fn add_count_bonus(&mut self, counts: &VisitCounts, scale: f32) {
+        self.intrinsic.push(Box::new(CountBonus {
+            counts: counts.counts.clone(),
+            scale,
+        }));
+    }
+
+    // counts the public state reached after every action
+    fn track_visits(&mut self, counts: &VisitCounts) {
+        let counts = counts.counts.clone();
+        self.state.subscribe(Box::new(move |_, state| {
+            counts.visit(state.public_hash());
+        }));
+    }
+
+    fn add_intrinsic_reward(&mut self, callback: PyObject) {
+        self.intrinsic.push(Box::new(PyIntrinsic(callback)));
+    }
+
+    // records every following action in rust, seen by the player to move with `encoder`,
+    // until `trajectory()` returns them; replaces the steps recorded so far
+    #[args(encoder = "\"v1\"")]
+    fn record(&mut self, encoder: &str) -> PyResult<()> {
+        let encoder = encoder_from_name(encoder)?;
+        if encoder == Encoder::Hle && self.state.encode_hle(0).is_none() {
+            return Err(PyErr::new::<exceptions::ValueError, _>(
+                "the hle encoder only supports the standard variant without modifiers",
+            ));
+        }
+        self.recorder = Some(Recorder::new(encoder, self.slots));
+        Ok(())
+    }
+
+    // the observations, legal masks, action ids, rewards (with the intrinsic rewards of `step`)
+    // and dones recorded since `record()` or the last call, stacked
+    fn trajectory(&mut self, py: Python) -> PyResult<PyObject> {
+        let trajectory = self
+            .recorder
+            .as_mut()
+            .ok_or_else(|| {
+                PyErr::new::<exceptions::ValueError, _>("the game is not recorded, see record()")
+            })?
+            .take();
+        let dict = PyDict::new(py);
+        dict.set_item("observations", trajectory.observations.into_pyarray(py))?;
+        dict.set_item("masks", trajectory.masks.into_pyarray(py))?;
+        dict.set_item("actions", trajectory.actions.into_pyarray(py))?;
+        dict.set_item("rewards", trajectory.rewards.into_pyarray(py))?;
+        dict.set_item("dones", trajectory.dones.into_pyarray(py))?;
+        Ok(dict.to_object(py))
+    }
+
+    // with `upto`, the game after its first `upto` actions; `debug` for every constructor as for
+    // `Game(...)`
+    #[staticmethod]
+    #[args(upto = "None", debug = "false")]
+    fn from_replay(json: &str, upto: Option<usize>, debug: bool) -> PyResult<Game> {
+        let replay = Replay::from_json(json).map_err(replay_err)?;
+        let state = match upto {
+            Some(upto) => replay.state_at(upto),
+            None => replay.play(|_, _| {}),
+        };
+        Ok(Game::wrap(state.map_err(replay_err)?).with_debug(debug))
+    }
+
+    // a game dealt from a deck with no 5 among the last `no_five_at_bottom` cards drawn, at most
+    // `max_criticals` 5s in the first hands and at most `max_per_suit` cards of a suit in each
+    #[staticmethod]
+    #[args(
+        seed = "0",
+        variant = "\"standard\"",
+        modifiers = "Vec::new()",
+        no_five_at_bottom = "0",
+        max_criticals = "None",
+        max_per_suit = "None",
+        debug = "false"
+    )]
+    #[allow(clippy::too_many_arguments)]
+    fn generate(
+        py: Python,
+        nplayer: usize,
+        seed: u64,
+        variant: &str,
+        modifiers: Vec<String>,
+        no_five_at_bottom: usize,
+        max_criticals: Option<usize>,
+        max_per_suit: Option<usize>,
+        debug: bool,
+    ) -> PyResult<Game> {
+        let variant = Variant::from_name(variant).ok_or_else(|| {
+            PyErr::new::<exceptions::ValueError, _>(format!("unknown variant {:?}", variant))
+        })?;
+        let mut rules = Vec::new();
+        for name in &modifiers {
+            rules.push(modifier::from_name(name).ok_or_else(|| {
+                PyErr::new::<exceptions::ValueError, _>(format!("unknown rule modifier {:?}", name))
+            })?);
+        }
+        let rules = modifier::Modifiers(rules);
+        let mut generator = deals::DealGenerator::new(nplayer, variant)
+            .hand_size(rules.hand_size(nplayer))
+            .no_five_at_bottom(no_five_at_bottom);
+        if let Some(k) = max_criticals {
+            generator = generator.max_criticals(k);
+        }
+        if let Some(k) = max_per_suit {
+            generator = generator.max_per_suit(k);
+        }
+        let state = py
+            .allow_threads(|| generator.state(seed))
+            .map_err(|err| PyErr::new::<exceptions::ValueError, _>(format!("{:?}", err)))?;
+        Ok(Game::wrap(state.modified(rules.0)).with_debug(debug))
+    }
+
+    // the game at a position of `endgames::Position` in json, without history
+    #[staticmethod]
+    #[args(debug = "false")]
+    fn from_position(json: &str, debug: bool) -> PyResult<Game> {
+        let state = endgames::Position::from_json(json)
+            .and_then(|position| State::from_position(&position))
+            .map_err(|err| PyErr::new::<exceptions::ValueError, _>(format!("{:?}", err)))?;
+        Ok(Game::wrap(state).with_debug(debug))
+    }
+
+    // like from_replay but repairs minor inconsistencies, returns the game and the repairs
+    #[staticmethod]
+    #[args(debug = "false")]
+    fn import_replay(json: &str, debug: bool) -> PyResult<(Game, Vec<String>)> {
+        let (replay, repairs) = Replay::from_json_tolerant(json).map_err(replay_err)?;
+        let state = replay.play(|_, _| {}).map_err(replay_err)?;
+        Ok((Game::wrap(state).with_debug(debug), repairs))
+    }
+
+    // plays `n` games of random legal actions in rust, returns the games, actions and seconds taken
+    // and the games per second
+    #[staticmethod]
+    #[args(nplayer = "4", seed = "0")]
+    fn bench_random_games(py: Python, n: usize, nplayer: usize, seed: u64) -> PyResult<PyObject> {
+        if !(2..=MAXPLAYERS).contains(&nplayer) {
+            return Err(PyErr::new::<exceptions::ValueError, _>(format!(
+                "{} players is not supported",
+                nplayer
+            )));
+        }
+        let throughput = py.allow_threads(|| bench::random_games(n, nplayer, seed));
+        let dict = PyDict::new(py);
+        dict.set_item("games", throughput.games)?;
+        dict.set_item("actions", throughput.actions)?;
+        dict.set_item("seconds", throughput.seconds)?;
+        dict.set_item(
+            "games_per_second",
+            throughput.games as f64 / throughput.seconds.max(1e-9),
+        )?;
+        Ok(dict.to_object(py))
+    }
+
+    // with the initial deck, only in debug mode
+    fn replay(&self) -> PyResult<String> {
+        self.check_debug("the replay")
+            .map_err(PyErr::new::<exceptions::ValueError, _>)?;
+        Ok(Replay::from_state(&self.state).to_json())
+    }
+
+    // the game in the binary format of `src/binary.rs`, a few bytes per action; it holds the seed
+    // or the deck, only in debug mode
+    fn dumps(&self, py: Python) -> PyResult<PyObject> {
+        self.check_debug("the binary record")
+            .map_err(PyErr::new::<exceptions::ValueError, _>)?;
+        Ok(PyBytes::new(py, &self.state.to_bytes()).to_object(py))
+    }
+
+    #[staticmethod]
+    #[args(debug = "false")]
+    fn loads(data: &PyBytes, debug: bool) -> PyResult<Game> {
+        let state = State::from_bytes(data.as_bytes()).map_err(replay_err)?;
+        Ok(Game::wrap(state).with_debug(debug))
+    }
+
+    // the games of concatenated `dumps()`
+    #[staticmethod]
+    #[args(debug = "false")]
+    fn loads_all(data: &PyBytes, debug: bool) -> PyResult<Vec<Game>> {
+        let mut bytes = data.as_bytes();
+        let mut games = Vec::new();
+        while !bytes.is_empty() {
+            let (state, n) = State::read_bytes(bytes).map_err(replay_err)?;
+            games.push(Game::wrap(state).with_debug(debug));
+            bytes = &bytes[n..];
+        }
+        Ok(games)
+    }
+
+    fn legal_mask(&self, py: Python) -> Py<PyArray1<f32>> {
+        self.state
+            .legal_mask_in(self.slots)
+            .into_pyarray(py)
+            .to_owned()
+    }
+
+    fn subscribe(&mut self, callback: PyObject) {
+        self.state.subscribe(Box::new(move |action, _| {
+            let gil = Python::acquire_gil();
+            let py = gil.python();
+            if let Err(err) = callback.call1(py, (format!("{}", action),)) {
+                err.print(py);
+            }
+        }));
+    }
+
+    // budgets in seconds; a move over budget forfeits the game ("forfeit") or is replaced by a
+    // discard of the chop ("default")
+    #[args(on_timeout = "\"forfeit\"")]
+    fn set_clock(
+        &mut self,
+        per_move: Option<f64>,
+        total: Option<f64>,
+        on_timeout: &str,
+    ) -> PyResult<()> {
+        self.on_timeout = OnTimeout::from_name(on_timeout).ok_or_else(|| {
+            PyErr::new::<exceptions::ValueError, _>(format!("unknown timeout {:?}", on_timeout))
+        })?;
+        self.clock = Some(Clock::new(
+            self.state.players().len(),
+            per_move.map(Duration::from_secs_f64),
+            total.map(Duration::from_secs_f64),
+        ));
+        Ok(())
+    }
+
+    // to call when the move is asked for: the move is timed until the action is played, so that
+    // only the time of the agent counts
+    fn start_clock(&mut self) {
+        if let Some(clock) = self.clock.as_mut() {
+            clock.start();
+        }
+    }
+
+    // the seat that forfeited the game by running out of time
+    #[getter]
+    fn forfeit(&self) -> Option<usize> {
+        self.forfeit
+    }
+
+    fn clock(&self, py: Python) -> PyResult<Vec<PyObject>> {
+        let mut seats = Vec::new();
+        if let Some(clock) = self.clock.as_ref() {
+            for time in clock.seats() {
+                seats.push(seat_time_dict(py, time)?);
+            }
+        }
+        Ok(seats)
+    }
+
+    // every hand without `player`, their own hidden with it
+    #[args(player = "None")]
+    fn render(&self, player: Option<usize>) -> PyResult<String> {
+        match player {
+            None => Ok(self.state.render()),
+            Some(player) if player < self.state.players().len() => {
+                Ok(self.state.render_for(player))
+            }
+            Some(player) => Err(PyErr::new::<exceptions::ValueError, _>(format!(
+                "no player {}",
+                player
+            ))),
+        }
+    }
+
+    // observation of the player to move, made of the features named in `blocks` (see
+    // `encoder::Feature`), core and history by default; with `beliefs` their flattened beliefs are
+    // appended, then with `counts` their flattened `unseen_counts`, with `playability` their
+    // flattened `playability` and with `teammates` the flattened `teammate_summary`; the "hle"
+    // encoder has its own slot order and does not take `blocks`
+    #[args(
+        beliefs = "false",
+        encoder = "\"v1\"",
+        counts = "false",
+        playability = "false",
+        teammates = "false",
+        blocks = "None"
+    )]
+    #[allow(clippy::too_many_arguments)]
+    fn encode(
+        &self,
+        py: Python,
+        beliefs: bool,
+        encoder: &str,
+        counts: bool,
+        playability: bool,
+        teammates: bool,
+        blocks: Option<Vec<String>>,
+    ) -> PyResult<Py<PyArray1<f32>>> {
+        let player = self.state.turn() % self.state.players().len();
+        let flags = [
+            (beliefs, Feature::Beliefs),
+            (counts, Feature::CardCounting),
+            (playability, Feature::Playability),
+            (teammates, Feature::Teammates),
+        ];
+        let slots = self.slots;
+        let extra = |builder: EncoderBuilder| {
+            flags
+                .iter()
+                .filter(|x| x.0)
+                .fold(builder, |builder, x| builder.feature(x.1))
+                .slots(slots)
+                .build()
+        };
+        let encoder = encoder_from_name(encoder)?;
+        let builder = match encoder {
+            Encoder::V1 => self.builder(blocks)?,
+            Encoder::Hle | Encoder::V2 if blocks.is_some() => {
+                return Err(PyErr::new::<exceptions::ValueError, _>(format!(
+                    "the {} encoder does not take blocks",
+                    encoder.name()
+                )))
+            }
+            Encoder::Hle => EncoderBuilder::new(),
+            Encoder::V2 => EncoderBuilder::v2(),
+        };
+        let state = &self.state;
+        let x = py.allow_threads(|| match encoder {
+            Encoder::V1 | Encoder::V2 => Some(extra(builder).encode(state, player)),
+            Encoder::Hle => state.encode_hle(player).map(|x| {
+                let y = extra(builder).encode(state, player);
+                ndarray::stack(Axis(0), &[x.view(), y.view()]).unwrap()
+            }),
+        });
+        let x = x.ok_or_else(|| {
+            PyErr::new::<exceptions::ValueError, _>(
+                "the hle encoder only supports the standard variant without modifiers",
+            )
+        })?;
+        Ok(x.into_pyarray(py).to_owned())
+    }
+
+    // `encode()` written in a float32 array of the same length instead of a new array; in the engine
+    // order nothing is allocated, in the other order the observation is converted first
+    fn encode_into(&self, py: Python, buffer: &PyArray1<f32>) -> PyResult<()> {
+        let size = self.state.encoding_size();
+        if buffer.len() != size {
+            return Err(PyErr::new::<exceptions::ValueError, _>(format!(
+                "the buffer has {} entries instead of {}",
+                buffer.len(),
+                size
+            )));
+        }
+        let mut x = buffer.as_array_mut();
+        let (state, slots) = (&self.state, self.slots);
+        py.allow_threads(|| {
+            if slots.for_state(state) == SlotOrder::NewestFirst {
+                state.encode_into(&mut x);
+            } else {
+                let player = state.turn() % state.players().len();
+                x.assign(&state.encode_in(player, slots));
+            }
+        });
+        Ok(())
+    }
+
+    // (name, start, length) of the blocks of the observation made of the features named in
+    // `blocks`, as in `encode()`
+    #[args(blocks = "None")]
+    fn encoding_layout(
+        &self,
+        blocks: Option<Vec<String>>,
+    ) -> PyResult<Vec<(String, usize, usize)>> {
+        Ok(self
+            .builder(blocks)?
+            .build()
+            .layout(&self.state)
+            .into_iter()
+            .map(|x| (x.name, x.start, x.len))
+            .collect())
+    }
+
+    // probability of every (suit, value) for each slot of the hand, shape (slots, suits, 5)
+    #[args(player = "None")]
+    fn beliefs(&self, py: Python, player: Option<usize>) -> PyResult<Py<PyArray3<f32>>> {
+        Ok(self.view(player)?.beliefs(py))
+    }
+
+    // 1 for every card each slot of the hand may be given the clues, negative information
+    // included, shape (slots, suits, 5)
+    #[args(player = "None")]
+    fn constraints(&self, py: Python, player: Option<usize>) -> PyResult<Py<PyArray3<f32>>> {
+        Ok(self.view(player)?.constraints(py))
+    }
+
+    // iterates over the (observation of the player to move, action) of every turn of the game
+    // played on from here with `actions`, ids in the slot order of the game; the game is unchanged
+    fn iter_replay(&self, actions: Vec<usize>) -> ReplayIter {
+        ReplayIter {
+            state: self.state.fork(),
+            slots: self.slots,
+            actions,
+            turn: 0,
+        }
+    }
+
+    // probability under the public beliefs that each slot of `player` is playable, dead or
+    // critical, shape (slots, 3)
+    #[args(player = "None")]
+    fn playability(&self, py: Python, player: Option<usize>) -> PyResult<Py<PyArray2<f32>>> {
+        Ok(self.view(player)?.playability(py))
+    }
+
+    // for each teammate of `player`, in seat order after them, the number of playable cards, whether
+    // the card on their chop is critical and the number of fully clued cards, shape (players - 1, 3)
+    #[args(player = "None")]
+    fn teammate_summary(&self, py: Python, player: Option<usize>) -> PyResult<Py<PyArray2<f32>>> {
+        Ok(self.view(player)?.teammate_summary(py))
+    }
+
+    // share of the copies of each card that `player` cannot see (deck and own hand), shape
+    // (suits, 5)
+    #[args(player = "None")]
+    fn unseen_counts(&self, py: Python, player: Option<usize>) -> PyResult<Py<PyArray2<f32>>> {
+        Ok(self.view(player)?.unseen_counts(py))
+    }
+
+    // structured view of the game from `player`, the player to move by default
+    #[args(player = "None")]
+    fn observation(&self, player: Option<usize>) -> PyResult<observation::Observation> {
+        Ok(self.view(player)?.observation())
+    }
+
+    // what `player`, the player to move by default, sees of the game; unlike the game, a view
+    // cannot be used to read the own hand or the deck; the methods of the game for a player
+    // (`beliefs`, `chop`, `observation`, ...) are those of this view
+    #[args(player = "None")]
+    fn view(&self, player: Option<usize>) -> PyResult<view::PlayerView> {
+        let n = self.state.players().len();
+        let player = player.unwrap_or(self.state.turn() % n);
+        if player >= n {
+            return Err(PyErr::new::<exceptions::ValueError, _>(format!(
+                "no player {}",
+                player
+            )));
+        }
+        Ok(view::PlayerView::new(&self.state, player, self.slots))
+    }
+
+    // every hand but not the deck
+    fn spectator_view(&self) -> view::SpectatorView {
+        view::SpectatorView::new(&self.state, self.slots)
+    }
+
+    // the hands and the deck, only in debug mode like `deck`
+    fn omniscient_view(&self) -> PyResult<view::OmniscientView> {
+        self.check_debug("the deck")
+            .map_err(PyErr::new::<exceptions::ValueError, _>)?;
+        Ok(view::OmniscientView::new(&self.state, self.slots))
+    }
+
+    // a hand of `player` agreeing with their clues and the cards they see, in the slot order of
+    // the game, e.g. ["1r", "4g", ...], None when no hand was found
+    #[args(player = "None", seed = "None")]
+    fn sample_hidden(
+        &self,
+        player: Option<usize>,
+        seed: Option<u64>,
+    ) -> PyResult<Option<Vec<String>>> {
+        Ok(self.view(player)?.sample_hidden(seed))
+    }
+
+    // common knowledge beliefs, shape (players, slots, suits, 5)
+    fn public_beliefs(&self, py: Python) -> Py<PyArray4<f32>> {
+        let state = &self.state;
+        let beliefs = py.allow_threads(|| state.public_beliefs());
+        beliefs.into_pyarray(py).to_owned()
+    }
+
+    // discarded copies of every card, shape (suits, 5)
+    fn discard_matrix(&self, py: Python) -> Py<PyArray2<i32>> {
+        self.state.discard_matrix().into_pyarray(py).to_owned()
+    }
+
+    // highest value played per suit
+    fn fireworks(&self, py: Python) -> Py<PyArray1<i32>> {
+        self.state.fireworks().into_pyarray(py).to_owned()
+    }
+
+    // (suit, value - 1) of every card held, -1 for empty slots, shape (players, slots, 2); only in
+    // debug mode and deprecated, `view(player).hands` is what a player sees
+    fn hands_matrix(&self, py: Python) -> PyResult<Py<PyArray3<i32>>> {
+        self.check_debug("every hand")
+            .map_err(PyErr::new::<exceptions::ValueError, _>)?;
+        deprecated(py, "hands_matrix()", "Game.view(player).hands")?;
+        Ok(self
+            .state
+            .hands_matrix(self.slots)
+            .into_pyarray(py)
+            .to_owned())
+    }
+
+    // the discarded cards in the order of the discards, misplays included, as (color, value)
+    // pairs, or their `Card::index` in a uint8 array with `codes`
+    #[args(codes = "false")]
+    fn discard_pile(&self, py: Python, codes: bool) -> PyObject {
+        cards_object(py, self.state.discard(), codes)
+    }
+
+    // the cards on the table, by suit in the order of the variant and by value, like
+    // `discard_pile`
+    #[args(codes = "false")]
+    fn played(&self, py: Python, codes: bool) -> PyObject {
+        cards_object(py, &self.state.played(), codes)
+    }
+
+    // `Card::index` of every card held (5 * color + value - 1, 0 to 24 in the standard game), 255
+    // for empty slots, shape (players, slots); only in debug mode and deprecated like
+    // `hands_matrix`
+    fn get_hands_codes(&self, py: Python) -> PyResult<Py<PyArray2<u8>>> {
+        self.check_debug("every hand")
+            .map_err(PyErr::new::<exceptions::ValueError, _>)?;
+        deprecated(py, "get_hands_codes()", "Game.view(player).hands")?;
+        Ok(self
+            .state
+            .hands_codes(self.slots)
+            .into_pyarray(py)
+            .to_owned())
+    }
+
+    // plays the action of the highest output, raises IllegalMove like `step`
+    #[args(tie = "\"first\"")]
+    fn decode(&mut self, x: &PyArray1<f32>, tie: &str) -> PyResult<()> {
+        let mut x = x.as_array().to_owned();
+        let hand = self.state.current_hand();
+        let order = self.slots.for_state(&self.state);
+        if x.len() == self.state.decoding_size() {
+            let positions = x.slice(ndarray::s![3..3 + hand]).to_owned();
+            for j in 0..hand {
+                x[3 + order.convert(j, hand)] = positions[j];
+            }
+        }
+        let tie = match tie {
+            "first" => TieBreak::First,
+            "last" => TieBreak::Last,
+            "random" => TieBreak::Random,
+            _ => {
+                return Err(PyErr::new::<exceptions::ValueError, _>(format!(
+                    "unknown tie break {:?}",
+                    tie
+                )))
+            }
+        };
+        if let Some(action) = self.check_clock()? {
+            return self.state.apply(&action).map_err(illegal_err);
+        }
+        self.state
+            .decode_with(&x.view(), tie)
+            .map(|_| ())
+            .map_err(illegal_err)
+    }
+
+    // the first broken invariant of the engine (cards conserved, tokens in bounds, clues true,
+    // history and turns consistent), None when the game is consistent; a debug check
+    fn check_invariants(&self) -> Option<String> {
+        self.state.check_invariants().err()
+    }
+
+    // equality up to a renaming of the suits
+    fn eq_up_to_colors(&self, other: &Game) -> bool {
+        self.state.eq_up_to_colors(&other.state)
+    }
+
+    #[getter]
+    fn get_gameover(&self) -> bool {
+        self.state.gameover()
+    }
+
+    #[getter]
+    fn get_turn(&self) -> usize {
+        *self.state.turn()
+    }
+
+    // the seed deals the deck again, only in debug mode
+    #[getter]
+    fn get_seed(&self) -> PyResult<Option<u64>> {
+        self.check_debug("the seed")
+            .map_err(PyErr::new::<exceptions::AttributeError, _>)?;
+        Ok(*self.state.seed())
+    }
+
+    #[getter]
+    fn get_state_hash(&self) -> u64 {
+        self.state.state_hash()
+    }
+
+    #[getter]
+    fn get_score(&self) -> usize {
+        self.state.score()
+    }
+
+    // the score with the scoring rule modifiers, 0 after a strikeout with "strikeout_zero"
+    // the highest score still reachable given the discard pile
+    #[getter]
+    fn get_max_score(&self) -> usize {
+        self.state.max_score()
+    }
+
+    #[getter]
+    fn get_pace(&self) -> isize {
+        self.state.pace()
+    }
+
+    #[getter]
+    fn get_final_score(&self) -> usize {
+        self.state.final_score()
+    }
+
+    // the cards still to draw, the next one last; hidden unless the game is in debug mode,
+    // deprecated for `omniscient_view().deck`
+    #[getter]
+    fn get_deck(&self) -> PyResult<Vec<String>> {
+        self.check_debug("the deck")
+            .map_err(PyErr::new::<exceptions::AttributeError, _>)?;
+        let gil = Python::acquire_gil();
+        deprecated(gil.python(), "deck", "Game.omniscient_view().deck")?;
+        Ok(self.state.deck().iter().map(|x| format!("{}", x)).collect())
+    }
+
+    #[getter]
+    fn get_deck_size(&self) -> usize {
+        self.state.deck().len()
+    }
+
+    #[getter]
+    fn get_debug(&self) -> bool {
+        self.debug
+    }
+
+    #[getter]
+    fn get_history(&self) -> Vec<String> {
+        let order = self.slots.for_state(&self.state);
+        self.state
+            .history()
+            .iter()
+            .zip(self.state.hands_before())
+            .map(|(x, hands)| format!("{}", order.convert_action(x, &hands)))
+            .collect()
+    }
+}
+
+#[pyproto]
+impl<'p> PyObjectProtocol<'p> for Game {
+    fn __str__(&self) -> PyResult<String> {
+        Ok(self.state.render())
+    }
+
+    // equal positions, whatever the actions that led to them
+    fn __richcmp__(&self, other: &PyAny, op: CompareOp) -> PyResult<PyObject> {
+        let py = other.py();
+        let other = match other.extract::<&Game>() {
+            Ok(other) => other,
+            Err(_) => return Ok(py.NotImplemented()),
+        };
+        match op {
+            CompareOp::Eq => Ok((self.state == other.state).to_object(py)),
+            CompareOp::Ne => Ok((self.state != other.state).to_object(py)),
+            _ => Ok(py.NotImplemented()),
+        }
+    }
+
+    // positions that look the same to everyone have the same hash
+    fn __hash__(&self) -> PyResult<isize> {
+        Ok(self.state.public_hash() as isize)
+    }
+}
@@ -0,0 +1,257 @@
+// PettingZoo agent-environment-cycle interface, every agent receives the score increase
+use crate::state::{ActionRequest, State, NACTIONS};
+use crate::variant::Variant;
+use numpy::IntoPyArray;
+use pyo3::exceptions;
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+use pyo3::PyIterProtocol;
+
+#[pyclass]
+pub struct HanabiAEC {
+    state: State,
+    variant: Variant,
+    // seats still in the cycle, a terminated agent leaves it when stepped with None
+    active: Vec<usize>,
+    selection: usize,
+    rewards: Vec<f32>,
+    cumulative: Vec<f32>,
+}
+
+fn agent_name(seat: usize) -> String {
+    format!("player_{}", seat)
+}
+
+impl HanabiAEC {
+    fn seat(&self, agent: &str) -> PyResult<usize> {
+        (0..self.rewards.len())
+            .find(|&seat| agent_name(seat) == agent)
+            .ok_or_else(|| PyErr::new::<exceptions::KeyError, _>(agent.to_string()))
+    }
+
+    fn agent_dict<T: ToPyObject>(&self, py: Python, f: impl Fn(usize) -> T) -> PyResult<PyObject> {
+        let dict = PyDict::new(py);
+        for &seat in &self.active {
+            dict.set_item(agent_name(seat), f(seat))?;
+        }
+        Ok(dict.to_object(py))
+    }
+}
+
+#[pymethods]
+impl HanabiAEC {
+    #[new]
+    #[args(seed = "None", variant = "\"standard\"")]
+    fn new(obj: &PyRawObject, nplayer: usize, seed: Option<u64>, variant: &str) -> PyResult<()> {
+        let variant = Variant::from_name(variant).ok_or_else(|| {
+            PyErr::new::<exceptions::ValueError, _>(format!("unknown variant {:?}", variant))
+        })?;
+        obj.init(HanabiAEC {
+            state: State::with_variant(nplayer, variant.clone(), seed),
+            variant,
+            active: (0..nplayer).collect(),
+            selection: 0,
+            rewards: vec![0.0; nplayer],
+            cumulative: vec![0.0; nplayer],
+        });
+        Ok(())
+    }
+
+    #[args(seed = "None", options = "None")]
+    fn reset(&mut self, seed: Option<u64>, options: Option<PyObject>) {
+        let _ = options;
+        let n = self.rewards.len();
+        self.state = State::with_variant(n, self.variant.clone(), seed);
+        self.active = (0..n).collect();
+        self.selection = 0;
+        self.rewards = vec![0.0; n];
+        self.cumulative = vec![0.0; n];
+    }
+
+    fn observe(&self, py: Python, agent: &str) -> PyResult<PyObject> {
+        let seat = self.seat(agent)?;
+        let mask = if seat == self.selection && !self.state.gameover() {
+            self.state.legal_mask().mapv(|x| x as i8)
+        } else {
+            ndarray::Array1::zeros(NACTIONS)
+        };
+        let dict = PyDict::new(py);
+        dict.set_item("observation", self.state.encode_for(seat).into_pyarray(py))?;
+        dict.set_item("action_mask", mask.into_pyarray(py))?;
+        Ok(dict.to_object(py))
+    }
+
+    #[args(observe = "true")]
+    fn last(&self, py: Python, observe: bool) -> PyResult<PyObject> {
+        let agent = agent_name(self.selection);
+        let observation = if observe {
+            self.observe(py, &agent)?
+        } else {
+            py.None()
+        };
+        let done = self.state.gameover();
+        Ok((
+            observation,
+            self.cumulative[self.selection],
+            done,
+            false,
+            PyDict::new(py),
+        )
+            .to_object(py))
+    }
+
+    fn step(&mut self, action: Option<usize>) -> PyResult<()> {
+        let seat = self.selection;
+        if self.state.gameover() {
+            if action.is_some() {
+                return Err(PyErr::new::<exceptions::ValueError, _>(
+                    "a terminated agent must be stepped with None",
+                ));
+            }
+            let i = self.active.iter().position(|&x| x == seat).unwrap();
+            self.active.remove(i);
+            if !self.active.is_empty() {
+                self.selection = self.active[i % self.active.len()];
+            }
+            return Ok(());
+        }
+
+        let action = match action {
+            Some(action) if action < NACTIONS => action,
+            _ => {
+                return Err(PyErr::new::<exceptions::ValueError, _>(format!(
+                    "invalid action {:?}",
+                    action
+                )))
+            }
+        };
+        let score = self.state.score();
+        self.state
+            .apply(&ActionRequest::from_id(action))
+            .map_err(super::illegal_err)?;
+        let reward = (self.state.score() - score) as f32;
+
+        self.cumulative[seat] = 0.0;
+        for seat in 0..self.rewards.len() {
+            self.rewards[seat] = reward;
+            self.cumulative[seat] += reward;
+        }
+        self.selection = *self.state.turn() % self.rewards.len();
+        Ok(())
+    }
+
+    fn agent_iter(slf: PyRef<Self>, max_iter: Option<usize>) -> AgentIter {
+        AgentIter {
+            env: slf.into(),
+            remaining: max_iter.unwrap_or(usize::MAX),
+        }
+    }
+
+    fn observation_space(&self, py: Python, agent: &str) -> PyResult<PyObject> {
+        self.seat(agent)?;
+        let spaces = py.import("gymnasium.spaces")?;
+        let numpy = py.import("numpy")?;
+        let observation = spaces.call1(
+            "Box",
+            (
+                -1.0,
+                1.0,
+                (self.state.encoding_size(),),
+                numpy.get("float32")?,
+            ),
+        )?;
+        let mask = spaces.call1("Box", (0, 1, (NACTIONS,), numpy.get("int8")?))?;
+        let dict = PyDict::new(py);
+        dict.set_item("observation", observation)?;
+        dict.set_item("action_mask", mask)?;
+        Ok(spaces.call1("Dict", (dict,))?.to_object(py))
+    }
+
+    fn action_space(&self, py: Python, agent: &str) -> PyResult<PyObject> {
+        self.seat(agent)?;
+        let spaces = py.import("gymnasium.spaces")?;
+        Ok(spaces.call1("Discrete", (NACTIONS,))?.to_object(py))
+    }
+
+    #[getter]
+    fn get_possible_agents(&self) -> Vec<String> {
+        (0..self.rewards.len()).map(agent_name).collect()
+    }
+
+    #[getter]
+    fn get_agents(&self) -> Vec<String> {
+        self.active.iter().map(|&seat| agent_name(seat)).collect()
+    }
+
+    #[getter]
+    fn get_agent_selection(&self) -> String {
+        agent_name(self.selection)
+    }
+
+    #[getter]
+    fn get_rewards(&self, py: Python) -> PyResult<PyObject> {
+        self.agent_dict(py, |seat| self.rewards[seat])
+    }
+
+    #[getter(_cumulative_rewards)]
+    fn get_cumulative_rewards(&self, py: Python) -> PyResult<PyObject> {
+        self.agent_dict(py, |seat| self.cumulative[seat])
+    }
+
+    #[getter]
+    fn get_terminations(&self, py: Python) -> PyResult<PyObject> {
+        let done = self.state.gameover();
+        self.agent_dict(py, |_| done)
+    }
+
+    #[getter]
+    fn get_truncations(&self, py: Python) -> PyResult<PyObject> {
+        self.agent_dict(py, |_| false)
+    }
+
+    #[getter]
+    fn get_infos(&self, py: Python) -> PyResult<PyObject> {
+        let dict = PyDict::new(py);
+        for &seat in &self.active {
+            dict.set_item(agent_name(seat), PyDict::new(py))?;
+        }
+        Ok(dict.to_object(py))
+    }
+
+    #[getter]
+    fn get_metadata(&self, py: Python) -> PyResult<PyObject> {
+        let dict = PyDict::new(py);
+        dict.set_item("name", "hanabi_v0")?;
+        dict.set_item("is_parallelizable", false)?;
+        Ok(dict.to_object(py))
+    }
+
+    fn render(&self) -> String {
+        self.state.render()
+    }
+}
+
+#[pyclass]
+pub struct AgentIter {
+    env: Py<HanabiAEC>,
+    remaining: usize,
+}
+
+#[pyproto]
+impl PyIterProtocol for AgentIter {
+    fn __iter__(slf: PyRefMut<Self>) -> PyResult<PyObject> {
+        let py = unsafe { Python::assume_gil_acquired() };
+        Ok(slf.to_object(py))
+    }
+
+    fn __next__(mut slf: PyRefMut<Self>) -> PyResult<Option<String>> {
+        let py = unsafe { Python::assume_gil_acquired() };
+        let env = slf.env.as_ref(py);
+        if slf.remaining == 0 || env.active.is_empty() {
+            return Ok(None);
+        }
+        let agent = agent_name(env.selection);
+        slf.remaining -= 1;
+        Ok(Some(agent))
+    }
+}
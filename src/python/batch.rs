@@ -0,0 +1,113 @@
+// many games stepped together for data generation, in parallel and without the gil
+use crate::batch::Batch;
+use crate::replay::Replay;
+use crate::state::MAXPLAYERS;
+use crate::variant::Variant;
+use numpy::{IntoPyArray, PyArray1, PyArray2};
+use pyo3::exceptions;
+use pyo3::prelude::*;
+
+#[pyclass]
+pub struct BatchGame {
+    batch: Batch,
+}
+
+#[pymethods]
+impl BatchGame {
+    // `size` games dealt from `seed`, the same batch whatever the number of threads; with
+    // `history` the games keep only their last `history` actions, the observations are the same
+    // as long as it is at least `MAXHISTORY` (100)
+    #[new]
+    #[args(nplayer = "2", seed = "0", variant = "\"standard\"", history = "None")]
+    fn new(
+        obj: &PyRawObject,
+        size: usize,
+        nplayer: usize,
+        seed: u64,
+        variant: &str,
+        history: Option<usize>,
+    ) -> PyResult<()> {
+        let variant = Variant::from_name(variant).ok_or_else(|| {
+            PyErr::new::<exceptions::ValueError, _>(format!("unknown variant {:?}", variant))
+        })?;
+        if !(2..=MAXPLAYERS).contains(&nplayer) {
+            return Err(PyErr::new::<exceptions::ValueError, _>(format!(
+                "{} players is not supported",
+                nplayer
+            )));
+        }
+        if history == Some(0) {
+            return Err(PyErr::new::<exceptions::ValueError, _>(
+                "the history keeps at least the last action",
+            ));
+        }
+        let mut batch = Batch::new(size, nplayer, &variant, seed);
+        batch.set_history_capacity(history);
+        obj.init(BatchGame { batch });
+        Ok(())
+    }
+
+    #[getter]
+    fn get_size(&self) -> usize {
+        self.batch.len()
+    }
+
+    // (size, width) float32, the observation of the player to move in every game
+    fn observations(&mut self, py: Python) -> Py<PyArray2<f32>> {
+        let batch = &mut self.batch;
+        let rows = py.allow_threads(|| batch.observations());
+        rows.into_pyarray(py).to_owned()
+    }
+
+    fn legal_masks(&mut self, py: Python) -> Py<PyArray2<f32>> {
+        let batch = &mut self.batch;
+        let rows = py.allow_threads(|| batch.legal_masks());
+        rows.into_pyarray(py).to_owned()
+    }
+
+    // plays one action id per game, ignored in finished games, and returns the score increases;
+    // raises a ValueError without stepping any game if an action is illegal
+    fn step(&mut self, py: Python, actions: Vec<usize>) -> PyResult<Py<PyArray1<f32>>> {
+        if actions.len() != self.batch.len() {
+            return Err(PyErr::new::<exceptions::ValueError, _>(format!(
+                "{} actions for {} games",
+                actions.len(),
+                self.batch.len()
+            )));
+        }
+        let batch = &mut self.batch;
+        match py.allow_threads(|| batch.step(&actions)) {
+            Ok(rewards) => Ok(rewards.into_pyarray(py).to_owned()),
+            Err((game, err)) => Err(super::illegal_move(format!("game {}: {}", game, err), &err)),
+        }
+    }
+
+    fn done(&self) -> Vec<bool> {
+        self.batch.done()
+    }
+
+    fn scores(&self) -> Vec<usize> {
+        self.batch.scores()
+    }
+
+    // deals a new game in place of every finished one, reusing its buffers, and returns their
+    // indices; the new deals follow from the seed of the batch
+    fn reset_done(&mut self, py: Python) -> Vec<usize> {
+        let batch = &mut self.batch;
+        py.allow_threads(|| batch.reset_done())
+    }
+
+    // plays random legal actions to the end of every game, returns the final scores
+    fn random_rollouts(&mut self, py: Python) -> Vec<usize> {
+        let batch = &mut self.batch;
+        py.allow_threads(|| batch.random_rollouts());
+        self.batch.scores()
+    }
+
+    fn replays(&self) -> Vec<String> {
+        self.batch
+            .states()
+            .map(|state| Replay::from_state(state).to_json())
+            .collect()
+    }
+}
@@ -0,0 +1,75 @@
+// structured observation of one player for symbolic bots written in python
+use super::view;
+use crate::slots::SlotOrder;
+use crate::state::{State, NACTIONS};
+use pyo3::prelude::*;
+use std::collections::HashMap;
+
+#[pyclass]
+pub struct Observation {
+    #[pyo3(get)]
+    player: usize,
+    #[pyo3(get)]
+    turn: usize,
+    // the cards of every hand, None for the own hand, e.g. [None, ["1r", "4g", ...]]
+    #[pyo3(get)]
+    hands: Vec<Option<Vec<String>>>,
+    // what every player was told about their cards, (color, value) per slot, None when unknown
+    #[pyo3(get)]
+    knowledge: Vec<Vec<(Option<String>, Option<usize>)>>,
+    // number of cards played per suit, e.g. {"r": 2, "g": 0, ...}
+    #[pyo3(get)]
+    fireworks: HashMap<String, usize>,
+    #[pyo3(get)]
+    clues: usize,
+    #[pyo3(get)]
+    mistakes: usize,
+    #[pyo3(get)]
+    discard_counts: HashMap<String, usize>,
+    #[pyo3(get)]
+    deck_size: usize,
+    #[pyo3(get)]
+    legal_actions: Vec<usize>,
+}
+
+impl Observation {
+    // slots and action ids follow `order`
+    pub fn new(state: &State, player: usize, order: SlotOrder) -> Observation {
+        let hands = view::hands(state, order)
+            .into_iter()
+            .enumerate()
+            .map(|(i, hand)| if i == player { None } else { Some(hand) })
+            .collect();
+        let knowledge = view::knowledge(state, order);
+        let fireworks = state
+            .variant()
+            .suits()
+            .iter()
+            .zip(state.table())
+            .map(|(suit, &n)| (format!("{}", suit), n))
+            .collect();
+        let mut discard_counts = HashMap::new();
+        for card in state.discard() {
+            *discard_counts.entry(format!("{}", card)).or_insert(0) += 1;
+        }
+        // legality only depends on what the player to move can see
+        let legal_actions = if state.turn() % state.players().len() == player {
+            let mask = state.legal_mask_in(order);
+            (0..NACTIONS).filter(|&id| mask[id] > 0.0).collect()
+        } else {
+            Vec::new()
+        };
+        Observation {
+            player,
+            turn: *state.turn(),
+            hands,
+            knowledge,
+            fireworks,
+            clues: *state.clues(),
+            mistakes: *state.mistakes(),
+            discard_counts,
+            deck_size: state.deck().len(),
+            legal_actions,
+        }
+    }
+}
@@ -0,0 +1,255 @@
+// what python code may read of a game, by who is looking: a player sees the hands of the others, a
+// spectator every hand and only an omniscient view, for debugging, the deck as well, so that a
+// benchmark handed a view cannot read what its seat does not see
+use super::observation::Observation;
+use crate::slots::SlotOrder;
+use crate::state::{Card, State, NACTIONS};
+use numpy::{IntoPyArray, PyArray2, PyArray3};
+use pyo3::prelude::*;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use std::collections::HashMap;
+
+// what everyone sees
+#[pyclass]
+#[derive(Clone)]
+pub struct Board {
+    #[pyo3(get)]
+    turn: usize,
+    #[pyo3(get)]
+    current_player: usize,
+    #[pyo3(get)]
+    clues: usize,
+    #[pyo3(get)]
+    mistakes: usize,
+    #[pyo3(get)]
+    score: usize,
+    #[pyo3(get)]
+    gameover: bool,
+    // number of cards played per suit, e.g. {"r": 2, "g": 0, ...}
+    #[pyo3(get)]
+    fireworks: HashMap<String, usize>,
+    // (color, value) of the discarded cards in the order of the discards
+    #[pyo3(get)]
+    discard_pile: Vec<(String, usize)>,
+    #[pyo3(get)]
+    deck_size: usize,
+    // what every player was told about their cards, (color, value) per slot, None when unknown
+    #[pyo3(get)]
+    knowledge: Vec<Vec<(Option<String>, Option<usize>)>>,
+    // the ids of the actions of the player to move, empty once the game is over
+    #[pyo3(get)]
+    legal_actions: Vec<usize>,
+}
+
+#[pyclass]
+pub struct PlayerView {
+    #[pyo3(get)]
+    player: usize,
+    #[pyo3(get)]
+    board: Board,
+    // None for the own hand, e.g. [None, ["1r", "4g", ...]]
+    #[pyo3(get)]
+    hands: Vec<Option<Vec<String>>>,
+    // what the methods below compute from, only through what `player` sees; not readable from
+    // python
+    state: State,
+    slots: SlotOrder,
+}
+
+#[pyclass]
+pub struct SpectatorView {
+    #[pyo3(get)]
+    board: Board,
+    #[pyo3(get)]
+    hands: Vec<Vec<String>>,
+}
+
+#[pyclass]
+pub struct OmniscientView {
+    #[pyo3(get)]
+    board: Board,
+    #[pyo3(get)]
+    hands: Vec<Vec<String>>,
+    // the cards still to draw, the next one last
+    #[pyo3(get)]
+    deck: Vec<String>,
+}
+
+// slots and action ids follow `order`
+impl Board {
+    pub fn new(state: &State, order: SlotOrder) -> Board {
+        let n = state.players().len();
+        Board {
+            turn: *state.turn(),
+            current_player: state.turn() % n,
+            clues: *state.clues(),
+            mistakes: *state.mistakes(),
+            score: state.score(),
+            gameover: state.gameover(),
+            fireworks: state
+                .variant()
+                .suits()
+                .iter()
+                .zip(state.table())
+                .map(|(suit, &n)| (format!("{}", suit), n))
+                .collect(),
+            discard_pile: state.discard().iter().map(pair).collect(),
+            deck_size: state.deck().len(),
+            knowledge: knowledge(state, order),
+            legal_actions: if state.gameover() {
+                Vec::new()
+            } else {
+                let mask = state.legal_mask_in(order);
+                (0..NACTIONS).filter(|&id| mask[id] > 0.0).collect()
+            },
+        }
+    }
+}
+
+impl PlayerView {
+    pub fn new(state: &State, player: usize, order: SlotOrder) -> PlayerView {
+        let hands = hands(state, order)
+            .into_iter()
+            .enumerate()
+            .map(|(i, hand)| if i == player { None } else { Some(hand) })
+            .collect();
+        PlayerView {
+            player,
+            board: Board::new(state, order),
+            hands,
+            state: state.fork(),
+            slots: order,
+        }
+    }
+}
+
+// what the game computes for a player, `Game.beliefs(player)` and the like are these
+#[pymethods]
+impl PlayerView {
+    // probability of every (suit, value) for each slot of the own hand, shape (slots, suits, 5)
+    pub fn beliefs(&self, py: Python) -> Py<PyArray3<f32>> {
+        let (state, player) = (&self.state, self.player);
+        let beliefs = py.allow_threads(|| state.beliefs(player));
+        beliefs.into_pyarray(py).to_owned()
+    }
+
+    // 1 for every card each slot of the own hand may be given the clues, negative information
+    // included, shape (slots, suits, 5)
+    pub fn constraints(&self, py: Python) -> Py<PyArray3<f32>> {
+        let (state, player) = (&self.state, self.player);
+        let constraints = py.allow_threads(|| state.constraints(player));
+        constraints.into_pyarray(py).to_owned()
+    }
+
+    // probability under the public beliefs that each slot of the own hand is playable, dead or
+    // critical, shape (slots, 3)
+    pub fn playability(&self, py: Python) -> Py<PyArray2<f32>> {
+        self.state
+            .playability(self.player)
+            .into_pyarray(py)
+            .to_owned()
+    }
+
+    // for each teammate, in seat order after the player, the number of playable cards, whether
+    // the card on their chop is critical and the number of fully clued cards, shape
+    // (players - 1, 3)
+    pub fn teammate_summary(&self, py: Python) -> Py<PyArray2<f32>> {
+        self.state
+            .teammate_summary(self.player)
+            .into_pyarray(py)
+            .to_owned()
+    }
+
+    // share of the copies of each card that the player cannot see (deck and own hand), shape
+    // (suits, 5)
+    pub fn unseen_counts(&self, py: Python) -> Py<PyArray2<f32>> {
+        self.state
+            .unseen_counts(self.player)
+            .into_pyarray(py)
+            .to_owned()
+    }
+
+    // the oldest unclued card of the own hand, None if every card is clued
+    pub fn chop(&self) -> Option<usize> {
+        let hand = self.state.players()[self.player].len();
+        let order = self.slots.for_state(&self.state);
+        self.state.chop(self.player).map(|i| order.convert(i, hand))
+    }
+
+    // an own hand agreeing with the clues and the cards the player sees, e.g. ["1r", "4g", ...],
+    // None when no hand was found
+    #[args(seed = "None")]
+    pub fn sample_hidden(&self, seed: Option<u64>) -> Option<Vec<String>> {
+        let mut rng = match seed {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => StdRng::from_entropy(),
+        };
+        let hand = self.state.sample_hidden(self.player, &mut rng)?;
+        let order = self.slots.for_state(&self.state);
+        let hand = (0..hand.len())
+            .map(|i| format!("{}", hand[order.convert(i, hand.len())]))
+            .collect();
+        Some(hand)
+    }
+
+    // the structured observation of the player
+    pub fn observation(&self) -> Observation {
+        Observation::new(&self.state, self.player, self.slots)
+    }
+}
+
+impl SpectatorView {
+    pub fn new(state: &State, order: SlotOrder) -> SpectatorView {
+        SpectatorView {
+            board: Board::new(state, order),
+            hands: hands(state, order),
+        }
+    }
+}
+
+impl OmniscientView {
+    pub fn new(state: &State, order: SlotOrder) -> OmniscientView {
+        OmniscientView {
+            board: Board::new(state, order),
+            hands: hands(state, order),
+            deck: state.deck().iter().map(|x| format!("{}", x)).collect(),
+        }
+    }
+}
+
+pub fn hands(state: &State, order: SlotOrder) -> Vec<Vec<String>> {
+    let engine = order.for_state(state);
+    state
+        .players()
+        .iter()
+        .map(|hand| {
+            (0..hand.len())
+                .map(|i| format!("{}", hand[engine.convert(i, hand.len())]))
+                .collect()
+        })
+        .collect()
+}
+
+pub fn knowledge(state: &State, order: SlotOrder) -> Vec<Vec<(Option<String>, Option<usize>)>> {
+    let engine = order.for_state(state);
+    state
+        .knowledge()
+        .iter()
+        .map(|slots| {
+            (0..slots.len())
+                .map(|i| {
+                    let k = slots[engine.convert(i, slots.len())];
+                    (
+                        k.color.map(|c| format!("{}", c)),
+                        k.value.map(|v| v.index() + 1),
+                    )
+                })
+                .collect()
+        })
+        .collect()
+}
+
+pub fn pair(card: &Card) -> (String, usize) {
+    (card.color().to_string(), card.value().index() + 1)
+}
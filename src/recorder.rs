@@ -0,0 +1,94 @@
+use crate::encoder::{Encoder, EncoderBuilder};
+use crate::slots::SlotOrder;
+use crate::state::{ActionRequest, IllegalMoves, State, NACTIONS};
+use ndarray::{Array1, Array2};
+
+// the (observation, action, reward, legal mask, done) of every step, stacked at the end
+pub struct Trajectory {
+    pub observations: Array2<f32>,
+    pub masks: Array2<f32>,
+    pub actions: Array1<i64>,
+    pub rewards: Array1<f32>,
+    pub dones: Array1<bool>,
+}
+
+// accumulates the steps of a game, seen by the player to move with `encoder` and with the
+// positions in the order `slots`
+pub struct Recorder {
+    encoder: Encoder,
+    slots: SlotOrder,
+    observations: Vec<f32>,
+    masks: Vec<f32>,
+    actions: Vec<i64>,
+    rewards: Vec<f32>,
+    dones: Vec<bool>,
+}
+
+impl Recorder {
+    pub fn new(encoder: Encoder, slots: SlotOrder) -> Recorder {
+        Recorder {
+            encoder,
+            slots,
+            observations: Vec::new(),
+            masks: Vec::new(),
+            actions: Vec::new(),
+            rewards: Vec::new(),
+            dones: Vec::new(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.actions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.actions.is_empty()
+    }
+
+    // applies `action` (in the engine order) and records it with the score increase as reward,
+    // nothing is recorded if it is illegal
+    pub fn apply(&mut self, state: &mut State, action: &ActionRequest) -> Result<(), IllegalMoves> {
+        let player = state.turn() % state.players().len();
+        let hand = state.current_hand();
+        let observation = match self.encoder {
+            Encoder::V1 => state.encode_in(player, self.slots),
+            Encoder::V2 => EncoderBuilder::v2()
+                .slots(self.slots)
+                .build()
+                .encode(state, player),
+            encoder => encoder.encode(state, player),
+        };
+        let mask = state.legal_mask_in(self.slots);
+        let score = state.score();
+        state.apply(action)?;
+        self.observations.extend(observation.iter());
+        self.masks.extend(mask.iter());
+        self.actions
+            .push(self.slots.for_state(state).convert_id(action.id(), hand) as i64);
+        self.rewards.push((state.score() - score) as f32);
+        self.dones.push(state.gameover());
+        Ok(())
+    }
+
+    // adds to the reward of the last step, e.g. an intrinsic reward
+    pub fn add_reward(&mut self, reward: f32) {
+        if let Some(last) = self.rewards.last_mut() {
+            *last += reward;
+        }
+    }
+
+    // the steps recorded so far, the recorder starts again empty
+    pub fn take(&mut self) -> Trajectory {
+        let n = self.actions.len();
+        let dim = self.observations.len().checked_div(n).unwrap_or(0);
+        let observations = std::mem::take(&mut self.observations);
+        let masks = std::mem::take(&mut self.masks);
+        Trajectory {
+            observations: Array2::from_shape_vec((n, dim), observations).unwrap(),
+            masks: Array2::from_shape_vec((n, NACTIONS), masks).unwrap(),
+            actions: Array1::from(std::mem::take(&mut self.actions)),
+            rewards: Array1::from(std::mem::take(&mut self.rewards)),
+            dones: Array1::from(std::mem::take(&mut self.dones)),
+        }
+    }
+}
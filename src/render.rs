@@ -0,0 +1,78 @@
+use crate::state::{State, MAXCLUES, MAXMISTAKES};
+use std::fmt::Write;
+
+impl State {
+    pub fn render(&self) -> String {
+        self.render_view(None)
+    }
+
+    // the viewer's own cards are replaced by what they have been told about them
+    pub fn render_for(&self, player: usize) -> String {
+        self.render_view(Some(player))
+    }
+
+    fn render_view(&self, viewer: Option<usize>) -> String {
+        let mut out = String::new();
+        let current = self.turn() % self.players().len();
+
+        if self.gameover() {
+            writeln!(
+                out,
+                "turn {} - game over, score {}",
+                self.turn(),
+                self.score()
+            )
+            .unwrap();
+        } else {
+            writeln!(out, "turn {} - P{} to play", self.turn(), current + 1).unwrap();
+        }
+        writeln!(
+            out,
+            "clues {}/{}  strikes {}/{}  deck {}",
+            self.clues(),
+            MAXCLUES,
+            self.mistakes(),
+            MAXMISTAKES,
+            self.deck().len()
+        )
+        .unwrap();
+
+        write!(out, "fireworks").unwrap();
+        for (color, n) in self.variant().suits().iter().zip(self.table().iter()) {
+            write!(out, " {}{}", color, n).unwrap();
+        }
+        writeln!(out).unwrap();
+
+        for (i, (cards, knowledge)) in self.players().iter().zip(self.knowledge()).enumerate() {
+            let marker = if i == current { ">" } else { " " };
+            write!(out, "P{} {} ", i + 1, marker).unwrap();
+            if viewer != Some(i) {
+                for card in cards {
+                    write!(out, " {}", card).unwrap();
+                }
+                write!(out, "\n     ").unwrap();
+            }
+            for k in knowledge {
+                write!(out, " {}", k).unwrap();
+            }
+            writeln!(out).unwrap();
+        }
+
+        write!(out, "discard").unwrap();
+        for &color in self.variant().suits() {
+            write!(out, "  {}:", color).unwrap();
+            let mut values: Vec<String> = self
+                .discard()
+                .iter()
+                .filter(|card| card.color() == color)
+                .map(|card| format!("{}", card.value()))
+                .collect();
+            values.sort();
+            for value in values {
+                write!(out, " {}", value).unwrap();
+            }
+        }
+        writeln!(out).unwrap();
+        out
+    }
+}
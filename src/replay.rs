@@ -0,0 +1,606 @@
+#![allow(dead_code)]
+
+use crate::modifier::{self, Modifiers};
+use crate::rules;
+use crate::slots::SlotOrder;
+use crate::state::{ActionRequest, Card, IllegalMoves, State};
+use crate::variant::Variant;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+#[derive(Debug)]
+pub enum ReplayError {
+    Io(std::io::Error),
+    Json(serde_json::Error),
+    Invalid(String),
+    // `field` locates the faulty entry, e.g. "deck[12]" or "actions[7].position"
+    Field { field: String, message: String },
+    Illegal { turn: usize, error: IllegalMoves },
+}
+
+fn field_error(field: String, message: String) -> ReplayError {
+    ReplayError::Field { field, message }
+}
+
+impl From<std::io::Error> for ReplayError {
+    fn from(err: std::io::Error) -> ReplayError {
+        ReplayError::Io(err)
+    }
+}
+
+impl From<serde_json::Error> for ReplayError {
+    fn from(err: serde_json::Error) -> ReplayError {
+        ReplayError::Json(err)
+    }
+}
+
+const FIELDS: [&str; 9] = [
+    "players",
+    "variant",
+    "modifiers",
+    "slots",
+    "deck",
+    "actions",
+    "names",
+    "notes",
+    "tags",
+];
+
+// what a player wrote down during the game, e.g. at the websocket server
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Note {
+    pub turn: usize,
+    pub seat: usize,
+    pub text: String,
+}
+
+// what `Replay::anonymized` does with the names of the players
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Names {
+    Keep,
+    // "seat 0", "seat 1", ...
+    Seats,
+    Remove,
+}
+
+impl Names {
+    pub fn from_name(name: &str) -> Option<Names> {
+        match name {
+            "keep" => Some(Names::Keep),
+            "seats" => Some(Names::Seats),
+            "remove" => Some(Names::Remove),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Anonymization {
+    pub names: Names,
+    pub keep_notes: bool,
+}
+
+impl Default for Anonymization {
+    fn default() -> Anonymization {
+        Anonymization {
+            names: Names::Seats,
+            keep_notes: false,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Replay {
+    pub players: usize,
+    #[serde(default = "Variant::standard")]
+    pub variant: Variant,
+    #[serde(default)]
+    pub modifiers: Vec<String>,
+    // convention of the positions in `actions`
+    #[serde(default)]
+    pub slots: SlotOrder,
+    pub deck: Vec<Card>,
+    pub actions: Vec<ActionRequest>,
+    // the players of a game between people, one per seat
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub names: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub notes: Vec<Note>,
+    // metadata of the game, e.g. the study or the condition it was collected for
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub tags: BTreeMap<String, String>,
+}
+
+impl Replay {
+    pub fn from_state(state: &State) -> Replay {
+        Replay {
+            players: state.players().len(),
+            variant: state.variant().clone(),
+            modifiers: state.modifiers().names(),
+            // the requests are in the engine order, oldest first when the cards are drawn last
+            slots: state.slot_order(),
+            deck: state.initial_deck().clone(),
+            actions: state.requests(),
+            names: None,
+            notes: Vec::new(),
+            tags: BTreeMap::new(),
+        }
+    }
+
+    // the same game without what identifies the people who played it, the seats, the actions and
+    // the tags are kept
+    pub fn anonymized(&self, anonymization: &Anonymization) -> Replay {
+        let names = match anonymization.names {
+            Names::Keep => self.names.clone(),
+            Names::Seats => self.names.as_ref().map(|names| {
+                (0..names.len())
+                    .map(|seat| format!("seat {}", seat))
+                    .collect()
+            }),
+            Names::Remove => None,
+        };
+        Replay {
+            players: self.players,
+            variant: self.variant.clone(),
+            modifiers: self.modifiers.clone(),
+            slots: self.slots,
+            deck: self.deck.clone(),
+            actions: self.actions.clone(),
+            names,
+            notes: if anonymization.keep_notes {
+                self.notes.clone()
+            } else {
+                Vec::new()
+            },
+            tags: self.tags.clone(),
+        }
+    }
+
+    pub fn from_json(json: &str) -> Result<Replay, ReplayError> {
+        Ok(serde_json::from_str(json)?)
+    }
+
+    // parses the json and repairs what can be, returns the repairs performed
+    pub fn from_json_tolerant(json: &str) -> Result<(Replay, Vec<String>), ReplayError> {
+        let mut value: serde_json::Value = serde_json::from_str(json)?;
+        let mut repairs = Vec::new();
+        if let Some(object) = value.as_object_mut() {
+            let unknown: Vec<String> = object
+                .keys()
+                .filter(|&key| !FIELDS.contains(&key.as_str()))
+                .cloned()
+                .collect();
+            for key in unknown {
+                object.remove(&key);
+                repairs.push(format!("ignored unknown field {:?}", key));
+            }
+        }
+        let mut replay: Replay = serde_json::from_value(value)?;
+        repairs.extend(replay.repair());
+        Ok((replay, repairs))
+    }
+
+    // fixes a truncated deck, 1-based positions and targets, and actions after the end of
+    // the game; returns a description of every repair
+    pub fn repair(&mut self) -> Vec<String> {
+        let mut repairs = Vec::new();
+
+        let mut missing = self.variant.deck();
+        for card in &self.deck {
+            if let Some(i) = missing.iter().position(|x| x == card) {
+                missing.remove(i);
+            }
+        }
+        if self.deck.len() < self.variant.deck_size() && !missing.is_empty() {
+            // the cards are drawn from the end: the missing ones go first after the deal, to be
+            // drawn last instead of replacing the next draws of the game
+            // the hand size of unknown modifiers is unknown, `validate` reports them
+            let hand_size = match modifier::resolve(&self.modifiers) {
+                Ok(modifiers) => Modifiers(modifiers).hand_size(self.players),
+                Err(_) => rules::hand_size(self.players),
+            };
+            let dealt = (self.players * hand_size).min(self.deck.len());
+            repairs.push(format!(
+                "put the {} cards missing from the deck under the deck",
+                missing.len()
+            ));
+            self.deck.splice(dealt..dealt, missing);
+        }
+
+        if let Ok((_, n)) = self.play_prefix() {
+            if n < self.actions.len() {
+                let shifted = self.shifted();
+                if let Ok((_, m)) = shifted.play_prefix() {
+                    if m > n {
+                        repairs
+                            .push("shifted 1-based positions and targets to 0-based".to_string());
+                        self.actions = shifted.actions;
+                    }
+                }
+            }
+        }
+
+        if let Ok((state, n)) = self.play_prefix() {
+            if n < self.actions.len() && state.gameover() {
+                repairs.push(format!(
+                    "dropped {} actions after the end of the game",
+                    self.actions.len() - n
+                ));
+                self.actions.truncate(n);
+            } else if n == self.actions.len() && !state.gameover() {
+                repairs.push(format!(
+                    "the game stops at turn {} before its end",
+                    state.turn()
+                ));
+            }
+        }
+        repairs
+    }
+
+    fn shifted(&self) -> Replay {
+        let shift = |x: usize| x.saturating_sub(1);
+        Replay {
+            players: self.players,
+            variant: self.variant.clone(),
+            modifiers: self.modifiers.clone(),
+            slots: self.slots,
+            deck: self.deck.clone(),
+            actions: self
+                .actions
+                .iter()
+                .map(|action| match *action {
+                    ActionRequest::Play { position } => ActionRequest::Play {
+                        position: shift(position),
+                    },
+                    ActionRequest::Discard { position } => ActionRequest::Discard {
+                        position: shift(position),
+                    },
+                    ActionRequest::ColorClue { target, color } => ActionRequest::ColorClue {
+                        target: shift(target),
+                        color,
+                    },
+                    ActionRequest::ValueClue { target, value } => ActionRequest::ValueClue {
+                        target: shift(target),
+                        value,
+                    },
+                })
+                .collect(),
+            names: self.names.clone(),
+            notes: self.notes.clone(),
+            tags: self.tags.clone(),
+        }
+    }
+
+    // plays the actions until the first one that fails, returns the state and how many were played
+    fn play_prefix(&self) -> Result<(State, usize), ReplayError> {
+        self.validate()?;
+        let mut state = self.initial_state()?;
+        for (turn, action) in self.actions.iter().enumerate() {
+            let action = &self
+                .slots
+                .for_state(&state)
+                .convert_request(action, state.current_hand());
+            if Replay::validate_action(&state, turn, action).is_err()
+                || state.apply(action).is_err()
+            {
+                return Ok((state, turn));
+            }
+        }
+        Ok((state, self.actions.len()))
+    }
+
+    fn initial_state(&self) -> Result<State, ReplayError> {
+        let modifiers = modifier::resolve(&self.modifiers).map_err(ReplayError::Invalid)?;
+        Ok(
+            State::from_deck_variant(self.players, self.variant.clone(), self.deck.clone())
+                .modified(modifiers),
+        )
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).unwrap()
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Replay, ReplayError> {
+        Replay::from_json(&fs::read_to_string(path)?)
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), ReplayError> {
+        Ok(fs::write(path, self.to_json())?)
+    }
+
+    // checks the players, the deck and the modifiers, the actions are checked while playing
+    pub fn validate(&self) -> Result<(), ReplayError> {
+        if self.players < 2 || self.players > 5 {
+            return Err(field_error(
+                "players".to_string(),
+                format!("{} players is not supported", self.players),
+            ));
+        }
+        let suits = self.variant.suits();
+        let mut counts = vec![[0; 5]; suits.len()];
+        for (i, card) in self.deck.iter().enumerate() {
+            let suit = match suits.iter().position(|&x| x == card.color()) {
+                Some(suit) => suit,
+                None => {
+                    return Err(field_error(
+                        format!("deck[{}]", i),
+                        format!(
+                            "card {} is not part of the {} variant",
+                            card,
+                            self.variant.name()
+                        ),
+                    ))
+                }
+            };
+            let count = &mut counts[suit][card.value().index()];
+            *count += 1;
+            if *count > card.value().copies() {
+                return Err(field_error(
+                    format!("deck[{}]", i),
+                    format!("more than {} copies of {}", card.value().copies(), card),
+                ));
+            }
+        }
+        if self.deck.len() != self.variant.deck().len() {
+            return Err(field_error(
+                "deck".to_string(),
+                format!(
+                    "{} cards instead of {}",
+                    self.deck.len(),
+                    self.variant.deck().len()
+                ),
+            ));
+        }
+        for (i, name) in self.modifiers.iter().enumerate() {
+            if modifier::from_name(name).is_none() {
+                return Err(field_error(
+                    format!("modifiers[{}]", i),
+                    format!("unknown rule modifier {:?}", name),
+                ));
+            }
+        }
+        if let Some(names) = &self.names {
+            if names.len() != self.players {
+                return Err(field_error(
+                    "names".to_string(),
+                    format!("{} names for {} players", names.len(), self.players),
+                ));
+            }
+        }
+        for (i, note) in self.notes.iter().enumerate() {
+            if note.seat >= self.players {
+                return Err(field_error(
+                    format!("notes[{}].seat", i),
+                    format!("no player {}", note.seat),
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    fn validate_action(
+        state: &State,
+        turn: usize,
+        action: &ActionRequest,
+    ) -> Result<(), ReplayError> {
+        let hand = state.players()[state.turn() % state.players().len()].len();
+        match *action {
+            ActionRequest::Play { position } | ActionRequest::Discard { position } => {
+                if position >= hand {
+                    return Err(field_error(
+                        format!("actions[{}].position", turn),
+                        format!("no position {} in a hand of {} cards", position, hand),
+                    ));
+                }
+            }
+            ActionRequest::ColorClue { target, color } => {
+                if target >= state.players().len() {
+                    return Err(field_error(
+                        format!("actions[{}].target", turn),
+                        format!("no player {}", target),
+                    ));
+                }
+                if !state.variant().colors().contains(&color) {
+                    return Err(field_error(
+                        format!("actions[{}].color", turn),
+                        format!(
+                            "no {} clue in the {} variant",
+                            color,
+                            state.variant().name()
+                        ),
+                    ));
+                }
+            }
+            ActionRequest::ValueClue { target, .. } => {
+                if target >= state.players().len() {
+                    return Err(field_error(
+                        format!("actions[{}].target", turn),
+                        format!("no player {}", target),
+                    ));
+                }
+            }
+        }
+        state
+            .check(action)
+            .map_err(|error| ReplayError::Illegal { turn, error })
+    }
+
+    // calls `f` before each action and returns the final state
+    pub fn play<F>(&self, f: F) -> Result<State, ReplayError>
+    where
+        F: FnMut(&State, &ActionRequest),
+    {
+        self.play_upto(self.actions.len(), f)
+    }
+
+    // the state after the first `upto` actions, every one checked, to scrub through a game
+    pub fn state_at(&self, upto: usize) -> Result<State, ReplayError> {
+        if upto > self.actions.len() {
+            return Err(ReplayError::Invalid(format!(
+                "no turn {} in a game of {} actions",
+                upto,
+                self.actions.len()
+            )));
+        }
+        self.play_upto(upto, |_, _| {})
+    }
+
+    fn play_upto<F>(&self, upto: usize, mut f: F) -> Result<State, ReplayError>
+    where
+        F: FnMut(&State, &ActionRequest),
+    {
+        self.validate()?;
+        let mut state = self.initial_state()?;
+        for (turn, action) in self.actions.iter().take(upto).enumerate() {
+            let action = &self
+                .slots
+                .for_state(&state)
+                .convert_request(action, state.current_hand());
+            Replay::validate_action(&state, turn, action)?;
+            f(&state, action);
+            state
+                .apply(action)
+                .map_err(|error| ReplayError::Illegal { turn, error })?;
+        }
+        Ok(state)
+    }
+}
+
+impl State {
+    // the state after the first `upto` actions of a game of the standard variant dealt from
+    // `deck`, every action checked; see `Replay::state_at` for the other variants
+    pub fn replay(
+        nplayer: usize,
+        deck: Vec<Card>,
+        actions: &[ActionRequest],
+        upto: usize,
+    ) -> Result<State, ReplayError> {
+        let replay = Replay {
+            players: nplayer,
+            variant: Variant::standard(),
+            modifiers: Vec::new(),
+            slots: SlotOrder::NewestFirst,
+            deck,
+            actions: actions.to_vec(),
+            names: None,
+            notes: Vec::new(),
+            tags: BTreeMap::new(),
+        };
+        replay.state_at(upto)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Replay;
+    use crate::agents::{Agent, ConventionBot, Observation};
+    use crate::modifier;
+    use crate::state::{ActionRequest, State};
+
+    // the first actions of a game whose deck lost `lost` cards of the drawing pile, the last drawn
+    fn cut(lost: usize) -> (State, Replay) {
+        let mut state = State::with_seed(3, 4);
+        for _ in 0..8 {
+            let action = ConventionBot.act(&Observation::new(&state));
+            state.apply(&action).unwrap();
+        }
+        let mut replay = Replay::from_state(&state);
+        let dealt = 3 * 5;
+        replay.deck.drain(dealt..dealt + lost);
+        (state, replay)
+    }
+
+    // a game played by the convention bot, with its history compacted as it goes
+    fn compacted(modifiers: &[&str]) -> State {
+        let mut state = State::with_seed(3, 7).modified(
+            modifiers
+                .iter()
+                .filter_map(|x| modifier::from_name(x))
+                .collect(),
+        );
+        state.set_history_capacity(Some(4));
+        while !state.gameover() {
+            let action = ConventionBot.act(&Observation::new(&state));
+            state.apply(&action).unwrap();
+        }
+        state
+    }
+
+    #[test]
+    fn replays_of_compacted_games_have_every_action() {
+        for modifiers in [&[][..], &["draw_last"], &["self_clue", "strikeout_zero"]] {
+            let state = compacted(modifiers);
+            assert!(!state.compacted().is_empty());
+            let replay = Replay::from_state(&state);
+            assert_eq!(replay.actions.len(), *state.turn());
+            let replayed = replay.play(|_, _| {}).unwrap();
+            assert_eq!(replayed.state_hash(), state.state_hash());
+            assert_eq!(replayed.final_score(), state.final_score());
+        }
+    }
+
+    #[test]
+    fn repairs_shift_positions_and_drop_the_extra_actions() {
+        let state = compacted(&[]);
+        let whole = Replay::from_state(&state);
+
+        // 1-based positions and targets
+        let mut replay = Replay::from_state(&state);
+        replay.actions = replay
+            .actions
+            .iter()
+            .map(|action| match *action {
+                ActionRequest::Play { position } => ActionRequest::Play {
+                    position: position + 1,
+                },
+                ActionRequest::Discard { position } => ActionRequest::Discard {
+                    position: position + 1,
+                },
+                ActionRequest::ColorClue { target, color } => ActionRequest::ColorClue {
+                    target: target + 1,
+                    color,
+                },
+                ActionRequest::ValueClue { target, value } => ActionRequest::ValueClue {
+                    target: target + 1,
+                    value,
+                },
+            })
+            .collect();
+        assert_eq!(
+            replay.repair(),
+            vec!["shifted 1-based positions and targets to 0-based"]
+        );
+        assert_eq!(replay.actions, whole.actions);
+
+        // actions after the end of the game
+        let mut replay = Replay::from_state(&state);
+        replay.actions.push(ActionRequest::Play { position: 0 });
+        assert_eq!(
+            replay.repair(),
+            vec!["dropped 1 actions after the end of the game"]
+        );
+        assert_eq!(replay.actions, whole.actions);
+
+        // a consistent replay is left alone
+        let mut replay = Replay::from_state(&state);
+        assert!(replay.repair().is_empty());
+    }
+
+    #[test]
+    fn repaired_decks_keep_the_next_draws() {
+        let (state, mut replay) = cut(3);
+        let repairs = replay.repair();
+        assert!(repairs[0].contains("3 cards missing"), "{:?}", repairs);
+        assert_eq!(replay.deck.len(), state.variant().deck_size());
+        let repaired = replay.play(|_, _| {}).unwrap();
+        // the same game, only the order of the cards drawn last may differ
+        assert_eq!(repaired.public_hash(), state.public_hash());
+        assert_eq!(repaired.players(), state.players());
+        assert_eq!(repaired.deck()[3..], state.deck()[3..]);
+    }
+}
@@ -0,0 +1,657 @@
+// multiplayer games over websocket, built with the `websocket` feature and served by the
+// `hanabi-server` binary
+//
+// one json text message per event, after the `Hello` of both sides (see `protocol`): a client
+// creates a room, joins it and is given the first free seat, the game starts once every seat is
+// taken; every seat then receives the numbered updates of the game's `Session` and what it sees
+// (the message of an external bot, see `external`) after each action, and only the player to move
+// can submit, the action being checked by `State`; a seat left during the game can be taken back
+// with its number and the token given when it was joined, the client then resumes from the last
+// update it acknowledged
+//
+// the replay of a finished game holds the names of its players and their notes, which are only
+// saved (see `Replay::anonymized` before sharing them), the players receive it without the notes
+//
+// in collection mode (see `Collection`), for imitation learning on human games, a client accepts
+// a consent banner before joining a room and every finished game is also exported anonymized
+use crate::agents::Observation;
+use crate::external::Message as Observed;
+use crate::manifest::fnv1a;
+use crate::protocol::{Agreement, Hello, Message as Update, Session, Submitted};
+use crate::replay::{Anonymization, Names, Note, Replay};
+use crate::server::lock;
+use crate::state::{ActionRequest, State};
+use crate::variant::Variant;
+use rand::random;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::io::{self, ErrorKind};
+use std::net::{TcpListener, TcpStream};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+use tungstenite::{Message, WebSocket};
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Request {
+    Create {
+        players: usize,
+        #[serde(default = "Variant::standard")]
+        variant: Variant,
+        // a random deal when missing
+        seed: Option<u64>,
+        // metadata kept with the replay
+        #[serde(default)]
+        tags: BTreeMap<String, String>,
+    },
+    // accepts the consent banner of the collection mode
+    Consent,
+    Join {
+        room: usize,
+        name: String,
+        // the seat to take back after a reconnection with the token of its `Joined`, the first
+        // free one when missing
+        #[serde(default)]
+        seat: Option<usize>,
+        #[serde(default)]
+        token: Option<String>,
+    },
+    // the action of the seat, `seq` is the number of updates it has seen
+    Submit {
+        seq: u64,
+        action: ActionRequest,
+    },
+    // every update up to `seq` was received
+    Ack {
+        seq: u64,
+    },
+    // resends the updates of the seat that were not acknowledged
+    Resume,
+    // kept with the replay, not sent to the others
+    Note {
+        text: String,
+    },
+}
+
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum Event<'a> {
+    Consent {
+        text: &'a str,
+    },
+    Created {
+        room: usize,
+    },
+    // `token` takes the seat back after a disconnection
+    Joined {
+        room: usize,
+        seat: usize,
+        token: &'a str,
+    },
+    // the names of the seats, null for a free seat
+    Seats {
+        seats: Vec<Option<String>>,
+    },
+    Observation {
+        seat: usize,
+        observation: Observed,
+    },
+    Gameover {
+        score: usize,
+        replay: &'a str,
+    },
+    Error {
+        message: String,
+    },
+}
+
+impl<'a> Event<'a> {
+    fn to_json(&self) -> String {
+        serde_json::to_string(self).unwrap()
+    }
+}
+
+struct Room {
+    session: Session,
+    names: Vec<Option<String>>,
+    // where the events of each seat go
+    seats: Vec<Option<Sender<String>>>,
+    // the last name that took each seat once the game started
+    players: Vec<String>,
+    // the secret of every seat joined, none for a seat never taken
+    tokens: Vec<Option<String>>,
+    notes: Vec<Note>,
+    tags: BTreeMap<String, String>,
+    started: bool,
+}
+
+impl Room {
+    fn broadcast(&self, event: &Event) {
+        self.send_all(event.to_json());
+    }
+
+    fn send_all(&self, json: String) {
+        for seat in self.seats.iter().flatten() {
+            let _ = seat.send(json.clone());
+        }
+    }
+
+    fn replay(&self) -> Replay {
+        Replay {
+            names: Some(self.players.clone()),
+            notes: self.notes.clone(),
+            tags: self.tags.clone(),
+            ..Replay::from_state(self.session.state())
+        }
+    }
+
+    // the view of each seat, or the end of the game
+    fn update(&self) {
+        let state = self.session.state();
+        if state.gameover() {
+            let anonymization = Anonymization {
+                names: Names::Keep,
+                keep_notes: false,
+            };
+            let replay = self.replay().anonymized(&anonymization).to_json();
+            self.broadcast(&Event::Gameover {
+                score: state.score(),
+                replay: &replay,
+            });
+            return;
+        }
+        for (seat, sender) in self.seats.iter().enumerate() {
+            if let Some(sender) = sender {
+                let event = Event::Observation {
+                    seat,
+                    observation: Observed::new(&Observation::of(state, seat)),
+                };
+                let _ = sender.send(event.to_json());
+            }
+        }
+    }
+}
+
+// the data collection mode of the server
+pub struct Collection {
+    // sent to every client once connected, joining a room requires `{"type": "consent"}`
+    pub consent: Option<String>,
+    // added to the tags of every game, over those given at its creation
+    pub tags: BTreeMap<String, String>,
+    pub anonymization: Anonymization,
+    // where the anonymized games are exported, `<room>.json`, the input of `dataset`
+    pub dir: PathBuf,
+}
+
+impl Collection {
+    // the tags of a game, with the hash of the consent banner its players accepted
+    fn tags(&self, mut tags: BTreeMap<String, String>) -> BTreeMap<String, String> {
+        tags.extend(self.tags.clone());
+        if let Some(text) = &self.consent {
+            tags.insert(
+                "consent".to_string(),
+                format!("{:016x}", fnv1a(text.as_bytes())),
+            );
+        }
+        tags
+    }
+}
+
+#[derive(Clone, Default)]
+struct Rooms {
+    rooms: Arc<Mutex<HashMap<usize, Room>>>,
+    // ids are not reused, a client never joins the room of an old id by mistake
+    next: Arc<AtomicUsize>,
+    // where the finished games are saved, `<room>.json`
+    games: Option<PathBuf>,
+    collection: Option<Arc<Collection>>,
+}
+
+impl Rooms {
+    // saves a finished game, and exports it in collection mode
+    fn save(&self, id: usize, room: &Room) -> Result<(), String> {
+        let replay = room.replay();
+        if let Some(games) = &self.games {
+            replay
+                .save(games.join(format!("{}.json", id)))
+                .map_err(|err| format!("cannot save the game: {:?}", err))?;
+        }
+        if let Some(collection) = &self.collection {
+            replay
+                .anonymized(&collection.anonymization)
+                .save(collection.dir.join(format!("{}.json", id)))
+                .map_err(|err| format!("cannot export the game: {:?}", err))?;
+        }
+        Ok(())
+    }
+}
+
+// a connection, its seat once it joined a room
+struct Client {
+    rooms: Rooms,
+    events: Sender<String>,
+    seat: Option<(usize, usize)>,
+    consented: bool,
+    // what the handshake allows, the variants of the rooms it creates
+    agreement: Agreement,
+}
+
+// serves forever on `address`, the finished games are saved in `games` if given and exported
+// as described by `collection`
+pub fn serve(
+    address: &str,
+    games: Option<PathBuf>,
+    collection: Option<Collection>,
+) -> io::Result<()> {
+    for dir in games.iter().chain(collection.iter().map(|x| &x.dir)) {
+        fs::create_dir_all(dir)?;
+    }
+    let listener = TcpListener::bind(address)?;
+    let rooms = Rooms {
+        games,
+        collection: collection.map(Arc::new),
+        ..Rooms::default()
+    };
+    listen(listener, rooms)
+}
+
+fn listen(listener: TcpListener, rooms: Rooms) -> io::Result<()> {
+    for stream in listener.incoming() {
+        let stream = stream?;
+        let rooms = rooms.clone();
+        thread::spawn(move || {
+            // a client that goes away only ends its own connection
+            let _ = handle(stream, rooms);
+        });
+    }
+    Ok(())
+}
+
+fn handle(stream: TcpStream, rooms: Rooms) -> Result<(), tungstenite::Error> {
+    let mut socket = tungstenite::accept(stream).map_err(|err| match err {
+        tungstenite::HandshakeError::Failure(err) => err,
+        tungstenite::HandshakeError::Interrupted(_) => {
+            tungstenite::Error::Io(io::Error::new(ErrorKind::WouldBlock, "handshake"))
+        }
+    })?;
+    // the socket alternates between sending the pending events and waiting for a message
+    socket
+        .get_ref()
+        .set_read_timeout(Some(Duration::from_millis(20)))?;
+    let hello = Hello::local();
+    socket.send(Message::text(hello.to_json()))?;
+    let theirs = loop {
+        match read(&mut socket)? {
+            Some(Message::Text(text)) => break text,
+            Some(Message::Close(_)) => return Ok(()),
+            _ => {}
+        }
+    };
+    let agreement = match Hello::from_json(theirs.as_str()).and_then(|x| hello.negotiate(&x)) {
+        Ok(agreement) => agreement,
+        Err(err) => {
+            let message = format!("{}", err);
+            socket.send(Message::text(Event::Error { message }.to_json()))?;
+            return socket.close(None);
+        }
+    };
+    let consent = rooms.collection.as_ref().and_then(|x| x.consent.clone());
+    if let Some(text) = &consent {
+        socket.send(Message::text(Event::Consent { text }.to_json()))?;
+    }
+    let (events, pending) = channel();
+    let mut client = Client {
+        rooms,
+        events,
+        seat: None,
+        consented: consent.is_none(),
+        agreement,
+    };
+    let result = client.run(&mut socket, &pending);
+    client.leave();
+    result
+}
+
+// the next message, none when the read timed out
+fn read(socket: &mut WebSocket<TcpStream>) -> Result<Option<Message>, tungstenite::Error> {
+    match socket.read() {
+        Ok(message) => Ok(Some(message)),
+        Err(tungstenite::Error::Io(ref err))
+            if matches!(err.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) =>
+        {
+            Ok(None)
+        }
+        Err(err) => Err(err),
+    }
+}
+
+impl Client {
+    fn run(
+        &mut self,
+        socket: &mut WebSocket<TcpStream>,
+        pending: &Receiver<String>,
+    ) -> Result<(), tungstenite::Error> {
+        loop {
+            while let Ok(json) = pending.try_recv() {
+                socket.send(Message::text(json))?;
+            }
+            match read(socket)? {
+                Some(Message::Text(text)) => {
+                    let event = match serde_json::from_str::<Request>(text.as_str()) {
+                        Ok(request) => self.request(request),
+                        Err(err) => Err(format!("invalid request: {}", err)),
+                    };
+                    if let Err(message) = event {
+                        socket.send(Message::text(Event::Error { message }.to_json()))?;
+                    }
+                }
+                Some(Message::Close(_)) => return Ok(()),
+                _ => {}
+            }
+        }
+    }
+
+    // the events are sent through the rooms, only the errors are returned
+    fn request(&mut self, request: Request) -> Result<(), String> {
+        match request {
+            Request::Create {
+                players,
+                variant,
+                seed,
+                tags,
+            } => {
+                if !(2..=5).contains(&players) {
+                    return Err(format!("{} players is not supported", players));
+                }
+                if !self.agreement.variants.iter().any(|x| x == variant.name()) {
+                    return Err(format!("variant {} was not agreed on", variant.name()));
+                }
+                let tags = match &self.rooms.collection {
+                    Some(collection) => collection.tags(tags),
+                    None => tags,
+                };
+                let room = self.rooms.next.fetch_add(1, Ordering::Relaxed);
+                lock(&self.rooms.rooms).insert(
+                    room,
+                    Room {
+                        session: Session::new(State::with_variant(players, variant, seed)),
+                        names: vec![None; players],
+                        seats: vec![None; players],
+                        players: vec![String::new(); players],
+                        tokens: vec![None; players],
+                        notes: Vec::new(),
+                        tags,
+                        started: false,
+                    },
+                );
+                let _ = self.events.send(Event::Created { room }.to_json());
+                Ok(())
+            }
+            Request::Consent => {
+                self.consented = true;
+                Ok(())
+            }
+            Request::Join {
+                room: id,
+                name,
+                seat,
+                token,
+            } => {
+                if self.seat.is_some() {
+                    return Err("already seated".to_string());
+                }
+                if !self.consented {
+                    return Err("the consent banner has not been accepted".to_string());
+                }
+                let mut rooms = lock(&self.rooms.rooms);
+                let room = rooms.get_mut(&id).ok_or(format!("no room {}", id))?;
+                let seat = match seat {
+                    Some(seat) if seat >= room.seats.len() => {
+                        return Err(format!("no seat {} in room {}", seat, id))
+                    }
+                    Some(seat) if room.seats[seat].is_some() => {
+                        return Err(format!("seat {} of room {} is taken", seat, id))
+                    }
+                    Some(seat) if room.tokens[seat].is_some() && room.tokens[seat] != token => {
+                        return Err(format!("wrong token for seat {} of room {}", seat, id))
+                    }
+                    Some(seat) => seat,
+                    None => room
+                        .tokens
+                        .iter()
+                        .position(|x| x.is_none())
+                        .ok_or(format!("room {} is full", id))?,
+                };
+                let token = room.tokens[seat]
+                    .get_or_insert_with(|| {
+                        format!("{:016x}{:016x}", random::<u64>(), random::<u64>())
+                    })
+                    .clone();
+                room.seats[seat] = Some(self.events.clone());
+                room.players[seat] = name.clone();
+                room.names[seat] = Some(name);
+                self.seat = Some((id, seat));
+                let joined = Event::Joined {
+                    room: id,
+                    seat,
+                    token: &token,
+                };
+                let _ = self.events.send(joined.to_json());
+                room.broadcast(&Event::Seats {
+                    seats: room.names.clone(),
+                });
+                if room.started {
+                    room.update();
+                } else if room.seats.iter().all(|x| x.is_some()) {
+                    room.started = true;
+                    room.update();
+                }
+                Ok(())
+            }
+            Request::Submit { seq, action } => {
+                let (id, seat) = self.seat.ok_or("not seated")?;
+                let mut rooms = lock(&self.rooms.rooms);
+                let room = rooms.get_mut(&id).ok_or(format!("no room {}", id))?;
+                if !room.started {
+                    return Err("the game has not started".to_string());
+                }
+                let submitted = room
+                    .session
+                    .submit(seat, seq, action)
+                    .map_err(|err| format!("{:?}", err))?;
+                // a resent action was already broadcast
+                if let Submitted::Accepted(seq) = submitted {
+                    room.send_all(serde_json::to_string(&Update::Update { seq, action }).unwrap());
+                    room.update();
+                    if room.session.state().gameover() {
+                        self.rooms.save(id, room)?;
+                    }
+                }
+                Ok(())
+            }
+            Request::Ack { seq } => {
+                let (id, seat) = self.seat.ok_or("not seated")?;
+                let mut rooms = lock(&self.rooms.rooms);
+                let room = rooms.get_mut(&id).ok_or(format!("no room {}", id))?;
+                room.session.ack(seat, seq);
+                Ok(())
+            }
+            Request::Resume => {
+                let (id, seat) = self.seat.ok_or("not seated")?;
+                let rooms = lock(&self.rooms.rooms);
+                let room = rooms.get(&id).ok_or(format!("no room {}", id))?;
+                for update in room.session.missed(seat) {
+                    let _ = self.events.send(serde_json::to_string(&update).unwrap());
+                }
+                Ok(())
+            }
+            Request::Note { text } => {
+                let (id, seat) = self.seat.ok_or("not seated")?;
+                let mut rooms = lock(&self.rooms.rooms);
+                let room = rooms.get_mut(&id).ok_or(format!("no room {}", id))?;
+                let turn = *room.session.state().turn();
+                room.notes.push(Note { turn, seat, text });
+                Ok(())
+            }
+        }
+    }
+
+    // frees the seat; a room is dropped once nobody is seated
+    fn leave(&mut self) {
+        if let Some((id, seat)) = self.seat.take() {
+            let mut rooms = lock(&self.rooms.rooms);
+            let empty = match rooms.get_mut(&id) {
+                Some(room) => {
+                    room.seats[seat] = None;
+                    room.names[seat] = None;
+                    // before the game, the seat is free for anyone
+                    if !room.started {
+                        room.tokens[seat] = None;
+                    }
+                    room.broadcast(&Event::Seats {
+                        seats: room.names.clone(),
+                    });
+                    room.seats.iter().all(|x| x.is_none())
+                }
+                None => false,
+            };
+            if empty {
+                rooms.remove(&id);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::{json, Value};
+    use tungstenite::stream::MaybeTlsStream;
+
+    type Socket = WebSocket<MaybeTlsStream<TcpStream>>;
+
+    fn server() -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        thread::spawn(move || listen(listener, Rooms::default()));
+        format!("ws://{}", address)
+    }
+
+    fn send(socket: &mut Socket, message: Value) {
+        socket.send(Message::text(message.to_string())).unwrap();
+    }
+
+    // the next message of type `kind`, skipping the others but the errors
+    fn next(socket: &mut Socket, kind: &str) -> Value {
+        loop {
+            if let Message::Text(text) = socket.read().unwrap() {
+                let message: Value = serde_json::from_str(text.as_str()).unwrap();
+                if message["type"] == kind {
+                    return message;
+                }
+                assert_ne!(message["type"], "error", "{}", message);
+            }
+        }
+    }
+
+    // a connection that went through the handshake
+    fn connect(address: &str, hello: &Hello) -> Socket {
+        let (mut socket, _) = tungstenite::connect(address).unwrap();
+        let theirs = socket.read().unwrap();
+        assert!(Hello::from_json(theirs.to_text().unwrap()).is_ok());
+        socket.send(Message::text(hello.to_json())).unwrap();
+        socket
+    }
+
+    #[test]
+    fn mismatched_hello_is_refused() {
+        let address = server();
+        let hello = Hello {
+            protocol_version: 0,
+            ..Hello::local()
+        };
+        let mut socket = connect(&address, &hello);
+        assert!(next(&mut socket, "error")["message"]
+            .as_str()
+            .unwrap()
+            .contains("protocol version"));
+    }
+
+    #[test]
+    fn reconnected_seat_resumes_from_its_last_ack() {
+        let address = server();
+        let mut alice = connect(&address, &Hello::local());
+        send(
+            &mut alice,
+            json!({"type": "create", "players": 2, "seed": 0}),
+        );
+        let room = next(&mut alice, "created")["room"].clone();
+        send(
+            &mut alice,
+            json!({"type": "join", "room": room, "name": "alice"}),
+        );
+        let mut bob = connect(&address, &Hello::local());
+        send(
+            &mut bob,
+            json!({"type": "join", "room": room, "name": "bob"}),
+        );
+        let joined = next(&mut bob, "joined");
+        assert_eq!(joined["seat"], 1);
+        let token = joined["token"].clone();
+
+        let play = json!({"type": "play", "position": 0});
+        send(
+            &mut alice,
+            json!({"type": "submit", "seq": 0, "action": play}),
+        );
+        assert_eq!(next(&mut bob, "update")["seq"], 0);
+        send(&mut bob, json!({"type": "ack", "seq": 0}));
+        send(
+            &mut bob,
+            json!({"type": "submit", "seq": 1, "action": play}),
+        );
+        assert_eq!(next(&mut alice, "update")["seq"], 0);
+        assert_eq!(next(&mut alice, "update")["seq"], 1);
+        bob.close(None).unwrap();
+        while next(&mut alice, "seats")["seats"][1] != Value::Null {}
+
+        // nobody else takes the seat, by its number or as a free one
+        let mut eve = connect(&address, &Hello::local());
+        for join in [
+            json!({"type": "join", "room": room, "name": "eve", "seat": 1}),
+            json!({"type": "join", "room": room, "name": "eve", "seat": 1, "token": "0"}),
+            json!({"type": "join", "room": room, "name": "eve"}),
+        ] {
+            send(&mut eve, join);
+            next(&mut eve, "error");
+        }
+
+        // the seat is taken back and only gets the update it did not acknowledge
+        let mut bob = connect(&address, &Hello::local());
+        send(
+            &mut bob,
+            json!({"type": "join", "room": room, "name": "bob", "seat": 1, "token": token}),
+        );
+        assert_eq!(next(&mut bob, "joined")["seat"], 1);
+        send(&mut bob, json!({"type": "resume"}));
+        let update = next(&mut bob, "update");
+        assert_eq!(update["seq"], 1);
+        assert_eq!(update["action"], play);
+        // the action resent after the reconnection is not played twice
+        send(
+            &mut bob,
+            json!({"type": "submit", "seq": 1, "action": play}),
+        );
+        send(&mut bob, json!({"type": "note", "text": "back"}));
+        send(
+            &mut alice,
+            json!({"type": "submit", "seq": 2, "action": play}),
+        );
+        assert_eq!(next(&mut bob, "update")["seq"], 2);
+    }
+}
@@ -0,0 +1,513 @@
+// the rules of the game on fixed size data, using nothing but `core` and `thiserror` without its
+// std feature (no std, alloc, rand or ndarray) so that the module can be moved as is into a
+// `#![no_std]` crate for constrained targets
+//
+// `State` layers the shuffling, the history, the rule modifiers and the encodings on top of these
+// checks, `Board` is a whole game without them
+
+use thiserror::Error;
+
+pub const MAXCLUES: usize = 8;
+pub const MAXMISTAKES: usize = 3;
+pub const MAXPLAYERS: usize = 5;
+pub const MAXCARDS: usize = 5;
+// clue colors are r g b y p t, suits can also be o (touched by r and y)
+pub const MAXCOLORS: usize = 6;
+pub const MAXSUITS: usize = 6;
+// the clue colors of the standard game, whose action ids come before those of the other colors
+pub const BASECOLORS: usize = 5;
+pub const NACTIONS: usize = 2 * MAXCARDS + MAXPLAYERS * (5 + MAXCOLORS);
+// moves of the history in the observation, the most recent first
+pub const MAXHISTORY: usize = 100;
+// cards of the largest deck
+pub const MAXDECK: usize = 10 * MAXSUITS;
+
+// copies of each value in a suit
+pub const COPIES: [usize; 5] = [3, 2, 2, 2, 1];
+
+// clue colors touching each suit, one bit per clue color, suits indexed like `Color`
+// (r g b y p t o)
+pub const TOUCH: [u8; 7] = [
+    0b00_0001, 0b00_0010, 0b00_0100, 0b00_1000, 0b01_0000, 0b10_0000, 0b00_1001,
+];
+
+// why an action is refused, its `Display` is the message shown to players and raised in python
+#[derive(Debug, Error)]
+pub enum IllegalMoves {
+    #[error("no discard with all the clue tokens")]
+    MaxClue,
+    #[error("no clue token left")]
+    NoMoreClues,
+    #[error("a player cannot clue themselves")]
+    SelfClue,
+    #[error("the clue touches no card")]
+    EmptyClue,
+    #[error("the game is over")]
+    GameOver,
+    #[error("forbidden by a rule modifier")]
+    Forbidden,
+    #[error("no position {position} in a hand of {hand_size} cards")]
+    PositionOutOfRange { position: usize, hand_size: usize },
+    #[error("no player {target} to clue")]
+    InvalidTarget { target: usize },
+    // a color or value that does not exist in the variant
+    #[error("no such clue in this game")]
+    InvalidClueToken,
+    #[error("no action of id {id}")]
+    InvalidActionId { id: usize },
+    #[error("{size} outputs to decode instead of {expected}")]
+    DecodingSize { size: usize, expected: usize },
+}
+
+impl IllegalMoves {
+    // the name of the variant, e.g. "PositionOutOfRange", for the bindings
+    pub fn kind(&self) -> &'static str {
+        match self {
+            IllegalMoves::MaxClue => "MaxClue",
+            IllegalMoves::NoMoreClues => "NoMoreClues",
+            IllegalMoves::SelfClue => "SelfClue",
+            IllegalMoves::EmptyClue => "EmptyClue",
+            IllegalMoves::GameOver => "GameOver",
+            IllegalMoves::Forbidden => "Forbidden",
+            IllegalMoves::PositionOutOfRange { .. } => "PositionOutOfRange",
+            IllegalMoves::InvalidTarget { .. } => "InvalidTarget",
+            IllegalMoves::InvalidClueToken => "InvalidClueToken",
+            IllegalMoves::InvalidActionId { .. } => "InvalidActionId",
+            IllegalMoves::DecodingSize { .. } => "DecodingSize",
+        }
+    }
+
+    // the fields of the variant by name, without allocating
+    pub fn fields(&self) -> impl Iterator<Item = (&'static str, usize)> {
+        let none = ("", 0);
+        let (fields, n) = match *self {
+            IllegalMoves::PositionOutOfRange {
+                position,
+                hand_size,
+            } => ([("position", position), ("hand_size", hand_size)], 2),
+            IllegalMoves::InvalidTarget { target } => ([("target", target), none], 1),
+            IllegalMoves::InvalidActionId { id } => ([("id", id), none], 1),
+            IllegalMoves::DecodingSize { size, expected } => {
+                ([("size", size), ("expected", expected)], 2)
+            }
+            _ => ([none; 2], 0),
+        };
+        IntoIterator::into_iter(fields).take(n)
+    }
+}
+
+// options of some conventions and experiments, all off in the standard game
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Rules {
+    // a player may clue their own hand
+    pub allow_self_clue: bool,
+    // a clue may touch no card
+    pub allow_empty_clue: bool,
+    // a game lost at the third strike scores 0, as in the official rules
+    pub strikeout_score_zero: bool,
+    // playing the 5 of a suit gives no clue token back
+    pub no_suit_bonus: bool,
+    // where a drawn card goes in the hand, which fixes what the positions of the actions mean
+    pub draw_position: DrawPosition,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum DrawPosition {
+    // position 0, the hands are newest card first
+    #[default]
+    First,
+    // after the last card, the hands are oldest card first
+    Last,
+}
+
+pub const NRULES: usize = 5;
+
+impl Rules {
+    // every option in the order of the fields
+    pub fn flags(&self) -> [bool; NRULES] {
+        [
+            self.allow_self_clue,
+            self.allow_empty_clue,
+            self.strikeout_score_zero,
+            self.no_suit_bonus,
+            self.draw_position == DrawPosition::Last,
+        ]
+    }
+}
+
+// clue tokens given back by a successful play of `value` (0 for a 1)
+pub fn suit_bonus(value: usize, rules: Rules) -> usize {
+    (value == 4 && !rules.no_suit_bonus) as usize
+}
+
+// the score of a finished game
+pub fn final_score(score: usize, mistakes: usize, rules: Rules) -> usize {
+    if rules.strikeout_score_zero && mistakes >= MAXMISTAKES {
+        0
+    } else {
+        score
+    }
+}
+
+pub fn touches(color: usize, suit: usize) -> bool {
+    TOUCH[suit] & (1 << color) != 0
+}
+
+pub fn hand_size(players: usize) -> usize {
+    [0, 0, MAXCARDS, MAXCARDS, MAXCARDS - 1, MAXCARDS - 1][players]
+}
+
+// the game ends a round after the last card is drawn, at the third strike or at the maximum score
+pub fn gameover(
+    final_round: Option<usize>,
+    mistakes: usize,
+    score: usize,
+    max_score: usize,
+) -> bool {
+    final_round == Some(0) || mistakes >= MAXMISTAKES || score >= max_score
+}
+
+// the turns left after an action, `final_round` before it: the final round starts when the last
+// card is drawn, and then every player, the one who drew it included, plays once more
+pub fn count_final_round(final_round: Option<usize>, deck: usize, players: usize) -> Option<usize> {
+    match final_round {
+        Some(left) => Some(left.saturating_sub(1)),
+        None if deck == 0 => Some(players),
+        None => None,
+    }
+}
+
+pub fn check_play(gameover: bool, hand: usize, position: usize) -> Result<(), IllegalMoves> {
+    if gameover {
+        return Err(IllegalMoves::GameOver);
+    }
+    if position >= hand {
+        return Err(IllegalMoves::PositionOutOfRange {
+            position,
+            hand_size: hand,
+        });
+    }
+    Ok(())
+}
+
+pub fn check_discard(
+    clues: usize,
+    gameover: bool,
+    hand: usize,
+    position: usize,
+) -> Result<(), IllegalMoves> {
+    if clues >= MAXCLUES {
+        return Err(IllegalMoves::MaxClue);
+    }
+    check_play(gameover, hand, position)
+}
+
+// everything but the cards touched, see `check_touched`
+pub fn check_clue(
+    players: usize,
+    player: usize,
+    target: usize,
+    clues: usize,
+    gameover: bool,
+    rules: Rules,
+) -> Result<(), IllegalMoves> {
+    if target >= players {
+        return Err(IllegalMoves::InvalidTarget { target });
+    }
+    if gameover {
+        return Err(IllegalMoves::GameOver);
+    }
+    if player == target && !rules.allow_self_clue {
+        return Err(IllegalMoves::SelfClue);
+    }
+    if clues == 0 {
+        return Err(IllegalMoves::NoMoreClues);
+    }
+    Ok(())
+}
+
+pub fn check_touched(touched: usize, rules: Rules) -> Result<(), IllegalMoves> {
+    if touched == 0 && !rules.allow_empty_clue {
+        return Err(IllegalMoves::EmptyClue);
+    }
+    Ok(())
+}
+
+// (color as in `Color`, value - 1)
+pub type RawCard = (u8, u8);
+
+// what the owner of a card has been told, the bit sets are the colors and values of the clues
+// that did not touch it
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Told {
+    pub color: Option<u8>,
+    pub value: Option<u8>,
+    pub not_colors: u8,
+    pub not_values: u8,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Move {
+    Play(usize),
+    Discard(usize),
+    ColorClue { target: usize, color: usize },
+    ValueClue { target: usize, value: usize },
+}
+
+// a whole game without rule modifiers (but with their `Rules`), positions and drawing as in
+// `State`: new cards where `Rules::draw_position` puts them, the deck drawn from its end
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Board {
+    pub players: usize,
+    pub turn: usize,
+    // turns left once the last card is drawn, see `count_final_round`
+    pub final_round: Option<usize>,
+    pub clues: usize,
+    pub mistakes: usize,
+    // colors of the suits of the variant
+    pub suits: [u8; MAXSUITS],
+    pub nsuits: usize,
+    pub hands: [[RawCard; MAXCARDS]; MAXPLAYERS],
+    pub told: [[Told; MAXCARDS]; MAXPLAYERS],
+    pub hand_sizes: [usize; MAXPLAYERS],
+    pub deck: [RawCard; MAXDECK],
+    pub deck_size: usize,
+    // indexed by the position of the suit in `suits`
+    pub fireworks: [usize; MAXSUITS],
+    pub discarded: [[u8; 5]; MAXSUITS],
+    pub rules: Rules,
+}
+
+impl Board {
+    // no cards yet
+    pub fn empty(players: usize, suits: &[u8]) -> Board {
+        assert!((2..=MAXPLAYERS).contains(&players));
+        let mut board = Board {
+            players,
+            turn: 0,
+            final_round: None,
+            clues: MAXCLUES,
+            mistakes: 0,
+            suits: [0; MAXSUITS],
+            nsuits: suits.len(),
+            hands: [[(0, 0); MAXCARDS]; MAXPLAYERS],
+            told: [[Told::default(); MAXCARDS]; MAXPLAYERS],
+            hand_sizes: [0; MAXPLAYERS],
+            deck: [(0, 0); MAXDECK],
+            deck_size: 0,
+            fireworks: [0; MAXSUITS],
+            discarded: [[0; 5]; MAXSUITS],
+            rules: Rules::default(),
+        };
+        board.suits[..suits.len()].copy_from_slice(suits);
+        board
+    }
+
+    // deals the hands from the start of `deck` like `State`
+    pub fn new(players: usize, suits: &[u8], deck: &[RawCard]) -> Board {
+        let mut board = Board::empty(players, suits);
+        let n = hand_size(players);
+        for player in 0..players {
+            board.hands[player][..n].copy_from_slice(&deck[player * n..(player + 1) * n]);
+            board.hand_sizes[player] = n;
+        }
+        board.deck_size = deck.len() - players * n;
+        board.deck[..board.deck_size].copy_from_slice(&deck[players * n..]);
+        board
+    }
+
+    pub fn player(&self) -> usize {
+        self.turn % self.players
+    }
+
+    pub fn score(&self) -> usize {
+        self.fireworks[..self.nsuits].iter().sum()
+    }
+
+    pub fn final_score(&self) -> usize {
+        final_score(self.score(), self.mistakes, self.rules)
+    }
+
+    pub fn gameover(&self) -> bool {
+        gameover(
+            self.final_round,
+            self.mistakes,
+            self.score(),
+            5 * self.nsuits,
+        )
+    }
+
+    fn suit_index(&self, color: u8) -> usize {
+        self.suits[..self.nsuits]
+            .iter()
+            .position(|&x| x == color)
+            .unwrap()
+    }
+
+    fn touched(&self, target: usize, m: &Move) -> usize {
+        let hand = &self.hands[target][..self.hand_sizes[target]];
+        hand.iter()
+            .filter(|&&(color, value)| match *m {
+                Move::ColorClue { color: c, .. } => touches(c, color as usize),
+                Move::ValueClue { value: v, .. } => value as usize == v,
+                _ => false,
+            })
+            .count()
+    }
+
+    pub fn check(&self, m: &Move) -> Result<(), IllegalMoves> {
+        let player = self.player();
+        let hand = self.hand_sizes[player];
+        match *m {
+            Move::Play(position) => check_play(self.gameover(), hand, position),
+            Move::Discard(position) => check_discard(self.clues, self.gameover(), hand, position),
+            Move::ColorClue { target, color } => {
+                // before `touches`, which shifts by the color
+                if color >= MAXCOLORS {
+                    return Err(IllegalMoves::InvalidClueToken);
+                }
+                let clued = self.suits[..self.nsuits]
+                    .iter()
+                    .any(|&suit| touches(color, suit as usize));
+                if !clued {
+                    return Err(IllegalMoves::InvalidClueToken);
+                }
+                check_clue(
+                    self.players,
+                    player,
+                    target,
+                    self.clues,
+                    self.gameover(),
+                    self.rules,
+                )?;
+                check_touched(self.touched(target, m), self.rules)
+            }
+            Move::ValueClue { target, value } => {
+                if value >= 5 {
+                    return Err(IllegalMoves::InvalidClueToken);
+                }
+                check_clue(
+                    self.players,
+                    player,
+                    target,
+                    self.clues,
+                    self.gameover(),
+                    self.rules,
+                )?;
+                check_touched(self.touched(target, m), self.rules)
+            }
+        }
+    }
+
+    pub fn apply(&mut self, m: &Move) -> Result<(), IllegalMoves> {
+        self.check(m)?;
+        let player = self.player();
+        match *m {
+            Move::Play(position) | Move::Discard(position) => {
+                let (color, value) = self.take(player, position);
+                let suit = self.suit_index(color);
+                let played = matches!(m, Move::Play(_)) && self.fireworks[suit] == value as usize;
+                if played {
+                    self.fireworks[suit] += 1;
+                    self.clues =
+                        (self.clues + suit_bonus(value as usize, self.rules)).min(MAXCLUES);
+                } else {
+                    self.discarded[suit][value as usize] += 1;
+                    if matches!(m, Move::Play(_)) {
+                        self.mistakes += 1;
+                    } else {
+                        self.clues += 1;
+                    }
+                }
+                self.draw(player);
+            }
+            Move::ColorClue { target, .. } | Move::ValueClue { target, .. } => {
+                for i in 0..self.hand_sizes[target] {
+                    let (color, value) = self.hands[target][i];
+                    match *m {
+                        Move::ColorClue { color: c, .. } if touches(c, color as usize) => {
+                            self.told[target][i].color = Some(c as u8)
+                        }
+                        Move::ColorClue { color: c, .. } => {
+                            self.told[target][i].not_colors |= 1 << c
+                        }
+                        Move::ValueClue { value: v, .. } if value as usize == v => {
+                            self.told[target][i].value = Some(v as u8)
+                        }
+                        Move::ValueClue { value: v, .. } => {
+                            self.told[target][i].not_values |= 1 << v
+                        }
+                        _ => {}
+                    }
+                }
+                self.clues -= 1;
+            }
+        }
+        self.final_round = count_final_round(self.final_round, self.deck_size, self.players);
+        self.turn += 1;
+        Ok(())
+    }
+
+    // removes the card at `position`, the newer cards move up
+    fn take(&mut self, player: usize, position: usize) -> RawCard {
+        let card = self.hands[player][position];
+        let n = self.hand_sizes[player];
+        self.hands[player].copy_within(position + 1..n, position);
+        self.told[player].copy_within(position + 1..n, position);
+        // the unused slots stay cleared so that equal positions compare equal
+        self.hands[player][n - 1] = (0, 0);
+        self.told[player][n - 1] = Told::default();
+        self.hand_sizes[player] -= 1;
+        card
+    }
+
+    fn draw(&mut self, player: usize) {
+        if self.deck_size == 0 {
+            return;
+        }
+        self.deck_size -= 1;
+        let n = self.hand_sizes[player];
+        let i = match self.rules.draw_position {
+            DrawPosition::First => {
+                self.hands[player].copy_within(0..n, 1);
+                self.told[player].copy_within(0..n, 1);
+                0
+            }
+            DrawPosition::Last => n,
+        };
+        self.hands[player][i] = self.deck[self.deck_size];
+        self.deck[self.deck_size] = (0, 0);
+        self.told[player][i] = Told::default();
+        self.hand_sizes[player] += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn out_of_range_colors_are_invalid_clues() {
+        let suits = [0, 1, 2, 3, 4];
+        let mut deck = [(0, 0); 50];
+        let cards = suits.iter().flat_map(|&color| {
+            (0..5u8).flat_map(move |value| (0..COPIES[value as usize]).map(move |_| (color, value)))
+        });
+        for (slot, card) in deck.iter_mut().zip(cards) {
+            *slot = card;
+        }
+        let board = Board::new(2, &suits, &deck);
+        for color in [MAXCOLORS, 200] {
+            let clue = Move::ColorClue { target: 1, color };
+            assert!(matches!(
+                board.check(&clue),
+                Err(IllegalMoves::InvalidClueToken)
+            ));
+        }
+        let red = Move::ColorClue {
+            target: 1,
+            color: 0,
+        };
+        assert!(board.check(&red).is_ok());
+    }
+}
@@ -0,0 +1,356 @@
+use crate::agents::{self, Agent, ConventionBot, Observation};
+use crate::state::{ActionRequest, State, NACTIONS};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use rayon::prelude::*;
+use std::collections::HashMap;
+
+// single agent monte carlo search: every legal action is rolled out with the blueprint on hidden
+// hands sampled from what the player knows, the blueprint action is kept unless another is better
+// by more than `threshold`; the search is flat, `MctsBot` keeps a tree from one move to the next
+pub struct RolloutBot {
+    pub samples: usize,
+    // turns played by the blueprint after the action, to the end of the game if None
+    pub depth: Option<usize>,
+    pub threshold: f64,
+    // how the other players act in the rollouts, an agent of `agents::from_name` (see
+    // `is_teammate_model`); the searcher itself plays the blueprint
+    pub teammates: String,
+    rng: StdRng,
+}
+
+// information set monte carlo tree search: every iteration deals the hidden cards of the player
+// again and walks down the tree of the actions of all the players, the player choosing by ucb and
+// the others acting as `teammates`, then plays out to the end of the game like `RolloutBot`
+//
+// the tree is kept between the moves of a game: a node is identified by the actions since the
+// root, so the next move goes down the action played and those observed from the teammates and
+// starts from that subtree, the rest of the tree is dropped
+pub struct MctsBot {
+    pub iterations: usize,
+    // the exploration constant of ucb, in points of score
+    pub exploration: f64,
+    pub teammates: String,
+    rng: StdRng,
+    tree: Option<Tree>,
+}
+
+struct Tree {
+    // the action ids of the game up to the root
+    path: Vec<usize>,
+    root: Node,
+}
+
+#[derive(Default)]
+struct Node {
+    visits: usize,
+    // sum of the final scores of the iterations through the node
+    total: f64,
+    // by engine action id
+    children: HashMap<usize, Node>,
+}
+
+// the agents that can model the teammates in the rollouts, any but a search
+pub fn is_teammate_model(name: &str) -> bool {
+    !name.starts_with("rollout")
+        && !name.starts_with("mcts")
+        && agents::from_name(name, 0).is_some()
+}
+
+impl RolloutBot {
+    pub fn new(samples: usize, depth: Option<usize>, seed: u64) -> RolloutBot {
+        RolloutBot {
+            samples,
+            depth,
+            threshold: 0.5,
+            teammates: "convention".to_string(),
+            rng: StdRng::seed_from_u64(seed),
+        }
+    }
+
+    // mean score of every legal action over the same sampled deals
+    pub fn values(&mut self, state: &State) -> Vec<(ActionRequest, f64)> {
+        let player = state.turn() % state.players().len();
+        // a seed per deal for the teammates, the same for every action
+        // the deals that could not be drawn are left out
+        let worlds: Vec<(State, u64)> = (0..self.samples)
+            .filter_map(|_| {
+                let (hand, deck) = state.sample_deal(player, &mut self.rng)?;
+                Some((state.with_hidden(player, hand, deck), self.rng.gen()))
+            })
+            .collect();
+        let actions: Vec<ActionRequest> = (0..NACTIONS)
+            .map(ActionRequest::from_id)
+            .filter(|action| state.check(action).is_ok())
+            .collect();
+        let depth = self.depth;
+        let teammates = &self.teammates;
+        let scores: Vec<Vec<usize>> = worlds
+            .into_par_iter()
+            .map(|(world, seed)| {
+                actions
+                    .iter()
+                    .map(|action| {
+                        let mut model =
+                            agents::from_name(teammates, seed).expect("unknown teammate model");
+                        rollout(&world, action, depth, player, model.as_mut())
+                    })
+                    .collect()
+            })
+            .collect();
+        let n = scores.len().max(1) as f64;
+        actions
+            .iter()
+            .enumerate()
+            .map(|(i, &action)| {
+                let total: usize = scores.iter().map(|x| x[i]).sum();
+                (action, total as f64 / n)
+            })
+            .collect()
+    }
+}
+
+impl Agent for RolloutBot {
+    fn act(&mut self, obs: &Observation) -> ActionRequest {
+        let blueprint = ConventionBot.act(obs);
+        // the hidden cards are resampled, the world only provides the public part
+        let world = match obs.sample_world(&mut self.rng) {
+            Some(world) => world,
+            None => return blueprint,
+        };
+        let values = self.values(&world);
+        let baseline = values
+            .iter()
+            .find(|(action, _)| *action == blueprint)
+            .map_or(f64::NEG_INFINITY, |x| x.1);
+        let mut best = blueprint;
+        let mut best_value = baseline + self.threshold;
+        for &(action, value) in &values {
+            if value > best_value {
+                best = action;
+                best_value = value;
+            }
+        }
+        best
+    }
+}
+
+impl MctsBot {
+    pub fn new(iterations: usize, seed: u64) -> MctsBot {
+        MctsBot {
+            iterations,
+            exploration: 2.0,
+            teammates: "convention".to_string(),
+            rng: StdRng::seed_from_u64(seed),
+            tree: None,
+        }
+    }
+
+    // the visits and the mean score of the legal actions of the player to move, after the
+    // iterations of this move added to those kept from the previous ones
+    pub fn search(&mut self, state: &State) -> Vec<(ActionRequest, usize, f64)> {
+        let player = state.turn() % state.players().len();
+        let legal: Vec<usize> = (0..NACTIONS)
+            .filter(|&id| state.check(&ActionRequest::from_id(id)).is_ok())
+            .collect();
+        let mut tree = self.reroot(state);
+        // the actions that were legal in the sampled worlds but are not in the real one
+        tree.root.children.retain(|id, _| legal.contains(id));
+        for _ in 0..self.iterations {
+            let (hand, deck) = match state.sample_deal(player, &mut self.rng) {
+                Some(deal) => deal,
+                None => continue,
+            };
+            let mut world = state.with_hidden(player, hand, deck);
+            let mut model =
+                agents::from_name(&self.teammates, self.rng.gen()).expect("unknown teammate model");
+            tree.root
+                .simulate(&mut world, player, model.as_mut(), self.exploration);
+        }
+        let stats = legal
+            .iter()
+            .map(|&id| match tree.root.children.get(&id) {
+                Some(child) => (
+                    ActionRequest::from_id(id),
+                    child.visits,
+                    child.total / child.visits.max(1) as f64,
+                ),
+                None => (ActionRequest::from_id(id), 0, 0.0),
+            })
+            .collect();
+        self.tree = Some(tree);
+        stats
+    }
+
+    // the subtree of the current position if the previous move was in the same game, a new tree
+    // otherwise
+    fn reroot(&mut self, state: &State) -> Tree {
+        let path: Vec<usize> = state.requests().iter().map(|x| x.id()).collect();
+        let kept = match self.tree.take() {
+            Some(tree) if path.starts_with(&tree.path) => {
+                tree.root.descend(&path[tree.path.len()..])
+            }
+            _ => None,
+        };
+        Tree {
+            path,
+            root: kept.unwrap_or_default(),
+        }
+    }
+}
+
+impl Node {
+    fn descend(mut self, ids: &[usize]) -> Option<Node> {
+        for id in ids {
+            self = self.children.remove(id)?;
+        }
+        Some(self)
+    }
+
+    // one iteration in `world` from this node, returns its final score
+    fn simulate(
+        &mut self,
+        world: &mut State,
+        searcher: usize,
+        teammates: &mut dyn Agent,
+        exploration: f64,
+    ) -> f64 {
+        let value = if world.gameover() {
+            world.score() as f64
+        } else {
+            let id = if world.turn() % world.players().len() == searcher {
+                self.select(world, exploration)
+            } else {
+                Some(teammates.act(&Observation::new(world)).id())
+            };
+            match id {
+                Some(id) if world.apply(&ActionRequest::from_id(id)).is_ok() => {
+                    let child = self.children.entry(id).or_default();
+                    if child.visits == 0 {
+                        let value = playout(world, None, searcher, teammates) as f64;
+                        child.visits = 1;
+                        child.total = value;
+                        value
+                    } else {
+                        child.simulate(world, searcher, teammates, exploration)
+                    }
+                }
+                _ => world.score() as f64,
+            }
+        };
+        self.visits += 1;
+        self.total += value;
+        value
+    }
+
+    // an action legal in `world` not tried yet, else the best by ucb
+    fn select(&self, world: &State, exploration: f64) -> Option<usize> {
+        let legal = (0..NACTIONS).filter(|&id| world.check(&ActionRequest::from_id(id)).is_ok());
+        let mut best = None;
+        let mut best_value = f64::NEG_INFINITY;
+        let log = (self.visits.max(1) as f64).ln();
+        for id in legal {
+            let value = match self.children.get(&id) {
+                Some(child) if child.visits > 0 => {
+                    child.total / child.visits as f64
+                        + exploration * (log / child.visits as f64).sqrt()
+                }
+                _ => return Some(id),
+            };
+            if value > best_value {
+                best = Some(id);
+                best_value = value;
+            }
+        }
+        best
+    }
+}
+
+impl Agent for MctsBot {
+    fn act(&mut self, obs: &Observation) -> ActionRequest {
+        // the hidden cards are resampled, the world only provides the public part
+        let world = match obs.sample_world(&mut self.rng) {
+            Some(world) => world,
+            None => return ConventionBot.act(obs),
+        };
+        let stats = self.search(&world);
+        match stats.iter().max_by_key(|(_, visits, _)| *visits) {
+            Some(&(action, visits, _)) if visits > 0 => action,
+            _ => ConventionBot.act(obs),
+        }
+    }
+}
+
+// `searcher` plays the blueprint and the others `teammates`
+fn rollout(
+    world: &State,
+    action: &ActionRequest,
+    depth: Option<usize>,
+    searcher: usize,
+    teammates: &mut dyn Agent,
+) -> usize {
+    let mut state = world.fork();
+    if state.apply(action).is_err() {
+        return 0;
+    }
+    playout(&mut state, depth, searcher, teammates)
+}
+
+// the game played on from `state` for `depth` turns or to its end, returns the score
+fn playout(
+    state: &mut State,
+    depth: Option<usize>,
+    searcher: usize,
+    teammates: &mut dyn Agent,
+) -> usize {
+    let mut turns = 0;
+    while !state.gameover() && depth.iter().all(|&depth| turns < depth) {
+        let obs = Observation::new(state);
+        let action = if state.turn() % state.players().len() == searcher {
+            ConventionBot.act(&obs)
+        } else {
+            teammates.act(&obs)
+        };
+        if state.apply(&action).is_err() {
+            break;
+        }
+        turns += 1;
+    }
+    state.score()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn visits(bot: &MctsBot, ids: &[usize]) -> usize {
+        let mut node = &bot.tree.as_ref().unwrap().root;
+        for id in ids {
+            match node.children.get(id) {
+                Some(child) => node = child,
+                None => return 0,
+            }
+        }
+        node.visits
+    }
+
+    #[test]
+    fn the_tree_is_kept_between_moves() {
+        let mut state = State::with_seed(2, 0);
+        let mut bot = MctsBot::new(100, 0);
+        let mut teammate = ConventionBot;
+        for _ in 0..4 {
+            let action = bot.act(&Observation::new(&state));
+            state.apply(&action).unwrap();
+            let answer = teammate.act(&Observation::new(&state));
+            let kept = visits(&bot, &[action.id(), answer.id()]);
+            state.apply(&answer).unwrap();
+            bot.search(&state);
+            assert_eq!(visits(&bot, &[]), kept + 100);
+            assert!(kept > 0);
+        }
+        // another game starts a new tree
+        bot.search(&State::with_seed(2, 1));
+        assert_eq!(visits(&bot, &[]), 100);
+    }
+}
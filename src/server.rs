@@ -0,0 +1,413 @@
+// headless evaluation server: clients submit experiments, a pool of workers runs them
+//
+// one json message per line over tcp, both sides first send their `Hello`
+//
+// every job is a directory `root/<job>` holding the submitted `experiment.toml` and the outputs
+// of the experiment, a failed job also has an `error.txt`; at start the server reloads the jobs
+// of `root` and resumes the unfinished ones where their replays stop
+//
+// built with the `leaderboard` feature, the server also keeps the verified games submitted by a
+// community in `root/leaderboard.sqlite` and answers rankings, see `leaderboard`
+use crate::experiment::{Experiment, GameResult};
+#[cfg(feature = "leaderboard")]
+use crate::leaderboard::{Entry, Leaderboard};
+use crate::manifest::Manifest;
+use crate::protocol::Hello;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::panic::{self, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::thread;
+use std::time::Instant;
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Request {
+    // the toml of an experiment, its `output` is replaced by the directory of the job
+    Submit {
+        experiment: String,
+    },
+    Status {
+        job: usize,
+    },
+    // the replay of the game of `seed` once it is played
+    Replay {
+        job: usize,
+        seed: u64,
+    },
+    // a claimed game (json, see `verify`), stored once verified
+    #[cfg(feature = "leaderboard")]
+    SubmitGame {
+        submitter: String,
+        claim: String,
+    },
+    #[cfg(feature = "leaderboard")]
+    Leaderboard {
+        variant: String,
+        players: usize,
+        #[serde(default)]
+        modifiers: Vec<String>,
+        limit: usize,
+    },
+    // the claim of a stored game
+    #[cfg(feature = "leaderboard")]
+    Game {
+        game: i64,
+    },
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Reply {
+    Accepted {
+        job: usize,
+    },
+    Queued,
+    Running {
+        done: usize,
+        total: usize,
+        // seconds left at the pace of the games played since the job (re)started
+        eta: Option<f64>,
+    },
+    Done {
+        manifest: Manifest,
+        results: Vec<GameResult>,
+    },
+    Failed {
+        error: String,
+    },
+    Replay {
+        replay: String,
+    },
+    #[cfg(feature = "leaderboard")]
+    Verified {
+        game: i64,
+    },
+    #[cfg(feature = "leaderboard")]
+    Ranking {
+        entries: Vec<Entry>,
+    },
+    #[cfg(feature = "leaderboard")]
+    Claim {
+        claim: String,
+    },
+    Error {
+        message: String,
+    },
+}
+
+enum Job {
+    Queued,
+    Running {
+        done: usize,
+        total: usize,
+        // games already done when the job started and when it started
+        resumed: usize,
+        start: Instant,
+    },
+    Done(Manifest, Vec<GameResult>),
+    Failed(String),
+}
+
+impl Job {
+    // state of the job written in `dir`
+    fn load(dir: &Path) -> Job {
+        if let Ok(error) = fs::read_to_string(dir.join("error.txt")) {
+            return Job::Failed(error);
+        }
+        let manifest = fs::read_to_string(dir.join("manifest.json"))
+            .ok()
+            .and_then(|x| serde_json::from_str(&x).ok());
+        let results = fs::read_to_string(dir.join("results.json"))
+            .ok()
+            .and_then(|x| serde_json::from_str(&x).ok());
+        match (manifest, results) {
+            (Some(manifest), Some(results)) => Job::Done(manifest, results),
+            _ => Job::Queued,
+        }
+    }
+
+    fn reply(&self) -> Reply {
+        match self {
+            Job::Queued => Reply::Queued,
+            Job::Running {
+                done,
+                total,
+                resumed,
+                start,
+            } => {
+                let played = done - resumed;
+                let eta = if played > 0 {
+                    let per_game = start.elapsed().as_secs_f64() / played as f64;
+                    Some(per_game * (total - done) as f64)
+                } else {
+                    None
+                };
+                Reply::Running {
+                    done: *done,
+                    total: *total,
+                    eta,
+                }
+            }
+            Job::Done(manifest, results) => Reply::Done {
+                manifest: manifest.clone(),
+                results: results.clone(),
+            },
+            Job::Failed(error) => Reply::Failed {
+                error: error.clone(),
+            },
+        }
+    }
+}
+
+// the guard of `mutex`, also when a thread panicked holding it: the jobs and the queue stay
+// consistent between two statements
+pub(crate) fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+// the message of a panic, as printed by the default hook
+fn panic_message(payload: &(dyn std::any::Any + Send)) -> String {
+    match (
+        payload.downcast_ref::<&str>(),
+        payload.downcast_ref::<String>(),
+    ) {
+        (Some(message), _) => message.to_string(),
+        (_, Some(message)) => message.clone(),
+        _ => "unknown panic".to_string(),
+    }
+}
+
+#[derive(Clone)]
+struct Shared {
+    jobs: Arc<Mutex<BTreeMap<usize, Job>>>,
+    queue: Arc<Mutex<Sender<(usize, Experiment)>>>,
+    root: PathBuf,
+    #[cfg(feature = "leaderboard")]
+    leaderboard: Arc<Mutex<Leaderboard>>,
+}
+
+// serves forever on `address`, jobs are kept in `root/<job>`
+pub fn serve<P: AsRef<Path>>(address: &str, workers: usize, root: P) -> io::Result<()> {
+    let root = root.as_ref().to_path_buf();
+    fs::create_dir_all(&root)?;
+    let listener = TcpListener::bind(address)?;
+    let (sender, receiver) = channel();
+    let shared = Shared {
+        jobs: Arc::new(Mutex::new(BTreeMap::new())),
+        queue: Arc::new(Mutex::new(sender)),
+        #[cfg(feature = "leaderboard")]
+        leaderboard: Arc::new(Mutex::new(
+            Leaderboard::open(root.join("leaderboard.sqlite"))
+                .map_err(|err| io::Error::other(format!("{:?}", err)))?,
+        )),
+        root,
+    };
+    shared.reload()?;
+    let receiver = Arc::new(Mutex::new(receiver));
+    for _ in 0..workers.max(1) {
+        let shared = shared.clone();
+        let receiver = receiver.clone();
+        thread::spawn(move || shared.work(&receiver));
+    }
+    for stream in listener.incoming() {
+        let stream = stream?;
+        let shared = shared.clone();
+        thread::spawn(move || {
+            // a client that goes away only ends its own connection
+            let _ = handle(stream, &shared);
+        });
+    }
+    Ok(())
+}
+
+fn handle(stream: TcpStream, shared: &Shared) -> io::Result<()> {
+    let mut writer = stream.try_clone()?;
+    let mut lines = BufReader::new(stream).lines();
+    let send = |writer: &mut TcpStream, json: String| writeln!(writer, "{}", json);
+
+    let hello = Hello::local();
+    send(&mut writer, hello.to_json())?;
+    let theirs = match lines.next() {
+        Some(line) => line?,
+        None => return Ok(()),
+    };
+    if let Err(err) = Hello::from_json(&theirs).and_then(|theirs| hello.negotiate(&theirs)) {
+        let reply = Reply::Error {
+            message: format!("{}", err),
+        };
+        return send(&mut writer, serde_json::to_string(&reply).unwrap());
+    }
+
+    for line in lines {
+        let reply = match serde_json::from_str::<Request>(&line?) {
+            Ok(request) => shared.reply(request),
+            Err(err) => Reply::Error {
+                message: format!("invalid request: {}", err),
+            },
+        };
+        send(&mut writer, serde_json::to_string(&reply).unwrap())?;
+    }
+    Ok(())
+}
+
+impl Shared {
+    fn dir(&self, job: usize) -> PathBuf {
+        self.root.join(job.to_string())
+    }
+
+    // the experiment of a job, its outputs go to the directory of the job; the clients cannot
+    // make the server read their files
+    fn experiment(&self, job: usize, text: &str) -> Result<Experiment, String> {
+        let mut experiment =
+            Experiment::from_remote_toml(text).map_err(|err| format!("{:?}", err))?;
+        experiment.output = self.dir(job);
+        Ok(experiment)
+    }
+
+    // loads the jobs of `root` and queues the unfinished ones
+    fn reload(&self) -> io::Result<()> {
+        let mut ids: Vec<usize> = fs::read_dir(&self.root)?
+            .filter_map(|entry| entry.ok()?.file_name().to_str()?.parse().ok())
+            .collect();
+        ids.sort_unstable();
+        let mut jobs = lock(&self.jobs);
+        for id in ids {
+            // a directory without experiment keeps its id taken
+            let text = fs::read_to_string(self.dir(id).join("experiment.toml"));
+            let job = match (Job::load(&self.dir(id)), text) {
+                (_, Err(err)) => Job::Failed(format!("no experiment.toml: {}", err)),
+                (Job::Queued, Ok(text)) => match self.experiment(id, &text) {
+                    Ok(experiment) => match lock(&self.queue).send((id, experiment)) {
+                        Ok(()) => Job::Queued,
+                        Err(_) => Job::Failed("the workers are gone".to_string()),
+                    },
+                    Err(error) => Job::Failed(error),
+                },
+                (job, _) => job,
+            };
+            jobs.insert(id, job);
+        }
+        Ok(())
+    }
+
+    fn work(&self, receiver: &Mutex<Receiver<(usize, Experiment)>>) {
+        loop {
+            let (id, experiment) = match lock(receiver).recv() {
+                Ok(job) => job,
+                Err(_) => return,
+            };
+            let start = Instant::now();
+            let mut resumed = None;
+            // a job that panics fails alone, its worker goes on with the next one
+            let run = panic::catch_unwind(AssertUnwindSafe(|| {
+                experiment.run_with(true, |done, total| {
+                    let resumed = *resumed.get_or_insert(done);
+                    lock(&self.jobs).insert(
+                        id,
+                        Job::Running {
+                            done,
+                            total,
+                            resumed,
+                            start,
+                        },
+                    );
+                })
+            }));
+            let failed = |error: String| {
+                let _ = fs::write(self.dir(id).join("error.txt"), &error);
+                Job::Failed(error)
+            };
+            let job = match run {
+                Ok(Ok(results)) => Job::Done(Manifest::new(&experiment), results),
+                Ok(Err(err)) => failed(format!("{:?}", err)),
+                Err(payload) => failed(format!("panicked: {}", panic_message(&*payload))),
+            };
+            lock(&self.jobs).insert(id, job);
+        }
+    }
+
+    fn reply(&self, request: Request) -> Reply {
+        match request {
+            Request::Submit { experiment: text } => {
+                let mut jobs = lock(&self.jobs);
+                let id = jobs.keys().next_back().map_or(0, |id| id + 1);
+                let experiment = match self.experiment(id, &text) {
+                    Ok(experiment) => experiment,
+                    Err(message) => return Reply::Error { message },
+                };
+                let saved = fs::create_dir_all(self.dir(id))
+                    .and_then(|_| fs::write(self.dir(id).join("experiment.toml"), &text));
+                if let Err(err) = saved {
+                    return Reply::Error {
+                        message: format!("cannot save job {}: {}", id, err),
+                    };
+                }
+                let job = match lock(&self.queue).send((id, experiment)) {
+                    Ok(()) => Job::Queued,
+                    Err(_) => Job::Failed("the workers are gone".to_string()),
+                };
+                jobs.insert(id, job);
+                Reply::Accepted { job: id }
+            }
+            Request::Status { job } => match lock(&self.jobs).get(&job) {
+                Some(job) => job.reply(),
+                None => Reply::Error {
+                    message: format!("no job {}", job),
+                },
+            },
+            Request::Replay { job, seed } => {
+                if !lock(&self.jobs).contains_key(&job) {
+                    return Reply::Error {
+                        message: format!("no job {}", job),
+                    };
+                }
+                match fs::read_to_string(self.dir(job).join(format!("{}.json", seed))) {
+                    Ok(replay) => Reply::Replay { replay },
+                    Err(_) => Reply::Error {
+                        message: format!("no replay of seed {} in job {}", seed, job),
+                    },
+                }
+            }
+            #[cfg(feature = "leaderboard")]
+            Request::SubmitGame { submitter, claim } => {
+                match lock(&self.leaderboard).submit(&submitter, &claim) {
+                    Ok(game) => Reply::Verified { game },
+                    Err(err) => Reply::Error {
+                        message: format!("{:?}", err),
+                    },
+                }
+            }
+            #[cfg(feature = "leaderboard")]
+            Request::Leaderboard {
+                variant,
+                players,
+                modifiers,
+                limit,
+            } => {
+                let leaderboard = lock(&self.leaderboard);
+                match leaderboard.ranking(&variant, players, &modifiers, limit) {
+                    Ok(entries) => Reply::Ranking { entries },
+                    Err(err) => Reply::Error {
+                        message: format!("{:?}", err),
+                    },
+                }
+            }
+            #[cfg(feature = "leaderboard")]
+            Request::Game { game } => match lock(&self.leaderboard).claim(game) {
+                Ok(Some(claim)) => Reply::Claim { claim },
+                Ok(None) => Reply::Error {
+                    message: format!("no game {}", game),
+                },
+                Err(err) => Reply::Error {
+                    message: format!("{:?}", err),
+                },
+            },
+        }
+    }
+}
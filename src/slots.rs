@@ -0,0 +1,213 @@
+use crate::rules::DrawPosition;
+use crate::state::{Action, ActionRequest, State, MAXCARDS, MAXPLAYERS, NACTIONS};
+use ndarray::Array1;
+use serde::{Deserialize, Serialize};
+
+// order of the positions in a hand; the engine draws to position 0 (newest first) unless
+// `Rules::draw_position` is `Last`, the conversions below are from the newest first engine order,
+// `for_state` adapts them to the engine order of a game
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SlotOrder {
+    #[default]
+    NewestFirst,
+    OldestFirst,
+}
+
+impl SlotOrder {
+    pub fn from_name(name: &str) -> Option<SlotOrder> {
+        match name {
+            "newest_first" => Some(SlotOrder::NewestFirst),
+            "oldest_first" => Some(SlotOrder::OldestFirst),
+            _ => None,
+        }
+    }
+
+    // converts a position of a hand of `hand` cards between the engine order and `self`,
+    // the conversion is its own inverse; out of range positions are left for `check`
+    pub fn convert(self, position: usize, hand: usize) -> usize {
+        match self {
+            SlotOrder::OldestFirst if position < hand => hand - 1 - position,
+            _ => position,
+        }
+    }
+
+    // the conversion from the engine order of `state` to `self`
+    pub fn for_state(self, state: &State) -> SlotOrder {
+        if self == state.slot_order() {
+            SlotOrder::NewestFirst
+        } else {
+            SlotOrder::OldestFirst
+        }
+    }
+
+    pub fn convert_request(self, action: &ActionRequest, hand: usize) -> ActionRequest {
+        match *action {
+            ActionRequest::Play { position } => ActionRequest::Play {
+                position: self.convert(position, hand),
+            },
+            ActionRequest::Discard { position } => ActionRequest::Discard {
+                position: self.convert(position, hand),
+            },
+            _ => *action,
+        }
+    }
+
+    pub fn convert_id(self, id: usize, hand: usize) -> usize {
+        self.convert_request(&ActionRequest::from_id(id), hand).id()
+    }
+
+    // `hands` are the sizes of every hand before the action, see `State::hands_before`
+    pub fn convert_action(self, action: &Action, hands: &[usize]) -> Action {
+        let hand = hands[action.player()];
+        let touched = |touched: &[usize], target: usize| -> Vec<usize> {
+            let mut touched: Vec<usize> = touched
+                .iter()
+                .map(|&i| self.convert(i, hands[target]))
+                .collect();
+            touched.sort_unstable();
+            touched
+        };
+        match *action {
+            Action::Play {
+                player,
+                position,
+                card,
+                success,
+            } => Action::Play {
+                player,
+                position: self.convert(position, hand),
+                card,
+                success,
+            },
+            Action::Discard {
+                player,
+                position,
+                card,
+            } => Action::Discard {
+                player,
+                position: self.convert(position, hand),
+                card,
+            },
+            Action::ColorClue {
+                player,
+                target,
+                color,
+                touched: ref positions,
+            } => Action::ColorClue {
+                player,
+                target,
+                color,
+                touched: touched(positions, target),
+            },
+            Action::ValueClue {
+                player,
+                target,
+                value,
+                touched: ref positions,
+            } => Action::ValueClue {
+                player,
+                target,
+                value,
+                touched: touched(positions, target),
+            },
+        }
+    }
+}
+
+impl State {
+    // order of the positions of the hands in the engine, see `Rules::draw_position`
+    pub fn slot_order(&self) -> SlotOrder {
+        match self.rules().draw_position {
+            DrawPosition::First => SlotOrder::NewestFirst,
+            DrawPosition::Last => SlotOrder::OldestFirst,
+        }
+    }
+
+    // number of cards in every hand before each action of the history
+    pub fn hands_before(&self) -> Vec<Vec<usize>> {
+        let mut hands: Vec<usize> = self.players().iter().map(|x| x.len()).collect();
+        // the plays and discards of the final round did not draw
+        let mut last = self
+            .final_round()
+            .map_or(0, |left| self.players().len() - left);
+        let mut sizes = Vec::new();
+        for action in self.history().iter().rev() {
+            if last > 0 {
+                last -= 1;
+                if let Action::Play { .. } | Action::Discard { .. } = action {
+                    hands[action.player()] += 1;
+                }
+            }
+            sizes.push(hands.clone());
+        }
+        sizes.reverse();
+        sizes
+    }
+
+    // number of cards in the hand of the acting player before each action of the history
+    pub fn hand_sizes(&self) -> Vec<usize> {
+        self.history()
+            .iter()
+            .zip(self.hands_before())
+            .map(|(action, hands)| hands[action.player()])
+            .collect()
+    }
+
+    pub fn current_hand(&self) -> usize {
+        self.players()[self.turn() % self.players().len()].len()
+    }
+
+    pub fn legal_mask_in(&self, order: SlotOrder) -> Array1<f32> {
+        let order = order.for_state(self);
+        let mask = self.legal_mask();
+        let hand = self.current_hand();
+        (0..NACTIONS)
+            .map(|id| mask[order.convert_id(id, hand)])
+            .collect()
+    }
+
+    // `encode_for` with the hands and the positions of the history in the order `order`
+    pub fn encode_in(&self, player: usize, order: SlotOrder) -> Array1<f32> {
+        let x = self.encode_for(player);
+        let order = order.for_state(self);
+        if order == SlotOrder::NewestFirst {
+            return x;
+        }
+        let mut y = x.clone();
+        let (width, cwidth) = self.encoding_widths();
+        let layout = self.encoding_layout();
+        for block in &layout {
+            if block.name.starts_with("hand ") {
+                let i: usize = block.name[5..].parse().unwrap();
+                let hand = self.players().get(i).map_or(0, |x| x.len());
+                for j in 0..hand {
+                    let k = order.convert(j, hand);
+                    for c in 0..width {
+                        y[block.start + k * width + c] = x[block.start + j * width + c];
+                    }
+                }
+            }
+        }
+        let sizes = self.hands_before();
+        let moves = layout.iter().filter(|x| x.name.starts_with("move "));
+        for (block, (action, hands)) in moves.zip(self.history().iter().zip(&sizes).rev()) {
+            // the position of a play or a discard, the positions touched by a clue
+            let (start, hand) = match action {
+                Action::Play { .. } | Action::Discard { .. } => {
+                    (block.start + 4, hands[action.player()])
+                }
+                Action::ColorClue { target, .. } | Action::ValueClue { target, .. } => {
+                    (block.start + 4 + MAXPLAYERS + 5 + cwidth, hands[*target])
+                }
+            };
+            if block.start + block.len < start + MAXCARDS {
+                break;
+            }
+            for j in 0..hand {
+                y[start + order.convert(j, hand)] = x[start + j];
+            }
+        }
+        y
+    }
+}
@@ -1,37 +1,51 @@
 #![allow(dead_code)]
 
+use crate::endgames::{Position, PositionError};
+use crate::hand::Hand;
+use crate::modifier::{Modifiers, RuleModifier};
+use crate::rules::{self, Board, DrawPosition, RawCard, Rules, NRULES};
+pub use crate::rules::{
+    IllegalMoves, BASECOLORS, MAXCARDS, MAXCLUES, MAXCOLORS, MAXHISTORY, MAXMISTAKES, MAXPLAYERS,
+    MAXSUITS, NACTIONS,
+};
+use crate::variant::Variant;
 use getset::Getters;
-use ndarray::{s, Array1, ArrayView1};
+use ndarray::{s, Array1, ArrayView1, ArrayViewMut1};
+use rand::rngs::StdRng;
 use rand::seq::SliceRandom;
-use rand::thread_rng;
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::convert::TryFrom;
 use std::fmt;
 
-#[derive(Clone, Copy, PartialEq)]
-pub struct Color(usize);
-
-const MAXCLUES: usize = 8;
-const MAXMISTAKES: usize = 3;
-const MAXPLAYERS: usize = 5;
-const MAXCARDS: usize = 5;
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Color(u8);
 
 impl Color {
     pub fn all() -> Vec<Color> {
-        (0..5).map(|x| Color(x)).collect()
+        (0..5).map(Color).collect()
     }
     pub fn new(color: usize) -> Color {
-        assert!(color < 5);
-        Color(color)
+        assert!(color < 7);
+        Color(color as u8)
     }
-    pub fn from_str(color: &str) -> Color {
+    pub fn from_letter(color: &str) -> Color {
         match color {
             "r" => Color(0),
             "g" => Color(1),
             "b" => Color(2),
             "y" => Color(3),
             "p" => Color(4),
+            "t" => Color(5),
+            "o" => Color(6),
             _ => panic!(),
         }
     }
+    pub fn index(&self) -> usize {
+        self.0 as usize
+    }
     pub fn r() -> Color {
         Color(0)
     }
@@ -47,6 +61,12 @@ impl Color {
     pub fn p() -> Color {
         Color(4)
     }
+    pub fn t() -> Color {
+        Color(5)
+    }
+    pub fn o() -> Color {
+        Color(6)
+    }
 }
 
 impl fmt::Display for Color {
@@ -57,25 +77,56 @@ impl fmt::Display for Color {
             2 => write!(f, "b"),
             3 => write!(f, "y"),
             4 => write!(f, "p"),
+            5 => write!(f, "t"),
+            6 => write!(f, "o"),
             _ => panic!(),
         }
     }
 }
 
-#[derive(Clone, Copy, PartialEq)]
-pub struct Value(usize);
+impl fmt::Debug for Color {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self)
+    }
+}
+
+impl TryFrom<String> for Color {
+    type Error = String;
+
+    fn try_from(color: String) -> Result<Color, String> {
+        if color.len() == 1 && "rgbypto".contains(&color) {
+            Ok(Color::from_letter(&color))
+        } else {
+            Err(format!("invalid color {:?}", color))
+        }
+    }
+}
+
+impl From<Color> for String {
+    fn from(color: Color) -> String {
+        format!("{}", color)
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "usize", into = "usize")]
+pub struct Value(u8);
 
 impl Value {
     pub fn new(value: usize) -> Value {
         assert!(value < 5);
-        Value(value)
+        Value(value as u8)
     }
     pub fn all() -> Vec<Value> {
-        (0..5).map(|x| Value(x)).collect()
+        (0..5).map(Value).collect()
+    }
+
+    pub fn index(&self) -> usize {
+        self.0 as usize
     }
 
     pub fn copies(&self) -> usize {
-        [3, 2, 2, 2, 1][self.0]
+        rules::COPIES[self.index()]
     }
 }
 
@@ -85,36 +136,66 @@ impl fmt::Display for Value {
     }
 }
 
-#[derive(Clone, Copy)]
-pub struct Card {
-    value: Value,
-    color: Color,
+impl fmt::Debug for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self)
+    }
 }
 
-impl Card {
-    fn new(value: Value, color: Color) -> Card {
-        Card {
-            value: value,
-            color: color,
+// values are exchanged 1-based, as printed on the cards
+impl TryFrom<usize> for Value {
+    type Error = String;
+
+    fn try_from(value: usize) -> Result<Value, String> {
+        if (1..=5).contains(&value) {
+            Ok(Value(value as u8 - 1))
+        } else {
+            Err(format!("invalid value {}", value))
         }
     }
+}
 
-    fn deck() -> Vec<Card> {
-        let mut deck = Vec::new();
-        for color in Color::all() {
-            for value in Value::all() {
-                for _ in 0..value.copies() {
-                    deck.push(Card::new(value, color));
-                }
-            }
-        }
-        deck
+impl From<Value> for usize {
+    fn from(value: Value) -> usize {
+        value.index() + 1
+    }
+}
+
+// one byte, its `index`: the hands, the deck and the knowledge are copied by every fork of a
+// search; the default card, 1 of the first color, only fills the empty slots of a `Hand`
+#[derive(Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Card(u8);
+
+impl Card {
+    pub fn new(value: Value, color: Color) -> Card {
+        Card(5 * color.0 + value.0)
+    }
+}
+
+impl Card {
+    pub fn value(&self) -> Value {
+        Value(self.0 % 5)
+    }
+
+    pub fn color(&self) -> Color {
+        Color(self.0 / 5)
+    }
+
+    // 5 * color + value - 1, from 0 to 24 for the colors of the standard game and up to 34 with
+    // the other colors, whatever the variant
+    pub fn index(&self) -> u8 {
+        self.0
+    }
+
+    pub fn from_index(index: u8) -> Option<Card> {
+        (index < 35).then_some(Card(index))
     }
 }
 
 impl fmt::Display for Card {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{}{}", self.value, self.color)
+        write!(f, "{}{}", self.value(), self.color())
     }
 }
 
@@ -124,6 +205,75 @@ impl fmt::Debug for Card {
     }
 }
 
+impl TryFrom<String> for Card {
+    type Error = String;
+
+    fn try_from(card: String) -> Result<Card, String> {
+        let mut chars = card.chars();
+        match (chars.next(), chars.next(), chars.next()) {
+            (Some(value), Some(color), None) => {
+                let value = value.to_digit(10).map_or(0, |x| x as usize);
+                Ok(Card::new(
+                    Value::try_from(value)?,
+                    Color::try_from(color.to_string())?,
+                ))
+            }
+            _ => Err(format!("invalid card {:?}", card)),
+        }
+    }
+}
+
+impl From<Card> for String {
+    fn from(card: Card) -> String {
+        format!("{}", card)
+    }
+}
+
+// what the owner of a card has been told about it; a clue also tells the cards it does not touch
+// what they are not, `not_values` and `not_colors` are the bit sets of the value and color indices
+// of those clues
+#[derive(Clone, Copy, Default, PartialEq)]
+pub struct Knowledge {
+    pub value: Option<Value>,
+    pub color: Option<Color>,
+    pub not_values: u8,
+    pub not_colors: u8,
+}
+
+impl Knowledge {
+    // whether `card` agrees with every clue, those that touched it and those that did not
+    pub fn allows(&self, card: &Card, variant: &Variant) -> bool {
+        self.value.iter().all(|&x| x == card.value())
+            && self.color.iter().all(|&x| variant.touches(x, card))
+            && self.not_values & (1 << card.value().index()) == 0
+            && variant
+                .colors()
+                .into_iter()
+                .filter(|x| self.not_colors & (1 << x.index()) != 0)
+                .all(|x| !variant.touches(x, card))
+    }
+}
+
+impl fmt::Display for Knowledge {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.value {
+            Some(value) => write!(f, "{}", value)?,
+            None => write!(f, "?")?,
+        }
+        match self.color {
+            Some(color) => write!(f, "{}", color),
+            None => write!(f, "?"),
+        }
+    }
+}
+
+impl fmt::Debug for Knowledge {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self)
+    }
+}
+
+#[derive(Clone)]
 pub enum Action {
     Play {
         player: usize,
@@ -136,15 +286,18 @@ pub enum Action {
         position: usize,
         card: Card,
     },
+    // `touched` are the positions of the cards of the clue in the hand of `target`
     ColorClue {
         player: usize,
         target: usize,
         color: Color,
+        touched: Vec<usize>,
     },
     ValueClue {
         player: usize,
         target: usize,
         value: Value,
+        touched: Vec<usize>,
     },
 }
 
@@ -190,11 +343,13 @@ impl fmt::Display for Action {
                 player,
                 target,
                 color,
+                ..
             } => write!(f, "P{} clues P{} about {}'s", player + 1, target + 1, color),
             Action::ValueClue {
                 player,
                 target,
                 value,
+                ..
             } => write!(f, "P{} clues P{} about {}'s", player + 1, target + 1, value),
         }
     }
@@ -206,178 +361,674 @@ impl fmt::Debug for Action {
     }
 }
 
+impl Action {
+    pub fn player(&self) -> usize {
+        match *self {
+            Action::Play { player, .. }
+            | Action::Discard { player, .. }
+            | Action::ColorClue { player, .. }
+            | Action::ValueClue { player, .. } => player,
+        }
+    }
+
+    // positions touched by a clue, in the hand of its target
+    pub fn touched(&self) -> &[usize] {
+        match self {
+            Action::ColorClue { touched, .. } | Action::ValueClue { touched, .. } => touched,
+            _ => &[],
+        }
+    }
+
+    pub fn request(&self) -> ActionRequest {
+        match *self {
+            Action::Play { position, .. } => ActionRequest::Play { position },
+            Action::Discard { position, .. } => ActionRequest::Discard { position },
+            Action::ColorClue { target, color, .. } => ActionRequest::ColorClue { target, color },
+            Action::ValueClue { target, value, .. } => ActionRequest::ValueClue { target, value },
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ActionRequest {
+    Play { position: usize },
+    Discard { position: usize },
+    ColorClue { target: usize, color: Color },
+    ValueClue { target: usize, value: Value },
+}
+
+impl ActionRequest {
+    // the same action on a `Board` of the rules core
+    pub fn to_move(&self) -> rules::Move {
+        match *self {
+            ActionRequest::Play { position } => rules::Move::Play(position),
+            ActionRequest::Discard { position } => rules::Move::Discard(position),
+            ActionRequest::ColorClue { target, color } => rules::Move::ColorClue {
+                target,
+                color: color.index(),
+            },
+            ActionRequest::ValueClue { target, value } => rules::Move::ValueClue {
+                target,
+                value: value.index(),
+            },
+        }
+    }
+
+    // flat index in 0..NACTIONS: plays, discards, 5 value and BASECOLORS color clues per target,
+    // then the clues of the other colors per target, so that the ids of the standard game are the
+    // same as before the six color variants
+    pub fn from_id(id: usize) -> ActionRequest {
+        assert!(id < NACTIONS);
+        const CLUES: usize = 5 + BASECOLORS;
+        const BASE: usize = 2 * MAXCARDS + MAXPLAYERS * CLUES;
+        if id < MAXCARDS {
+            ActionRequest::Play { position: id }
+        } else if id < 2 * MAXCARDS {
+            ActionRequest::Discard {
+                position: id - MAXCARDS,
+            }
+        } else if id < BASE {
+            let target = (id - 2 * MAXCARDS) / CLUES;
+            let i = (id - 2 * MAXCARDS) % CLUES;
+            if i < 5 {
+                ActionRequest::ValueClue {
+                    target,
+                    value: Value(i as u8),
+                }
+            } else {
+                ActionRequest::ColorClue {
+                    target,
+                    color: Color((i - 5) as u8),
+                }
+            }
+        } else {
+            let extra = MAXCOLORS - BASECOLORS;
+            ActionRequest::ColorClue {
+                target: (id - BASE) / extra,
+                color: Color((BASECOLORS + (id - BASE) % extra) as u8),
+            }
+        }
+    }
+
+    pub fn id(&self) -> usize {
+        const CLUES: usize = 5 + BASECOLORS;
+        const BASE: usize = 2 * MAXCARDS + MAXPLAYERS * CLUES;
+        match *self {
+            ActionRequest::Play { position } => position,
+            ActionRequest::Discard { position } => MAXCARDS + position,
+            ActionRequest::ValueClue { target, value } => {
+                2 * MAXCARDS + CLUES * target + value.index()
+            }
+            ActionRequest::ColorClue { target, color } if color.index() < BASECOLORS => {
+                2 * MAXCARDS + CLUES * target + 5 + color.index()
+            }
+            ActionRequest::ColorClue { target, color } => {
+                BASE + (MAXCOLORS - BASECOLORS) * target + color.index() - BASECOLORS
+            }
+        }
+    }
+}
+
+pub type Listener = Box<dyn Fn(&Action, &State) + Send + Sync>;
+
+#[derive(Default)]
+pub struct Listeners(Vec<Listener>);
+
+impl fmt::Debug for Listeners {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} listeners", self.0.len())
+    }
+}
+
 #[derive(Debug, Getters)]
 #[get = "pub"]
 pub struct State {
     turn: usize,
-    turn_empty_deck: usize,
+    // turns left once the last card is drawn, the game ends when it reaches 0
+    final_round: Option<usize>,
     clues: usize,
     mistakes: usize,
-    players: Vec<Vec<Card>>,
-    table: [usize; 5],
+    players: Vec<Hand<Card>>,
+    knowledge: Vec<Hand<Knowledge>>,
+    table: Vec<usize>,
     deck: Vec<Card>,
     discard: Vec<Card>,
-    history: Vec<Action>,
+    history: VecDeque<Action>,
+    // the ids of the actions dropped from the front of the history, one byte each, so that the
+    // game can still be replayed, see `compact_history`
+    compacted: Vec<u8>,
+    // the most actions the history keeps, the oldest dropped first, None to keep them all
+    history_capacity: Option<usize>,
+    initial_deck: Vec<Card>,
+    variant: Variant,
+    modifiers: Modifiers,
+    seed: Option<u64>,
+    rng: StdRng,
+    listeners: Listeners,
 }
 
-#[derive(Debug)]
-pub enum IllegalMoves {
-    MaxClue,
-    NoMoreClues,
-    SelfClue,
-    EmptyClue,
-    GameOver,
-    Error,
+// which index `decode` picks when several outputs share the maximum
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TieBreak {
+    First,
+    Last,
+    Random,
 }
 
 impl State {
     pub fn new(nplayer: usize) -> State {
-        let mut deck = Card::deck();
-        deck.shuffle(&mut thread_rng());
+        State::with_variant(nplayer, Variant::standard(), None)
+    }
+
+    // the same seed gives the same deal and the same random tie-breaks
+    pub fn with_seed(nplayer: usize, seed: u64) -> State {
+        State::with_variant(nplayer, Variant::standard(), Some(seed))
+    }
+
+    pub fn with_variant(nplayer: usize, variant: Variant, seed: Option<u64>) -> State {
+        let mut rng = match seed {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => StdRng::from_entropy(),
+        };
+        let mut deck = variant.deck();
+        deck.shuffle(&mut rng);
+        let mut state = State::from_deck_variant(nplayer, variant, deck);
+        state.seed = seed;
+        state.rng = rng;
+        state
+    }
+
+    pub fn from_deck(nplayer: usize, deck: Vec<Card>) -> State {
+        State::from_deck_variant(nplayer, Variant::standard(), deck)
+    }
+
+    pub fn from_deck_variant(nplayer: usize, variant: Variant, deck: Vec<Card>) -> State {
+        State::deal(nplayer, variant, Modifiers::default(), deck)
+    }
+
+    // deals again the same deck with the rule modifiers, before any action
+    pub fn modified(self, modifiers: Vec<Box<dyn RuleModifier>>) -> State {
+        assert!(self.history.is_empty());
+        let mut state = State::deal(
+            self.players.len(),
+            self.variant,
+            Modifiers(modifiers),
+            self.initial_deck,
+        );
+        state.seed = self.seed;
+        state.rng = self.rng;
+        state.listeners = self.listeners;
+        state.set_history_capacity(self.history_capacity);
+        state
+    }
+
+    // a new game dealt in place from `seed`, the same as `with_variant` then `modified` with the
+    // players, variant and rule modifiers of this one, the modifiers started again and the
+    // listeners kept; the buffers of the hands, deck, discard and history are reused
+    pub fn redeal(&mut self, seed: u64) {
+        // the deck of the variant in its order, as shuffled by `with_variant`
+        let variant = &self.variant;
+        self.initial_deck
+            .sort_unstable_by_key(|card| (variant.suit_index(card.color()), card.value().index()));
+        let mut rng = StdRng::seed_from_u64(seed);
+        self.initial_deck.shuffle(&mut rng);
+        if !self.modifiers.0.is_empty() {
+            self.modifiers = Modifiers(self.modifiers.0.iter().map(|x| x.fresh()).collect());
+        }
+        let nplayer = self.players.len();
+        let nc = self.modifiers.hand_size(nplayer);
+        for (i, hand) in self.players.iter_mut().enumerate() {
+            *hand = Hand::from_slice(&self.initial_deck[i * nc..(i + 1) * nc]);
+        }
+        for knowledge in &mut self.knowledge {
+            *knowledge = Hand::filled(nc, Knowledge::default());
+        }
+        self.deck.clear();
+        self.deck
+            .extend_from_slice(&self.initial_deck[nplayer * nc..]);
+        self.table.iter_mut().for_each(|x| *x = 0);
+        self.discard.clear();
+        self.history.clear();
+        self.compacted.clear();
+        self.turn = 0;
+        self.final_round = None;
+        self.clues = MAXCLUES;
+        self.mistakes = 0;
+        self.seed = Some(seed);
+        self.rng = rng;
+    }
+
+    // copy of the position without the listeners, the rule modifiers with what they counted so far
+    pub fn fork(&self) -> State {
+        State {
+            turn: self.turn,
+            final_round: self.final_round,
+            clues: self.clues,
+            mistakes: self.mistakes,
+            players: self.players.clone(),
+            knowledge: self.knowledge.clone(),
+            table: self.table.clone(),
+            deck: self.deck.clone(),
+            discard: self.discard.clone(),
+            history: self.history.clone(),
+            compacted: self.compacted.clone(),
+            history_capacity: self.history_capacity,
+            initial_deck: self.initial_deck.clone(),
+            variant: self.variant.clone(),
+            modifiers: self.modifiers.clone(),
+            seed: self.seed,
+            rng: self.rng.clone(),
+            listeners: Listeners::default(),
+        }
+    }
+
+    // fork in which the hand of `player` and the deck (drawn from the end) are replaced
+    pub fn with_hidden(&self, player: usize, hand: Vec<Card>, deck: Vec<Card>) -> State {
+        assert_eq!(hand.len(), self.players[player].len());
+        assert_eq!(deck.len(), self.deck.len());
+        let mut state = self.fork();
+        state.players[player] = Hand::from_slice(&hand);
+        state.deck = deck;
+        state
+    }
+
+    // the position without history, nothing told about the cards; the initial deck is a deal
+    // that leads to it, the cards gone in any order
+    pub fn from_position(position: &Position) -> Result<State, PositionError> {
+        position.validate()?;
+        let nplayer = position.hands.len();
+        let discard = position.discard()?;
+        // drawn from the end
+        let deck: Vec<Card> = position.deck.iter().rev().copied().collect();
+        let mut initial_deck = position.hands.concat();
+        initial_deck.extend(&discard);
+        initial_deck.extend(position.played());
+        initial_deck.extend(&deck);
+        let final_round = match position.final_round {
+            None if deck.is_empty() => Some(nplayer),
+            left => left,
+        };
+        Ok(State {
+            turn: position.turn,
+            final_round,
+            clues: position.clues,
+            mistakes: position.mistakes,
+            players: position.hands.iter().map(|x| Hand::from_slice(x)).collect(),
+            knowledge: position
+                .hands
+                .iter()
+                .map(|hand| Hand::filled(hand.len(), Knowledge::default()))
+                .collect(),
+            table: position.fireworks.clone(),
+            deck,
+            discard,
+            history: VecDeque::new(),
+            compacted: Vec::new(),
+            history_capacity: None,
+            initial_deck,
+            variant: position.variant.clone(),
+            modifiers: Modifiers::default(),
+            seed: None,
+            rng: StdRng::from_entropy(),
+            listeners: Listeners::default(),
+        })
+    }
+
+    // the position on the fixed size board of the rules core, without the rule modifiers
+    pub fn board(&self) -> Board {
+        let raw = |card: &Card| -> RawCard { (card.color().0, card.value().0) };
+        let suits: Vec<u8> = self.variant.suits().iter().map(|x| x.0).collect();
+        let mut board = Board::empty(self.players.len(), &suits);
+        board.turn = self.turn;
+        board.final_round = self.final_round;
+        board.clues = self.clues;
+        board.mistakes = self.mistakes;
+        board.rules = self.rules();
+        for (i, hand) in self.players.iter().enumerate() {
+            board.hand_sizes[i] = hand.len();
+            for (j, card) in hand.iter().enumerate() {
+                board.hands[i][j] = raw(card);
+                let knowledge = self.knowledge[i][j];
+                board.told[i][j] = rules::Told {
+                    color: knowledge.color.map(|x| x.0),
+                    value: knowledge.value.map(|x| x.0),
+                    not_colors: knowledge.not_colors,
+                    not_values: knowledge.not_values,
+                };
+            }
+        }
+        board.deck_size = self.deck.len();
+        for (i, card) in self.deck.iter().enumerate() {
+            board.deck[i] = raw(card);
+        }
+        board.fireworks[..self.table.len()].copy_from_slice(&self.table);
+        for card in &self.discard {
+            board.discarded[self.variant.suit_index(card.color())][card.value().index()] += 1;
+        }
+        board
+    }
 
-        let nc = [0, 0, MAXCARDS, MAXCARDS, MAXCARDS - 1, MAXCARDS - 1][nplayer];
-        let players: Vec<Vec<Card>> = (0..nplayer)
-            .map(|i| deck[i * nc..(i + 1) * nc].to_vec())
+    fn deal(nplayer: usize, variant: Variant, modifiers: Modifiers, deck: Vec<Card>) -> State {
+        let initial_deck = deck.clone();
+        let mut deck = deck;
+
+        let nc = modifiers.hand_size(nplayer);
+        let players: Vec<Hand<Card>> = (0..nplayer)
+            .map(|i| Hand::from_slice(&deck[i * nc..(i + 1) * nc]))
             .collect();
         deck = deck[nplayer * nc..].to_vec();
+        let knowledge = vec![Hand::filled(nc, Knowledge::default()); nplayer];
 
         State {
             turn: 0,
-            turn_empty_deck: 0,
+            final_round: None,
             clues: MAXCLUES,
             mistakes: 0,
-            players: players,
-            table: [0; 5],
-            deck: deck,
+            players,
+            knowledge,
+            table: vec![0; variant.suits().len()],
+            deck,
             discard: Vec::new(),
-            history: Vec::new(),
+            history: VecDeque::new(),
+            compacted: Vec::new(),
+            history_capacity: None,
+            initial_deck,
+            variant,
+            modifiers,
+            seed: None,
+            rng: StdRng::from_entropy(),
+            listeners: Listeners::default(),
+        }
+    }
+
+    fn record(&mut self, action: Action) {
+        if self.history_capacity == Some(self.history.len()) {
+            // a ring buffer, the oldest action makes room without moving the others
+            let oldest = self.history.pop_front().unwrap();
+            self.compacted.push(oldest.request().id() as u8);
+        }
+        self.history.push_back(action);
+    }
+
+    // drops all but the last `keep_last` actions of the history, enough for the observation with
+    // `MAXHISTORY`, to keep the memory of long running games flat: a byte is kept per action
+    // dropped, for the replays; the agents reading the whole history (the convention bots) need it
+    // complete
+    pub fn compact_history(&mut self, keep_last: usize) {
+        let drop = self.history.len().saturating_sub(keep_last);
+        let ids = self.history.drain(..drop).map(|x| x.request().id() as u8);
+        self.compacted.extend(ids);
+    }
+
+    // every action of the game as requested, in the engine slot order, the compacted ones included
+    pub fn requests(&self) -> Vec<ActionRequest> {
+        self.compacted
+            .iter()
+            .map(|&id| ActionRequest::from_id(id as usize))
+            .chain(self.history.iter().map(|x| x.request()))
+            .collect()
+    }
+
+    // keeps at most the last `capacity` actions from now on, in a buffer allocated once, or all of
+    // them with None
+    pub fn set_history_capacity(&mut self, capacity: Option<usize>) {
+        if let Some(capacity) = capacity {
+            // the listeners are given the last action
+            assert!(capacity > 0);
+            self.compact_history(capacity);
+            self.history.shrink_to(capacity);
+            self.history.reserve_exact(capacity - self.history.len());
+        }
+        self.history_capacity = capacity;
+    }
+
+    // listeners are called after every successful action, with the action and the new state
+    pub fn subscribe(&mut self, listener: Listener) {
+        self.listeners.0.push(listener);
+    }
+
+    fn notify(&self) {
+        if let Some(action) = self.history.back() {
+            for listener in &self.listeners.0 {
+                listener(action, self);
+            }
         }
     }
 
     pub fn gameover(&self) -> bool {
-        self.turn_empty_deck > self.players.len()
-            || self.mistakes >= MAXMISTAKES
-            || self.score() >= 25
+        rules::gameover(
+            self.final_round,
+            self.mistakes,
+            self.score(),
+            self.variant.max_score(),
+        )
     }
 
-    pub fn play(&mut self, position: usize) -> Result<(), IllegalMoves> {
-        if self.gameover() {
-            return Err(IllegalMoves::GameOver);
+    pub fn check(&self, action: &ActionRequest) -> Result<(), IllegalMoves> {
+        let p = self.turn % self.players.len();
+        match *action {
+            ActionRequest::Play { position } => {
+                rules::check_play(self.gameover(), self.players[p].len(), position)?;
+            }
+            ActionRequest::Discard { position } => {
+                rules::check_discard(self.clues, self.gameover(), self.players[p].len(), position)?;
+            }
+            ActionRequest::ColorClue { target, color } => {
+                if !self.variant.colors().contains(&color) {
+                    return Err(IllegalMoves::InvalidClueToken);
+                }
+                self.check_clue(target, |x| self.variant.touches(color, x))?;
+            }
+            ActionRequest::ValueClue { target, value } => {
+                self.check_clue(target, |x| x.value() == value)?;
+            }
         }
+        Ok(())
+    }
+
+    fn check_clue<F>(&self, target: usize, f: F) -> Result<(), IllegalMoves>
+    where
+        F: Fn(&Card) -> bool,
+    {
         let p = self.turn % self.players.len();
-        let cards = &mut self.players[p];
-        if position >= cards.len() {
-            return Err(IllegalMoves::Error);
+        let relaxed = self.rules();
+        rules::check_clue(
+            self.players.len(),
+            p,
+            target,
+            self.clues,
+            self.gameover(),
+            relaxed,
+        )?;
+        // positions touched, on the stack since the legal mask checks every clue
+        let mut touched = [0; MAXCARDS];
+        let mut n = 0;
+        for (i, card) in self.players[target].iter().enumerate() {
+            if f(card) {
+                touched[n] = i;
+                n += 1;
+            }
+        }
+        rules::check_touched(n, relaxed)?;
+        for modifier in &self.modifiers.0 {
+            modifier.on_clue(p, target, &touched[..n])?;
         }
-        let card = cards.remove(position);
-        let success = self.table[card.color.0] == card.value.0;
+        Ok(())
+    }
+
+    // the options set by the rule modifiers
+    pub fn rules(&self) -> Rules {
+        self.modifiers
+            .0
+            .iter()
+            .fold(Rules::default(), |rules, modifier| modifier.rules(rules))
+    }
+
+    pub fn legal_mask(&self) -> Array1<f32> {
+        (0..NACTIONS)
+            .map(|id| {
+                if self.check(&ActionRequest::from_id(id)).is_ok() {
+                    1.0
+                } else {
+                    0.0
+                }
+            })
+            .collect()
+    }
+
+    pub fn apply(&mut self, action: &ActionRequest) -> Result<(), IllegalMoves> {
+        match *action {
+            ActionRequest::Play { position } => self.play(position),
+            ActionRequest::Discard { position } => self.play_discard(position),
+            ActionRequest::ColorClue { target, color } => self.clue_color(target, color),
+            ActionRequest::ValueClue { target, value } => self.clue_value(target, value),
+        }
+    }
+
+    pub fn play(&mut self, position: usize) -> Result<(), IllegalMoves> {
+        self.check(&ActionRequest::Play { position })?;
+        let p = self.turn % self.players.len();
+        let card = self.players[p].remove(position);
+        self.knowledge[p].remove(position);
+        let suit = self.variant.suit_index(card.color());
+        let success = self.table[suit] == card.value().index();
 
         if success {
-            self.table[card.color.0] += 1;
+            self.table[suit] += 1;
         } else {
             self.discard.push(card);
             self.mistakes += 1;
         }
-
-        if let Some(card) = self.deck.pop() {
-            self.players[p].insert(0, card);
+        let mut clues = if success {
+            rules::suit_bonus(card.value().index(), self.rules())
         } else {
-            self.turn_empty_deck += 1
+            0
+        };
+        for modifier in self.modifiers.0.iter_mut() {
+            clues = modifier.on_play(p, card, success, clues);
         }
+        self.clues = (self.clues + clues).min(MAXCLUES);
+
+        self.draw(p);
+        self.count_final_round();
 
-        self.history.push(Action::Play {
+        self.record(Action::Play {
             player: p,
-            position: position,
-            card: card,
-            success: success,
+            position,
+            card,
+            success,
         });
         self.turn += 1;
+        self.notify();
 
         Ok(())
     }
 
-    pub fn play_discard(&mut self, position: usize) -> Result<(), IllegalMoves> {
-        if self.clues >= MAXCLUES {
-            return Err(IllegalMoves::MaxClue);
-        }
-        if self.gameover() {
-            return Err(IllegalMoves::GameOver);
+    // the next card of the deck to `p`, where `Rules::draw_position` puts it
+    fn draw(&mut self, p: usize) {
+        if let Some(card) = self.deck.pop() {
+            let i = match self.rules().draw_position {
+                DrawPosition::First => 0,
+                DrawPosition::Last => self.players[p].len(),
+            };
+            self.players[p].insert(i, card);
+            self.knowledge[p].insert(i, Knowledge::default());
         }
+    }
+
+    pub fn play_discard(&mut self, position: usize) -> Result<(), IllegalMoves> {
+        self.check(&ActionRequest::Discard { position })?;
         let p = self.turn % self.players.len();
-        let cards = &mut self.players[p];
-        if position >= cards.len() {
-            return Err(IllegalMoves::Error);
-        }
-        let card = cards.remove(position);
+        let card = self.players[p].remove(position);
+        self.knowledge[p].remove(position);
         self.discard.push(card);
-        self.clues += 1;
-
-        if let Some(card) = self.deck.pop() {
-            self.players[p].insert(0, card);
-        } else {
-            self.turn_empty_deck += 1;
+        let mut clues = 1;
+        for modifier in self.modifiers.0.iter_mut() {
+            clues = modifier.on_discard(p, card, clues);
         }
+        self.clues = (self.clues + clues).min(MAXCLUES);
 
-        self.history.push(Action::Discard {
+        self.draw(p);
+        self.count_final_round();
+
+        self.record(Action::Discard {
             player: p,
-            position: position,
-            card: card,
+            position,
+            card,
         });
         self.turn += 1;
+        self.notify();
 
         Ok(())
     }
 
-    fn clue<F>(&mut self, target: usize, f: F) -> Result<usize, IllegalMoves>
-    where
-        F: Fn(&Card) -> bool,
-    {
-        if target >= self.players.len() {
-            return Err(IllegalMoves::Error);
-        }
-        if self.gameover() {
-            return Err(IllegalMoves::GameOver);
-        }
+    // after every action: every player, the one who drew the last card included, has one more turn
+    fn count_final_round(&mut self) {
+        self.final_round =
+            rules::count_final_round(self.final_round, self.deck.len(), self.players.len());
+    }
+
+    fn clue(&mut self, action: &ActionRequest) -> Result<usize, IllegalMoves> {
+        self.check(action)?;
         let p = self.turn % self.players.len();
-        if p == target {
-            return Err(IllegalMoves::SelfClue);
-        }
-        if self.clues == 0 {
-            return Err(IllegalMoves::NoMoreClues);
-        }
-        if !self.players[target].iter().any(f) {
-            return Err(IllegalMoves::EmptyClue);
-        }
         self.clues -= 1;
-
-        if self.deck.is_empty() {
-            self.turn_empty_deck += 1;
-        }
+        self.count_final_round();
         self.turn += 1;
 
         Ok(p)
     }
 
     pub fn clue_color(&mut self, target: usize, color: Color) -> Result<(), IllegalMoves> {
-        let p = self.clue(target, |x| x.color == color)?;
+        let p = self.clue(&ActionRequest::ColorClue { target, color })?;
+        let mut touched = Vec::new();
+        for (i, (card, knowledge)) in self.players[target]
+            .iter()
+            .zip(self.knowledge[target].iter_mut())
+            .enumerate()
+        {
+            if self.variant.touches(color, card) {
+                knowledge.color = Some(color);
+                touched.push(i);
+            } else {
+                knowledge.not_colors |= 1 << color.index();
+            }
+        }
 
-        self.history.push(Action::ColorClue {
+        self.record(Action::ColorClue {
             player: p,
-            target: target,
-            color: color,
+            target,
+            color,
+            touched,
         });
+        self.notify();
 
         Ok(())
     }
 
     pub fn clue_value(&mut self, target: usize, value: Value) -> Result<(), IllegalMoves> {
-        let p = self.clue(target, |x| x.value == value)?;
+        let p = self.clue(&ActionRequest::ValueClue { target, value })?;
+        let mut touched = Vec::new();
+        for (i, (card, knowledge)) in self.players[target]
+            .iter()
+            .zip(self.knowledge[target].iter_mut())
+            .enumerate()
+        {
+            if card.value() == value {
+                knowledge.value = Some(value);
+                touched.push(i);
+            } else {
+                knowledge.not_values |= 1 << value.index();
+            }
+        }
 
-        self.history.push(Action::ValueClue {
+        self.record(Action::ValueClue {
             player: p,
-            target: target,
-            value: value,
+            target,
+            value,
+            touched,
         });
+        self.notify();
 
         Ok(())
     }
@@ -386,24 +1037,95 @@ impl State {
         self.table.iter().sum()
     }
 
+    // the cards on the table, by suit in the order of the variant and by value
+    pub fn played(&self) -> Vec<Card> {
+        self.variant
+            .suits()
+            .iter()
+            .zip(&self.table)
+            .flat_map(|(&suit, &n)| (0..n).map(move |value| Card::new(Value::new(value), suit)))
+            .collect()
+    }
+
+    // the highest firework each suit can still reach, all the copies of the next value missing
+    // stop it, in the order of the variant
+    pub fn max_values(&self) -> Vec<usize> {
+        self.variant
+            .suits()
+            .iter()
+            .map(|&suit| {
+                (0..5)
+                    .find(|&v| {
+                        let card = Card::new(Value::new(v), suit);
+                        self.discard.iter().filter(|&&x| x == card).count() == card.value().copies()
+                    })
+                    .unwrap_or(5)
+            })
+            .collect()
+    }
+
+    // the highest score still reachable, the maximum of the variant less the cards lost
+    pub fn max_score(&self) -> usize {
+        self.max_values().iter().sum()
+    }
+
+    // score + deck + players - max score: the number of discards left before the maximum score is
+    // out of reach in the final round, negative when it is already
+    pub fn pace(&self) -> isize {
+        (self.score() + self.deck.len() + self.players.len()) as isize - self.max_score() as isize
+    }
+
+    // the score with the scoring options of `rules`, e.g. 0 after a strikeout
+    pub fn final_score(&self) -> usize {
+        rules::final_score(self.score(), self.mistakes, self.rules())
+    }
+
+    // width of a card (value and suit) and of the color part of an action in the encoding
+    pub fn encoding_widths(&self) -> (usize, usize) {
+        let nsuits = self.variant.suits().len();
+        (5 + nsuits, nsuits.max(self.variant.ncolors()))
+    }
+
+    pub fn encoding_size(&self) -> usize {
+        let (card, color) = self.encoding_widths();
+        let ncards = self.variant.deck_size();
+        (MAXPLAYERS - 2 + 1)
+            + MAXPLAYERS
+            + MAXCLUES
+            + MAXMISTAKES
+            + ncards
+            + ncards
+            + MAXPLAYERS * MAXCARDS * card
+            + 5 * self.variant.nsuits()
+            + NRULES
+            + MAXHISTORY * (4 + MAXPLAYERS + 5 + color + MAXCARDS)
+    }
+
     pub fn encode(&self) -> Array1<f32> {
-        let mut x = Array1::from_elem(
-            (MAXPLAYERS - 2 + 1)
-                + MAXPLAYERS
-                + MAXCLUES
-                + MAXMISTAKES
-                + 50
-                + 5 * 10
-                + MAXPLAYERS * MAXCARDS * 10
-                + 100 * 19,
-            -1.0,
-        );
+        self.encode_for(self.turn % self.players.len())
+    }
+
+    // observation of `player`, whose own cards are hidden
+    pub fn encode_for(&self, player: usize) -> Array1<f32> {
+        let mut x = Array1::zeros(self.encoding_size());
+        self.encode_for_into(player, &mut x.view_mut());
+        x
+    }
+
+    // `encode` written in `x`, without allocating, panics if `x` has not `encoding_size()` entries
+    pub fn encode_into(&self, x: &mut ArrayViewMut1<f32>) {
+        self.encode_for_into(self.turn % self.players.len(), x)
+    }
+
+    pub fn encode_for_into(&self, player: usize, x: &mut ArrayViewMut1<f32>) {
+        assert_eq!(x.len(), self.encoding_size());
+        let (width, cwidth) = self.encoding_widths();
+        x.fill(-1.0);
         let mut off = 0;
 
         x[off + self.players.len() - 2] = 1.0;
         off += MAXPLAYERS - 2 + 1;
 
-        let player = self.turn % self.players.len();
         x[off + player] = 1.0;
         off += MAXPLAYERS;
 
@@ -420,17 +1142,12 @@ impl State {
         for i in 0..self.deck.len() {
             x[off + i] = 1.0;
         }
-        off += 50;
+        off += self.variant.deck_size();
 
-        for color in Color::all() {
-            let cards: Vec<Card> = self
-                .discard
-                .iter()
-                .filter(|card| card.color == color)
-                .cloned()
-                .collect();
-            for value in Value::all() {
-                for i in 0..cards.iter().filter(|card| card.value == value).count() {
+        for &color in self.variant.suits() {
+            for value in (0..5).map(Value::new) {
+                let card = Card::new(value, color);
+                for i in 0..self.discard.iter().filter(|&&x| x == card).count() {
                     x[off + i] = 1.0;
                 }
                 off += value.copies();
@@ -440,13 +1157,13 @@ impl State {
         for (i, cards) in self.players.iter().enumerate() {
             if i != player {
                 for (j, card) in cards.iter().enumerate() {
-                    x[off + 10 * j + card.value.0] = 1.0;
-                    x[off + 10 * j + 5 + card.color.0] = 1.0;
+                    x[off + width * j + card.value().index()] = 1.0;
+                    x[off + width * j + 5 + self.variant.suit_index(card.color())] = 1.0;
                 }
             }
-            off += MAXCARDS * 10;
+            off += MAXCARDS * width;
         }
-        off += (MAXPLAYERS - self.players.len()) * MAXCARDS * 10;
+        off += (MAXPLAYERS - self.players.len()) * MAXCARDS * width;
 
         for &cards in &self.table {
             for _ in 0..cards {
@@ -456,8 +1173,16 @@ impl State {
             off += 5 - cards;
         }
 
-        for action in self.history.iter().rev() {
-            assert!(MAXCARDS == MAXPLAYERS);
+        for (i, &flag) in self.rules().flags().iter().enumerate() {
+            if flag {
+                x[off + i] = 1.0;
+            }
+        }
+        off += NRULES;
+
+        // the positions and the targets of the moves share their entries
+        const _: () = assert!(MAXCARDS == MAXPLAYERS);
+        for action in self.history.iter().rev().take(MAXHISTORY) {
             match action {
                 Action::Play {
                     player: _,
@@ -475,10 +1200,11 @@ impl State {
                     x[off + position] = 1.0;
                     off += MAXCARDS;
 
-                    x[off + card.value.0] = 1.0;
-                    off += 5;
-                    x[off + card.color.0] = 1.0;
+                    x[off + card.value().index()] = 1.0;
                     off += 5;
+                    x[off + self.variant.suit_index(card.color())] = 1.0;
+                    off += cwidth;
+                    off += MAXCARDS;
                 }
                 Action::Discard {
                     player: _,
@@ -491,15 +1217,17 @@ impl State {
                     x[off + position] = 1.0;
                     off += MAXCARDS;
 
-                    x[off + card.value.0] = 1.0;
-                    off += 5;
-                    x[off + card.color.0] = 1.0;
+                    x[off + card.value().index()] = 1.0;
                     off += 5;
+                    x[off + self.variant.suit_index(card.color())] = 1.0;
+                    off += cwidth;
+                    off += MAXCARDS;
                 }
                 Action::ColorClue {
                     player: _,
                     target,
                     color,
+                    touched,
                 } => {
                     x[off + 3] = 1.0;
                     off += 4;
@@ -508,13 +1236,19 @@ impl State {
                     off += MAXPLAYERS;
 
                     off += 5;
-                    x[off + color.0] = 1.0;
-                    off += 5;
+                    x[off + color.index()] = 1.0;
+                    off += cwidth;
+
+                    for &i in touched {
+                        x[off + i] = 1.0;
+                    }
+                    off += MAXCARDS;
                 }
                 Action::ValueClue {
                     player: _,
                     target,
                     value,
+                    touched,
                 } => {
                     x[off + 3] = 1.0;
                     off += 4;
@@ -522,34 +1256,56 @@ impl State {
                     x[off + target] = 1.0;
                     off += MAXPLAYERS;
 
-                    x[off + value.0] = 1.0;
-                    off += 5;
+                    x[off + value.index()] = 1.0;
                     off += 5;
+                    off += cwidth;
+
+                    for &i in touched {
+                        x[off + i] = 1.0;
+                    }
+                    off += MAXCARDS;
                 }
             }
         }
-
-        x
     }
 
     pub fn decode(&mut self, x: &ArrayView1<f32>) -> Result<(), IllegalMoves> {
-        if x.len() != 3 + MAXCARDS + MAXPLAYERS + 10 {
-            return Err(IllegalMoves::Error);
+        self.decode_with(x, TieBreak::First)
+    }
+
+    pub fn decoding_size(&self) -> usize {
+        3 + MAXCARDS + MAXPLAYERS + 5 + self.variant.ncolors()
+    }
+
+    pub fn decode_with(&mut self, x: &ArrayView1<f32>, tie: TieBreak) -> Result<(), IllegalMoves> {
+        if x.len() != self.decoding_size() {
+            return Err(IllegalMoves::DecodingSize {
+                size: x.len(),
+                expected: self.decoding_size(),
+            });
         }
-        match argmax(&x.slice(s![..3])) {
+        let colors = self.variant.colors();
+        let rng = &mut self.rng;
+        match argmax(&x.slice(s![..3]), tie, rng) {
             0 => {
-                self.play(argmax(&x.slice(s![3..3 + MAXCARDS])))?;
+                let position = argmax(&x.slice(s![3..3 + MAXCARDS]), tie, rng);
+                self.play(position)?;
             }
             1 => {
-                self.play_discard(argmax(&x.slice(s![3..3 + MAXCARDS])))?;
+                let position = argmax(&x.slice(s![3..3 + MAXCARDS]), tie, rng);
+                self.play_discard(position)?;
             }
             2 => {
-                let target = argmax(&x.slice(s![3 + MAXCARDS..3 + MAXCARDS + MAXPLAYERS]));
-                let i = argmax(&x.slice(s![-10..]));
+                let target = argmax(
+                    &x.slice(s![3 + MAXCARDS..3 + MAXCARDS + MAXPLAYERS]),
+                    tie,
+                    rng,
+                );
+                let i = argmax(&x.slice(s![3 + MAXCARDS + MAXPLAYERS..]), tie, rng);
                 if i < 5 {
                     self.clue_value(target, Value::new(i))?;
                 } else {
-                    self.clue_color(target, Color::new(i - 5))?;
+                    self.clue_color(target, colors[i - 5])?;
                 }
             }
             _ => {
@@ -560,7 +1316,7 @@ impl State {
     }
 }
 
-fn argmax(x: &ArrayView1<f32>) -> usize {
+fn argmax<R: Rng>(x: &ArrayView1<f32>, tie: TieBreak, rng: &mut R) -> usize {
     let mut i = 0;
     let mut max = x[0];
     for j in 1..x.len() {
@@ -569,5 +1325,571 @@ fn argmax(x: &ArrayView1<f32>) -> usize {
             max = x[j];
         }
     }
-    i
+    let ties: Vec<usize> = (i..x.len()).filter(|&j| x[j] == max).collect();
+    match tie {
+        TieBreak::First => i,
+        TieBreak::Last => *ties.last().unwrap(),
+        TieBreak::Random => *ties.choose(rng).unwrap(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::modifier;
+    use crate::slots::SlotOrder;
+
+    // a clue to the next player touching their first card
+    fn clue_next(state: &State) -> ActionRequest {
+        let target = (state.turn() + 1) % state.players().len();
+        ActionRequest::ValueClue {
+            target,
+            value: state.players()[target][0].value(),
+        }
+    }
+
+    // discards, or clues at the maximum of tokens, until the last card is drawn
+    fn empty_deck(nplayer: usize) -> State {
+        let mut state = State::from_deck(nplayer, Variant::standard().deck());
+        while !state.deck().is_empty() {
+            assert_eq!(*state.final_round(), None);
+            let action = if *state.clues() == MAXCLUES {
+                clue_next(&state)
+            } else {
+                ActionRequest::Discard { position: 0 }
+            };
+            state.apply(&action).unwrap();
+        }
+        state
+    }
+
+    #[test]
+    fn action_ids_of_the_standard_game_come_first() {
+        for id in 0..NACTIONS {
+            assert_eq!(ActionRequest::from_id(id).id(), id);
+        }
+        // the last id of the five standard colors, then the teal clues of every target
+        let standard = 2 * MAXCARDS + MAXPLAYERS * (5 + BASECOLORS);
+        let purple = ActionRequest::ColorClue {
+            target: MAXPLAYERS - 1,
+            color: Color::from_letter("p"),
+        };
+        assert_eq!(purple.id(), standard - 1);
+        let teal = |target| ActionRequest::ColorClue {
+            target,
+            color: Color::from_letter("t"),
+        };
+        assert_eq!(teal(0).id(), standard);
+        assert_eq!(teal(MAXPLAYERS - 1).id(), NACTIONS - 1);
+    }
+
+    #[test]
+    fn final_round_gives_every_player_one_turn() {
+        for nplayer in 2..=MAXPLAYERS {
+            let mut state = empty_deck(nplayer);
+            assert_eq!(*state.final_round(), Some(nplayer));
+            for left in (0..nplayer).rev() {
+                assert!(!state.gameover());
+                let action = if *state.clues() == MAXCLUES {
+                    clue_next(&state)
+                } else {
+                    ActionRequest::Discard { position: 0 }
+                };
+                state.apply(&action).unwrap();
+                assert_eq!(*state.final_round(), Some(left));
+            }
+            assert!(state.gameover());
+            assert!(state.apply(&ActionRequest::Play { position: 0 }).is_err());
+        }
+    }
+
+    #[test]
+    fn clues_count_as_turns_of_the_final_round() {
+        let mut state = empty_deck(3);
+        let turn = *state.turn();
+        while !state.gameover() {
+            state.apply(&clue_next(&state)).unwrap();
+        }
+        assert_eq!(*state.turn(), turn + 3);
+    }
+
+    #[test]
+    fn hand_sizes_after_the_final_round() {
+        let mut state = empty_deck(4);
+        let mut sizes = state.hand_sizes();
+        while !state.gameover() {
+            sizes.push(state.current_hand());
+            let action = if state.turn().is_multiple_of(2) && *state.clues() > 0 {
+                clue_next(&state)
+            } else {
+                ActionRequest::Play { position: 0 }
+            };
+            state.apply(&action).unwrap();
+        }
+        assert_eq!(state.hand_sizes(), sizes);
+    }
+
+    #[test]
+    fn third_strike_ends_the_game() {
+        let mut state = State::with_variant(3, Variant::standard(), Some(0));
+        for strike in 1..=MAXMISTAKES {
+            assert!(!state.gameover());
+            let hand = state.players()[state.turn() % 3];
+            let position = hand
+                .iter()
+                .position(|card| state.table()[card.color().index()] != card.value().index())
+                .unwrap();
+            state.apply(&ActionRequest::Play { position }).unwrap();
+            assert_eq!(*state.mistakes(), strike);
+        }
+        assert_eq!(*state.final_round(), None);
+        assert!(state.gameover());
+    }
+
+    #[test]
+    fn maximum_score_ends_the_game() {
+        assert!(rules::gameover(None, 0, 25, 25));
+        assert!(!rules::gameover(Some(1), 0, 24, 25));
+        assert!(rules::gameover(Some(0), 0, 0, 25));
+    }
+
+    // a 2 player game dealing every card of the first suit, played until its 5 is playable, the
+    // player to move clueing when they hold nothing to play; returns the position of the 5
+    fn until_five(modifiers: Vec<Box<dyn RuleModifier>>) -> (State, usize) {
+        let deck = Variant::standard().deck();
+        let red = deck[0].color();
+        let (mut deck, others): (Vec<Card>, Vec<Card>) =
+            deck.into_iter().partition(|card| card.color() == red);
+        deck.extend(others);
+        let mut state = State::from_deck(2, deck).modified(modifiers);
+        let mut board = state.board();
+        loop {
+            let hand = &state.players()[state.turn() % 2];
+            let playable = hand
+                .iter()
+                .position(|card| card.color() == red && card.value().index() == state.table()[0]);
+            let action = match playable {
+                Some(position) if hand[position].value().index() == 4 => return (state, position),
+                Some(position) => {
+                    let clues = *state.clues();
+                    state.apply(&ActionRequest::Play { position }).unwrap();
+                    assert_eq!(*state.clues(), clues);
+                    ActionRequest::Play { position }
+                }
+                None => {
+                    let action = clue_next(&state);
+                    state.apply(&action).unwrap();
+                    action
+                }
+            };
+            board.apply(&action.to_move()).unwrap();
+            assert_eq!(board.clues, *state.clues());
+        }
+    }
+
+    // the entries of the clue thermometer of the observation that are set
+    fn encoded_clues(state: &State) -> usize {
+        let layout = state.encoding_layout();
+        let block = layout.iter().find(|x| x.name == "clues").unwrap();
+        let x = state.encode();
+        (block.start..block.start + block.len)
+            .filter(|&i| x[i] == 1.0)
+            .count()
+    }
+
+    #[test]
+    fn playing_a_five_gives_a_clue_back() {
+        let (mut state, position) = until_five(Vec::new());
+        let clues = *state.clues();
+        assert!(clues < MAXCLUES);
+        let mut board = state.board();
+        state.apply(&ActionRequest::Play { position }).unwrap();
+        board
+            .apply(&ActionRequest::Play { position }.to_move())
+            .unwrap();
+        assert_eq!(state.table()[0], 5);
+        assert_eq!(*state.clues(), clues + 1);
+        assert_eq!(board.clues, clues + 1);
+        assert_eq!(encoded_clues(&state), clues + 1);
+    }
+
+    #[test]
+    fn the_clue_back_is_capped() {
+        let (mut state, position) = until_five(Vec::new());
+        state.clues = MAXCLUES;
+        let mut board = state.board();
+        state.apply(&ActionRequest::Play { position }).unwrap();
+        board
+            .apply(&ActionRequest::Play { position }.to_move())
+            .unwrap();
+        assert_eq!(*state.clues(), MAXCLUES);
+        assert_eq!(board.clues, MAXCLUES);
+        assert_eq!(encoded_clues(&state), MAXCLUES);
+    }
+
+    #[test]
+    fn no_suit_bonus_keeps_the_tokens() {
+        let (mut state, position) = until_five(vec![modifier::from_name("no_suit_bonus").unwrap()]);
+        let clues = *state.clues();
+        let mut board = state.board();
+        state.apply(&ActionRequest::Play { position }).unwrap();
+        board
+            .apply(&ActionRequest::Play { position }.to_move())
+            .unwrap();
+        assert_eq!(state.table()[0], 5);
+        assert_eq!(*state.clues(), clues);
+        assert_eq!(board.clues, clues);
+        assert_eq!(encoded_clues(&state), clues);
+    }
+
+    #[test]
+    fn draw_last_mirrors_the_hands() {
+        let mut rng = StdRng::seed_from_u64(1);
+        for seed in 0..50 {
+            let nplayer = 2 + seed as usize % 4;
+            let mut first = State::with_variant(nplayer, Variant::standard(), Some(seed));
+            let mut last = State::with_variant(nplayer, Variant::standard(), Some(seed))
+                .modified(vec![modifier::from_name("draw_last").unwrap()]);
+            // as if the first hands had been drawn one card at a time
+            for hand in last.players.iter_mut() {
+                hand.reverse();
+            }
+            let mut board = last.board();
+            let rules = last
+                .encoding_layout()
+                .into_iter()
+                .find(|x| x.name == "rules")
+                .unwrap();
+            while !first.gameover() {
+                let legal: Vec<usize> = (0..NACTIONS)
+                    .filter(|&id| first.check(&ActionRequest::from_id(id)).is_ok())
+                    .collect();
+                let action = ActionRequest::from_id(*legal.choose(&mut rng).unwrap());
+                let mirrored =
+                    SlotOrder::OldestFirst.convert_request(&action, first.current_hand());
+                first.apply(&action).unwrap();
+                last.apply(&mirrored).unwrap();
+                board.apply(&mirrored.to_move()).unwrap();
+                for (a, b) in first.players.iter().zip(&last.players) {
+                    assert!(a.iter().eq(b.iter().rev()));
+                }
+                // the same observation in the same slot order, but for the rules
+                let mut x = last.encode_in(0, SlotOrder::NewestFirst);
+                x.slice_mut(s![rules.start..rules.start + rules.len])
+                    .assign(
+                        &first
+                            .encode_for(0)
+                            .slice(s![rules.start..rules.start + rules.len]),
+                    );
+                assert_eq!(x, first.encode_for(0));
+                assert_eq!(board.clues, last.clues);
+                assert_eq!(board.gameover(), last.gameover());
+            }
+            assert!(last.gameover());
+            assert_eq!(first.score(), last.score());
+        }
+    }
+
+    #[test]
+    fn clues_record_the_touched_positions() {
+        let mut state = State::with_variant(3, Variant::standard(), Some(2));
+        let value = state.players()[1][0].value();
+        state
+            .apply(&ActionRequest::ValueClue { target: 1, value })
+            .unwrap();
+        let touched: Vec<usize> = (0..state.players()[1].len())
+            .filter(|&i| state.players()[1][i].value() == value)
+            .collect();
+        assert_eq!(state.history()[0].touched(), &touched[..]);
+
+        let layout = state.encoding_layout();
+        let block = layout.iter().find(|x| x.name == "move -1").unwrap();
+        let start = block.start + block.len - MAXCARDS;
+        let (x, y) = (
+            state.encode_for(0),
+            state.encode_in(0, SlotOrder::OldestFirst),
+        );
+        let hand = state.players()[1].len();
+        for i in 0..MAXCARDS {
+            let set = touched.contains(&i);
+            assert_eq!(x[start + i] == 1.0, set);
+            if i < hand {
+                assert_eq!(y[start + hand - 1 - i] == 1.0, set);
+            }
+        }
+    }
+
+    #[test]
+    fn clues_tell_the_untouched_cards_what_they_are_not() {
+        let mut state = State::with_variant(2, Variant::standard(), Some(3));
+        let mut board = state.board();
+        let card = state.players()[1][0];
+        let actions = [
+            ActionRequest::ValueClue {
+                target: 1,
+                value: card.value(),
+            },
+            ActionRequest::ColorClue {
+                target: 0,
+                color: state.players()[0][1].color(),
+            },
+            ActionRequest::Discard { position: 0 },
+        ];
+        for action in &actions {
+            state.apply(action).unwrap();
+            board.apply(&action.to_move()).unwrap();
+        }
+        for player in 0..2 {
+            let constraints = state.constraints(player);
+            for (i, (card, knowledge)) in state.players()[player]
+                .iter()
+                .zip(&state.knowledge()[player])
+                .enumerate()
+            {
+                assert!(knowledge.allows(card, state.variant()));
+                let [s, v] = state.index(card);
+                assert_eq!(constraints[[i, s, v]], 1.0);
+                assert_eq!(board.told[player][i].not_values, knowledge.not_values);
+                assert_eq!(board.told[player][i].not_colors, knowledge.not_colors);
+            }
+        }
+        let hand = &state.players()[1];
+        let knowledge = &state.knowledge()[1];
+        for i in 0..hand.len() {
+            if hand[i].value() != card.value() {
+                assert!(!knowledge[i].allows(&card, state.variant()));
+            }
+        }
+    }
+
+    #[test]
+    fn board_ends_with_the_state() {
+        let mut rng = StdRng::seed_from_u64(0);
+        for seed in 0..200 {
+            let mut state =
+                State::with_variant(2 + seed as usize % 4, Variant::standard(), Some(seed));
+            let mut board = state.board();
+            while !state.gameover() {
+                let legal: Vec<usize> = (0..NACTIONS)
+                    .filter(|&id| state.check(&ActionRequest::from_id(id)).is_ok())
+                    .collect();
+                let action = ActionRequest::from_id(*legal.choose(&mut rng).unwrap());
+                state.apply(&action).unwrap();
+                board.apply(&action.to_move()).unwrap();
+                assert_eq!(board.final_round, *state.final_round());
+                assert_eq!(board.gameover(), state.gameover());
+            }
+        }
+    }
+
+    #[test]
+    fn redeal_is_a_new_deal() {
+        let mut rng = StdRng::seed_from_u64(0);
+        for seed in 0..50 {
+            let players = 2 + seed as usize % 4;
+            let modifiers = || -> Vec<Box<dyn RuleModifier>> {
+                (seed % 2 == 1)
+                    .then(|| modifier::from_name("clue_starved").unwrap())
+                    .into_iter()
+                    .collect()
+            };
+            let mut state =
+                State::with_variant(players, Variant::standard(), Some(seed)).modified(modifiers());
+            while !state.gameover() {
+                let legal: Vec<usize> = (0..NACTIONS)
+                    .filter(|&id| state.check(&ActionRequest::from_id(id)).is_ok())
+                    .collect();
+                state
+                    .apply(&ActionRequest::from_id(*legal.choose(&mut rng).unwrap()))
+                    .unwrap();
+            }
+            state.redeal(seed + 100);
+            let fresh = State::with_variant(players, Variant::standard(), Some(seed + 100))
+                .modified(modifiers());
+            assert_eq!(format!("{:?}", state), format!("{:?}", fresh));
+        }
+    }
+
+    // smaller hands, and a clue regained by the first discard of a game only
+    #[derive(Clone, Default)]
+    struct FirstDiscard {
+        discards: usize,
+    }
+
+    impl RuleModifier for FirstDiscard {
+        fn name(&self) -> &str {
+            "first_discard"
+        }
+
+        fn box_clone(&self) -> Box<dyn RuleModifier> {
+            Box::new(self.clone())
+        }
+
+        fn fresh(&self) -> Box<dyn RuleModifier> {
+            Box::new(FirstDiscard::default())
+        }
+
+        fn hand_size(&self, _nplayer: usize, size: usize) -> usize {
+            size - 1
+        }
+
+        fn on_discard(&mut self, _player: usize, _card: Card, clues: usize) -> usize {
+            self.discards += 1;
+            if self.discards == 1 {
+                clues
+            } else {
+                0
+            }
+        }
+    }
+
+    #[test]
+    fn redeal_starts_the_user_defined_modifiers_again() {
+        let mut state = State::with_seed(2, 0).modified(vec![Box::new(FirstDiscard::default())]);
+        let clue_and_discard = |state: &mut State| {
+            state.apply(&clue_next(state)).unwrap();
+            state
+                .apply(&ActionRequest::Discard { position: 0 })
+                .unwrap();
+            *state.clues()
+        };
+        assert_eq!(clue_and_discard(&mut state), MAXCLUES);
+        assert_eq!(clue_and_discard(&mut state), MAXCLUES - 1);
+        state.redeal(1);
+        assert_eq!(state.modifiers().names(), vec!["first_discard"]);
+        assert_eq!(state.players()[0].len(), 4);
+        assert_eq!(clue_and_discard(&mut state), MAXCLUES);
+    }
+
+    #[test]
+    fn forks_play_on_like_the_game() {
+        let mut rng = StdRng::seed_from_u64(0);
+        for seed in 0..20 {
+            let mut state = State::with_seed(2 + seed as usize % 4, seed)
+                .modified(vec![modifier::from_name("clue_starved").unwrap()]);
+            let mut forks = Vec::new();
+            while !state.gameover() {
+                // half a token counted by the modifier is kept in the fork
+                forks.push(state.fork());
+                let legal: Vec<usize> = (0..NACTIONS)
+                    .filter(|&id| state.check(&ActionRequest::from_id(id)).is_ok())
+                    .collect();
+                let action = ActionRequest::from_id(*legal.choose(&mut rng).unwrap());
+                for state in forks.iter_mut().chain(Some(&mut state)) {
+                    state.apply(&action).unwrap();
+                }
+                for fork in &forks {
+                    assert!(*fork == state);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn compacted_history_keeps_the_last_actions() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let text = |x: &VecDeque<Action>, skip: usize| -> Vec<String> {
+            x.iter().skip(skip).map(|x| x.to_string()).collect()
+        };
+        let ids = |x: &State| -> Vec<usize> { x.requests().iter().map(|x| x.id()).collect() };
+        for seed in 0..20 {
+            let mut whole = State::with_seed(2 + seed as usize % 4, seed);
+            let mut ring = whole.fork();
+            ring.set_history_capacity(Some(MAXHISTORY));
+            let mut short = whole.fork();
+            short.set_history_capacity(Some(7));
+            while !whole.gameover() {
+                let legal: Vec<usize> = (0..NACTIONS)
+                    .filter(|&id| whole.check(&ActionRequest::from_id(id)).is_ok())
+                    .collect();
+                let action = ActionRequest::from_id(*legal.choose(&mut rng).unwrap());
+                for state in [&mut whole, &mut ring, &mut short] {
+                    state.apply(&action).unwrap();
+                }
+                assert_eq!(ring.encode(), whole.encode());
+                let n = whole.history().len();
+                assert_eq!(
+                    text(short.history(), 0),
+                    text(whole.history(), n.saturating_sub(7))
+                );
+                assert_eq!(short.compacted().len() + short.history().len(), n);
+                assert!(short.history().capacity() <= 7);
+                // the actions dropped are still those of the game
+                assert_eq!(ids(&short), ids(&whole));
+            }
+            whole.compact_history(3);
+            assert_eq!(text(whole.history(), 0), text(short.history(), 4));
+        }
+    }
+
+    #[test]
+    fn encoding_size_fits_full_games_of_every_variant() {
+        for variant in [Variant::six_suits(), Variant::dual_color()] {
+            let mut state = State::with_variant(2, variant, Some(0));
+            // a discard for each clue, the longest game the deck allows
+            while !state.gameover() {
+                let action = if *state.clues() == MAXCLUES {
+                    clue_next(&state)
+                } else {
+                    ActionRequest::Discard { position: 0 }
+                };
+                state.apply(&action).unwrap();
+                assert_eq!(state.encode().len(), state.encoding_size());
+            }
+            assert!(state.history().len() >= MAXHISTORY);
+            let layout = state.encoding_layout();
+            let last = layout.last().unwrap();
+            assert_eq!(last.start + last.len, state.encoding_size());
+        }
+    }
+
+    #[test]
+    fn broken_states_are_caught() {
+        let state = State::with_seed(3, 0);
+        assert_eq!(state.check_invariants(), Ok(()));
+        let mut broken = state.fork();
+        broken.clues = MAXCLUES + 1;
+        assert!(broken.check_invariants().is_err());
+        let mut broken = state.fork();
+        let card = broken.deck.pop().unwrap();
+        broken.discard.extend(&[card, card]);
+        assert!(broken.check_invariants().is_err());
+        let mut broken = state.fork();
+        broken.knowledge[1][0].not_values = 0b11111;
+        assert!(broken.check_invariants().is_err());
+        let mut broken = state.fork();
+        broken.players[0].pop();
+        assert!(broken.check_invariants().is_err());
+    }
+
+    #[test]
+    fn card_indices_round_trip() {
+        for index in 0..35 {
+            assert_eq!(Card::from_index(index).unwrap().index(), index);
+        }
+        assert!(Card::from_index(35).is_none());
+        let card = |x: &str| Card::try_from(x.to_string()).unwrap();
+        assert_eq!(card("3r").index(), 2);
+        assert_eq!(card("5p").index(), 24);
+        let codes: Vec<u8> = Variant::standard().deck().iter().map(Card::index).collect();
+        assert!(codes.iter().all(|&x| x < 25));
+    }
+
+    #[test]
+    fn positions_round_trip() {
+        for position in crate::endgames::puzzles() {
+            let state = State::from_position(&position).unwrap();
+            let back = Position::of_state(&state);
+            assert_eq!(back.hands, position.hands);
+            assert_eq!(back.deck, position.deck);
+            assert_eq!(back.fireworks, position.fireworks);
+            assert_eq!(back.turn, position.turn);
+            // every card of the deck is somewhere
+            let cards = state.players().concat().len() + state.deck().len();
+            assert_eq!(
+                cards + state.score() + state.discard().len(),
+                state.variant().deck().len()
+            );
+        }
+    }
 }
@@ -0,0 +1,306 @@
+use crate::agents::{is_critical, is_playable, is_useless};
+use crate::efficiency::Efficiency;
+use crate::manifest::fnv1a;
+use crate::replay::{Anonymization, Names, Replay};
+use crate::state::{ActionRequest, State, MAXPLAYERS};
+use rayon::prelude::*;
+use std::collections::{BTreeMap, HashMap};
+
+// what a seat did over the games
+#[derive(Clone, Debug, Default)]
+pub struct Seat {
+    pub plays: usize,
+    // plays of a card that is not playable, a mistake
+    pub misplays: usize,
+    pub discards: usize,
+    pub color_clues: usize,
+    pub value_clues: usize,
+}
+
+impl Seat {
+    pub fn actions(&self) -> usize {
+        self.plays + self.discards + self.color_clues + self.value_clues
+    }
+
+    fn merge(&mut self, other: &Seat) {
+        self.plays += other.plays;
+        self.misplays += other.misplays;
+        self.discards += other.discards;
+        self.color_clues += other.color_clues;
+        self.value_clues += other.value_clues;
+    }
+}
+
+// a game that should not be trained on as is
+#[derive(Clone, Debug)]
+pub enum Anomaly {
+    // the replay cannot be loaded or played
+    Invalid { path: String, error: String },
+    // the actions stop before the end of the game
+    Truncated { path: String, turns: usize },
+    // the same deal and actions as an earlier game, whoever played it
+    Duplicate { path: String, of: String },
+}
+
+// summary of a dataset of replays (of people or of agents), to vet it before training
+#[derive(Clone, Debug, Default)]
+pub struct Report {
+    pub games: usize,
+    pub turns: usize,
+    // number of games of each final score
+    pub scores: BTreeMap<usize, usize>,
+    // games with a perfect score
+    pub perfect: usize,
+    pub seats: Vec<Seat>,
+    pub anomalies: Vec<Anomaly>,
+}
+
+struct Game {
+    hash: u64,
+    score: usize,
+    perfect: bool,
+    gameover: bool,
+    turns: usize,
+    seats: Vec<Seat>,
+}
+
+impl Game {
+    fn of_replay(replay: &Replay) -> Result<Game, String> {
+        let mut seats = vec![Seat::default(); replay.players];
+        let state = replay
+            .play(|state, action| {
+                let player = state.turn() % state.players().len();
+                let seat = &mut seats[player];
+                match *action {
+                    ActionRequest::Play { position } => {
+                        seat.plays += 1;
+                        if !is_playable(state, &state.players()[player][position]) {
+                            seat.misplays += 1;
+                        }
+                    }
+                    ActionRequest::Discard { .. } => seat.discards += 1,
+                    ActionRequest::ColorClue { .. } => seat.color_clues += 1,
+                    ActionRequest::ValueClue { .. } => seat.value_clues += 1,
+                }
+            })
+            .map_err(|err| format!("{:?}", err))?;
+        // the names, notes and tags do not make a game different
+        let anonymization = Anonymization {
+            names: Names::Remove,
+            keep_notes: false,
+        };
+        let mut game = replay.anonymized(&anonymization);
+        game.tags.clear();
+        Ok(Game {
+            hash: fnv1a(game.to_json().as_bytes()),
+            score: state.score(),
+            perfect: state.score() == state.variant().max_score(),
+            gameover: state.gameover(),
+            turns: replay.actions.len(),
+            seats,
+        })
+    }
+}
+
+impl Report {
+    pub fn clues(&self) -> (usize, usize) {
+        let color = self.seats.iter().map(|x| x.color_clues).sum();
+        let value = self.seats.iter().map(|x| x.value_clues).sum();
+        (color, value)
+    }
+
+    // share of the clues that are value clues
+    pub fn value_clue_ratio(&self) -> f64 {
+        let (color, value) = self.clues();
+        ratio(value, color + value)
+    }
+
+    pub fn mean_score(&self) -> f64 {
+        ratio(
+            self.scores.iter().map(|(score, n)| score * n).sum(),
+            self.games,
+        )
+    }
+
+    // the games of a dataset read from `paths`, in that order for the anomalies
+    pub fn from_files(paths: &[String]) -> Report {
+        let games: Vec<Result<Game, String>> = paths
+            .par_iter()
+            .map(|path| Replay::load(path).map_err(|err| format!("{:?}", err)))
+            .map(|replay| Game::of_replay(&replay?))
+            .collect();
+
+        let mut report = Report {
+            seats: vec![Seat::default(); MAXPLAYERS],
+            ..Report::default()
+        };
+        let mut seen: HashMap<u64, &String> = HashMap::new();
+        for (path, game) in paths.iter().zip(games) {
+            let game = match game {
+                Ok(game) => game,
+                Err(error) => {
+                    report.anomalies.push(Anomaly::Invalid {
+                        path: path.clone(),
+                        error,
+                    });
+                    continue;
+                }
+            };
+            if let Some(&of) = seen.get(&game.hash) {
+                report.anomalies.push(Anomaly::Duplicate {
+                    path: path.clone(),
+                    of: of.clone(),
+                });
+            }
+            seen.entry(game.hash).or_insert(path);
+            if !game.gameover {
+                report.anomalies.push(Anomaly::Truncated {
+                    path: path.clone(),
+                    turns: game.turns,
+                });
+            }
+            report.games += 1;
+            report.turns += game.turns;
+            *report.scores.entry(game.score).or_insert(0) += 1;
+            if game.perfect {
+                report.perfect += 1;
+            }
+            for (total, seat) in report.seats.iter_mut().zip(&game.seats) {
+                total.merge(seat);
+            }
+        }
+        // up to the largest table of the dataset
+        while report.seats.last().is_some_and(|x| x.actions() == 0) {
+            report.seats.pop();
+        }
+        report
+    }
+}
+
+// clues and tempo of one game, to compare agents beyond the score
+#[derive(Clone, Debug, Default)]
+pub struct Tempo {
+    pub turns: usize,
+    pub clues: usize,
+    // cards touched for the first time by a clue that are not bad touches, see `Efficiency`
+    pub cards_gotten: usize,
+    pub misplays: usize,
+    pub critical_discards: usize,
+    // misplays, discards of a card still useful and clues that get no card
+    pub wasted_turns: usize,
+}
+
+impl Tempo {
+    // the game so far of `state`, replayed from its deal
+    pub fn of_state(state: &State) -> Tempo {
+        let mut tempo = Tempo::default();
+        let mut touches = Efficiency::default();
+        Replay::from_state(state)
+            .play(|state, action| {
+                let card = |position: usize| {
+                    let player = state.turn() % state.players().len();
+                    state.players()[player][position]
+                };
+                tempo.turns += 1;
+                match *action {
+                    ActionRequest::Play { position } => {
+                        if !is_playable(state, &card(position)) {
+                            tempo.misplays += 1;
+                            tempo.wasted_turns += 1;
+                        }
+                    }
+                    ActionRequest::Discard { position } => {
+                        let card = card(position);
+                        if is_critical(state, &card) {
+                            tempo.critical_discards += 1;
+                        }
+                        if !is_useless(state, &card) {
+                            tempo.wasted_turns += 1;
+                        }
+                    }
+                    ActionRequest::ColorClue { target, color } => {
+                        let gotten = touches.touched - touches.bad_touches;
+                        touches.touch(state, target, |card| state.variant().touches(color, card));
+                        tempo.clue(touches.touched - touches.bad_touches - gotten);
+                    }
+                    ActionRequest::ValueClue { target, value } => {
+                        let gotten = touches.touched - touches.bad_touches;
+                        touches.touch(state, target, |card| card.value() == value);
+                        tempo.clue(touches.touched - touches.bad_touches - gotten);
+                    }
+                }
+            })
+            .expect("the history of a state replays");
+        tempo
+    }
+
+    fn clue(&mut self, gotten: usize) {
+        self.clues += 1;
+        self.cards_gotten += gotten;
+        if gotten == 0 {
+            self.wasted_turns += 1;
+        }
+    }
+
+    // cards gotten per clue given
+    pub fn efficiency(&self) -> f64 {
+        ratio(self.cards_gotten, self.clues)
+    }
+}
+
+fn ratio(a: usize, b: usize) -> f64 {
+    if b == 0 {
+        0.0
+    } else {
+        a as f64 / b as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agents::{Agent, ConventionBot};
+    use crate::driver::GameDriver;
+    use crate::variant::Variant;
+    use std::fs;
+
+    #[test]
+    fn invalid_truncated_and_duplicate_replays_are_reported() {
+        let dir = std::env::temp_dir().join(format!("hanabi-stats-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = |name: &str| dir.join(name).to_string_lossy().into_owned();
+        let seats: Vec<Box<dyn Agent>> = vec![Box::new(ConventionBot), Box::new(ConventionBot)];
+        let mut replay = GameDriver::new(Variant::standard(), seats)
+            .play(Some(0))
+            .replay;
+        replay.save(path("game.json")).unwrap();
+        // the names and the tags do not make another game
+        replay.names = Some(vec!["alice".to_string(), "bob".to_string()]);
+        replay.tags.insert("study".to_string(), "pilot".to_string());
+        replay.save(path("again.json")).unwrap();
+        replay.actions.truncate(4);
+        replay.save(path("short.json")).unwrap();
+        fs::write(path("broken.json"), "{\"players\": 2,").unwrap();
+
+        let paths: Vec<String> = ["game.json", "again.json", "short.json", "broken.json"]
+            .iter()
+            .map(|x| path(x))
+            .collect();
+        let report = Report::from_files(&paths);
+        assert_eq!(report.games, 3);
+        assert_eq!(report.anomalies.len(), 3);
+        assert!(matches!(
+            &report.anomalies[0],
+            Anomaly::Duplicate { path, of } if *path == paths[1] && *of == paths[0]
+        ));
+        assert!(matches!(
+            &report.anomalies[1],
+            Anomaly::Truncated { path, turns: 4 } if *path == paths[2]
+        ));
+        assert!(matches!(
+            &report.anomalies[2],
+            Anomaly::Invalid { path, .. } if *path == paths[3]
+        ));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,227 @@
+// finished games in sqlite, for self-play corpora, built with the `storage` feature
+//
+// a game is stored as its replay with its seed, variant, players, modifiers, score and turns as
+// columns to select on; games are read back in the order of their ids as replays or as the
+// tensors of `dataset`, a batch at a time so that a corpus never has to fit in memory
+use crate::dataset::{self, Dataset};
+use crate::encoder::Encoder;
+use crate::replay::{Replay, ReplayError};
+use crate::state::State;
+use rusqlite::types::Value;
+use rusqlite::{params, params_from_iter, Connection};
+use std::path::Path;
+
+#[derive(Debug)]
+pub enum StorageError {
+    Sql(rusqlite::Error),
+    Replay(ReplayError),
+}
+
+impl From<rusqlite::Error> for StorageError {
+    fn from(err: rusqlite::Error) -> StorageError {
+        StorageError::Sql(err)
+    }
+}
+
+impl From<ReplayError> for StorageError {
+    fn from(err: ReplayError) -> StorageError {
+        StorageError::Replay(err)
+    }
+}
+
+// the games to read, every game by default
+#[derive(Clone, Debug, Default)]
+pub struct Query {
+    pub variant: Option<String>,
+    pub players: Option<usize>,
+    pub min_score: Option<usize>,
+    // only the games of a greater id, to read a corpus in several passes
+    pub after: Option<i64>,
+    pub limit: Option<usize>,
+}
+
+impl Query {
+    fn sql(&self, columns: &str) -> (String, Vec<Value>) {
+        let mut conditions = Vec::new();
+        let mut values = Vec::new();
+        if let Some(variant) = &self.variant {
+            conditions.push("variant = ?");
+            values.push(Value::Text(variant.clone()));
+        }
+        if let Some(players) = self.players {
+            conditions.push("players = ?");
+            values.push(Value::Integer(players as i64));
+        }
+        if let Some(score) = self.min_score {
+            conditions.push("score >= ?");
+            values.push(Value::Integer(score as i64));
+        }
+        if let Some(after) = self.after {
+            conditions.push("id > ?");
+            values.push(Value::Integer(after));
+        }
+        let mut sql = format!("SELECT {} FROM games", columns);
+        if !conditions.is_empty() {
+            sql += &format!(" WHERE {}", conditions.join(" AND "));
+        }
+        sql += " ORDER BY id";
+        if let Some(limit) = self.limit {
+            sql += &format!(" LIMIT {}", limit);
+        }
+        (sql, values)
+    }
+}
+
+pub struct Stored {
+    pub id: i64,
+    pub seed: Option<u64>,
+    pub score: usize,
+    pub replay: Replay,
+}
+
+// `state` is the end of the game of `replay`
+fn insert(
+    conn: &Connection,
+    replay: &Replay,
+    seed: Option<u64>,
+    state: &State,
+) -> Result<i64, StorageError> {
+    conn.execute(
+        "INSERT INTO games (seed, variant, players, modifiers, replay, score, turns)
+        VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+        params![
+            // sqlite integers are signed, the bits of the seed are kept
+            seed.map(|seed| seed as i64),
+            replay.variant.name(),
+            replay.players as i64,
+            replay.modifiers.join(","),
+            replay.to_json(),
+            state.score() as i64,
+            *state.turn() as i64,
+        ],
+    )?;
+    Ok(conn.last_insert_rowid())
+}
+
+pub struct Storage {
+    conn: Connection,
+}
+
+impl Storage {
+    // opens the database, creating it if needed
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Storage, StorageError> {
+        let conn = Connection::open(path)?;
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS games (
+                id INTEGER PRIMARY KEY,
+                seed INTEGER,
+                variant TEXT NOT NULL,
+                players INTEGER NOT NULL,
+                modifiers TEXT NOT NULL,
+                replay TEXT NOT NULL,
+                score INTEGER NOT NULL,
+                turns INTEGER NOT NULL
+            );
+            CREATE INDEX IF NOT EXISTS games_config ON games (variant, players, score);",
+        )?;
+        Ok(Storage { conn })
+    }
+
+    // stores a game, returns its id
+    pub fn insert(&self, state: &State) -> Result<i64, StorageError> {
+        insert(&self.conn, &Replay::from_state(state), *state.seed(), state)
+    }
+
+    // stores the games in a single transaction, much faster than one at a time
+    pub fn insert_all<'a, I>(&mut self, states: I) -> Result<Vec<i64>, StorageError>
+    where
+        I: IntoIterator<Item = &'a State>,
+    {
+        let transaction = self.conn.transaction()?;
+        let ids = states
+            .into_iter()
+            .map(|state| {
+                insert(
+                    &transaction,
+                    &Replay::from_state(state),
+                    *state.seed(),
+                    state,
+                )
+            })
+            .collect::<Result<Vec<i64>, StorageError>>()?;
+        transaction.commit()?;
+        Ok(ids)
+    }
+
+    // stores a replay played to its end, returns its id
+    pub fn insert_replay(&self, replay: &Replay, seed: Option<u64>) -> Result<i64, StorageError> {
+        let state = replay.play(|_, _| {})?;
+        insert(&self.conn, replay, seed, &state)
+    }
+
+    pub fn count(&self, query: &Query) -> Result<usize, StorageError> {
+        let (sql, values) = Query {
+            limit: None,
+            ..query.clone()
+        }
+        .sql("COUNT(*)");
+        let n: i64 = self
+            .conn
+            .query_row(&sql, params_from_iter(values), |row| row.get(0))?;
+        Ok(match query.limit {
+            Some(limit) => (n as usize).min(limit),
+            None => n as usize,
+        })
+    }
+
+    // calls `f` on every game of the query, one row at a time
+    pub fn for_each<F>(&self, query: &Query, mut f: F) -> Result<(), StorageError>
+    where
+        F: FnMut(Stored) -> Result<(), StorageError>,
+    {
+        let (sql, values) = query.sql("id, seed, score, replay");
+        let mut statement = self.conn.prepare(&sql)?;
+        let mut rows = statement.query(params_from_iter(values))?;
+        while let Some(row) = rows.next()? {
+            let json: String = row.get(3)?;
+            f(Stored {
+                id: row.get(0)?,
+                seed: row.get::<_, Option<i64>>(1)?.map(|seed| seed as u64),
+                score: row.get::<_, i64>(2)? as usize,
+                replay: Replay::from_json(&json)?,
+            })?;
+        }
+        Ok(())
+    }
+
+    // calls `f` with the tensors of `batch` games at a time (see `dataset`) and their ids
+    pub fn for_each_batch<F>(
+        &self,
+        query: &Query,
+        batch: usize,
+        gamma: f32,
+        encoder: Encoder,
+        mut f: F,
+    ) -> Result<(), StorageError>
+    where
+        F: FnMut(Dataset, Vec<i64>) -> Result<(), StorageError>,
+    {
+        let batch = batch.max(1);
+        let mut ids = Vec::new();
+        let mut replays = Vec::new();
+        self.for_each(query, |stored| {
+            ids.push(stored.id);
+            replays.push(stored.replay);
+            if replays.len() == batch {
+                let data = dataset::from_replays(&replays, gamma, encoder)?;
+                replays.clear();
+                f(data, std::mem::take(&mut ids))?;
+            }
+            Ok(())
+        })?;
+        if !replays.is_empty() {
+            f(dataset::from_replays(&replays, gamma, encoder)?, ids)?;
+        }
+        Ok(())
+    }
+}
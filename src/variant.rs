@@ -0,0 +1,124 @@
+use crate::rules;
+use crate::state::{Card, Color, Value, MAXCOLORS};
+use serde::{Deserialize, Serialize};
+use std::convert::TryFrom;
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Variant {
+    name: String,
+    suits: Vec<Color>,
+}
+
+impl Variant {
+    pub fn standard() -> Variant {
+        Variant {
+            name: "standard".to_string(),
+            suits: Color::all(),
+        }
+    }
+
+    // a sixth suit, teal, with its own clue color
+    pub fn six_suits() -> Variant {
+        let mut suits = Color::all();
+        suits.push(Color::t());
+        Variant {
+            name: "six_suits".to_string(),
+            suits,
+        }
+    }
+
+    // a sixth suit, orange, touched by both red and yellow clues
+    pub fn dual_color() -> Variant {
+        let mut suits = Color::all();
+        suits.push(Color::o());
+        Variant {
+            name: "dual_color".to_string(),
+            suits,
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Variant> {
+        match name {
+            "standard" => Some(Variant::standard()),
+            "six_suits" => Some(Variant::six_suits()),
+            "dual_color" => Some(Variant::dual_color()),
+            _ => None,
+        }
+    }
+
+    pub fn names() -> Vec<&'static str> {
+        vec!["standard", "six_suits", "dual_color"]
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn suits(&self) -> &[Color] {
+        &self.suits
+    }
+
+    pub fn nsuits(&self) -> usize {
+        self.suits.len()
+    }
+
+    // the colors that can be clued, in `Color` order
+    pub fn colors(&self) -> Vec<Color> {
+        (0..MAXCOLORS)
+            .filter(|&c| self.suits.iter().any(|s| rules::touches(c, s.index())))
+            .map(Color::new)
+            .collect()
+    }
+
+    // `colors().len()` without collecting them, for the encoders
+    pub fn ncolors(&self) -> usize {
+        (0..MAXCOLORS)
+            .filter(|&c| self.suits.iter().any(|s| rules::touches(c, s.index())))
+            .count()
+    }
+
+    pub fn touches(&self, color: Color, card: &Card) -> bool {
+        rules::touches(color.index(), card.color().index())
+    }
+
+    // position of the suit in `suits`, used to index the fireworks
+    pub fn suit_index(&self, suit: Color) -> usize {
+        self.suits.iter().position(|&x| x == suit).unwrap()
+    }
+
+    pub fn deck(&self) -> Vec<Card> {
+        let mut deck = Vec::new();
+        for &color in &self.suits {
+            for value in Value::all() {
+                for _ in 0..value.copies() {
+                    deck.push(Card::new(value, color));
+                }
+            }
+        }
+        deck
+    }
+
+    // `deck().len()` without building the deck
+    pub fn deck_size(&self) -> usize {
+        self.suits.len() * rules::COPIES.iter().sum::<usize>()
+    }
+
+    pub fn max_score(&self) -> usize {
+        5 * self.suits.len()
+    }
+}
+
+impl TryFrom<String> for Variant {
+    type Error = String;
+
+    fn try_from(name: String) -> Result<Variant, String> {
+        Variant::from_name(&name).ok_or_else(|| format!("unknown variant {:?}", name))
+    }
+}
+
+impl From<Variant> for String {
+    fn from(variant: Variant) -> String {
+        variant.name
+    }
+}
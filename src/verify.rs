@@ -0,0 +1,184 @@
+// checks a score claimed by a third party (a bot author, a leaderboard submission) by playing its
+// actions again, e.g.
+//
+//     {"players": 3, "variant": "standard", "seed": 42, "actions": [...], "score": 21}
+//
+// the deal is given by its `seed` (see `State::with_seed`), its `deck` (as in a replay) or both,
+// the other fields are those of a replay
+use crate::replay::{Replay, ReplayError};
+use crate::slots::SlotOrder;
+use crate::state::{ActionRequest, Card, State};
+use crate::variant::Variant;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+#[derive(Debug)]
+pub enum VerifyError {
+    Io(std::io::Error),
+    Json(serde_json::Error),
+    Invalid(String),
+    Replay(ReplayError),
+    Unfinished { turn: usize },
+    Score { claimed: usize, actual: usize },
+}
+
+impl From<std::io::Error> for VerifyError {
+    fn from(err: std::io::Error) -> VerifyError {
+        VerifyError::Io(err)
+    }
+}
+
+impl From<serde_json::Error> for VerifyError {
+    fn from(err: serde_json::Error) -> VerifyError {
+        VerifyError::Json(err)
+    }
+}
+
+impl From<ReplayError> for VerifyError {
+    fn from(err: ReplayError) -> VerifyError {
+        VerifyError::Replay(err)
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Claim {
+    pub players: usize,
+    #[serde(default = "Variant::standard")]
+    pub variant: Variant,
+    #[serde(default)]
+    pub modifiers: Vec<String>,
+    #[serde(default)]
+    pub slots: SlotOrder,
+    pub seed: Option<u64>,
+    pub deck: Option<Vec<Card>>,
+    pub actions: Vec<ActionRequest>,
+    pub score: usize,
+}
+
+impl Claim {
+    pub fn from_json(json: &str) -> Result<Claim, VerifyError> {
+        Ok(serde_json::from_str(json)?)
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Claim, VerifyError> {
+        Claim::from_json(&fs::read_to_string(path)?)
+    }
+
+    // the deck of the seed, or the one given, which must then be the deck of the seed
+    fn deck(&self) -> Result<Vec<Card>, VerifyError> {
+        let seeded = self.seed.map(|seed| {
+            State::with_variant(self.players, self.variant.clone(), Some(seed))
+                .initial_deck()
+                .clone()
+        });
+        match (seeded, &self.deck) {
+            (Some(seeded), Some(deck)) if &seeded != deck => Err(VerifyError::Invalid(format!(
+                "the deck is not the one of seed {}",
+                self.seed.unwrap()
+            ))),
+            (Some(seeded), _) => Ok(seeded),
+            (None, Some(deck)) => Ok(deck.clone()),
+            (None, None) => Err(VerifyError::Invalid(
+                "neither a seed nor a deck".to_string(),
+            )),
+        }
+    }
+
+    pub fn replay(&self) -> Result<Replay, VerifyError> {
+        if self.players < 2 || self.players > 5 {
+            return Err(VerifyError::Invalid(format!(
+                "{} players is not supported",
+                self.players
+            )));
+        }
+        Ok(Replay {
+            players: self.players,
+            variant: self.variant.clone(),
+            modifiers: self.modifiers.clone(),
+            slots: self.slots,
+            deck: self.deck()?,
+            actions: self.actions.clone(),
+            names: None,
+            notes: Vec::new(),
+            tags: BTreeMap::new(),
+        })
+    }
+
+    // plays the actions to the end of the game and compares the scores, returns the final state
+    pub fn verify(&self) -> Result<State, VerifyError> {
+        let state = self.replay()?.play(|_, _| {})?;
+        if !state.gameover() {
+            return Err(VerifyError::Unfinished {
+                turn: *state.turn(),
+            });
+        }
+        if state.score() != self.score {
+            return Err(VerifyError::Score {
+                claimed: self.score,
+                actual: state.score(),
+            });
+        }
+        Ok(state)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Claim, VerifyError};
+    use crate::agents::{Agent, ConventionBot, Observation};
+    use crate::replay::Replay;
+    use crate::state::State;
+
+    // the json claim of a game of the convention bot, with its seed or its deck
+    fn claim(seed: bool, deck: bool, score: Option<usize>, drop: usize) -> String {
+        let mut state = State::with_seed(4, 11);
+        while !state.gameover() {
+            let action = ConventionBot.act(&Observation::new(&state));
+            state.apply(&action).unwrap();
+        }
+        let mut json = serde_json::to_value(Replay::from_state(&state)).unwrap();
+        let claim = json.as_object_mut().unwrap();
+        let actions = claim["actions"].as_array_mut().unwrap();
+        actions.truncate(actions.len() - drop);
+        claim.insert("score".to_string(), score.unwrap_or(state.score()).into());
+        if seed {
+            claim.insert("seed".to_string(), 11.into());
+        }
+        if !deck {
+            claim.remove("deck");
+        }
+        json.to_string()
+    }
+
+    #[test]
+    fn claims_are_played_again() {
+        for &(seed, deck) in &[(true, false), (false, true), (true, true)] {
+            let json = claim(seed, deck, None, 0);
+            assert!(Claim::from_json(&json).unwrap().verify().is_ok());
+        }
+        let json = claim(true, false, Some(26), 0);
+        match Claim::from_json(&json).unwrap().verify() {
+            Err(VerifyError::Score { claimed: 26, .. }) => {}
+            other => panic!("{:?}", other.map(|x| x.score())),
+        }
+        let json = claim(true, false, None, 3);
+        match Claim::from_json(&json).unwrap().verify() {
+            Err(VerifyError::Unfinished { .. }) => {}
+            other => panic!("{:?}", other.map(|x| x.score())),
+        }
+        let json = claim(false, false, None, 0);
+        assert!(matches!(
+            Claim::from_json(&json).unwrap().verify(),
+            Err(VerifyError::Invalid(_))
+        ));
+        // a deck that is not the one of the seed
+        let json = claim(true, true, None, 0).replace("\"seed\":11", "\"seed\":12");
+        assert!(matches!(
+            Claim::from_json(&json).unwrap().verify(),
+            Err(VerifyError::Invalid(_))
+        ));
+    }
+}
@@ -0,0 +1,111 @@
+// bindings for a browser front-end running the engine client-side, built with the `wasm` feature
+// (`wasm-pack build -- --no-default-features --features wasm`); states and actions go through
+// json, an action being an id of `ActionRequest::id` or its json, e.g. {"type": "play", "position": 0}
+//
+// a game is always dealt from a seed given by the page, e.g. `Math.random() * 2 ** 32`, so the
+// engine draws no entropy of its own; the generator of `State` falls back on `crypto.getRandomValues`
+// through `rand/wasm-bindgen`
+use crate::agents::{self, Observation};
+use crate::daily;
+use crate::external::Message;
+use crate::replay::Replay;
+use crate::state::{ActionRequest, State, NACTIONS};
+use crate::variant::Variant;
+use wasm_bindgen::prelude::*;
+
+fn js_err<E: std::fmt::Debug>(err: E) -> JsValue {
+    JsValue::from_str(&format!("{:?}", err))
+}
+
+#[wasm_bindgen]
+pub struct Game {
+    state: State,
+}
+
+#[wasm_bindgen]
+impl Game {
+    #[wasm_bindgen(constructor)]
+    pub fn new(players: usize, variant: &str, seed: u64) -> Result<Game, JsValue> {
+        if !(2..=5).contains(&players) {
+            return Err(JsValue::from_str(&format!(
+                "{} players is not supported",
+                players
+            )));
+        }
+        let variant = Variant::from_name(variant)
+            .ok_or_else(|| JsValue::from_str(&format!("unknown variant {:?}", variant)))?;
+        Ok(Game {
+            state: State::with_variant(players, variant, Some(seed)),
+        })
+    }
+
+    // the deal of the day of `date` ("YYYY-MM-DD"), see `daily`
+    pub fn daily(players: usize, variant: &str, date: &str) -> Result<Game, JsValue> {
+        Game::new(players, variant, daily::seed(date).map_err(js_err)?)
+    }
+
+    // with `upto`, the game after its first `upto` actions
+    #[wasm_bindgen(js_name = fromReplay)]
+    pub fn from_replay(json: &str, upto: Option<usize>) -> Result<Game, JsValue> {
+        let replay = Replay::from_json(json).map_err(js_err)?;
+        let state = match upto {
+            Some(upto) => replay.state_at(upto),
+            None => replay.play(|_, _| {}),
+        };
+        Ok(Game {
+            state: state.map_err(js_err)?,
+        })
+    }
+
+    pub fn replay(&self) -> String {
+        Replay::from_state(&self.state).to_json()
+    }
+
+    // what the player to move sees, the message of an external bot (see `src/external.rs`)
+    pub fn observation(&self) -> String {
+        serde_json::to_string(&Message::new(&Observation::new(&self.state))).unwrap()
+    }
+
+    pub fn player(&self) -> usize {
+        self.state.turn() % self.state.players().len()
+    }
+
+    pub fn score(&self) -> usize {
+        self.state.score()
+    }
+
+    pub fn gameover(&self) -> bool {
+        self.state.gameover()
+    }
+
+    // the json of the action of an id
+    pub fn action(id: usize) -> Result<String, JsValue> {
+        if id >= NACTIONS {
+            return Err(JsValue::from_str(&format!("no action {}", id)));
+        }
+        Ok(serde_json::to_string(&ActionRequest::from_id(id)).unwrap())
+    }
+
+    pub fn act(&mut self, id: usize) -> Result<(), JsValue> {
+        if id >= NACTIONS {
+            return Err(JsValue::from_str(&format!("no action {}", id)));
+        }
+        self.state
+            .apply(&ActionRequest::from_id(id))
+            .map_err(js_err)
+    }
+
+    #[wasm_bindgen(js_name = actJson)]
+    pub fn act_json(&mut self, json: &str) -> Result<(), JsValue> {
+        let action: ActionRequest = serde_json::from_str(json).map_err(js_err)?;
+        self.state.apply(&action).map_err(js_err)
+    }
+
+    // the id of the action of a rust agent ("random", "convention", ...) for the player to move
+    #[wasm_bindgen(js_name = botAction)]
+    pub fn bot_action(&self, agent: &str, seed: u64) -> Result<usize, JsValue> {
+        let mut agent = agents::from_name(agent, seed)
+            .ok_or_else(|| JsValue::from_str(&format!("unknown agent {:?}", agent)))?;
+        Ok(agent.act(&Observation::new(&self.state)).id())
+    }
+}